@@ -8,7 +8,7 @@ use naumachia::{
     logic::{SCLogicError, SCLogicResult},
     output::OutputId,
     scripts::ValidatorCode,
-    transaction::TxActions,
+    transaction::{validity::ValidityInterval, TxActions},
     values::Values,
 };
 
@@ -22,10 +22,9 @@ pub async fn pull_from_account<LC: LedgerClient<CheckingAccountDatums, ()>>(
     let allow_pull_validator = pull_validator()?;
     let allow_pull_address = allow_pull_validator.address(network)?;
     let allow_pull_output = ledger_client
-        .all_outputs_at_address(&allow_pull_address)
+        .output_by_id(&allow_pull_output_id)
         .await?
-        .into_iter()
-        .find(|o| o.id() == &allow_pull_output_id)
+        .filter(|o| o.owner() == allow_pull_address)
         .ok_or(CheckingAccountError::OutputNotFound(
             allow_pull_output_id.clone(),
         ))
@@ -34,10 +33,9 @@ pub async fn pull_from_account<LC: LedgerClient<CheckingAccountDatums, ()>>(
     let validator = checking_account_validator()?;
     let checking_account_address = validator.address(network)?;
     let checking_account_output = ledger_client
-        .all_outputs_at_address(&checking_account_address)
+        .output_by_id(&checking_account_output_id)
         .await?
-        .into_iter()
-        .find(|o| o.id() == &checking_account_output_id)
+        .filter(|o| o.owner() == checking_account_address)
         .ok_or(CheckingAccountError::OutputNotFound(
             checking_account_output_id.clone(),
         ))
@@ -77,7 +75,8 @@ pub async fn pull_from_account<LC: LedgerClient<CheckingAccountDatums, ()>>(
     let current_time = ledger_client
         .current_time_secs()
         .await
-        .map_err(|e| SCLogicError::Endpoint(Box::new(e)))?;
+        .map_err(|e| SCLogicError::Endpoint(Box::new(e)))?
+        * 1000;
 
     if current_time < old_pull_time {
         let err = CheckingAccountError::TooEarlyToPull {
@@ -121,6 +120,6 @@ pub async fn pull_from_account<LC: LedgerClient<CheckingAccountDatums, ()>>(
             new_account_value,
             checking_account_address,
         )
-        .with_valid_range_secs(Some(current_time / 1000), None);
+        .with_validity_interval(ValidityInterval::posix_millis(Some(current_time), None));
     Ok(actions)
 }
@@ -20,11 +20,10 @@ pub async fn remove_puller<LC: LedgerClient<CheckingAccountDatums, ()>>(
         .address(network)
         .map_err(SCLogicError::ValidatorScript)?;
     let output = ledger_client
-        .all_outputs_at_address(&address)
+        .output_by_id(&output_id)
         .await
         .map_err(|e| SCLogicError::Lookup(Box::new(e)))?
-        .into_iter()
-        .find(|o| o.id() == &output_id)
+        .filter(|o| o.owner() == address)
         .ok_or(CheckingAccountError::OutputNotFound(output_id))
         .map_err(|e| SCLogicError::Endpoint(Box::new(e)))?;
     let redeemer = ();
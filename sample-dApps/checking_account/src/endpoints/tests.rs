@@ -276,7 +276,7 @@ async fn pull_from_account__replaces_existing_balances_with_updated_amounts_and_
     }
     .into();
     let backend = TestLedgerClientBuilder::new(&puller)
-        .with_starting_time(next_pull)
+        .with_starting_time(next_pull / 1000)
         .start_output(&account_address)
         .with_datum(account_datum)
         .with_value(PolicyId::Lovelace, account_amount)
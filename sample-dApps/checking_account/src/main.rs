@@ -45,8 +45,9 @@ async fn hit_endpoint(endpoint: CheckingAccountEndpoints) -> Result<()> {
     let logic = CheckingAccountLogic;
     let ledger_client = get_trireme_ledger_client_from_file().await?;
     let contract = SmartContract::new(logic, ledger_client);
-    let res = contract.hit_endpoint(endpoint).await?;
-    Ok(res)
+    let tx_id = contract.hit_endpoint(endpoint).await?;
+    println!("tx: {:?}", tx_id);
+    Ok(())
 }
 
 async fn run_lookup(lookup: CheckingAccountLookups) -> Result<CheckingAccountLookupResponses> {
@@ -25,11 +25,10 @@ pub async fn withdraw_from_account<LC: LedgerClient<CheckingAccountDatums, ()>>(
         .address(network)
         .map_err(SCLogicError::ValidatorScript)?;
     let output = ledger_client
-        .all_outputs_at_address(&address)
+        .output_by_id(&output_id)
         .await
         .map_err(|e| SCLogicError::Lookup(Box::new(e)))?
-        .into_iter()
-        .find(|o| o.id() == &output_id)
+        .filter(|o| o.owner() == address)
         .ok_or(CheckingAccountError::OutputNotFound(output_id.clone()))
         .map_err(|e| SCLogicError::Endpoint(Box::new(e)))?;
 
@@ -27,9 +27,12 @@ async fn main() {
     let contract = SmartContract::new(logic, ledger_client);
 
     match args.action {
-        ActionParams::Mint { amount } => contract
-            .hit_endpoint(FreeMintingEndpoints::Mint { amount })
-            .await
-            .unwrap(),
+        ActionParams::Mint { amount } => {
+            let tx_id = contract
+                .hit_endpoint(FreeMintingEndpoints::Mint { amount })
+                .await
+                .unwrap();
+            println!("tx: {:?}", tx_id);
+        }
     }
 }
@@ -27,6 +27,9 @@ async fn main() {
     let contract = SmartContract::new(logic, ledger_client);
 
     match args.action {
-        ActionParams::Mint => contract.hit_endpoint(MintNFTEndpoints::Mint).await.unwrap(),
+        ActionParams::Mint => {
+            let tx_id = contract.hit_endpoint(MintNFTEndpoints::Mint).await.unwrap();
+            println!("tx: {:?}", tx_id);
+        }
     }
 }
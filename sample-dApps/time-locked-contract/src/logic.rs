@@ -7,7 +7,7 @@ use naumachia::{
     logic::{SCLogic, SCLogicError, SCLogicResult},
     output::{Output, OutputId},
     scripts::ValidatorCode,
-    transaction::TxActions,
+    transaction::{validity::ValidityInterval, TxActions},
     values::Values,
 };
 use thiserror::Error;
@@ -95,16 +95,15 @@ async fn impl_claim<LC: LedgerClient<i64, ()>>(
     let script = get_script()?;
     let address = script.address(network)?;
     let output = ledger_client
-        .all_outputs_at_address(&address)
+        .output_by_id(&output_id)
         .await?
-        .into_iter()
-        .find(|o| o.id() == &output_id)
+        .filter(|o| o.owner() == address)
         .ok_or(TimeLockedError::OutputNotFound(output_id.clone()))
         .map_err(|e| SCLogicError::Endpoint(Box::new(e)))?;
     let redeemer = ();
     let script_box = Box::new(script);
     let lower_bound = if let DatumKind::Typed(inner) = output.datum().clone() {
-        inner / 1000
+        inner
     } else {
         return Err(SCLogicError::Endpoint(Box::new(
             TimeLockedError::OutputNotFound(output_id),
@@ -112,7 +111,7 @@ async fn impl_claim<LC: LedgerClient<i64, ()>>(
     };
     let tx_actions = TxActions::v2()
         .with_script_redeem(output, redeemer, script_box)
-        .with_valid_range_secs(Some(lower_bound), None);
+        .with_validity_interval(ValidityInterval::posix_millis(Some(lower_bound), None));
     Ok(tx_actions)
 }
 
@@ -187,7 +186,7 @@ mod tests {
         // given
         let me = Address::from_bech32("addr_test1qpmtp5t0t5y6cqkaz7rfsyrx7mld77kpvksgkwm0p7en7qum7a589n30e80tclzrrnj8qr4qvzj6al0vpgtnmrkkksnqd8upj0").unwrap();
         let start_amount = 100_000_000;
-        let start_time = 10_000;
+        let start_time = 10;
 
         let script_address = get_script().unwrap().address(Network::Testnet).unwrap();
         let locked_amount = 10_000_000;
@@ -241,7 +240,7 @@ mod tests {
         // given
         let me = Address::from_bech32("addr_test1qpmtp5t0t5y6cqkaz7rfsyrx7mld77kpvksgkwm0p7en7qum7a589n30e80tclzrrnj8qr4qvzj6al0vpgtnmrkkksnqd8upj0").unwrap();
         let start_amount = 100_000_000;
-        let start_time = 10_000;
+        let start_time = 10;
 
         let script_address = get_script().unwrap().address(Network::Testnet).unwrap();
         let locked_amount = 10_000_000;
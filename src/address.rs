@@ -6,6 +6,12 @@ pub enum PolicyId {
     NativeToken(String, Option<String>),
 }
 
+/// The bytes of a policy id. Test ledgers accept any string as a policy id, so ids that aren't
+/// hex are taken as their UTF-8 bytes.
+pub(crate) fn policy_id_bytes(policy_id: &str) -> Vec<u8> {
+    hex::decode(policy_id).unwrap_or_else(|_| policy_id.as_bytes().to_vec())
+}
+
 impl PolicyId {
     pub fn ada() -> PolicyId {
         PolicyId::Lovelace
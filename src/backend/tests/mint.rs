@@ -37,7 +37,11 @@ impl<R> MintingPolicy<R> for AliceCanMintPolicy {
 #[tokio::test]
 async fn mint__alice_can_mint() {
     let signer = Address::from_bech32(ALICE).unwrap();
-    let ledger_client = TestLedgerClientBuilder::<(), ()>::new(&signer).build_in_memory();
+    let ledger_client = TestLedgerClientBuilder::<(), ()>::new(&signer)
+        .start_output(&signer)
+        .with_value(PolicyId::Lovelace, 10_000_000)
+        .finish_output()
+        .build_in_memory();
     let amount = 100;
 
     let asset_name = None;
@@ -62,7 +66,11 @@ async fn mint__alice_can_mint() {
 #[tokio::test]
 async fn mint__bob_cannot_mint() {
     let signer = Address::from_bech32(BOB).unwrap();
-    let ledger_client = TestLedgerClientBuilder::<(), ()>::new(&signer).build_in_memory();
+    let ledger_client = TestLedgerClientBuilder::<(), ()>::new(&signer)
+        .start_output(&signer)
+        .with_value(PolicyId::Lovelace, 10_000_000)
+        .finish_output()
+        .build_in_memory();
     let amount = 100;
 
     let asset_name = None;
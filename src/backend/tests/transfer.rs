@@ -36,7 +36,8 @@ prop_compose! {
                 builder = builder.start_output(&recipient).with_value(decoy.to_owned(), new_amount as u64).finish_output();
             }
         }
-        (signer, recipient, min_amount as u64, builder.build_in_memory(), decoys)
+        // Amounts are arbitrary, so they can be below min ADA
+        (signer, recipient, min_amount as u64, builder.build_in_memory().without_min_ada(), decoys)
     }
 }
 
@@ -2,7 +2,9 @@ use pallas_addresses::Address;
 use thiserror::Error;
 
 use crate::scripts::ScriptError;
-use crate::{address::PolicyId, ledger_client::LedgerClientError, logic::SCLogicError};
+use crate::{
+    address::PolicyId, ledger_client::LedgerClientError, logic::SCLogicError, output::OutputId,
+};
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
 
@@ -24,6 +26,12 @@ pub enum Error {
     FailedToRetrieveScriptFor(Address),
     #[error("Error: Failed to retrieve redeemer for {0:?}.")]
     FailedToRetrieveRedeemerFor(Address),
+    #[error("Error: Output {0:?} has no reference script.")]
+    MissingReferenceScript(OutputId),
+    #[error("Error: Reference script of output {0:?} doesn't match the script.")]
+    ReferenceScriptMismatch(OutputId),
+    #[error("Error: Amount {0} is too large to mint or burn.")]
+    MintAmountOutOfRange(u64),
     #[error("Unable to mint ADA/Lovelace")]
     ImpossibleToMintADA,
     #[error("Error with Trireme integration: {0:?}")]
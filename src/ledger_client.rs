@@ -1,23 +1,27 @@
 use thiserror::Error;
 
 pub mod test_ledger_client;
+pub mod typed_ledger_client;
 
 use async_trait::async_trait;
 
 use crate::{
     output::{Output, OutputId},
     transaction::TxId,
-    transaction::UnbuiltTransaction,
+    transaction::{TxSimulation, UnbuiltTransaction, UnsignedTransaction},
     PolicyId,
 };
 use pallas_addresses::{Address, Network};
-use std::error;
+use std::{
+    error,
+    time::{Duration, Instant},
+};
+
+/// How long `LedgerClient::await_tx` waits between checks
+pub const AWAIT_TX_POLL_INTERVAL: Duration = Duration::from_secs(5);
 
-// TODO: Having this bound to a specific Datum/Redeemer doesn't really make sense at this scope.
-//   It's convenient from the backend's perspective, but it's constricting else-wise.
-//   https://github.com/MitchTurner/naumachia/issues/38
-// TODO: Add methods for finding specific output by id
-//   (getting all is expensive if you just want the output for a specific ID)
+/// Use `LedgerClient<PlutusData, PlutusData>` as an untyped client and wrap it in
+/// `typed_ledger_client::TypedLedgerClient` for each datum/redeemer type a dApp works with
 #[async_trait]
 pub trait LedgerClient<Datum, Redeemer>: Send + Sync {
     async fn signer_base_address(&self) -> LedgerClientResult<Address>;
@@ -32,6 +36,24 @@ pub trait LedgerClient<Datum, Redeemer>: Send + Sync {
         address: &Address,
     ) -> LedgerClientResult<Vec<Output<Datum>>>;
 
+    /// The unspent output with `id`, or `None` if it doesn't exist or was spent
+    async fn output_by_id(&self, _id: &OutputId) -> LedgerClientResult<Option<Output<Datum>>> {
+        Err(LedgerClientError::Unsupported(
+            "Looking up outputs by id".to_string(),
+        ))
+    }
+
+    /// Unspent outputs holding any of `asset`, e.g. the output carrying a thread token or NFT.
+    /// A policy without an asset name matches every asset under that policy.
+    async fn outputs_with_asset(
+        &self,
+        _asset: &PolicyId,
+    ) -> LedgerClientResult<Vec<Output<Datum>>> {
+        Err(LedgerClientError::Unsupported(
+            "Looking up outputs by asset".to_string(),
+        ))
+    }
+
     async fn balance_at_address(
         &self,
         address: &Address,
@@ -52,6 +74,64 @@ pub trait LedgerClient<Datum, Redeemer>: Send + Sync {
     }
     async fn issue(&self, tx: UnbuiltTransaction<Datum, Redeemer>) -> LedgerClientResult<TxId>; // TODO: Move to other trait
 
+    /// Build and balance `tx` without signing or submitting it
+    async fn build_unsigned(
+        &self,
+        _tx: UnbuiltTransaction<Datum, Redeemer>,
+    ) -> LedgerClientResult<UnsignedTransaction> {
+        Err(LedgerClientError::Unsupported(
+            "Building unsigned transactions".to_string(),
+        ))
+    }
+
+    /// Dry-run `tx`: run its scripts and work out its fee, inputs, and outputs without
+    /// committing anything. Failing scripts are returned as errors, just like with `issue`.
+    async fn simulate(
+        &self,
+        _tx: UnbuiltTransaction<Datum, Redeemer>,
+    ) -> LedgerClientResult<TxSimulation> {
+        Err(LedgerClientError::Unsupported(
+            "Simulating transactions".to_string(),
+        ))
+    }
+
+    /// Submit a tx that was built with `build_unsigned` and then signed elsewhere
+    async fn submit_signed(&self, _cbor_hex: &str) -> LedgerClientResult<TxId> {
+        Err(LedgerClientError::Unsupported(
+            "Submitting signed transactions".to_string(),
+        ))
+    }
+
+    /// Number of blocks from the one including `tx_id` up to the tip, or `None` if it hasn't
+    /// landed yet
+    async fn tx_confirmations(&self, _tx_id: &TxId) -> LedgerClientResult<Option<u64>> {
+        Err(LedgerClientError::Unsupported(
+            "Looking up transactions".to_string(),
+        ))
+    }
+
+    /// Poll until `tx_id` has at least `confirmations` confirmations. Errors if that takes longer
+    /// than `timeout`.
+    async fn await_tx(
+        &self,
+        tx_id: &TxId,
+        confirmations: u64,
+        timeout: Duration,
+    ) -> LedgerClientResult<()> {
+        let start = Instant::now();
+        loop {
+            if let Some(current) = self.tx_confirmations(tx_id).await? {
+                if current >= confirmations {
+                    return Ok(());
+                }
+            }
+            if start.elapsed() >= timeout {
+                return Err(LedgerClientError::TxNotConfirmed(tx_id.as_str(), timeout));
+            }
+            tokio::time::sleep(AWAIT_TX_POLL_INTERVAL).await;
+        }
+    }
+
     async fn network(&self) -> LedgerClientResult<Network>;
 
     /// Get the posix time of the most recent block
@@ -73,6 +153,8 @@ pub enum LedgerClientError {
     FailedToRetrieveOutputsAt(Address, Box<dyn error::Error + Send + Sync>),
     #[error("Failed to retrieve UTXO with ID {0:?}.")]
     FailedToRetrieveOutputWithId(OutputId, Box<dyn error::Error + Send + Sync>),
+    #[error("Failed to retrieve outputs holding {0:?}: {1:?}.")]
+    FailedToRetrieveOutputsWithAsset(PolicyId, Box<dyn error::Error + Send + Sync>),
     #[error("Failed to issue transaction: {0:?}")]
     FailedToIssueTx(Box<dyn error::Error + Send + Sync>),
     #[error("There isn't a single utxo big enough for collateral")]
@@ -87,6 +169,12 @@ pub enum LedgerClientError {
     ValidityRange(String),
     #[error("While getting last block time: {0:?}")]
     FailedToGetBlockTime(Box<dyn error::Error + Send + Sync>),
+    #[error("{0} isn't supported by this ledger client")]
+    Unsupported(String),
+    #[error("Transaction {0} wasn't confirmed within {1:?}")]
+    TxNotConfirmed(String, Duration),
+    #[error("Failed to look up transaction: {0:?}")]
+    FailedToLookUpTx(Box<dyn error::Error + Send + Sync>),
 }
 
 pub type LedgerClientResult<T> = Result<T, LedgerClientError>;
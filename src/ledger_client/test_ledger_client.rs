@@ -1,25 +1,28 @@
-use std::{
-    fmt::Debug,
-    hash::Hash,
-    marker::PhantomData,
-    path::Path,
-    sync::{Arc, Mutex},
-};
+use std::{fmt::Debug, hash::Hash, marker::PhantomData, path::Path, time::Duration};
 
 use crate::{
+    address::policy_id_bytes,
     ledger_client::{
         test_ledger_client::in_memory_storage::InMemoryStorage, LedgerClient, LedgerClientError,
         LedgerClientResult,
     },
-    output::{DatumKind, Output, UnbuiltOutput},
+    output::{min_ada::MinAdaCalculator, DatumKind, Output, OutputId, UnbuiltOutput},
     scripts::context::{
-        pub_key_hash_from_address_if_available, CtxDatum, CtxOutput, CtxOutputReference,
+        pub_key_hash_from_address_if_available, stake_key_hash_from_address_if_available, CtxDatum,
+        CtxOutput, CtxOutputReference, PubKeyHash,
     },
     scripts::{
-        context::{CtxScriptPurpose, CtxValue, Input, TxContext, ValidRange},
+        context::{CtxMintValue, CtxScriptPurpose, CtxValue, Input, TxContext, ValidRange},
         raw_validator_script::plutus_data::PlutusData,
+        ExecutionCost, MintingPolicy, ReferenceScript, StakingValidator, ValidatorCode,
+    },
+    transaction::{
+        metadata::TransactionMetadata,
+        staking::{StakeCertificate, StakeCredential, StakeWitness, STAKE_KEY_DEPOSIT},
+        validity::{ValidityBound, ValidityInterval},
+        RedeemerCost, RedeemerKind, TxId, TxSimulation, TxSummary,
     },
-    transaction::TxId,
+    trireme_ledger_client::cml_client::protocol_parameters::ProtocolParameters,
     values::Values,
     PolicyId, UnbuiltTransaction,
 };
@@ -27,10 +30,13 @@ use async_trait::async_trait;
 use local_persisted_storage::LocalPersistedStorage;
 use pallas_addresses::{Address, Network};
 use rand::Rng;
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
+use tx_body::TxBody;
 
 pub mod in_memory_storage;
 pub mod local_persisted_storage;
+mod tx_body;
 
 #[cfg(test)]
 mod tests;
@@ -154,6 +160,38 @@ enum TestLCError {
     TxTooLate,
     #[error("Not a valid signer address")]
     InvalidAddress,
+    #[error("Reference input not found: {0:?}")]
+    ReferenceInputNotFound(OutputId),
+    #[error("Reference input doesn't carry the reference script: {0:?}")]
+    ReferenceScriptNotFound(OutputId),
+    #[error("Stake credential already registered: {0:?}")]
+    StakeAlreadyRegistered(StakeCredential),
+    #[error("Stake credential not registered: {0:?}")]
+    StakeNotRegistered(StakeCredential),
+    #[error("Stake credential still has {0:?} lovelace in rewards")]
+    UnwithdrawnRewards(u64),
+    #[error("Withdrawal of {requested:?} must match the full reward balance of {available:?}")]
+    PartialWithdrawal { requested: u64, available: u64 },
+    #[error("Signer doesn't hold the stake key for: {0:?}")]
+    MissingStakeWitness(StakeCredential),
+    #[error("Required signer didn't sign: {0:?}")]
+    MissingRequiredSigner(PubKeyHash),
+    #[error("Output {index} to {owner} holds {provided} lovelace, but needs at least {required}")]
+    BelowMinAda {
+        index: usize,
+        owner: String,
+        provided: u64,
+        required: u64,
+    },
+    #[error("Failed to encode tx body: {0}")]
+    TxBodyEncoding(String),
+}
+
+/// Registered stake credential state kept by the test ledger
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StakeAccount {
+    pub rewards: u64,
+    pub delegation: Option<Vec<u8>>,
 }
 
 #[async_trait::async_trait]
@@ -165,17 +203,38 @@ pub trait TestLedgerStorage<Datum> {
         count: usize,
     ) -> LedgerClientResult<Vec<Output<Datum>>>;
     async fn all_outputs(&self, address: &Address) -> LedgerClientResult<Vec<Output<Datum>>>;
+    async fn output_by_id(&self, id: &OutputId) -> LedgerClientResult<Option<Output<Datum>>>;
+    async fn outputs_with_asset(&self, asset: &PolicyId) -> LedgerClientResult<Vec<Output<Datum>>>;
     async fn remove_output(&self, output: &Output<Datum>) -> LedgerClientResult<()>;
     async fn add_output(&self, output: &Output<Datum>) -> LedgerClientResult<()>;
     async fn current_time(&self) -> LedgerClientResult<i64>;
     async fn set_current_time(&self, posix_time: i64) -> LedgerClientResult<()>;
     async fn get_block_length(&self) -> LedgerClientResult<i64>;
     async fn network(&self) -> LedgerClientResult<Network>;
+    async fn add_metadata(
+        &self,
+        tx_id: &TxId,
+        metadata: &TransactionMetadata,
+    ) -> LedgerClientResult<()>;
+    async fn metadata(&self, tx_id: &TxId) -> LedgerClientResult<Option<TransactionMetadata>>;
+    async fn stake_account(
+        &self,
+        credential: &StakeCredential,
+    ) -> LedgerClientResult<Option<StakeAccount>>;
+    /// `None` removes the account
+    async fn set_stake_account(
+        &self,
+        credential: &StakeCredential,
+        account: Option<StakeAccount>,
+    ) -> LedgerClientResult<()>;
 }
 
 #[derive(Debug)]
 pub struct TestLedgerClient<Datum, Redeemer, Storage: TestLedgerStorage<Datum>> {
     storage: Storage,
+    cosigners: Vec<PubKeyHash>,
+    min_ada: Option<MinAdaCalculator>,
+    key_deposit: u64,
     _datum: PhantomData<Datum>, // This is useless but makes calling it's functions easier
     _redeemer: PhantomData<Redeemer>, // This is useless but makes calling it's functions easier
 }
@@ -190,14 +249,12 @@ where
         block_length: i64,
         starting_time: i64,
     ) -> Self {
-        let storage = InMemoryStorage {
-            signer,
-            outputs: Arc::new(Mutex::new(outputs)),
-            current_posix_time: Arc::new(Mutex::new(starting_time)),
-            block_length,
-        };
+        let storage = InMemoryStorage::new(signer, outputs, block_length, starting_time);
         TestLedgerClient {
             storage,
+            cosigners: Vec::new(),
+            min_ada: Some(MinAdaCalculator::default()),
+            key_deposit: STAKE_KEY_DEPOSIT,
             _datum: Default::default(),
             _redeemer: Default::default(),
         }
@@ -223,6 +280,9 @@ where
         let _ = storage.get_data();
         TestLedgerClient {
             storage,
+            cosigners: Vec::new(),
+            min_ada: Some(MinAdaCalculator::default()),
+            key_deposit: STAKE_KEY_DEPOSIT,
             _datum: Default::default(),
             _redeemer: Default::default(),
         }
@@ -233,6 +293,9 @@ where
         let _ = storage.get_data();
         TestLedgerClient {
             storage,
+            cosigners: Vec::new(),
+            min_ada: Some(MinAdaCalculator::default()),
+            key_deposit: STAKE_KEY_DEPOSIT,
             _datum: Default::default(),
             _redeemer: Default::default(),
         }
@@ -244,6 +307,33 @@ where
     Datum: Clone + Send + Sync + PartialEq,
     Storage: TestLedgerStorage<Datum> + Send + Sync,
 {
+    /// Also sign issued txs as `cosigner`, as if their witness was collected before submission
+    pub fn with_cosigner(mut self, cosigner: PubKeyHash) -> Self {
+        self.cosigners.push(cosigner);
+        self
+    }
+
+    /// Reject txs with outputs, change included, holding less lovelace than `calculator`
+    /// requires, like mainnet does. `MinAdaCalculator::default()` is used unless set.
+    pub fn with_min_ada(mut self, calculator: MinAdaCalculator) -> Self {
+        self.min_ada = Some(calculator);
+        self
+    }
+
+    /// Accept outputs of any size, e.g. for tests working with tiny amounts
+    pub fn without_min_ada(mut self) -> Self {
+        self.min_ada = None;
+        self
+    }
+
+    /// Take stake deposits and check min ADA according to `params` instead of the mainnet
+    /// defaults
+    pub fn with_protocol_parameters(mut self, params: &ProtocolParameters) -> Self {
+        self.key_deposit = params.key_deposit;
+        self.min_ada = Some(params.min_ada_calculator());
+        self
+    }
+
     pub async fn current_time_secs(&self) -> LedgerClientResult<i64> {
         self.storage.current_time().await
     }
@@ -263,57 +353,128 @@ where
         let new_time = advanced_time + current_time;
         self.storage.set_current_time(new_time).await
     }
+
+    /// Metadata attached to an issued tx, if it had any
+    pub async fn metadata(&self, tx_id: &TxId) -> LedgerClientResult<Option<TransactionMetadata>> {
+        self.storage.metadata(tx_id).await
+    }
+
+    pub async fn stake_account(
+        &self,
+        credential: &StakeCredential,
+    ) -> LedgerClientResult<Option<StakeAccount>> {
+        self.storage.stake_account(credential).await
+    }
+
+    /// Pay out rewards to a registered stake credential, like the end of an epoch would
+    pub async fn add_rewards(
+        &self,
+        credential: &StakeCredential,
+        amount: u64,
+    ) -> LedgerClientResult<()> {
+        let mut account = self
+            .storage
+            .stake_account(credential)
+            .await?
+            .ok_or_else(|| TestLCError::StakeNotRegistered(credential.clone()))
+            .map_err(|e| LedgerClientError::FailedToIssueTx(Box::new(e)))?;
+        account.rewards += amount;
+        self.storage
+            .set_stake_account(credential, Some(account))
+            .await
+    }
+
+    async fn pending_stake_account(
+        &self,
+        credential: &StakeCredential,
+        pending: &[(StakeCredential, Option<StakeAccount>)],
+    ) -> LedgerClientResult<Option<StakeAccount>> {
+        if let Some((_, account)) = pending.iter().rev().find(|(c, _)| c == credential) {
+            Ok(account.clone())
+        } else {
+            self.storage.stake_account(credential).await
+        }
+    }
 }
 
-#[async_trait]
-impl<Datum, Redeemer, Storage> LedgerClient<Datum, Redeemer>
-    for TestLedgerClient<Datum, Redeemer, Storage>
+impl<Datum, Redeemer, Storage> TestLedgerClient<Datum, Redeemer, Storage>
 where
     Datum: Clone + PartialEq + Debug + Send + Sync + Into<PlutusData>,
-    Redeemer: Clone + Eq + PartialEq + Debug + Hash + Send + Sync,
+    Redeemer: Clone + Eq + PartialEq + Debug + Hash + Send + Sync + Into<PlutusData>,
     Storage: TestLedgerStorage<Datum> + Send + Sync,
 {
-    async fn signer_base_address(&self) -> LedgerClientResult<Address> {
-        self.storage.signer().await
-    }
-
-    async fn outputs_at_address(
+    /// Raise outputs to min ADA when the tx asked for a top-up
+    fn top_up_outputs(
         &self,
-        address: &Address,
-        count: usize,
-    ) -> LedgerClientResult<Vec<Output<Datum>>> {
-        self.storage.outputs_by_count(address, count).await
+        mut tx: UnbuiltTransaction<Datum, Redeemer>,
+    ) -> UnbuiltTransaction<Datum, Redeemer> {
+        if tx.min_ada_top_up {
+            let calculator = self.min_ada.unwrap_or_default();
+            tx.unbuilt_outputs = tx
+                .unbuilt_outputs
+                .into_iter()
+                .map(|output| calculator.top_up(output))
+                .collect();
+        }
+        tx
     }
 
-    async fn all_outputs_at_address(
+    /// Validate `tx` and work out its effects without touching storage
+    async fn process_tx(
         &self,
-        address: &Address,
-    ) -> LedgerClientResult<Vec<Output<Datum>>> {
-        self.storage.all_outputs(address).await
-    }
-
-    async fn issue(&self, tx: UnbuiltTransaction<Datum, Redeemer>) -> LedgerClientResult<TxId> {
+        tx: &UnbuiltTransaction<Datum, Redeemer>,
+    ) -> LedgerClientResult<ProcessedTx<Datum>> {
         // Setup
-        let valid_range = tx.valid_range;
-        let current_time = self.current_time_secs().await?;
-        check_time_valid(valid_range, current_time)
+        let current_time_secs = self.current_time_secs().await?;
+        check_time_valid(&tx.valid_range, current_time_secs)
             .map_err(|e| LedgerClientError::FailedToIssueTx(Box::new(e)))?;
 
         let signer = self.signer_base_address().await?;
+        check_required_signers(tx, &signer, &self.cosigners)?;
+
+        for reference_input in tx.reference_inputs() {
+            let available = self
+                .all_outputs_at_address(&reference_input.owner())
+                .await?;
+            let stored = available
+                .iter()
+                .find(|output| output.id() == reference_input.id());
+            if let Some(stored) = stored {
+                if reference_input.reference_script().is_some()
+                    && stored.reference_script() != reference_input.reference_script()
+                {
+                    return Err(LedgerClientError::FailedToIssueTx(Box::new(
+                        TestLCError::ReferenceScriptNotFound(reference_input.id().to_owned()),
+                    )));
+                }
+            }
+            if stored != Some(reference_input) {
+                return Err(LedgerClientError::FailedToIssueTx(Box::new(
+                    TestLCError::ReferenceInputNotFound(reference_input.id().to_owned()),
+                )));
+            }
+        }
 
         // TODO: Optimize selection
         let mut combined_inputs = self.all_outputs_at_address(&signer).await?;
 
+        // Scripts run once the tx id is known, since they see it in their context
+        let mut script_runs = Vec::new();
         let mut spending_outputs: Vec<Output<_>> = Vec::new();
-        for (input, redeemer, script) in tx.script_inputs().iter() {
+        for (index, (input, redeemer, script)) in tx.script_inputs().iter().enumerate() {
             if let DatumKind::Typed(datum) = input.datum() {
                 if !spending_outputs.contains(input) {
-                    let ctx = spend_tx_context(&tx, &signer, input)?;
+                    let id = input.id();
+                    let out_ref = CtxOutputReference::new(id.tx_hash().to_vec(), id.index());
                     // TODO: Check that the output is at the script address
                     //  https://github.com/MitchTurner/naumachia/issues/86
-                    script
-                        .execute(datum.to_owned(), redeemer.to_owned(), ctx)
-                        .map_err(|e| LedgerClientError::FailedToIssueTx(Box::new(e)))?;
+                    script_runs.push(ScriptRun {
+                        kind: RedeemerKind::Spend,
+                        index,
+                        purpose: CtxScriptPurpose::Spend(out_ref),
+                        redeemer,
+                        script: RunnableScript::Validator(datum, script.as_ref()),
+                    });
                     combined_inputs.push(input.clone());
                     spending_outputs.push(input.clone());
                 } else {
@@ -336,23 +497,130 @@ where
                     acc
                 });
 
-        let mut construction_ctx = TxIdConstructionCtx::new();
-
         let mut minted_value = Values::default();
+        let mut burned_value = Values::default();
 
-        for (amount, asset_name, redeemer, policy) in tx.minting.iter() {
+        for (index, (assets, redeemer, policy)) in tx.minting.iter().enumerate() {
             let id = policy
                 .id()
                 .map_err(|e| LedgerClientError::FailedToIssueTx(Box::new(e)))?;
-            let policy_id = PolicyId::native_token(&id, asset_name);
-            let ctx = mint_tx_context(&tx, &signer, &id)?;
-            policy
-                .execute(redeemer.to_owned(), ctx)
+            let policy_id = policy_id_bytes(&id);
+            script_runs.push(ScriptRun {
+                kind: RedeemerKind::Mint,
+                index,
+                purpose: CtxScriptPurpose::Mint(policy_id),
+                redeemer,
+                script: RunnableScript::Policy(policy.as_ref()),
+            });
+            for (asset_name, amount) in assets {
+                let policy_id = PolicyId::native_token(&id, asset_name);
+                if *amount >= 0 {
+                    minted_value.add_one_value(&policy_id, amount.unsigned_abs());
+                } else {
+                    burned_value.add_one_value(&policy_id, amount.unsigned_abs());
+                }
+            }
+        }
+
+        let mut stake_updates: Vec<(StakeCredential, Option<StakeAccount>)> = Vec::new();
+        let mut stake_deposits = 0;
+        let mut stake_refunds_and_withdrawals = 0;
+
+        for (index, certificate) in tx.certificates.iter().enumerate() {
+            let credential = certificate
+                .credential()
+                .map_err(|e| LedgerClientError::FailedToIssueTx(Box::new(e)))?;
+            let account = self
+                .pending_stake_account(&credential, &stake_updates)
+                .await?;
+            if let Some(witness) = certificate.witness() {
+                let dcert = certificate
+                    .to_ctx()
+                    .map_err(|e| LedgerClientError::FailedToIssueTx(Box::new(e)))?;
+                if let Some((redeemer, validator)) = check_stake_witness(witness, &signer)? {
+                    script_runs.push(ScriptRun {
+                        kind: RedeemerKind::Certificate,
+                        index,
+                        purpose: CtxScriptPurpose::Publish(dcert),
+                        redeemer,
+                        script: RunnableScript::Staking(validator),
+                    });
+                }
+            }
+            let update = match certificate {
+                StakeCertificate::Registration(_) => {
+                    if account.is_some() {
+                        return Err(LedgerClientError::FailedToIssueTx(Box::new(
+                            TestLCError::StakeAlreadyRegistered(credential),
+                        )));
+                    }
+                    stake_deposits += self.key_deposit;
+                    Some(StakeAccount::default())
+                }
+                StakeCertificate::Deregistration(_) => {
+                    let account = registered_account(account, &credential)?;
+                    if account.rewards > 0 {
+                        return Err(LedgerClientError::FailedToIssueTx(Box::new(
+                            TestLCError::UnwithdrawnRewards(account.rewards),
+                        )));
+                    }
+                    stake_refunds_and_withdrawals += self.key_deposit;
+                    None
+                }
+                StakeCertificate::Delegation { pool_key_hash, .. } => {
+                    let mut account = registered_account(account, &credential)?;
+                    account.delegation = Some(pool_key_hash.clone());
+                    Some(account)
+                }
+            };
+            stake_updates.push((credential, update));
+        }
+
+        for (index, withdrawal) in tx.withdrawals.iter().enumerate() {
+            let credential = withdrawal
+                .witness
+                .credential()
                 .map_err(|e| LedgerClientError::FailedToIssueTx(Box::new(e)))?;
-            minted_value.add_one_value(&policy_id, *amount);
+            let account = self
+                .pending_stake_account(&credential, &stake_updates)
+                .await?;
+            let mut account = registered_account(account, &credential)?;
+            if withdrawal.amount != account.rewards {
+                return Err(LedgerClientError::FailedToIssueTx(Box::new(
+                    TestLCError::PartialWithdrawal {
+                        requested: withdrawal.amount,
+                        available: account.rewards,
+                    },
+                )));
+            }
+            if let Some((redeemer, validator)) = check_stake_witness(&withdrawal.witness, &signer)?
+            {
+                script_runs.push(ScriptRun {
+                    kind: RedeemerKind::Withdrawal,
+                    index,
+                    purpose: CtxScriptPurpose::WithdrawFrom(credential.clone()),
+                    redeemer,
+                    script: RunnableScript::Staking(validator),
+                });
+            }
+            stake_refunds_and_withdrawals += withdrawal.amount;
+            account.rewards = 0;
+            stake_updates.push((credential, Some(account)));
+        }
+
+        if stake_refunds_and_withdrawals > 0 {
+            minted_value.add_one_value(&PolicyId::Lovelace, stake_refunds_and_withdrawals);
+        }
+        if stake_deposits > 0 {
+            burned_value.add_one_value(&PolicyId::Lovelace, stake_deposits);
         }
 
         total_input_value.add_values(&minted_value);
+        let total_input_value = total_input_value
+            .try_subtract(&burned_value)
+            .map_err(|_| TestLCError::NotEnoughInputs)
+            .map_err(|e| LedgerClientError::FailedToIssueTx(Box::new(e)))?
+            .unwrap_or_default();
 
         let total_output_value =
             tx.unbuilt_outputs()
@@ -367,30 +635,142 @@ where
             .map_err(|_| TestLCError::NotEnoughInputs)
             .map_err(|e| LedgerClientError::FailedToIssueTx(Box::new(e)))?;
 
-        for input in combined_inputs {
-            self.storage.remove_output(&input).await?;
+        let mut body_outputs = Vec::new();
+        if let Some(remainder) = maybe_remainder {
+            body_outputs.push(UnbuiltOutput::new_wallet(signer.clone(), remainder));
+        }
+        body_outputs.extend(tx.unbuilt_outputs.clone());
+        if let Some(calculator) = &self.min_ada {
+            check_min_ada(&body_outputs, calculator)?;
         }
 
-        let mut combined_outputs = Vec::new();
-        if let Some(remainder) = maybe_remainder {
-            combined_outputs.push(new_wallet_output(
-                &signer,
-                &remainder,
-                &mut construction_ctx,
-            ));
+        // Without inputs, identical txs would share a body hash, so they get an arbitrary id
+        let tx_hash = if combined_inputs.is_empty() {
+            arbitrary_tx_id().to_vec()
+        } else {
+            TxBody::new(tx, &combined_inputs, &body_outputs, TEST_LEDGER_FEE)?.hash()?
+        };
+
+        let redeemers: Vec<(CtxScriptPurpose, PlutusData)> = script_runs
+            .iter()
+            .map(|run| (run.purpose.clone(), run.redeemer.to_owned().into()))
+            .collect();
+        let mut execution_costs = Vec::new();
+        for run in script_runs {
+            let ctx = tx_context(tx, &signer, run.purpose, &tx_hash, &redeemers)?;
+            let cost = match run.script {
+                RunnableScript::Validator(datum, validator) => {
+                    validator.execute(datum.to_owned(), run.redeemer.to_owned(), ctx)
+                }
+                RunnableScript::Policy(policy) => policy.execute(run.redeemer.to_owned(), ctx),
+                RunnableScript::Staking(validator) => {
+                    validator.execute(run.redeemer.to_owned(), ctx)
+                }
+            }
+            .map_err(|e| LedgerClientError::FailedToIssueTx(Box::new(e)))?;
+            execution_costs.push(redeemer_cost(run.kind, run.index, cost));
         }
 
-        let built_outputs = build_outputs(tx.unbuilt_outputs, &mut construction_ctx);
+        let mut construction_ctx = TxIdConstructionCtx::new(tx_hash.clone());
+        let new_outputs = build_outputs(body_outputs, &mut construction_ctx);
+
+        let tx_id = TxId::new(&hex::encode(tx_hash));
 
-        combined_outputs.extend(built_outputs);
+        let processed = ProcessedTx {
+            tx_id,
+            spent_inputs: combined_inputs,
+            new_outputs,
+            stake_updates,
+            execution_costs,
+        };
+        Ok(processed)
+    }
+}
 
-        for output in combined_outputs {
-            self.storage.add_output(&output).await?;
+#[async_trait]
+impl<Datum, Redeemer, Storage> LedgerClient<Datum, Redeemer>
+    for TestLedgerClient<Datum, Redeemer, Storage>
+where
+    Datum: Clone + PartialEq + Debug + Send + Sync + Into<PlutusData>,
+    Redeemer: Clone + Eq + PartialEq + Debug + Hash + Send + Sync + Into<PlutusData>,
+    Storage: TestLedgerStorage<Datum> + Send + Sync,
+{
+    async fn signer_base_address(&self) -> LedgerClientResult<Address> {
+        self.storage.signer().await
+    }
+
+    async fn outputs_at_address(
+        &self,
+        address: &Address,
+        count: usize,
+    ) -> LedgerClientResult<Vec<Output<Datum>>> {
+        self.storage.outputs_by_count(address, count).await
+    }
+
+    async fn all_outputs_at_address(
+        &self,
+        address: &Address,
+    ) -> LedgerClientResult<Vec<Output<Datum>>> {
+        self.storage.all_outputs(address).await
+    }
+
+    async fn output_by_id(&self, id: &OutputId) -> LedgerClientResult<Option<Output<Datum>>> {
+        self.storage.output_by_id(id).await
+    }
+
+    async fn outputs_with_asset(&self, asset: &PolicyId) -> LedgerClientResult<Vec<Output<Datum>>> {
+        self.storage.outputs_with_asset(asset).await
+    }
+
+    async fn issue(&self, tx: UnbuiltTransaction<Datum, Redeemer>) -> LedgerClientResult<TxId> {
+        let tx = self.top_up_outputs(tx);
+        let processed = self.process_tx(&tx).await?;
+
+        for input in processed.spent_inputs.iter() {
+            self.storage.remove_output(input).await?;
+        }
+
+        for output in processed.new_outputs.iter() {
+            self.storage.add_output(output).await?;
+        }
+
+        for (credential, account) in processed.stake_updates {
+            self.storage.set_stake_account(&credential, account).await?;
+        }
+
+        if !tx.metadata.is_empty() {
+            self.storage
+                .add_metadata(&processed.tx_id, &tx.metadata)
+                .await?;
         }
 
         self.advance_time_one_block().await?;
 
-        Ok(TxId::new(&hex::encode(construction_ctx.tx_hash())))
+        Ok(processed.tx_id)
+    }
+
+    async fn simulate(
+        &self,
+        tx: UnbuiltTransaction<Datum, Redeemer>,
+    ) -> LedgerClientResult<TxSimulation> {
+        let tx = self.top_up_outputs(tx);
+        let processed = self.process_tx(&tx).await?;
+        let summary = processed.summary()?;
+        let simulation = TxSimulation {
+            summary,
+            execution_costs: processed.execution_costs,
+        };
+        Ok(simulation)
+    }
+
+    /// Every tx is on chain as soon as `issue` returns
+    async fn await_tx(
+        &self,
+        _tx_id: &TxId,
+        _confirmations: u64,
+        _timeout: Duration,
+    ) -> LedgerClientResult<()> {
+        Ok(())
     }
 
     async fn network(&self) -> LedgerClientResult<Network> {
@@ -406,30 +786,84 @@ where
     }
 }
 
+/// The test ledger has one slot per second, with slot `0` at POSIX time `0`
+const SLOT_LENGTH_MS: i64 = 1000;
+
+/// The test ledger doesn't charge fees
+const TEST_LEDGER_FEE: u64 = 0;
+
+fn bound_slot(bound: ValidityBound) -> i64 {
+    match bound {
+        ValidityBound::PosixMillis(posix) => posix.div_euclid(SLOT_LENGTH_MS),
+        ValidityBound::Slot(slot) => slot as i64,
+    }
+}
+
+/// Checks the slot of `current_time_secs` against `valid_range` as the ledger does, including the
+/// lower bound and excluding the upper
 fn check_time_valid(
-    valid_range: (Option<i64>, Option<i64>),
-    current_time: i64,
+    valid_range: &ValidityInterval,
+    current_time_secs: i64,
 ) -> Result<(), TestLCError> {
-    if let Some(lower) = valid_range.0 {
-        if current_time < lower * 1000 {
+    let current_slot = bound_slot(ValidityBound::PosixMillis(
+        current_time_secs.saturating_mul(1000),
+    ));
+    if let Some(lower) = valid_range.lower.map(bound_slot) {
+        if current_slot < lower {
             return Err(TestLCError::TxTooEarly);
         }
-    } else if let Some(upper) = valid_range.1 {
-        if current_time >= upper * 1000 {
+    }
+    if let Some(upper) = valid_range.upper.map(bound_slot) {
+        if current_slot >= upper {
             return Err(TestLCError::TxTooLate);
         }
     }
     Ok(())
 }
 
+struct ProcessedTx<Datum> {
+    tx_id: TxId,
+    spent_inputs: Vec<Output<Datum>>,
+    new_outputs: Vec<Output<Datum>>,
+    stake_updates: Vec<(StakeCredential, Option<StakeAccount>)>,
+    execution_costs: Vec<RedeemerCost>,
+}
+
+impl<Datum> ProcessedTx<Datum> {
+    fn summary(&self) -> LedgerClientResult<TxSummary> {
+        let inputs = self
+            .spent_inputs
+            .iter()
+            .map(|input| input.id().to_owned())
+            .collect();
+        let outputs = self
+            .new_outputs
+            .iter()
+            .map(|output| {
+                let owner = output
+                    .owner()
+                    .to_bech32()
+                    .map_err(|e| LedgerClientError::FailedToIssueTx(Box::new(e)))?;
+                Ok((owner, output.values().to_owned()))
+            })
+            .collect::<LedgerClientResult<Vec<_>>>()?;
+        let summary = TxSummary {
+            tx_id: self.tx_id.as_str(),
+            inputs,
+            outputs,
+            fee: TEST_LEDGER_FEE,
+        };
+        Ok(summary)
+    }
+}
+
 struct TxIdConstructionCtx {
     tx_hash: Vec<u8>,
     next_index: u64,
 }
 
 impl TxIdConstructionCtx {
-    pub fn new() -> Self {
-        let tx_hash = arbitrary_tx_id().to_vec();
+    pub fn new(tx_hash: Vec<u8>) -> Self {
         TxIdConstructionCtx {
             tx_hash,
             next_index: 0,
@@ -474,51 +908,68 @@ fn build_outputs<Datum>(
 ) -> Vec<Output<Datum>> {
     unbuilt_outputs
         .into_iter()
-        .map(|output| match output {
-            UnbuiltOutput::Wallet { owner, values } => {
-                let addr = Address::from_bech32(&owner).expect("Already validated");
-                new_wallet_output(&addr, &values, construction_ctx)
-            }
-            UnbuiltOutput::Validator {
-                script_address: owner,
-                values,
-                datum,
-            } => {
-                let addr = Address::from_bech32(&owner).expect("Already validated");
-                new_validator_output(&addr, &values, datum, construction_ctx)
+        .map(|output| {
+            let (output, reference_script) = match output {
+                UnbuiltOutput::Wallet {
+                    owner,
+                    values,
+                    reference_script,
+                } => {
+                    let addr = Address::from_bech32(&owner).expect("Already validated");
+                    let output = new_wallet_output(&addr, &values, construction_ctx);
+                    (output, reference_script)
+                }
+                UnbuiltOutput::Validator {
+                    script_address: owner,
+                    values,
+                    datum,
+                    reference_script,
+                } => {
+                    let addr = Address::from_bech32(&owner).expect("Already validated");
+                    let output = new_validator_output(&addr, &values, datum, construction_ctx);
+                    (output, reference_script)
+                }
+            };
+            if let Some(script) = reference_script {
+                output.with_reference_script(script)
+            } else {
+                output
             }
         })
         .collect()
 }
 
-fn spend_tx_context<Datum: Into<PlutusData> + Clone, Redeemer>(
-    tx: &UnbuiltTransaction<Datum, Redeemer>,
-    signer_address: &Address,
-    output: &Output<Datum>,
-) -> LedgerClientResult<TxContext> {
-    let id = output.id();
-    let out_ref = CtxOutputReference::new(id.tx_hash().to_vec(), id.index());
-    let purpose = CtxScriptPurpose::Spend(out_ref);
-    tx_context(tx, signer_address, purpose)
+/// Script waiting to be run by `process_tx`
+struct ScriptRun<'a, Datum, Redeemer> {
+    kind: RedeemerKind,
+    index: usize,
+    purpose: CtxScriptPurpose,
+    redeemer: &'a Redeemer,
+    script: RunnableScript<'a, Datum, Redeemer>,
 }
 
-fn mint_tx_context<Datum: Into<PlutusData> + Clone, Redeemer>(
-    tx: &UnbuiltTransaction<Datum, Redeemer>,
-    signer_address: &Address,
-    policy_id: &str,
-) -> LedgerClientResult<TxContext> {
-    let id = hex::decode(policy_id).map_err(|e| LedgerClientError::FailedToIssueTx(Box::new(e)))?;
-    let purpose = CtxScriptPurpose::Mint(id);
-    tx_context(tx, signer_address, purpose)
+enum RunnableScript<'a, Datum, Redeemer> {
+    Validator(&'a Datum, &'a dyn ValidatorCode<Datum, Redeemer>),
+    Policy(&'a dyn MintingPolicy<Redeemer>),
+    Staking(&'a dyn StakingValidator<Redeemer>),
 }
 
 fn tx_context<Datum: Into<PlutusData> + Clone, Redeemer>(
     tx: &UnbuiltTransaction<Datum, Redeemer>,
     signer_address: &Address,
     purpose: CtxScriptPurpose,
+    tx_hash: &[u8],
+    redeemers: &[(CtxScriptPurpose, PlutusData)],
 ) -> LedgerClientResult<TxContext> {
-    let lower = tx.valid_range.0.map(|n| (n * 1000, true));
-    let upper = tx.valid_range.1.map(|n| (n * 1000, false));
+    // Scripts see the POSIX time of the bounds' slots, in milliseconds
+    let lower = tx
+        .valid_range
+        .lower
+        .map(|bound| (bound_slot(bound) * SLOT_LENGTH_MS, true));
+    let upper = tx
+        .valid_range
+        .upper
+        .map(|bound| (bound_slot(bound) * SLOT_LENGTH_MS, false));
 
     let mut inputs = Vec::new();
     let mut outputs = Vec::new();
@@ -534,7 +985,7 @@ fn tx_context<Datum: Into<PlutusData> + Clone, Redeemer>(
             address,
             value,
             datum,
-            reference_script: None,
+            reference_script: reference_script_hash(utxo.reference_script())?,
         };
         inputs.push(input);
     }
@@ -552,14 +1003,32 @@ fn tx_context<Datum: Into<PlutusData> + Clone, Redeemer>(
             address,
             value,
             datum: CtxDatum::NoDatum,
-            reference_script: None,
+            reference_script: reference_script_hash(input.reference_script())?,
         };
         inputs.push(new_input)
     }
 
+    let mut reference_inputs = Vec::new();
+    for utxo in tx.reference_inputs.iter() {
+        let id = utxo.id();
+        let reference_input = Input {
+            transaction_id: id.tx_hash().to_vec(),
+            output_index: id.index(),
+            address: utxo.owner(),
+            value: CtxValue::from(utxo.values().to_owned()),
+            datum: utxo.typed_datum().into(),
+            reference_script: reference_script_hash(utxo.reference_script())?,
+        };
+        reference_inputs.push(reference_input);
+    }
+
     for output in tx.unbuilt_outputs.iter() {
         let new_output = match output {
-            UnbuiltOutput::Wallet { owner, values } => {
+            UnbuiltOutput::Wallet {
+                owner,
+                values,
+                reference_script,
+            } => {
                 let address = Address::from_bech32(owner)
                     .map_err(|e| LedgerClientError::FailedToIssueTx(Box::new(e)))?;
                 let value = CtxValue::from(values.to_owned());
@@ -567,13 +1036,14 @@ fn tx_context<Datum: Into<PlutusData> + Clone, Redeemer>(
                     address,
                     value,
                     datum: CtxDatum::NoDatum,
-                    reference_script: None,
+                    reference_script: reference_script_hash(reference_script.as_ref())?,
                 }
             }
             UnbuiltOutput::Validator {
                 script_address,
                 values,
                 datum,
+                reference_script,
             } => {
                 let address = Address::from_bech32(script_address)
                     .map_err(|e| LedgerClientError::FailedToIssueTx(Box::new(e)))?;
@@ -583,31 +1053,151 @@ fn tx_context<Datum: Into<PlutusData> + Clone, Redeemer>(
                     address,
                     value,
                     datum,
-                    reference_script: None,
+                    reference_script: reference_script_hash(reference_script.as_ref())?,
                 }
             }
         };
         outputs.push(new_output)
     }
 
+    let mut mint = CtxMintValue::default();
+    for (assets, _, policy) in tx.minting.iter() {
+        let policy_id = policy
+            .id()
+            .map_err(|e| LedgerClientError::FailedToIssueTx(Box::new(e)))?;
+        for (asset_name, amount) in assets {
+            mint.add(&policy_id, asset_name.as_deref().unwrap_or(""), *amount);
+        }
+    }
+
+    let dcert = tx
+        .certificates
+        .iter()
+        .map(|certificate| certificate.to_ctx())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| LedgerClientError::FailedToIssueTx(Box::new(e)))?;
+    let wdrl = tx
+        .withdrawals
+        .iter()
+        .map(|withdrawal| {
+            withdrawal
+                .witness
+                .credential()
+                .map(|credential| (credential, withdrawal.amount))
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| LedgerClientError::FailedToIssueTx(Box::new(e)))?;
+
     let signer = pub_key_hash_from_address_if_available(signer_address).ok_or(
         LedgerClientError::FailedToIssueTx(Box::new(TestLCError::InvalidAddress)),
     )?;
     let range = ValidRange { lower, upper };
 
-    // TODO: Extra Signatories, and Datums (they are already included in CTX Builder)
+    // TODO: Datums (they are already included in CTX Builder)
     let ctx = TxContext {
         purpose,
         signer,
         range,
         inputs,
+        reference_inputs,
         outputs,
-        extra_signatories: vec![],
+        fee: TEST_LEDGER_FEE,
+        mint,
+        dcert,
+        wdrl,
+        extra_signatories: tx.required_signers.clone(),
+        redeemers: redeemers.to_vec(),
         datums: vec![],
+        id: tx_hash.to_vec(),
     };
     Ok(ctx)
 }
 
+fn check_required_signers<Datum, Redeemer>(
+    tx: &UnbuiltTransaction<Datum, Redeemer>,
+    signer_address: &Address,
+    cosigners: &[PubKeyHash],
+) -> LedgerClientResult<()> {
+    let signer = pub_key_hash_from_address_if_available(signer_address);
+    for required in tx.required_signers() {
+        if signer.as_ref() != Some(required) && !cosigners.contains(required) {
+            return Err(LedgerClientError::FailedToIssueTx(Box::new(
+                TestLCError::MissingRequiredSigner(required.clone()),
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// `outputs` are all outputs of the tx, change first
+fn check_min_ada<Datum: Into<PlutusData> + Clone>(
+    outputs: &[UnbuiltOutput<Datum>],
+    calculator: &MinAdaCalculator,
+) -> LedgerClientResult<()> {
+    for (index, output) in outputs.iter().enumerate() {
+        let required = calculator.min_ada(output);
+        let provided = output.values().get(&PolicyId::Lovelace).unwrap_or_default();
+        if provided < required {
+            return Err(LedgerClientError::FailedToIssueTx(Box::new(
+                TestLCError::BelowMinAda {
+                    index,
+                    owner: output.owner().to_bech32().expect("Already validated"),
+                    provided,
+                    required,
+                },
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn registered_account(
+    account: Option<StakeAccount>,
+    credential: &StakeCredential,
+) -> LedgerClientResult<StakeAccount> {
+    account
+        .ok_or_else(|| TestLCError::StakeNotRegistered(credential.clone()))
+        .map_err(|e| LedgerClientError::FailedToIssueTx(Box::new(e)))
+}
+
+/// Checks a key witness, or hands back the script to run for a script witness
+fn check_stake_witness<'a, Redeemer>(
+    witness: &'a StakeWitness<Redeemer>,
+    signer_address: &Address,
+) -> LedgerClientResult<Option<(&'a Redeemer, &'a dyn StakingValidator<Redeemer>)>> {
+    match witness {
+        StakeWitness::Key(pkh) => {
+            if stake_key_hash_from_address_if_available(signer_address).as_ref() != Some(pkh) {
+                return Err(LedgerClientError::FailedToIssueTx(Box::new(
+                    TestLCError::MissingStakeWitness(StakeCredential::Key(pkh.clone())),
+                )));
+            }
+            Ok(None)
+        }
+        StakeWitness::Script {
+            redeemer,
+            validator,
+        } => Ok(Some((redeemer, validator.as_ref()))),
+    }
+}
+
+fn redeemer_cost(kind: RedeemerKind, index: usize, cost: ExecutionCost) -> RedeemerCost {
+    RedeemerCost {
+        kind,
+        index: index as u64,
+        cost,
+    }
+}
+
+fn reference_script_hash(
+    reference_script: Option<&ReferenceScript>,
+) -> LedgerClientResult<Option<Vec<u8>>> {
+    reference_script
+        .map(|script| script.hash())
+        .transpose()
+        .map_err(|e| LedgerClientError::FailedToIssueTx(Box::new(e)))
+}
+
 fn arbitrary_tx_id() -> [u8; 32] {
     rand::thread_rng().gen()
 }
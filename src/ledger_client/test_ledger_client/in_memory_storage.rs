@@ -1,18 +1,74 @@
-use crate::ledger_client::test_ledger_client::{TestLCError, TestLedgerStorage};
+use crate::ledger_client::test_ledger_client::{StakeAccount, TestLCError, TestLedgerStorage};
 use crate::ledger_client::LedgerClientError::FailedToIssueTx;
 use crate::ledger_client::{LedgerClientError, LedgerClientResult};
-use crate::output::Output;
+use crate::output::{Output, OutputId};
+use crate::transaction::{metadata::TransactionMetadata, staking::StakeCredential, TxId};
+use crate::PolicyId;
 use pallas_addresses::{Address, Network};
-use std::sync::{Arc, Mutex};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
 
 type MutableData<Datum> = Arc<Mutex<Vec<(Address, Output<Datum>)>>>;
+type AssetIndex<Datum> = Arc<Mutex<HashMap<String, Vec<Output<Datum>>>>>;
 
 #[derive(Debug)]
 pub struct InMemoryStorage<Datum> {
     pub signer: Address,
     pub outputs: MutableData<Datum>,
+    /// Outputs holding each policy id, so looking up the holder of an asset doesn't scan
+    /// `outputs`
+    pub asset_index: AssetIndex<Datum>,
     pub current_posix_time: Arc<Mutex<i64>>,
     pub block_length: i64,
+    pub metadata: Arc<Mutex<HashMap<String, TransactionMetadata>>>,
+    pub stake_accounts: Arc<Mutex<HashMap<StakeCredential, StakeAccount>>>,
+}
+
+impl<Datum: Clone> InMemoryStorage<Datum> {
+    pub fn new(
+        signer: Address,
+        outputs: Vec<(Address, Output<Datum>)>,
+        block_length: i64,
+        starting_time: i64,
+    ) -> Self {
+        let mut asset_index: HashMap<String, Vec<Output<Datum>>> = HashMap::new();
+        for (_, output) in &outputs {
+            for policy_id in policy_ids(output) {
+                asset_index
+                    .entry(policy_id)
+                    .or_default()
+                    .push(output.clone());
+            }
+        }
+        InMemoryStorage {
+            signer,
+            outputs: Arc::new(Mutex::new(outputs)),
+            asset_index: Arc::new(Mutex::new(asset_index)),
+            current_posix_time: Arc::new(Mutex::new(starting_time)),
+            block_length,
+            metadata: Default::default(),
+            stake_accounts: Default::default(),
+        }
+    }
+
+    fn lock_asset_index(
+        &self,
+    ) -> Result<MutexGuard<'_, HashMap<String, Vec<Output<Datum>>>>, TestLCError> {
+        self.asset_index
+            .lock()
+            .map_err(|e| TestLCError::Mutex(format! {"{e:?}"}))
+    }
+}
+
+fn policy_ids<Datum>(output: &Output<Datum>) -> Vec<String> {
+    let mut policy_ids: Vec<_> = output
+        .values()
+        .as_iter()
+        .map(|(policy_id, _)| policy_id.id())
+        .collect();
+    policy_ids.sort();
+    policy_ids.dedup();
+    policy_ids
 }
 
 #[async_trait::async_trait]
@@ -56,6 +112,36 @@ impl<Datum: Clone + Send + Sync + PartialEq> TestLedgerStorage<Datum> for InMemo
         Ok(outputs)
     }
 
+    async fn output_by_id(&self, id: &OutputId) -> LedgerClientResult<Option<Output<Datum>>> {
+        let output = self
+            .outputs
+            .lock()
+            .map_err(|e| TestLCError::Mutex(format! {"{e:?}"}))
+            .map_err(|e| LedgerClientError::FailedToRetrieveOutputWithId(id.clone(), Box::new(e)))?
+            .iter()
+            .find(|(_, o)| o.id() == id)
+            .map(|(_, o)| o.clone());
+        Ok(output)
+    }
+
+    async fn outputs_with_asset(&self, asset: &PolicyId) -> LedgerClientResult<Vec<Output<Datum>>> {
+        let outputs = self
+            .lock_asset_index()
+            .map_err(|e| {
+                LedgerClientError::FailedToRetrieveOutputsWithAsset(asset.clone(), Box::new(e))
+            })?
+            .get(&asset.id())
+            .map(|outputs| {
+                outputs
+                    .iter()
+                    .filter(|o| o.values().contains_asset(asset))
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default();
+        Ok(outputs)
+    }
+
     async fn remove_output(&self, output: &Output<Datum>) -> LedgerClientResult<()> {
         let mut ledger_utxos = self
             .outputs
@@ -72,6 +158,17 @@ impl<Datum: Clone + Send + Sync + PartialEq> TestLedgerStorage<Datum> for InMemo
                 )
             })?;
         ledger_utxos.remove(index);
+        let mut asset_index = self
+            .lock_asset_index()
+            .map_err(|e| FailedToIssueTx(Box::new(e)))?;
+        for policy_id in policy_ids(output) {
+            if let Some(holders) = asset_index.get_mut(&policy_id) {
+                holders.retain(|holder| holder != output);
+                if holders.is_empty() {
+                    asset_index.remove(&policy_id);
+                }
+            }
+        }
         Ok(())
     }
 
@@ -82,6 +179,15 @@ impl<Datum: Clone + Send + Sync + PartialEq> TestLedgerStorage<Datum> for InMemo
             .map_err(|e| TestLCError::Mutex(format! {"{e:?}"}))
             .map_err(|e| FailedToIssueTx(Box::new(e)))?;
         ledger_utxos.push((output.owner(), output.clone()));
+        let mut asset_index = self
+            .lock_asset_index()
+            .map_err(|e| FailedToIssueTx(Box::new(e)))?;
+        for policy_id in policy_ids(output) {
+            asset_index
+                .entry(policy_id)
+                .or_default()
+                .push(output.clone());
+        }
         Ok(())
     }
 
@@ -111,4 +217,60 @@ impl<Datum: Clone + Send + Sync + PartialEq> TestLedgerStorage<Datum> for InMemo
     async fn network(&self) -> LedgerClientResult<Network> {
         Ok(Network::Testnet)
     }
+
+    async fn add_metadata(
+        &self,
+        tx_id: &TxId,
+        metadata: &TransactionMetadata,
+    ) -> LedgerClientResult<()> {
+        self.metadata
+            .lock()
+            .map_err(|e| TestLCError::Mutex(format! {"{e:?}"}))
+            .map_err(|e| FailedToIssueTx(Box::new(e)))?
+            .insert(tx_id.as_str(), metadata.clone());
+        Ok(())
+    }
+
+    async fn metadata(&self, tx_id: &TxId) -> LedgerClientResult<Option<TransactionMetadata>> {
+        let metadata = self
+            .metadata
+            .lock()
+            .map_err(|e| TestLCError::Mutex(format! {"{e:?}"}))
+            .map_err(|e| FailedToIssueTx(Box::new(e)))?
+            .get(&tx_id.as_str())
+            .cloned();
+        Ok(metadata)
+    }
+
+    async fn stake_account(
+        &self,
+        credential: &StakeCredential,
+    ) -> LedgerClientResult<Option<StakeAccount>> {
+        let account = self
+            .stake_accounts
+            .lock()
+            .map_err(|e| TestLCError::Mutex(format! {"{e:?}"}))
+            .map_err(|e| FailedToIssueTx(Box::new(e)))?
+            .get(credential)
+            .cloned();
+        Ok(account)
+    }
+
+    async fn set_stake_account(
+        &self,
+        credential: &StakeCredential,
+        account: Option<StakeAccount>,
+    ) -> LedgerClientResult<()> {
+        let mut stake_accounts = self
+            .stake_accounts
+            .lock()
+            .map_err(|e| TestLCError::Mutex(format! {"{e:?}"}))
+            .map_err(|e| FailedToIssueTx(Box::new(e)))?;
+        if let Some(account) = account {
+            stake_accounts.insert(credential.clone(), account);
+        } else {
+            stake_accounts.remove(credential);
+        }
+        Ok(())
+    }
 }
@@ -13,8 +13,13 @@ use thiserror::Error;
 use crate::ledger_client::test_ledger_client::arbitrary_tx_id;
 use crate::output::OutputId;
 use crate::scripts::raw_validator_script::plutus_data::PlutusData;
+use crate::scripts::ReferenceScript;
+use crate::transaction::{metadata::TransactionMetadata, staking::StakeCredential, TxId};
 use crate::{
-    ledger_client::{test_ledger_client::TestLedgerStorage, LedgerClientError, LedgerClientResult},
+    ledger_client::{
+        test_ledger_client::{StakeAccount, TestLedgerStorage},
+        LedgerClientError, LedgerClientResult,
+    },
     output::Output,
     values::Values,
     PolicyId,
@@ -28,6 +33,10 @@ pub(crate) struct LedgerData {
     outputs: Vec<LDOutput>,
     current_time: i64,
     block_length: i64,
+    #[serde(default)]
+    metadata: HashMap<String, TransactionMetadata>,
+    #[serde(default)]
+    stake_accounts: Vec<(StakeCredential, StakeAccount)>,
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
@@ -36,6 +45,8 @@ struct LDOutput {
     owner: String,
     values: Values,
     datum: Option<PlutusData>,
+    #[serde(default)]
+    reference_script: Option<ReferenceScript>,
 }
 
 impl<Datum: Clone + Into<PlutusData>> From<Output<Datum>> for LDOutput {
@@ -45,6 +56,7 @@ impl<Datum: Clone + Into<PlutusData>> From<Output<Datum>> for LDOutput {
             owner: output.owner().to_bech32().expect("Already validated"),
             values: output.values().clone(),
             datum: output.datum_plutus_data(),
+            reference_script: output.reference_script().cloned(),
         }
     }
 }
@@ -56,14 +68,20 @@ impl<Datum> From<LDOutput> for Output<Datum> {
             owner,
             values,
             datum,
+            reference_script,
         } = value;
         let tx_hash = id.tx_hash().to_owned();
         let index = id.index();
         let owner = Address::from_bech32(&owner).unwrap(); // TODO: Unwrap
-        if let Some(datum) = datum {
+        let output = if let Some(datum) = datum {
             Output::new_untyped_validator(tx_hash, index, owner, values, datum)
         } else {
             Output::new_wallet(tx_hash, index, owner, values)
+        };
+        if let Some(script) = reference_script {
+            output.with_reference_script(script)
+        } else {
+            output
         }
     }
 }
@@ -89,6 +107,8 @@ impl LedgerData {
             outputs,
             current_time: 0,
             block_length,
+            metadata: HashMap::new(),
+            stake_accounts: Vec::new(),
         }
     }
 
@@ -201,6 +221,29 @@ where
         Ok(())
     }
 
+    fn add_metadata_for(&self, tx_id: &TxId, metadata: &TransactionMetadata) {
+        let path_ref: &Path = self.dir.as_ref();
+        let path = path_ref.to_owned().join(DATA);
+        let mut data = self.get_data();
+        data.metadata.insert(tx_id.as_str(), metadata.clone());
+        let serialized = serde_json::to_string(&data).unwrap();
+        let mut file = File::create(path).unwrap();
+        file.write_all(&serialized.into_bytes()).unwrap();
+    }
+
+    fn update_stake_account(&self, credential: &StakeCredential, account: Option<StakeAccount>) {
+        let path_ref: &Path = self.dir.as_ref();
+        let path = path_ref.to_owned().join(DATA);
+        let mut data = self.get_data();
+        data.stake_accounts.retain(|(c, _)| c != credential);
+        if let Some(account) = account {
+            data.stake_accounts.push((credential.clone(), account));
+        }
+        let serialized = serde_json::to_string(&data).unwrap();
+        let mut file = File::create(path).unwrap();
+        file.write_all(&serialized.into_bytes()).unwrap();
+    }
+
     pub fn add_new_signer(&self, name: &str, address: &Address, starting_amount: u64) {
         let path_ref: &Path = self.dir.as_ref();
         let path = path_ref.to_owned().join(DATA);
@@ -274,6 +317,31 @@ where
         Ok(outputs)
     }
 
+    async fn output_by_id(&self, id: &OutputId) -> LedgerClientResult<Option<Output<Datum>>> {
+        let output = self
+            .get_data()
+            .outputs
+            .into_iter()
+            .find(|o| &o.id == id)
+            .map(Into::<Output<Datum>>::into)
+            .map(|output| output.with_typed_datum_if_possible());
+        Ok(output)
+    }
+
+    /// Scans the outputs rather than keeping an index, since every call reads and parses the
+    /// whole data file anyway
+    async fn outputs_with_asset(&self, asset: &PolicyId) -> LedgerClientResult<Vec<Output<Datum>>> {
+        let outputs = self
+            .get_data()
+            .outputs
+            .into_iter()
+            .filter(|o| o.values.contains_asset(asset))
+            .map(Into::<Output<Datum>>::into)
+            .map(|output| output.with_typed_datum_if_possible())
+            .collect();
+        Ok(outputs)
+    }
+
     async fn remove_output(&self, output: &Output<Datum>) -> LedgerClientResult<()> {
         let mut ledger_utxos = self.get_data().outputs;
         let sanitized_output = output.clone().into();
@@ -317,6 +385,42 @@ where
     async fn network(&self) -> LedgerClientResult<Network> {
         Ok(Network::Testnet)
     }
+
+    async fn add_metadata(
+        &self,
+        tx_id: &TxId,
+        metadata: &TransactionMetadata,
+    ) -> LedgerClientResult<()> {
+        self.add_metadata_for(tx_id, metadata);
+        Ok(())
+    }
+
+    async fn metadata(&self, tx_id: &TxId) -> LedgerClientResult<Option<TransactionMetadata>> {
+        let metadata = self.get_data().metadata.get(&tx_id.as_str()).cloned();
+        Ok(metadata)
+    }
+
+    async fn stake_account(
+        &self,
+        credential: &StakeCredential,
+    ) -> LedgerClientResult<Option<StakeAccount>> {
+        let account = self
+            .get_data()
+            .stake_accounts
+            .into_iter()
+            .find(|(c, _)| c == credential)
+            .map(|(_, account)| account);
+        Ok(account)
+    }
+
+    async fn set_stake_account(
+        &self,
+        credential: &StakeCredential,
+        account: Option<StakeAccount>,
+    ) -> LedgerClientResult<()> {
+        self.update_stake_account(credential, account);
+        Ok(())
+    }
 }
 
 #[cfg(test)]
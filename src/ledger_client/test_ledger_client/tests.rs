@@ -1,16 +1,32 @@
 #![allow(non_snake_case)]
 
 use super::*;
-use crate::scripts::{ExecutionCost, MintingPolicy, ScriptError, ScriptResult, ValidatorCode};
-use crate::transaction::TransactionVersion;
+use crate::scripts::{
+    context::{
+        pub_key_hash_from_address_if_available, stake_key_hash_from_address_if_available,
+        PubKeyHash,
+    },
+    raw_script::PlutusScriptFile,
+    raw_validator_script::RawPlutusValidator,
+    ExecutionCost, MintingPolicy, ReferenceScript, ScriptError, ScriptResult, StakingValidator,
+    ValidatorCode,
+};
+use crate::transaction::{
+    metadata::{cip20_message, CIP20_LABEL},
+    staking::{StakeWitness, STAKE_KEY_DEPOSIT},
+    validity::ValidityInterval,
+    RedeemerKind, TransactionVersion, TxActions,
+};
 use crate::{
+    error::Error,
     ledger_client::{
         test_ledger_client::{local_persisted_storage::starting_output, TestLedgerClient},
         LedgerClient,
     },
-    output::UnbuiltOutput,
+    output::{min_ada::MinAdaCalculator, OutputId, UnbuiltOutput},
     PolicyId, UnbuiltTransaction,
 };
+use std::path::PathBuf;
 
 const ALICE: &str = "addr_test1qrmezjhpelwzvz83wjl0e6mx766de7j3nksu2338s00yzx870xyxfa97xyz2zn5rknyntu5g0c66s7ktjnx0p6f0an6s3dyxwr";
 const BOB: &str = "addr_test1qzvrhz9v6lwcr26a52y8mmk2nzq37lky68359keq3dgth4lkzpnnjv8vf98m20lhqdzl60mcftq7r2lc4xtcsv0w6xjstag0ua";
@@ -69,7 +85,13 @@ async fn issue_transfer() {
         unbuilt_outputs: vec![new_output],
         minting: Default::default(),
         specific_wallet_inputs: vec![],
-        valid_range: (None, None),
+        reference_inputs: vec![],
+        metadata: Default::default(),
+        certificates: vec![],
+        withdrawals: vec![],
+        required_signers: vec![],
+        min_ada_top_up: false,
+        valid_range: ValidityInterval::default(),
     };
     record.issue(tx).await.unwrap();
     let actual_bob = record
@@ -109,7 +131,13 @@ async fn issuing_tx_advances_time_by_block_length() {
         unbuilt_outputs: vec![],
         minting: Default::default(),
         specific_wallet_inputs: vec![],
-        valid_range: (None, None),
+        reference_inputs: vec![],
+        metadata: Default::default(),
+        certificates: vec![],
+        withdrawals: vec![],
+        required_signers: vec![],
+        min_ada_top_up: false,
+        valid_range: ValidityInterval::default(),
     };
     record.issue(tx).await.unwrap();
     let expected = starting_time + BLOCK_LENGTH;
@@ -135,7 +163,13 @@ async fn errors_if_spending_more_than_you_own() {
         unbuilt_outputs: vec![new_output],
         minting: Default::default(),
         specific_wallet_inputs: vec![],
-        valid_range: (None, None),
+        reference_inputs: vec![],
+        metadata: Default::default(),
+        certificates: vec![],
+        withdrawals: vec![],
+        required_signers: vec![],
+        min_ada_top_up: false,
+        valid_range: ValidityInterval::default(),
     };
     let error = record.issue(tx).await.unwrap_err();
 
@@ -167,7 +201,13 @@ async fn cannot_transfer_before_valid_range() {
         unbuilt_outputs: vec![new_output],
         minting: Default::default(),
         specific_wallet_inputs: vec![],
-        valid_range: (Some(valid_time), None),
+        reference_inputs: vec![],
+        metadata: Default::default(),
+        certificates: vec![],
+        withdrawals: vec![],
+        required_signers: vec![],
+        min_ada_top_up: false,
+        valid_range: ValidityInterval::posix_secs(Some(valid_time), None),
     };
     let error = record.issue(tx).await.unwrap_err();
 
@@ -199,13 +239,100 @@ async fn cannot_transfer_after_valid_range() {
         unbuilt_outputs: vec![new_output],
         minting: Default::default(),
         specific_wallet_inputs: vec![],
-        valid_range: (None, Some(valid_time)),
+        reference_inputs: vec![],
+        metadata: Default::default(),
+        certificates: vec![],
+        withdrawals: vec![],
+        required_signers: vec![],
+        min_ada_top_up: false,
+        valid_range: ValidityInterval::posix_secs(None, Some(valid_time)),
     };
     let error = record.issue(tx).await.unwrap_err();
 
     assert!(matches!(error, LedgerClientError::FailedToIssueTx(_),));
 }
 
+#[tokio::test]
+async fn can_transfer_with_upper_bound_beyond_millis_range() {
+    let sender = Address::from_bech32(ALICE).unwrap();
+    let output = starting_output::<()>(&sender, 10_000_000);
+    let outputs = vec![(sender.clone(), output)];
+    let record: TestLedgerClient<(), (), _> =
+        TestLedgerClient::new_in_memory(sender.clone(), outputs, BLOCK_LENGTH, 0);
+    record.set_current_time_secs(10_000).await.unwrap();
+
+    let recipient = Address::from_bech32(BOB).unwrap();
+    let tx: UnbuiltTransaction<(), ()> = TxActions::v2()
+        .with_transfer(3_000_000, recipient, PolicyId::Lovelace)
+        .with_valid_range_secs(Some(5), Some(i64::MAX))
+        .to_unbuilt_tx()
+        .unwrap();
+
+    record.issue(tx).await.unwrap();
+}
+
+#[tokio::test]
+async fn cannot_transfer_after_valid_range_with_lower_bound() {
+    let sender = Address::from_bech32(ALICE).unwrap();
+    let output = starting_output::<()>(&sender, 10_000_000);
+    let outputs = vec![(sender.clone(), output)];
+    let record: TestLedgerClient<(), (), _> =
+        TestLedgerClient::new_in_memory(sender.clone(), outputs, BLOCK_LENGTH, 0);
+    record.set_current_time_secs(10_000).await.unwrap();
+
+    let recipient = Address::from_bech32(BOB).unwrap();
+    let tx: UnbuiltTransaction<(), ()> = TxActions::v2()
+        .with_transfer(3_000_000, recipient, PolicyId::Lovelace)
+        .with_valid_range_secs(Some(5), Some(10))
+        .to_unbuilt_tx()
+        .unwrap();
+    let error = record.issue(tx).await.unwrap_err();
+
+    assert!(matches!(error, LedgerClientError::FailedToIssueTx(_)));
+}
+
+#[test]
+fn check_time_valid__lower_bound_inclusive_upper_bound_exclusive() {
+    let interval = ValidityInterval::posix_millis(Some(10_000), Some(20_000));
+
+    assert!(matches!(
+        check_time_valid(&interval, 9),
+        Err(TestLCError::TxTooEarly)
+    ));
+    assert!(check_time_valid(&interval, 10).is_ok());
+    assert!(check_time_valid(&interval, 19).is_ok());
+    assert!(matches!(
+        check_time_valid(&interval, 20),
+        Err(TestLCError::TxTooLate)
+    ));
+}
+
+#[test]
+fn check_time_valid__millis_within_a_slot_round_down_to_it() {
+    let interval = ValidityInterval::posix_millis(Some(10_999), Some(20_999));
+
+    assert!(check_time_valid(&interval, 10).is_ok());
+    assert!(matches!(
+        check_time_valid(&interval, 20),
+        Err(TestLCError::TxTooLate)
+    ));
+}
+
+#[test]
+fn check_time_valid__slot_bounds() {
+    let interval = ValidityInterval::slots(Some(10), Some(20));
+
+    assert!(matches!(
+        check_time_valid(&interval, 9),
+        Err(TestLCError::TxTooEarly)
+    ));
+    assert!(check_time_valid(&interval, 10).is_ok());
+    assert!(matches!(
+        check_time_valid(&interval, 20),
+        Err(TestLCError::TxTooLate)
+    ));
+}
+
 #[derive(Clone, Copy)]
 struct AlwaysTrueFakeValidator;
 
@@ -251,7 +378,13 @@ async fn redeeming_datum() {
         unbuilt_outputs: vec![new_output],
         minting: Default::default(),
         specific_wallet_inputs: vec![],
-        valid_range: (None, None),
+        reference_inputs: vec![],
+        metadata: Default::default(),
+        certificates: vec![],
+        withdrawals: vec![],
+        required_signers: vec![],
+        min_ada_top_up: false,
+        valid_range: ValidityInterval::default(),
     };
     record.issue(tx).await.unwrap();
 
@@ -282,7 +415,13 @@ async fn redeeming_datum() {
         unbuilt_outputs: vec![],
         minting: Default::default(),
         specific_wallet_inputs: vec![],
-        valid_range: (None, None),
+        reference_inputs: vec![],
+        metadata: Default::default(),
+        certificates: vec![],
+        withdrawals: vec![],
+        required_signers: vec![],
+        min_ada_top_up: false,
+        valid_range: ValidityInterval::default(),
     };
 
     record.issue(tx).await.unwrap();
@@ -345,7 +484,13 @@ async fn failing_script_will_not_redeem() {
         unbuilt_outputs: vec![new_output],
         minting: Default::default(),
         specific_wallet_inputs: vec![],
-        valid_range: (None, None),
+        reference_inputs: vec![],
+        metadata: Default::default(),
+        certificates: vec![],
+        withdrawals: vec![],
+        required_signers: vec![],
+        min_ada_top_up: false,
+        valid_range: ValidityInterval::default(),
     };
     record.issue(tx).await.unwrap();
 
@@ -376,7 +521,13 @@ async fn failing_script_will_not_redeem() {
         unbuilt_outputs: vec![],
         minting: Default::default(),
         specific_wallet_inputs: vec![],
-        valid_range: (None, None),
+        reference_inputs: vec![],
+        metadata: Default::default(),
+        certificates: vec![],
+        withdrawals: vec![],
+        required_signers: vec![],
+        min_ada_top_up: false,
+        valid_range: ValidityInterval::default(),
     };
 
     record.issue(tx).await.unwrap_err();
@@ -407,7 +558,13 @@ async fn cannot_redeem_datum_twice() {
         unbuilt_outputs: vec![new_output],
         minting: Default::default(),
         specific_wallet_inputs: vec![],
-        valid_range: (None, None),
+        reference_inputs: vec![],
+        metadata: Default::default(),
+        certificates: vec![],
+        withdrawals: vec![],
+        required_signers: vec![],
+        min_ada_top_up: false,
+        valid_range: ValidityInterval::default(),
     };
     record.issue(tx).await.unwrap();
 
@@ -441,7 +598,13 @@ async fn cannot_redeem_datum_twice() {
         unbuilt_outputs: vec![],
         minting: Default::default(),
         specific_wallet_inputs: vec![],
-        valid_range: (None, None),
+        reference_inputs: vec![],
+        metadata: Default::default(),
+        certificates: vec![],
+        withdrawals: vec![],
+        required_signers: vec![],
+        min_ada_top_up: false,
+        valid_range: ValidityInterval::default(),
     };
 
     // Then should error
@@ -483,9 +646,15 @@ async fn mint_always_true() {
         script_version: TransactionVersion::V2,
         script_inputs: vec![],
         unbuilt_outputs: vec![],
-        minting: vec![(minting_amount, None, (), script_box)],
+        minting: vec![(vec![(None, minting_amount as i64)], (), script_box)],
         specific_wallet_inputs: vec![],
-        valid_range: (None, None),
+        reference_inputs: vec![],
+        metadata: Default::default(),
+        certificates: vec![],
+        withdrawals: vec![],
+        required_signers: vec![],
+        min_ada_top_up: false,
+        valid_range: ValidityInterval::default(),
     };
     record.issue(tx).await.unwrap();
 
@@ -531,9 +700,15 @@ async fn mint_always_fails_errors() {
         script_version: TransactionVersion::V2,
         script_inputs: vec![],
         unbuilt_outputs: vec![],
-        minting: vec![(minting_amount, None, (), script_box)],
+        minting: vec![(vec![(None, minting_amount as i64)], (), script_box)],
         specific_wallet_inputs: vec![],
-        valid_range: (None, None),
+        reference_inputs: vec![],
+        metadata: Default::default(),
+        certificates: vec![],
+        withdrawals: vec![],
+        required_signers: vec![],
+        min_ada_top_up: false,
+        valid_range: ValidityInterval::default(),
     };
     record.issue(tx).await.unwrap_err();
 
@@ -604,9 +779,19 @@ async fn spends_specific_script_value() {
         script_version: TransactionVersion::V2,
         script_inputs: vec![redeem_info],
         unbuilt_outputs: vec![],
-        minting: vec![(minting_amount, asset_name.clone(), (), script_box)],
+        minting: vec![(
+            vec![(asset_name.clone(), minting_amount as i64)],
+            (),
+            script_box,
+        )],
         specific_wallet_inputs: vec![],
-        valid_range: (None, None),
+        reference_inputs: vec![],
+        metadata: Default::default(),
+        certificates: vec![],
+        withdrawals: vec![],
+        required_signers: vec![],
+        min_ada_top_up: false,
+        valid_range: ValidityInterval::default(),
     };
     record.issue(tx).await.unwrap();
 
@@ -616,3 +801,1280 @@ async fn spends_specific_script_value() {
         .unwrap();
     assert_eq!(alice_balance, minting_amount);
 }
+
+pub struct RequiresBurnPolicy;
+
+impl MintingPolicy<()> for RequiresBurnPolicy {
+    fn execute(&self, _redeemer: (), ctx: TxContext) -> ScriptResult<ExecutionCost> {
+        let id = self.id()?;
+        let burned = ctx
+            .mint
+            .inner
+            .get(&id)
+            .and_then(|assets| assets.get(""))
+            .map(|amt| *amt < 0)
+            .unwrap_or(false);
+        if burned {
+            Ok(ExecutionCost::default())
+        } else {
+            Err(ScriptError::FailedToExecute("no burn found".to_string()))
+        }
+    }
+
+    fn id(&self) -> ScriptResult<String> {
+        Ok(hex::encode(vec![4, 4, 4, 4, 4]))
+    }
+
+    fn script_hex(&self) -> ScriptResult<String> {
+        todo!()
+    }
+}
+
+#[tokio::test]
+async fn burn_removes_tokens_and_keeps_ada() {
+    // given
+    let sender = Address::from_bech32(ALICE).unwrap();
+    let starting_amount = 10_000_000;
+    let starting_tokens = 5;
+    let burn_amount = 2;
+
+    let policy = RequiresBurnPolicy;
+    let id = policy.id().unwrap();
+    let token = PolicyId::NativeToken(id, None);
+    let mut values = Values::default();
+    values.add_one_value(&PolicyId::Lovelace, starting_amount);
+    values.add_one_value(&token, starting_tokens);
+    let output = Output::new_wallet(vec![1, 2, 3, 4], 0, sender.clone(), values);
+    let outputs = vec![(sender.clone(), output)];
+    let record: TestLedgerClient<(), (), _> =
+        TestLedgerClient::new_in_memory(sender.clone(), outputs, BLOCK_LENGTH, 0);
+
+    // when
+    let tx: UnbuiltTransaction<(), ()> = TxActions::v2()
+        .with_burn(burn_amount, None, (), Box::new(policy))
+        .to_unbuilt_tx()
+        .unwrap();
+    record.issue(tx).await.unwrap();
+
+    // then
+    let token_balance = record.balance_at_address(&sender, &token).await.unwrap();
+    assert_eq!(token_balance, starting_tokens - burn_amount);
+    let ada_balance = record
+        .balance_at_address(&sender, &PolicyId::Lovelace)
+        .await
+        .unwrap();
+    assert_eq!(ada_balance, starting_amount);
+}
+
+#[tokio::test]
+async fn burn_more_than_held_errors() {
+    // given
+    let sender = Address::from_bech32(ALICE).unwrap();
+    let starting_amount = 10_000_000;
+    let output = starting_output::<()>(&sender, starting_amount);
+    let outputs = vec![(sender.clone(), output)];
+    let record: TestLedgerClient<(), (), _> =
+        TestLedgerClient::new_in_memory(sender.clone(), outputs, BLOCK_LENGTH, 0);
+
+    // when
+    let tx: UnbuiltTransaction<(), ()> = TxActions::v2()
+        .with_burn(1, None, (), Box::new(RequiresBurnPolicy))
+        .to_unbuilt_tx()
+        .unwrap();
+    let res = record.issue(tx).await;
+
+    // then
+    assert!(res.is_err());
+}
+
+#[test]
+fn mint_or_burn_amount_beyond_i64_errors() {
+    // given
+    let amount = i64::MAX as u64 + 1;
+
+    // when
+    let mint = TxActions::<(), ()>::v2()
+        .with_mint(amount, None, (), Box::new(AlwaysTruePolicy))
+        .to_unbuilt_tx();
+    let burn = TxActions::<(), ()>::v2()
+        .with_burn(amount, None, (), Box::new(AlwaysTruePolicy))
+        .to_unbuilt_tx();
+
+    // then
+    assert!(matches!(mint, Err(Error::MintAmountOutOfRange(a)) if a == amount));
+    assert!(matches!(burn, Err(Error::MintAmountOutOfRange(a)) if a == amount));
+}
+
+pub struct CountingPolicy {
+    runs: Arc<Mutex<Vec<usize>>>,
+}
+
+impl MintingPolicy<()> for CountingPolicy {
+    fn execute(&self, _redeemer: (), ctx: TxContext) -> ScriptResult<ExecutionCost> {
+        let id = self.id()?;
+        let asset_count = ctx.mint.inner.get(&id).map(|a| a.len()).unwrap_or(0);
+        self.runs.lock().unwrap().push(asset_count);
+        Ok(ExecutionCost::default())
+    }
+
+    fn id(&self) -> ScriptResult<String> {
+        Ok(hex::encode(vec![5, 5, 5, 5, 5]))
+    }
+
+    fn script_hex(&self) -> ScriptResult<String> {
+        todo!()
+    }
+}
+
+#[tokio::test]
+async fn mint_assets_runs_policy_once_with_full_mint() {
+    // given
+    let sender = Address::from_bech32(ALICE).unwrap();
+    let starting_amount = 10_000_000;
+    let output = starting_output::<()>(&sender, starting_amount);
+    let outputs = vec![(sender.clone(), output)];
+    let record: TestLedgerClient<(), (), _> =
+        TestLedgerClient::new_in_memory(sender.clone(), outputs, BLOCK_LENGTH, 0);
+    let runs = Arc::new(Mutex::new(Vec::new()));
+    let policy = CountingPolicy { runs: runs.clone() };
+    let id = policy.id().unwrap();
+    let reference_name = Some("ref".to_string());
+    let user_name = Some("user".to_string());
+
+    // when
+    let tx: UnbuiltTransaction<(), ()> = TxActions::v2()
+        .with_mint_assets(
+            vec![(reference_name.clone(), 1), (user_name.clone(), 10)],
+            (),
+            Box::new(policy),
+        )
+        .to_unbuilt_tx()
+        .unwrap();
+    record.issue(tx).await.unwrap();
+
+    // then
+    assert_eq!(*runs.lock().unwrap(), vec![2]);
+    let reference_balance = record
+        .balance_at_address(&sender, &PolicyId::NativeToken(id.clone(), reference_name))
+        .await
+        .unwrap();
+    assert_eq!(reference_balance, 1);
+    let user_balance = record
+        .balance_at_address(&sender, &PolicyId::NativeToken(id, user_name))
+        .await
+        .unwrap();
+    assert_eq!(user_balance, 10);
+}
+
+struct RequiresReferenceInputValidator {
+    reference_tx_hash: Vec<u8>,
+}
+
+impl ValidatorCode<(), ()> for RequiresReferenceInputValidator {
+    fn execute(&self, _datum: (), _redeemer: (), ctx: TxContext) -> ScriptResult<ExecutionCost> {
+        if ctx
+            .reference_inputs
+            .iter()
+            .any(|input| input.transaction_id == self.reference_tx_hash)
+        {
+            Ok(ExecutionCost::default())
+        } else {
+            Err(ScriptError::FailedToExecute(
+                "reference input not found".to_string(),
+            ))
+        }
+    }
+
+    fn address(&self, _network: Network) -> ScriptResult<Address> {
+        Ok(
+            Address::from_bech32("addr_test1wrme5jjggy97th309h2dwpv57wsphxskuc8jkw00c2kn47gu8mkzu")
+                .unwrap(),
+        )
+    }
+
+    fn script_hex(&self) -> ScriptResult<String> {
+        todo!()
+    }
+}
+
+#[tokio::test]
+async fn reference_input_is_visible_to_script_but_not_spent() {
+    // given
+    let sender = Address::from_bech32(ALICE).unwrap();
+    let oracle_address = Address::from_bech32(BOB).unwrap();
+    let starting_amount = 10_000_000;
+    let locking_amount = 3_000_000;
+
+    let mut oracle_values = Values::default();
+    oracle_values.add_one_value(&PolicyId::Lovelace, 2_000_000);
+    let oracle_output = Output::new_validator(
+        vec![9, 9, 9, 9],
+        0,
+        oracle_address.clone(),
+        oracle_values,
+        (),
+    );
+
+    let validator = RequiresReferenceInputValidator {
+        reference_tx_hash: oracle_output.id().tx_hash().to_vec(),
+    };
+    let network = Network::Testnet;
+    let script_address = validator.address(network).unwrap();
+    let mut locked_values = Values::default();
+    locked_values.add_one_value(&PolicyId::Lovelace, locking_amount);
+    let locked_output = Output::new_validator(
+        vec![1, 2, 3, 4],
+        0,
+        script_address.clone(),
+        locked_values,
+        (),
+    );
+
+    let outputs = vec![
+        (
+            sender.clone(),
+            starting_output::<()>(&sender, starting_amount),
+        ),
+        (oracle_address.clone(), oracle_output.clone()),
+        (script_address.clone(), locked_output.clone()),
+    ];
+    let record: TestLedgerClient<(), (), _> =
+        TestLedgerClient::new_in_memory(sender.clone(), outputs, BLOCK_LENGTH, 0);
+
+    // when
+    let script_box: Box<dyn ValidatorCode<(), ()>> = Box::new(validator);
+    let tx: UnbuiltTransaction<(), ()> = UnbuiltTransaction {
+        script_version: TransactionVersion::V2,
+        script_inputs: vec![(locked_output, (), script_box)],
+        unbuilt_outputs: vec![],
+        minting: Default::default(),
+        specific_wallet_inputs: vec![],
+        reference_inputs: vec![oracle_output.clone()],
+        metadata: Default::default(),
+        certificates: vec![],
+        withdrawals: vec![],
+        required_signers: vec![],
+        min_ada_top_up: false,
+        valid_range: ValidityInterval::default(),
+    };
+    record.issue(tx).await.unwrap();
+
+    // then
+    let alice_balance = record
+        .balance_at_address(&sender, &PolicyId::Lovelace)
+        .await
+        .unwrap();
+    assert_eq!(alice_balance, starting_amount + locking_amount);
+    let oracle_outputs = record
+        .all_outputs_at_address(&oracle_address)
+        .await
+        .unwrap();
+    assert_eq!(oracle_outputs, vec![oracle_output]);
+}
+
+struct CtxRecordingPolicy {
+    seen: Arc<Mutex<Option<TxContext>>>,
+}
+
+impl MintingPolicy<()> for CtxRecordingPolicy {
+    fn execute(&self, _redeemer: (), ctx: TxContext) -> ScriptResult<ExecutionCost> {
+        *self.seen.lock().unwrap() = Some(ctx);
+        Ok(ExecutionCost::default())
+    }
+
+    fn id(&self) -> ScriptResult<String> {
+        Ok(hex::encode(vec![6, 6, 6, 6, 6]))
+    }
+
+    fn script_hex(&self) -> ScriptResult<String> {
+        todo!()
+    }
+}
+
+#[tokio::test]
+async fn script_sees_tx_id_fee_and_redeemers() {
+    // given
+    let sender = Address::from_bech32(ALICE).unwrap();
+    let starting_amount = 10_000_000;
+    let output = starting_output::<()>(&sender, starting_amount);
+    let outputs = vec![(sender.clone(), output)];
+    let record: TestLedgerClient<(), (), _> =
+        TestLedgerClient::new_in_memory(sender.clone(), outputs, BLOCK_LENGTH, 0);
+    let seen = Arc::new(Mutex::new(None));
+    let policy = CtxRecordingPolicy { seen: seen.clone() };
+    let policy_id = policy.id().unwrap();
+
+    // when
+    let tx: UnbuiltTransaction<(), ()> = TxActions::v2()
+        .with_mint(1, Some("token".to_string()), (), Box::new(policy))
+        .to_unbuilt_tx()
+        .unwrap();
+    let tx_id = record.issue(tx).await.unwrap();
+
+    // then
+    let ctx = seen.lock().unwrap().take().unwrap();
+    assert_eq!(ctx.id, hex::decode(tx_id.as_str()).unwrap());
+    assert_eq!(ctx.fee, 0);
+    assert_eq!(ctx.redeemers.len(), 1);
+    let (purpose, redeemer) = &ctx.redeemers[0];
+    assert!(
+        matches!(purpose, CtxScriptPurpose::Mint(id) if *id == hex::decode(&policy_id).unwrap())
+    );
+    assert_eq!(*redeemer, PlutusData::from(()));
+    let new_outputs = record.all_outputs_at_address(&sender).await.unwrap();
+    assert!(new_outputs
+        .iter()
+        .all(|output| output.id().tx_hash() == ctx.id.as_slice()));
+}
+
+#[tokio::test]
+async fn tx_id_is_the_hash_of_the_tx_body() {
+    // given
+    let sender = Address::from_bech32(ALICE).unwrap();
+    let recipient = Address::from_bech32(BOB).unwrap();
+    let mut starting_values = Values::default();
+    starting_values.add_one_value(&PolicyId::Lovelace, 10_000_000);
+    let output: Output<()> = Output::new_wallet(vec![1; 32], 0, sender.clone(), starting_values);
+
+    // when
+    let mut tx_ids = Vec::new();
+    for amount in [1_000_000, 1_000_000, 2_000_000] {
+        let record: TestLedgerClient<(), (), _> = TestLedgerClient::new_in_memory(
+            sender.clone(),
+            vec![(sender.clone(), output.clone())],
+            BLOCK_LENGTH,
+            0,
+        );
+        let tx: UnbuiltTransaction<(), ()> = TxActions::v2()
+            .with_transfer(amount, recipient.clone(), PolicyId::Lovelace)
+            .to_unbuilt_tx()
+            .unwrap();
+        tx_ids.push(record.issue(tx).await.unwrap().as_str());
+    }
+
+    // then
+    assert_eq!(tx_ids[0], tx_ids[1]);
+    assert_ne!(tx_ids[0], tx_ids[2]);
+    assert_eq!(hex::decode(&tx_ids[0]).unwrap().len(), 32);
+}
+
+#[tokio::test]
+async fn reference_script_output_is_stored_with_script() {
+    // given
+    let sender = Address::from_bech32(ALICE).unwrap();
+    let recipient = Address::from_bech32(BOB).unwrap();
+    let starting_amount = 10_000_000;
+    let deploy_amount = 5_000_000;
+    let outputs = vec![(
+        sender.clone(),
+        starting_output::<()>(&sender, starting_amount),
+    )];
+    let record: TestLedgerClient<(), (), _> =
+        TestLedgerClient::new_in_memory(sender.clone(), outputs, BLOCK_LENGTH, 0);
+    let script = ReferenceScript::new(TransactionVersion::V2, "4e4d01000033222220051200120011");
+
+    // when
+    let mut values = Values::default();
+    values.add_one_value(&PolicyId::Lovelace, deploy_amount);
+    let tx: UnbuiltTransaction<(), ()> = TxActions::v2()
+        .with_reference_script_output(script.clone(), values, recipient.clone())
+        .to_unbuilt_tx()
+        .unwrap();
+    record.issue(tx).await.unwrap();
+
+    // then
+    let recipient_outputs = record.all_outputs_at_address(&recipient).await.unwrap();
+    assert_eq!(recipient_outputs.len(), 1);
+    let deployed = recipient_outputs.first().unwrap();
+    assert_eq!(deployed.reference_script(), Some(&script));
+}
+
+const REFERENCE_SCRIPT_HEX: &str = "4e4d01000033222220051200120011";
+
+struct ReferencedValidator;
+
+impl ValidatorCode<(), ()> for ReferencedValidator {
+    fn execute(&self, _datum: (), _redeemer: (), _ctx: TxContext) -> ScriptResult<ExecutionCost> {
+        Ok(ExecutionCost::default())
+    }
+
+    fn address(&self, _network: Network) -> ScriptResult<Address> {
+        Ok(
+            Address::from_bech32("addr_test1wrme5jjggy97th309h2dwpv57wsphxskuc8jkw00c2kn47gu8mkzu")
+                .unwrap(),
+        )
+    }
+
+    fn script_hex(&self) -> ScriptResult<String> {
+        Ok(REFERENCE_SCRIPT_HEX.to_string())
+    }
+}
+
+struct ReferencedPolicy;
+
+impl MintingPolicy<()> for ReferencedPolicy {
+    fn execute(&self, _redeemer: (), _ctx: TxContext) -> ScriptResult<ExecutionCost> {
+        Ok(ExecutionCost::default())
+    }
+
+    fn id(&self) -> ScriptResult<String> {
+        Ok(hex::encode(vec![2, 2, 2, 2, 2]))
+    }
+
+    fn script_hex(&self) -> ScriptResult<String> {
+        Ok(REFERENCE_SCRIPT_HEX.to_string())
+    }
+}
+
+fn deployed_script_output(owner: &Address) -> Output<()> {
+    let mut values = Values::default();
+    values.add_one_value(&PolicyId::Lovelace, 5_000_000);
+    let script = ReferenceScript::new(TransactionVersion::V2, REFERENCE_SCRIPT_HEX);
+    Output::new_wallet(vec![7, 7, 7, 7], 0, owner.clone(), values).with_reference_script(script)
+}
+
+#[tokio::test]
+async fn redeem_by_reference_spends_output_and_keeps_reference_input() {
+    // given
+    let sender = Address::from_bech32(ALICE).unwrap();
+    let deployer = Address::from_bech32(BOB).unwrap();
+    let starting_amount = 10_000_000;
+    let locking_amount = 3_000_000;
+    let validator = ReferencedValidator;
+    let script_address = validator.address(Network::Testnet).unwrap();
+    let script_output = deployed_script_output(&deployer);
+    let mut locked_values = Values::default();
+    locked_values.add_one_value(&PolicyId::Lovelace, locking_amount);
+    let locked_output = Output::new_validator(
+        vec![1, 2, 3, 4],
+        0,
+        script_address.clone(),
+        locked_values,
+        (),
+    );
+    let outputs = vec![
+        (
+            sender.clone(),
+            starting_output::<()>(&sender, starting_amount),
+        ),
+        (deployer.clone(), script_output.clone()),
+        (script_address.clone(), locked_output.clone()),
+    ];
+    let record: TestLedgerClient<(), (), _> =
+        TestLedgerClient::new_in_memory(sender.clone(), outputs, BLOCK_LENGTH, 0);
+
+    // when
+    let tx: UnbuiltTransaction<(), ()> = TxActions::v2()
+        .with_script_redeem_by_reference(
+            locked_output,
+            (),
+            Box::new(validator),
+            script_output.clone(),
+        )
+        .to_unbuilt_tx()
+        .unwrap();
+    record.issue(tx).await.unwrap();
+
+    // then
+    let script_balance = record
+        .balance_at_address(&script_address, &PolicyId::Lovelace)
+        .await
+        .unwrap();
+    assert_eq!(script_balance, 0);
+    let deployer_outputs = record.all_outputs_at_address(&deployer).await.unwrap();
+    assert_eq!(deployer_outputs, vec![script_output]);
+}
+
+#[tokio::test]
+async fn mint_by_reference_mints_tokens() {
+    // given
+    let sender = Address::from_bech32(ALICE).unwrap();
+    let deployer = Address::from_bech32(BOB).unwrap();
+    let starting_amount = 10_000_000;
+    let minting_amount = 100;
+    let policy = ReferencedPolicy;
+    let id = policy.id().unwrap();
+    let policy_output = deployed_script_output(&deployer);
+    let outputs = vec![
+        (
+            sender.clone(),
+            starting_output::<()>(&sender, starting_amount),
+        ),
+        (deployer.clone(), policy_output.clone()),
+    ];
+    let record: TestLedgerClient<(), (), _> =
+        TestLedgerClient::new_in_memory(sender.clone(), outputs, BLOCK_LENGTH, 0);
+
+    // when
+    let tx: UnbuiltTransaction<(), ()> = TxActions::v2()
+        .with_mint_by_reference(minting_amount, None, (), Box::new(policy), policy_output)
+        .to_unbuilt_tx()
+        .unwrap();
+    record.issue(tx).await.unwrap();
+
+    // then
+    let balance = record
+        .balance_at_address(&sender, &PolicyId::NativeToken(id, None))
+        .await
+        .unwrap();
+    assert_eq!(balance, minting_amount);
+}
+
+#[test]
+fn redeem_by_reference_errors_without_reference_script() {
+    // given
+    let deployer = Address::from_bech32(BOB).unwrap();
+    let validator = ReferencedValidator;
+    let script_address = validator.address(Network::Testnet).unwrap();
+    let locked_output =
+        Output::new_validator(vec![1, 2, 3, 4], 0, script_address, Values::default(), ());
+    let plain_output = starting_output::<()>(&deployer, 5_000_000);
+
+    // when
+    let res = TxActions::v2()
+        .with_script_redeem_by_reference(locked_output, (), Box::new(validator), plain_output)
+        .to_unbuilt_tx();
+
+    // then
+    assert!(matches!(res, Err(Error::MissingReferenceScript(_))));
+}
+
+#[test]
+fn mint_by_reference_errors_if_reference_script_is_another_script() {
+    // given
+    let deployer = Address::from_bech32(BOB).unwrap();
+    let other_script = ReferenceScript::new(TransactionVersion::V2, "4d01000033222220051200120011");
+    let policy_output =
+        starting_output::<()>(&deployer, 5_000_000).with_reference_script(other_script);
+
+    // when
+    let res = TxActions::<(), ()>::v2()
+        .with_mint_by_reference(1, None, (), Box::new(ReferencedPolicy), policy_output)
+        .to_unbuilt_tx();
+
+    // then
+    assert!(matches!(res, Err(Error::ReferenceScriptMismatch(_))));
+}
+
+#[tokio::test]
+async fn mint_by_reference_errors_if_ledger_output_has_no_reference_script() {
+    // given
+    let sender = Address::from_bech32(ALICE).unwrap();
+    let deployer = Address::from_bech32(BOB).unwrap();
+    let starting_amount = 10_000_000;
+    let plain_output = starting_output::<()>(&deployer, 5_000_000);
+    let outputs = vec![
+        (
+            sender.clone(),
+            starting_output::<()>(&sender, starting_amount),
+        ),
+        (deployer.clone(), plain_output.clone()),
+    ];
+    let record: TestLedgerClient<(), (), _> =
+        TestLedgerClient::new_in_memory(sender.clone(), outputs, BLOCK_LENGTH, 0);
+    let script = ReferenceScript::new(TransactionVersion::V2, REFERENCE_SCRIPT_HEX);
+    let claimed_output = plain_output.with_reference_script(script);
+
+    // when
+    let tx: UnbuiltTransaction<(), ()> = TxActions::v2()
+        .with_mint_by_reference(1, None, (), Box::new(ReferencedPolicy), claimed_output)
+        .to_unbuilt_tx()
+        .unwrap();
+    let error = record.issue(tx).await.unwrap_err();
+
+    // then
+    match error {
+        LedgerClientError::FailedToIssueTx(inner) => assert!(matches!(
+            inner.downcast_ref::<TestLCError>(),
+            Some(TestLCError::ReferenceScriptNotFound(_))
+        )),
+        other => panic!("expected FailedToIssueTx, got {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn issue_keeps_tx_metadata() {
+    // given
+    let sender = Address::from_bech32(ALICE).unwrap();
+    let recipient = Address::from_bech32(BOB).unwrap();
+    let starting_amount = 10_000_000;
+    let outputs = vec![(
+        sender.clone(),
+        starting_output::<()>(&sender, starting_amount),
+    )];
+    let record: TestLedgerClient<(), (), _> =
+        TestLedgerClient::new_in_memory(sender.clone(), outputs, BLOCK_LENGTH, 0);
+    let message = cip20_message(&["thanks for the fish"]);
+
+    // when
+    let tx: UnbuiltTransaction<(), ()> = TxActions::v2()
+        .with_transfer(1_000_000, recipient, PolicyId::Lovelace)
+        .with_metadata(CIP20_LABEL, message.clone())
+        .to_unbuilt_tx()
+        .unwrap();
+    let tx_id = record.issue(tx).await.unwrap();
+
+    // then
+    let metadata = record.metadata(&tx_id).await.unwrap().unwrap();
+    assert_eq!(metadata.get(CIP20_LABEL), Some(&message));
+}
+
+#[tokio::test]
+async fn stake_registration_withdrawal_and_deregistration() {
+    // given
+    let signer = Address::from_bech32(ALICE).unwrap();
+    let starting_amount = 10_000_000;
+    let outputs = vec![(
+        signer.clone(),
+        starting_output::<()>(&signer, starting_amount),
+    )];
+    let record: TestLedgerClient<(), (), _> =
+        TestLedgerClient::new_in_memory(signer.clone(), outputs, BLOCK_LENGTH, 0);
+    let stake_key_hash = stake_key_hash_from_address_if_available(&signer).unwrap();
+    let credential = StakeCredential::Key(stake_key_hash.clone());
+
+    // when
+    let register: UnbuiltTransaction<(), ()> = TxActions::v2()
+        .with_stake_registration(credential.clone())
+        .to_unbuilt_tx()
+        .unwrap();
+    record.issue(register).await.unwrap();
+
+    // then
+    let balance = record
+        .balance_at_address(&signer, &PolicyId::Lovelace)
+        .await
+        .unwrap();
+    assert_eq!(balance, starting_amount - STAKE_KEY_DEPOSIT);
+
+    // when
+    let rewards = 500_000;
+    record.add_rewards(&credential, rewards).await.unwrap();
+    let withdraw: UnbuiltTransaction<(), ()> = TxActions::v2()
+        .with_withdrawal(StakeWitness::Key(stake_key_hash.clone()), rewards)
+        .to_unbuilt_tx()
+        .unwrap();
+    record.issue(withdraw).await.unwrap();
+    let deregister: UnbuiltTransaction<(), ()> = TxActions::v2()
+        .with_stake_deregistration(StakeWitness::Key(stake_key_hash))
+        .to_unbuilt_tx()
+        .unwrap();
+    record.issue(deregister).await.unwrap();
+
+    // then
+    let balance = record
+        .balance_at_address(&signer, &PolicyId::Lovelace)
+        .await
+        .unwrap();
+    assert_eq!(balance, starting_amount + rewards);
+    assert!(record.stake_account(&credential).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn stake_registration_takes_key_deposit_from_protocol_parameters() {
+    // given
+    let signer = Address::from_bech32(ALICE).unwrap();
+    let starting_amount = 10_000_000;
+    let outputs = vec![(
+        signer.clone(),
+        starting_output::<()>(&signer, starting_amount),
+    )];
+    let params_file = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("src/trireme_ledger_client/cml_client/protocol_parameters/vasil.json");
+    let mut params = ProtocolParameters::load(&params_file).unwrap();
+    params.key_deposit = 3_000_000;
+    let record: TestLedgerClient<(), (), _> =
+        TestLedgerClient::new_in_memory(signer.clone(), outputs, BLOCK_LENGTH, 0)
+            .with_protocol_parameters(&params);
+    let stake_key_hash = stake_key_hash_from_address_if_available(&signer).unwrap();
+
+    // when
+    let register: UnbuiltTransaction<(), ()> = TxActions::v2()
+        .with_stake_registration(StakeCredential::Key(stake_key_hash))
+        .to_unbuilt_tx()
+        .unwrap();
+    record.issue(register).await.unwrap();
+
+    // then
+    let balance = record
+        .balance_at_address(&signer, &PolicyId::Lovelace)
+        .await
+        .unwrap();
+    assert_eq!(balance, starting_amount - params.key_deposit);
+}
+
+#[tokio::test]
+async fn cannot_deregister_with_unwithdrawn_rewards() {
+    // given
+    let signer = Address::from_bech32(ALICE).unwrap();
+    let outputs = vec![(signer.clone(), starting_output::<()>(&signer, 10_000_000))];
+    let record: TestLedgerClient<(), (), _> =
+        TestLedgerClient::new_in_memory(signer.clone(), outputs, BLOCK_LENGTH, 0);
+    let stake_key_hash = stake_key_hash_from_address_if_available(&signer).unwrap();
+    let credential = StakeCredential::Key(stake_key_hash.clone());
+    let register: UnbuiltTransaction<(), ()> = TxActions::v2()
+        .with_stake_registration(credential.clone())
+        .to_unbuilt_tx()
+        .unwrap();
+    record.issue(register).await.unwrap();
+    record.add_rewards(&credential, 1_000).await.unwrap();
+
+    // when
+    let deregister: UnbuiltTransaction<(), ()> = TxActions::v2()
+        .with_stake_deregistration(StakeWitness::Key(stake_key_hash))
+        .to_unbuilt_tx()
+        .unwrap();
+    let error = record.issue(deregister).await.unwrap_err();
+
+    // then
+    assert!(matches!(error, LedgerClientError::FailedToIssueTx(_)));
+}
+
+struct RequiresWithdrawalValidator;
+
+impl StakingValidator<()> for RequiresWithdrawalValidator {
+    fn execute(&self, _redeemer: (), ctx: TxContext) -> ScriptResult<ExecutionCost> {
+        let own_credential = StakeCredential::from_validator(self)?;
+        if ctx.wdrl.iter().any(|(cred, _)| cred == &own_credential) {
+            Ok(ExecutionCost::default())
+        } else {
+            Err(ScriptError::FailedToExecute(
+                "Withdrawal missing from context".to_string(),
+            ))
+        }
+    }
+
+    fn hash(&self) -> ScriptResult<String> {
+        Ok("12341234".to_string())
+    }
+
+    fn script_hex(&self) -> ScriptResult<String> {
+        unimplemented!()
+    }
+}
+
+#[tokio::test]
+async fn script_withdrawal_runs_staking_validator() {
+    // given
+    let signer = Address::from_bech32(ALICE).unwrap();
+    let starting_amount = 10_000_000;
+    let outputs = vec![(
+        signer.clone(),
+        starting_output::<()>(&signer, starting_amount),
+    )];
+    let record: TestLedgerClient<(), (), _> =
+        TestLedgerClient::new_in_memory(signer.clone(), outputs, BLOCK_LENGTH, 0);
+    let credential = StakeCredential::from_validator(&RequiresWithdrawalValidator).unwrap();
+    let register: UnbuiltTransaction<(), ()> = TxActions::v2()
+        .with_stake_registration(credential.clone())
+        .to_unbuilt_tx()
+        .unwrap();
+    record.issue(register).await.unwrap();
+    let rewards = 3_000_000;
+    record.add_rewards(&credential, rewards).await.unwrap();
+
+    // when
+    let witness = StakeWitness::Script {
+        redeemer: (),
+        validator: Box::new(RequiresWithdrawalValidator),
+    };
+    let withdraw: UnbuiltTransaction<(), ()> = TxActions::v2()
+        .with_withdrawal(witness, rewards)
+        .to_unbuilt_tx()
+        .unwrap();
+    record.issue(withdraw).await.unwrap();
+
+    // then
+    let balance = record
+        .balance_at_address(&signer, &PolicyId::Lovelace)
+        .await
+        .unwrap();
+    assert_eq!(balance, starting_amount - STAKE_KEY_DEPOSIT + rewards);
+    let account = record.stake_account(&credential).await.unwrap().unwrap();
+    assert_eq!(account.rewards, 0);
+}
+
+#[tokio::test]
+async fn cannot_issue_without_required_signer() {
+    // given
+    let sender = Address::from_bech32(ALICE).unwrap();
+    let other = Address::from_bech32(BOB).unwrap();
+    let outputs = vec![(sender.clone(), starting_output::<()>(&sender, 10_000_000))];
+    let record: TestLedgerClient<(), (), _> =
+        TestLedgerClient::new_in_memory(sender.clone(), outputs, BLOCK_LENGTH, 0);
+    let other_pkh = pub_key_hash_from_address_if_available(&other).unwrap();
+
+    // when
+    let tx: UnbuiltTransaction<(), ()> = TxActions::v2()
+        .with_transfer(1_000_000, other, PolicyId::Lovelace)
+        .with_required_signer(other_pkh)
+        .to_unbuilt_tx()
+        .unwrap();
+    let error = record.issue(tx).await.unwrap_err();
+
+    // then
+    assert!(matches!(error, LedgerClientError::FailedToIssueTx(_)));
+}
+
+struct RequiresSignatoryValidator {
+    signatory: PubKeyHash,
+}
+
+impl ValidatorCode<(), ()> for RequiresSignatoryValidator {
+    fn execute(&self, _datum: (), _redeemer: (), ctx: TxContext) -> ScriptResult<ExecutionCost> {
+        if ctx.extra_signatories.contains(&self.signatory) {
+            Ok(ExecutionCost::default())
+        } else {
+            Err(ScriptError::FailedToExecute(
+                "Missing signatory".to_string(),
+            ))
+        }
+    }
+
+    fn address(&self, _network: Network) -> ScriptResult<Address> {
+        Ok(
+            Address::from_bech32("addr_test1wrme5jjggy97th309h2dwpv57wsphxskuc8jkw00c2kn47gu8mkzu")
+                .unwrap(),
+        )
+    }
+
+    fn script_hex(&self) -> ScriptResult<String> {
+        unimplemented!()
+    }
+}
+
+#[tokio::test]
+async fn cosigner_satisfies_required_signer_and_is_seen_by_script() {
+    // given
+    let signer = Address::from_bech32(ALICE).unwrap();
+    let cosigner = Address::from_bech32(BOB).unwrap();
+    let cosigner_pkh = pub_key_hash_from_address_if_available(&cosigner).unwrap();
+    let validator = RequiresSignatoryValidator {
+        signatory: cosigner_pkh.clone(),
+    };
+    let script_address = validator.address(Network::Testnet).unwrap();
+    let locked_amount = 2_000_000;
+    let mut values = Values::default();
+    values.add_one_value(&PolicyId::Lovelace, locked_amount);
+    let script_output =
+        Output::new_validator(vec![1, 2, 3, 4], 0, script_address.clone(), values, ());
+    let outputs = vec![
+        (signer.clone(), starting_output::<()>(&signer, 10_000_000)),
+        (script_address, script_output.clone()),
+    ];
+    let record: TestLedgerClient<(), (), _> =
+        TestLedgerClient::new_in_memory(signer.clone(), outputs, BLOCK_LENGTH, 0)
+            .with_cosigner(cosigner_pkh.clone());
+
+    // when
+    let tx: UnbuiltTransaction<(), ()> = TxActions::v2()
+        .with_script_redeem(script_output, (), Box::new(validator))
+        .with_required_signer(cosigner_pkh)
+        .to_unbuilt_tx()
+        .unwrap();
+    record.issue(tx).await.unwrap();
+
+    // then
+    let balance = record
+        .balance_at_address(&signer, &PolicyId::Lovelace)
+        .await
+        .unwrap();
+    assert_eq!(balance, 10_000_000 + locked_amount);
+}
+
+struct FixedCostValidator;
+
+impl ValidatorCode<(), ()> for FixedCostValidator {
+    fn execute(&self, _datum: (), _redeemer: (), _ctx: TxContext) -> ScriptResult<ExecutionCost> {
+        Ok(ExecutionCost::new(10, 20))
+    }
+
+    fn address(&self, _network: Network) -> ScriptResult<Address> {
+        Ok(
+            Address::from_bech32("addr_test1wrme5jjggy97th309h2dwpv57wsphxskuc8jkw00c2kn47gu8mkzu")
+                .unwrap(),
+        )
+    }
+
+    fn script_hex(&self) -> ScriptResult<String> {
+        todo!()
+    }
+}
+
+#[tokio::test]
+async fn simulate_reports_costs_without_changing_ledger() {
+    // given
+    let sender = Address::from_bech32(ALICE).unwrap();
+    let starting_amount = 10_000_000;
+    let locked_amount = 3_000_000;
+    let script_address = FixedCostValidator.address(Network::Testnet).unwrap();
+    let mut values = Values::default();
+    values.add_one_value(&PolicyId::Lovelace, locked_amount);
+    let locked_output =
+        Output::new_validator(vec![1, 2, 3, 4], 0, script_address.clone(), values, ());
+    let outputs = vec![
+        (
+            sender.clone(),
+            starting_output::<()>(&sender, starting_amount),
+        ),
+        (script_address.clone(), locked_output.clone()),
+    ];
+    let record: TestLedgerClient<(), (), _> =
+        TestLedgerClient::new_in_memory(sender.clone(), outputs, BLOCK_LENGTH, 0);
+
+    // when
+    let tx: UnbuiltTransaction<(), ()> = TxActions::v2()
+        .with_script_redeem(locked_output.clone(), (), Box::new(FixedCostValidator))
+        .to_unbuilt_tx()
+        .unwrap();
+    let simulation = record.simulate(tx).await.unwrap();
+
+    // then
+    assert_eq!(simulation.execution_costs.len(), 1);
+    let redeemer_cost = simulation.execution_costs.first().unwrap();
+    assert_eq!(redeemer_cost.kind, RedeemerKind::Spend);
+    assert_eq!(redeemer_cost.cost.mem(), 10);
+    assert_eq!(redeemer_cost.cost.cpu(), 20);
+    assert!(simulation.summary.inputs.contains(locked_output.id()));
+    let sender_bech32 = sender.to_bech32().unwrap();
+    let (_, change) = simulation
+        .summary
+        .outputs
+        .iter()
+        .find(|(owner, _)| owner == &sender_bech32)
+        .unwrap();
+    assert_eq!(
+        change.get(&PolicyId::Lovelace),
+        Some(starting_amount + locked_amount)
+    );
+    let script_outputs = record
+        .all_outputs_at_address(&script_address)
+        .await
+        .unwrap();
+    assert_eq!(script_outputs, vec![locked_output]);
+    assert_eq!(record.current_time_secs().await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn simulate_errors_when_script_fails() {
+    // given
+    let sender = Address::from_bech32(ALICE).unwrap();
+    let script_address = AlwaysFailsFakeValidator.address(Network::Testnet).unwrap();
+    let mut values = Values::default();
+    values.add_one_value(&PolicyId::Lovelace, 3_000_000);
+    let locked_output =
+        Output::new_validator(vec![1, 2, 3, 4], 0, script_address.clone(), values, ());
+    let outputs = vec![
+        (sender.clone(), starting_output::<()>(&sender, 10_000_000)),
+        (script_address, locked_output.clone()),
+    ];
+    let record: TestLedgerClient<(), (), _> =
+        TestLedgerClient::new_in_memory(sender.clone(), outputs, BLOCK_LENGTH, 0);
+
+    // when
+    let tx: UnbuiltTransaction<(), ()> = TxActions::v2()
+        .with_script_redeem(locked_output, (), Box::new(AlwaysFailsFakeValidator))
+        .to_unbuilt_tx()
+        .unwrap();
+    let error = record.simulate(tx).await.unwrap_err();
+
+    // then
+    assert!(matches!(error, LedgerClientError::FailedToIssueTx(_)));
+}
+
+#[tokio::test]
+async fn raw_validator_runs_with_both_validity_bounds() {
+    // given
+    let sender = Address::from_bech32(ALICE).unwrap();
+    let script_file = PlutusScriptFile {
+        r#type: "PlutusScriptV1".to_string(),
+        description: "".to_string(),
+        cborHex: "4e4d01000033222220051200120011".to_string(),
+    };
+    let validator: RawPlutusValidator<(), ()> = RawPlutusValidator::new_v1(script_file).unwrap();
+    let script_address = validator.address(Network::Testnet).unwrap();
+    let mut values = Values::default();
+    values.add_one_value(&PolicyId::Lovelace, 3_000_000);
+    let locked_output =
+        Output::new_validator(vec![1, 2, 3, 4], 0, script_address.clone(), values, ());
+    let outputs = vec![
+        (sender.clone(), starting_output::<()>(&sender, 10_000_000)),
+        (script_address.clone(), locked_output.clone()),
+    ];
+    let record: TestLedgerClient<(), (), _> =
+        TestLedgerClient::new_in_memory(sender.clone(), outputs, BLOCK_LENGTH, 0);
+    record.set_current_time_secs(10_000).await.unwrap();
+
+    // when
+    let tx: UnbuiltTransaction<(), ()> = TxActions::v1()
+        .with_script_redeem(locked_output, (), Box::new(validator))
+        .with_valid_range_secs(Some(9_000), Some(11_000))
+        .to_unbuilt_tx()
+        .unwrap();
+    record.issue(tx).await.unwrap();
+
+    // then
+    let script_balance = record
+        .balance_at_address(&script_address, &PolicyId::Lovelace)
+        .await
+        .unwrap();
+    assert_eq!(script_balance, 0);
+}
+
+#[tokio::test]
+async fn await_tx_resolves_immediately() {
+    // given
+    let sender = Address::from_bech32(ALICE).unwrap();
+    let recipient = Address::from_bech32(BOB).unwrap();
+    let outputs = vec![(sender.clone(), starting_output::<()>(&sender, 10_000_000))];
+    let record: TestLedgerClient<(), (), _> =
+        TestLedgerClient::new_in_memory(sender.clone(), outputs, BLOCK_LENGTH, 0);
+    let tx: UnbuiltTransaction<(), ()> = TxActions::v2()
+        .with_transfer(1_000_000, recipient, PolicyId::Lovelace)
+        .to_unbuilt_tx()
+        .unwrap();
+    let tx_id = record.issue(tx).await.unwrap();
+
+    // when
+    let res = record.await_tx(&tx_id, 10, Duration::ZERO).await;
+
+    // then
+    assert!(res.is_ok());
+}
+
+#[tokio::test]
+async fn cannot_issue_output_below_min_ada() {
+    // given
+    let sender = Address::from_bech32(ALICE).unwrap();
+    let recipient = Address::from_bech32(BOB).unwrap();
+    let outputs = vec![(sender.clone(), starting_output::<()>(&sender, 10_000_000))];
+    let record: TestLedgerClient<(), (), _> =
+        TestLedgerClient::new_in_memory(sender.clone(), outputs, BLOCK_LENGTH, 0);
+
+    // when
+    let tx: UnbuiltTransaction<(), ()> = TxActions::v2()
+        .with_transfer(10, recipient, PolicyId::Lovelace)
+        .to_unbuilt_tx()
+        .unwrap();
+    let error = record.issue(tx).await.unwrap_err();
+
+    // then
+    assert_below_min_ada(error, 1, 10);
+}
+
+#[tokio::test]
+async fn cannot_leave_change_below_min_ada() {
+    // given
+    let sender = Address::from_bech32(ALICE).unwrap();
+    let recipient = Address::from_bech32(BOB).unwrap();
+    let starting_amount = 10_000_000;
+    let outputs = vec![(
+        sender.clone(),
+        starting_output::<()>(&sender, starting_amount),
+    )];
+    let record: TestLedgerClient<(), (), _> =
+        TestLedgerClient::new_in_memory(sender.clone(), outputs, BLOCK_LENGTH, 0);
+
+    // when
+    let tx: UnbuiltTransaction<(), ()> = TxActions::v2()
+        .with_transfer(starting_amount - 10, recipient, PolicyId::Lovelace)
+        .to_unbuilt_tx()
+        .unwrap();
+    let error = record.issue(tx).await.unwrap_err();
+
+    // then
+    assert_below_min_ada(error, 0, 10);
+}
+
+#[tokio::test]
+async fn without_min_ada_accepts_tiny_outputs() {
+    // given
+    let sender = Address::from_bech32(ALICE).unwrap();
+    let recipient = Address::from_bech32(BOB).unwrap();
+    let outputs = vec![(sender.clone(), starting_output::<()>(&sender, 10_000_000))];
+    let record: TestLedgerClient<(), (), _> =
+        TestLedgerClient::new_in_memory(sender.clone(), outputs, BLOCK_LENGTH, 0).without_min_ada();
+
+    // when
+    let tx: UnbuiltTransaction<(), ()> = TxActions::v2()
+        .with_transfer(10, recipient.clone(), PolicyId::Lovelace)
+        .to_unbuilt_tx()
+        .unwrap();
+    record.issue(tx).await.unwrap();
+
+    // then
+    let balance = record
+        .balance_at_address(&recipient, &PolicyId::Lovelace)
+        .await
+        .unwrap();
+    assert_eq!(balance, 10);
+}
+
+fn assert_below_min_ada(error: LedgerClientError, expected_index: usize, expected_provided: u64) {
+    match error {
+        LedgerClientError::FailedToIssueTx(inner) => match inner.downcast_ref::<TestLCError>() {
+            Some(TestLCError::BelowMinAda {
+                index, provided, ..
+            }) => {
+                assert_eq!(*index, expected_index);
+                assert_eq!(*provided, expected_provided);
+            }
+            other => panic!("expected BelowMinAda, got {other:?}"),
+        },
+        other => panic!("expected FailedToIssueTx, got {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn min_ada_top_up_raises_output_to_minimum() {
+    // given
+    let sender = Address::from_bech32(ALICE).unwrap();
+    let recipient = Address::from_bech32(BOB).unwrap();
+    let outputs = vec![(sender.clone(), starting_output::<()>(&sender, 10_000_000))];
+    let calculator = MinAdaCalculator::default();
+    let record: TestLedgerClient<(), (), _> =
+        TestLedgerClient::new_in_memory(sender.clone(), outputs, BLOCK_LENGTH, 0)
+            .with_min_ada(calculator);
+
+    // when
+    let tx: UnbuiltTransaction<(), ()> = TxActions::v2()
+        .with_transfer(10, recipient.clone(), PolicyId::Lovelace)
+        .with_min_ada_top_up()
+        .to_unbuilt_tx()
+        .unwrap();
+    record.issue(tx).await.unwrap();
+
+    // then
+    let recipient_output = record
+        .all_outputs_at_address(&recipient)
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let expected = calculator.min_ada(&UnbuiltOutput::<()>::new_wallet(
+        recipient,
+        recipient_output.values().clone(),
+    ));
+    assert_eq!(
+        recipient_output.values().get(&PolicyId::Lovelace).unwrap(),
+        expected
+    );
+}
+
+#[tokio::test]
+async fn output_by_id__finds_unspent_output() {
+    // given
+    let signer = Address::from_bech32(ALICE).unwrap();
+    let output = starting_output::<()>(&signer, 10_000_000);
+    let outputs = vec![(signer.clone(), output.clone())];
+    let record: TestLedgerClient<(), (), _> =
+        TestLedgerClient::new_in_memory(signer, outputs, BLOCK_LENGTH, 0);
+
+    // when
+    let actual = record.output_by_id(output.id()).await.unwrap();
+
+    // then
+    assert_eq!(actual, Some(output));
+}
+
+#[tokio::test]
+async fn output_by_id__none_for_unknown_id() {
+    // given
+    let signer = Address::from_bech32(ALICE).unwrap();
+    let outputs = vec![(signer.clone(), starting_output::<()>(&signer, 10_000_000))];
+    let record: TestLedgerClient<(), (), _> =
+        TestLedgerClient::new_in_memory(signer, outputs, BLOCK_LENGTH, 0);
+
+    // when
+    let actual = record
+        .output_by_id(&OutputId::new(vec![1, 2, 3], 0))
+        .await
+        .unwrap();
+
+    // then
+    assert!(actual.is_none());
+}
+
+#[tokio::test]
+async fn output_by_id__none_once_spent() {
+    // given
+    let sender = Address::from_bech32(ALICE).unwrap();
+    let recipient = Address::from_bech32(BOB).unwrap();
+    let output = starting_output::<()>(&sender, 10_000_000);
+    let outputs = vec![(sender.clone(), output.clone())];
+    let record: TestLedgerClient<(), (), _> =
+        TestLedgerClient::new_in_memory(sender, outputs, BLOCK_LENGTH, 0);
+
+    // when
+    let tx: UnbuiltTransaction<(), ()> = TxActions::v2()
+        .with_transfer(3_000_000, recipient, PolicyId::Lovelace)
+        .to_unbuilt_tx()
+        .unwrap();
+    record.issue(tx).await.unwrap();
+
+    // then
+    let actual = record.output_by_id(output.id()).await.unwrap();
+    assert!(actual.is_none());
+}
+
+#[tokio::test]
+async fn outputs_with_asset__finds_output_holding_nft() {
+    // given
+    let alice = Address::from_bech32(ALICE).unwrap();
+    let bob = Address::from_bech32(BOB).unwrap();
+    let policy = "3f1b5974f4f09f0974be655e4ce94f8a2d087df378b79ef3916c26b2";
+    let nft = PolicyId::native_token(policy, &Some("ACCOUNT".to_string()));
+    let mut values = Values::default();
+    values.add_one_value(&PolicyId::Lovelace, 2_000_000);
+    values.add_one_value(&nft, 1);
+    let nft_output = Output::new_wallet(vec![1, 2, 3, 4], 0, bob.clone(), values);
+    let outputs = vec![
+        (alice.clone(), starting_output::<()>(&alice, 10_000_000)),
+        (bob, nft_output.clone()),
+    ];
+    let record: TestLedgerClient<(), (), _> =
+        TestLedgerClient::new_in_memory(alice, outputs, BLOCK_LENGTH, 0);
+
+    // when
+    let by_asset = record.outputs_with_asset(&nft).await.unwrap();
+    let by_policy = record
+        .outputs_with_asset(&PolicyId::native_token(policy, &None))
+        .await
+        .unwrap();
+    let other_asset = record
+        .outputs_with_asset(&PolicyId::native_token(policy, &Some("OTHER".to_string())))
+        .await
+        .unwrap();
+
+    // then
+    assert_eq!(by_asset, vec![nft_output.clone()]);
+    assert_eq!(by_policy, vec![nft_output]);
+    assert!(other_asset.is_empty());
+}
+
+#[tokio::test]
+async fn outputs_with_asset__follows_outputs_added_and_removed() {
+    // given
+    let alice = Address::from_bech32(ALICE).unwrap();
+    let policy = "3f1b5974f4f09f0974be655e4ce94f8a2d087df378b79ef3916c26b2";
+    let nft = PolicyId::native_token(policy, &Some("ACCOUNT".to_string()));
+    let mut values = Values::default();
+    values.add_one_value(&PolicyId::Lovelace, 2_000_000);
+    values.add_one_value(&nft, 1);
+    let old_holder = Output::new_wallet(vec![1, 2, 3, 4], 0, alice.clone(), values.clone());
+    let new_holder = Output::new_wallet(vec![5, 6, 7, 8], 0, alice.clone(), values);
+    let record: TestLedgerClient<(), (), _> = TestLedgerClient::new_in_memory(
+        alice.clone(),
+        vec![(alice, old_holder.clone())],
+        BLOCK_LENGTH,
+        0,
+    );
+
+    // when
+    record.storage.remove_output(&old_holder).await.unwrap();
+    record.storage.add_output(&new_holder).await.unwrap();
+
+    // then
+    let holders = record.outputs_with_asset(&nft).await.unwrap();
+    assert_eq!(holders, vec![new_holder]);
+}
@@ -0,0 +1,204 @@
+use super::{bound_slot, TestLCError};
+use crate::{
+    address::policy_id_bytes,
+    ledger_client::{LedgerClientError, LedgerClientResult},
+    output::{Output, UnbuiltOutput},
+    scripts::raw_validator_script::plutus_data::PlutusData,
+    values::Values,
+    PolicyId, UnbuiltTransaction,
+};
+use minicbor::{data::Tag, encode, Encode, Encoder};
+use pallas_crypto::hash::Hasher;
+use std::{collections::BTreeMap, convert::Infallible};
+
+type MultiAsset<T> = BTreeMap<Vec<u8>, BTreeMap<Vec<u8>, T>>;
+
+type EncodeResult = Result<(), encode::Error<Infallible>>;
+
+/// Babbage-shaped body of a tx processed by the test ledger, hashed for the tx id.
+///
+/// Certificates and withdrawals are left out; the inputs already make every body unique.
+pub(super) struct TxBody {
+    inputs: Vec<(Vec<u8>, u64)>,
+    outputs: Vec<BodyOutput>,
+    fee: u64,
+    ttl: Option<u64>,
+    validity_start: Option<u64>,
+    mint: MultiAsset<i64>,
+    required_signers: Vec<Vec<u8>>,
+    reference_inputs: Vec<(Vec<u8>, u64)>,
+}
+
+struct BodyOutput {
+    address: Vec<u8>,
+    coin: u64,
+    assets: MultiAsset<u64>,
+    datum: Option<Vec<u8>>,
+}
+
+impl TxBody {
+    pub fn new<Datum: Into<PlutusData> + Clone, Redeemer>(
+        tx: &UnbuiltTransaction<Datum, Redeemer>,
+        inputs: &[Output<Datum>],
+        outputs: &[UnbuiltOutput<Datum>],
+        fee: u64,
+    ) -> LedgerClientResult<Self> {
+        let outputs = outputs.iter().map(BodyOutput::new).collect();
+        let mut mint = MultiAsset::new();
+        for (assets, _, policy) in tx.minting.iter() {
+            let policy_id = policy
+                .id()
+                .map_err(|e| LedgerClientError::FailedToIssueTx(Box::new(e)))?;
+            let policy_assets = mint.entry(policy_id_bytes(&policy_id)).or_default();
+            for (asset_name, amount) in assets {
+                let name = asset_name.clone().unwrap_or_default().into_bytes();
+                *policy_assets.entry(name).or_default() += *amount;
+            }
+        }
+        let slot = |bound| u64::try_from(bound_slot(bound)).unwrap_or_default();
+        let body = TxBody {
+            inputs: input_set(inputs),
+            outputs,
+            fee,
+            ttl: tx.valid_range.upper.map(slot),
+            validity_start: tx.valid_range.lower.map(slot),
+            mint,
+            required_signers: tx.required_signers.iter().map(|pkh| pkh.bytes()).collect(),
+            reference_inputs: input_set(&tx.reference_inputs),
+        };
+        Ok(body)
+    }
+
+    /// Blake2b-256 hash of the CBOR body, like the ledger's tx id
+    pub fn hash(&self) -> LedgerClientResult<Vec<u8>> {
+        let mut encoder = Encoder::new(Vec::new());
+        self.encode(&mut encoder)
+            .map_err(|e| TestLCError::TxBodyEncoding(e.to_string()))
+            .map_err(|e| LedgerClientError::FailedToIssueTx(Box::new(e)))?;
+        let hash = Hasher::<256>::hash(&encoder.into_writer());
+        Ok(hash.to_vec())
+    }
+
+    fn encode(&self, e: &mut Encoder<Vec<u8>>) -> EncodeResult {
+        let optional_fields = [
+            self.ttl.is_some(),
+            self.validity_start.is_some(),
+            !self.mint.is_empty(),
+            !self.required_signers.is_empty(),
+            !self.reference_inputs.is_empty(),
+        ];
+        let len = 3 + optional_fields.iter().filter(|present| **present).count();
+        e.map(len as u64)?;
+
+        e.u8(0)?;
+        encode_input_set(e, &self.inputs)?;
+        e.u8(1)?.array(self.outputs.len() as u64)?;
+        for output in &self.outputs {
+            output.encode(e)?;
+        }
+        e.u8(2)?.u64(self.fee)?;
+        if let Some(ttl) = self.ttl {
+            e.u8(3)?.u64(ttl)?;
+        }
+        if let Some(validity_start) = self.validity_start {
+            e.u8(8)?.u64(validity_start)?;
+        }
+        if !self.mint.is_empty() {
+            e.u8(9)?;
+            encode_multi_asset(e, &self.mint)?;
+        }
+        if !self.required_signers.is_empty() {
+            e.u8(14)?.array(self.required_signers.len() as u64)?;
+            for signer in &self.required_signers {
+                e.bytes(signer)?;
+            }
+        }
+        if !self.reference_inputs.is_empty() {
+            e.u8(18)?;
+            encode_input_set(e, &self.reference_inputs)?;
+        }
+        Ok(())
+    }
+}
+
+impl BodyOutput {
+    fn new<Datum: Into<PlutusData> + Clone>(output: &UnbuiltOutput<Datum>) -> Self {
+        let (coin, assets) = split_values(output.values());
+        let datum = output
+            .datum()
+            .map(|datum| Into::<PlutusData>::into(datum.clone()).bytes());
+        BodyOutput {
+            address: output.owner().to_vec(),
+            coin,
+            assets,
+            datum,
+        }
+    }
+
+    fn encode(&self, e: &mut Encoder<Vec<u8>>) -> EncodeResult {
+        let len = if self.datum.is_some() { 3 } else { 2 };
+        e.map(len)?;
+        e.u8(0)?.bytes(&self.address)?;
+        e.u8(1)?;
+        if self.assets.is_empty() {
+            e.u64(self.coin)?;
+        } else {
+            e.array(2)?.u64(self.coin)?;
+            encode_multi_asset(e, &self.assets)?;
+        }
+        if let Some(datum) = &self.datum {
+            // Inline datum
+            e.u8(2)?.array(2)?.u8(1)?.tag(Tag::Cbor)?.bytes(datum)?;
+        }
+        Ok(())
+    }
+}
+
+fn input_set<Datum>(outputs: &[Output<Datum>]) -> Vec<(Vec<u8>, u64)> {
+    let mut inputs: Vec<_> = outputs
+        .iter()
+        .map(|output| (output.id().tx_hash().to_vec(), output.id().index()))
+        .collect();
+    inputs.sort();
+    inputs
+}
+
+fn split_values(values: &Values) -> (u64, MultiAsset<u64>) {
+    let mut coin = 0;
+    let mut assets = MultiAsset::new();
+    for (policy, amount) in values.as_iter() {
+        match policy {
+            PolicyId::Lovelace => coin = *amount,
+            PolicyId::NativeToken(policy_id, asset_name) => {
+                let name = asset_name.clone().unwrap_or_default().into_bytes();
+                assets
+                    .entry(policy_id_bytes(policy_id))
+                    .or_default()
+                    .insert(name, *amount);
+            }
+        }
+    }
+    (coin, assets)
+}
+
+fn encode_input_set(e: &mut Encoder<Vec<u8>>, inputs: &[(Vec<u8>, u64)]) -> EncodeResult {
+    e.array(inputs.len() as u64)?;
+    for (tx_hash, index) in inputs {
+        e.array(2)?.bytes(tx_hash)?.u64(*index)?;
+    }
+    Ok(())
+}
+
+fn encode_multi_asset<T: Encode<()>>(
+    e: &mut Encoder<Vec<u8>>,
+    multi_asset: &MultiAsset<T>,
+) -> EncodeResult {
+    e.map(multi_asset.len() as u64)?;
+    for (policy_id, assets) in multi_asset {
+        e.bytes(policy_id)?.map(assets.len() as u64)?;
+        for (asset_name, amount) in assets {
+            e.bytes(asset_name)?.encode(amount)?;
+        }
+    }
+    Ok(())
+}
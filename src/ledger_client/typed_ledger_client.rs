@@ -0,0 +1,146 @@
+use crate::{
+    ledger_client::{LedgerClient, LedgerClientResult},
+    output::{Output, OutputId},
+    scripts::raw_validator_script::plutus_data::PlutusData,
+    transaction::{TxId, TxSimulation, UnbuiltTransaction, UnsignedTransaction},
+    PolicyId,
+};
+use async_trait::async_trait;
+use pallas_addresses::{Address, Network};
+use std::{marker::PhantomData, sync::Arc, time::Duration};
+
+#[cfg(test)]
+mod tests;
+
+/// Typed view of an untyped `LedgerClient<PlutusData, PlutusData>`.
+///
+/// Outputs are decoded into `Datum` where possible and txs are converted with
+/// `UnbuiltTransaction::into_untyped`, so contracts with different datum and redeemer types can
+/// share one underlying client.
+pub struct TypedLedgerClient<Datum, Redeemer, LC> {
+    inner: Arc<LC>,
+    _datum: PhantomData<Datum>,
+    _redeemer: PhantomData<Redeemer>,
+}
+
+impl<Datum, Redeemer, LC> TypedLedgerClient<Datum, Redeemer, LC>
+where
+    LC: LedgerClient<PlutusData, PlutusData>,
+{
+    pub fn new(inner: LC) -> Self {
+        Self::from_shared(Arc::new(inner))
+    }
+
+    /// Share `inner` with other typed views
+    pub fn from_shared(inner: Arc<LC>) -> Self {
+        TypedLedgerClient {
+            inner,
+            _datum: PhantomData,
+            _redeemer: PhantomData,
+        }
+    }
+
+    pub fn untyped(&self) -> &Arc<LC> {
+        &self.inner
+    }
+}
+
+fn typed_outputs<Datum: Clone + TryFrom<PlutusData>>(
+    outputs: Vec<Output<PlutusData>>,
+) -> Vec<Output<Datum>> {
+    outputs.into_iter().map(Output::into_typed).collect()
+}
+
+#[async_trait]
+impl<Datum, Redeemer, LC> LedgerClient<Datum, Redeemer> for TypedLedgerClient<Datum, Redeemer, LC>
+where
+    Datum: Clone + Into<PlutusData> + TryFrom<PlutusData> + Send + Sync + 'static,
+    Redeemer: Into<PlutusData> + TryFrom<PlutusData> + Send + Sync + 'static,
+    LC: LedgerClient<PlutusData, PlutusData>,
+{
+    async fn signer_base_address(&self) -> LedgerClientResult<Address> {
+        self.inner.signer_base_address().await
+    }
+
+    async fn outputs_at_address(
+        &self,
+        address: &Address,
+        count: usize,
+    ) -> LedgerClientResult<Vec<Output<Datum>>> {
+        let outputs = self.inner.outputs_at_address(address, count).await?;
+        Ok(typed_outputs(outputs))
+    }
+
+    async fn all_outputs_at_address(
+        &self,
+        address: &Address,
+    ) -> LedgerClientResult<Vec<Output<Datum>>> {
+        let outputs = self.inner.all_outputs_at_address(address).await?;
+        Ok(typed_outputs(outputs))
+    }
+
+    async fn output_by_id(&self, id: &OutputId) -> LedgerClientResult<Option<Output<Datum>>> {
+        let output = self.inner.output_by_id(id).await?;
+        Ok(output.map(Output::into_typed))
+    }
+
+    async fn outputs_with_asset(&self, asset: &PolicyId) -> LedgerClientResult<Vec<Output<Datum>>> {
+        let outputs = self.inner.outputs_with_asset(asset).await?;
+        Ok(typed_outputs(outputs))
+    }
+
+    async fn balance_at_address(
+        &self,
+        address: &Address,
+        policy: &PolicyId,
+    ) -> LedgerClientResult<u64> {
+        self.inner.balance_at_address(address, policy).await
+    }
+
+    async fn issue(&self, tx: UnbuiltTransaction<Datum, Redeemer>) -> LedgerClientResult<TxId> {
+        self.inner.issue(tx.into_untyped()).await
+    }
+
+    async fn build_unsigned(
+        &self,
+        tx: UnbuiltTransaction<Datum, Redeemer>,
+    ) -> LedgerClientResult<UnsignedTransaction> {
+        self.inner.build_unsigned(tx.into_untyped()).await
+    }
+
+    async fn simulate(
+        &self,
+        tx: UnbuiltTransaction<Datum, Redeemer>,
+    ) -> LedgerClientResult<TxSimulation> {
+        self.inner.simulate(tx.into_untyped()).await
+    }
+
+    async fn submit_signed(&self, cbor_hex: &str) -> LedgerClientResult<TxId> {
+        self.inner.submit_signed(cbor_hex).await
+    }
+
+    async fn tx_confirmations(&self, tx_id: &TxId) -> LedgerClientResult<Option<u64>> {
+        self.inner.tx_confirmations(tx_id).await
+    }
+
+    async fn await_tx(
+        &self,
+        tx_id: &TxId,
+        confirmations: u64,
+        timeout: Duration,
+    ) -> LedgerClientResult<()> {
+        self.inner.await_tx(tx_id, confirmations, timeout).await
+    }
+
+    async fn network(&self) -> LedgerClientResult<Network> {
+        self.inner.network().await
+    }
+
+    async fn last_block_time_secs(&self) -> LedgerClientResult<i64> {
+        self.inner.last_block_time_secs().await
+    }
+
+    async fn current_time_secs(&self) -> LedgerClientResult<i64> {
+        self.inner.current_time_secs().await
+    }
+}
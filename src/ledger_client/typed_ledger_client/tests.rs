@@ -0,0 +1,190 @@
+#![allow(non_snake_case)]
+
+use super::*;
+use crate::{
+    ledger_client::test_ledger_client::{
+        in_memory_storage::InMemoryStorage, local_persisted_storage::starting_output,
+        TestLedgerClient,
+    },
+    scripts::{context::TxContext, ExecutionCost, ScriptError, ScriptResult, ValidatorCode},
+    transaction::TxActions,
+    values::Values,
+};
+use std::sync::Arc;
+
+const ALICE: &str = "addr_test1qrmezjhpelwzvz83wjl0e6mx766de7j3nksu2338s00yzx870xyxfa97xyz2zn5rknyntu5g0c66s7ktjnx0p6f0an6s3dyxwr";
+const COUNTER_SCRIPT: &str = "addr_test1wrme5jjggy97th309h2dwpv57wsphxskuc8jkw00c2kn47gu8mkzu";
+const NAME_SCRIPT: &str = "addr_test1wpe9mt7mkjmkkuqjmevzafm6mle9t0spprr9335q0e6p92cur7fvl";
+
+const BLOCK_LENGTH: i64 = 20;
+
+/// Only spends counters that are incremented by the redeemer's amount to 10
+struct CounterValidator;
+
+impl ValidatorCode<i64, i64> for CounterValidator {
+    fn execute(&self, datum: i64, redeemer: i64, _ctx: TxContext) -> ScriptResult<ExecutionCost> {
+        if datum + redeemer == 10 {
+            Ok(ExecutionCost::default())
+        } else {
+            Err(ScriptError::FailedToExecute("Wrong increment".to_string()))
+        }
+    }
+
+    fn address(&self, _network: Network) -> ScriptResult<Address> {
+        Ok(Address::from_bech32(COUNTER_SCRIPT).unwrap())
+    }
+
+    fn script_hex(&self) -> ScriptResult<String> {
+        todo!()
+    }
+}
+
+struct AlwaysTrueNameValidator;
+
+impl ValidatorCode<PlutusData, ()> for AlwaysTrueNameValidator {
+    fn execute(
+        &self,
+        _datum: PlutusData,
+        _redeemer: (),
+        _ctx: TxContext,
+    ) -> ScriptResult<ExecutionCost> {
+        Ok(ExecutionCost::default())
+    }
+
+    fn address(&self, _network: Network) -> ScriptResult<Address> {
+        Ok(Address::from_bech32(NAME_SCRIPT).unwrap())
+    }
+
+    fn script_hex(&self) -> ScriptResult<String> {
+        todo!()
+    }
+}
+
+fn untyped_ledger() -> TestLedgerClient<PlutusData, PlutusData, InMemoryStorage<PlutusData>> {
+    let signer = Address::from_bech32(ALICE).unwrap();
+    let outputs = vec![(
+        signer.clone(),
+        starting_output::<PlutusData>(&signer, 10_000_000),
+    )];
+    TestLedgerClient::new_in_memory(signer, outputs, BLOCK_LENGTH, 0)
+}
+
+fn lovelace(amount: u64) -> Values {
+    let mut values = Values::default();
+    values.add_one_value(&PolicyId::Lovelace, amount);
+    values
+}
+
+#[tokio::test]
+async fn typed_ledger_client__decodes_datums_and_runs_typed_validators() {
+    // given
+    let untyped = Arc::new(untyped_ledger());
+    let counters: TypedLedgerClient<i64, i64, _> = TypedLedgerClient::from_shared(untyped.clone());
+    let script_address = Address::from_bech32(COUNTER_SCRIPT).unwrap();
+    let lock = TxActions::v2()
+        .with_script_init(7, lovelace(2_000_000), script_address.clone())
+        .to_unbuilt_tx()
+        .unwrap();
+    counters.issue(lock).await.unwrap();
+
+    // when
+    let output = counters
+        .all_outputs_at_address(&script_address)
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let redeem = TxActions::v2()
+        .with_script_redeem(output.clone(), 3, Box::new(CounterValidator))
+        .to_unbuilt_tx()
+        .unwrap();
+    counters.issue(redeem).await.unwrap();
+
+    // then
+    assert_eq!(output.typed_datum(), Some(7));
+    let remaining = untyped
+        .all_outputs_at_address(&script_address)
+        .await
+        .unwrap();
+    assert!(remaining.is_empty());
+}
+
+#[tokio::test]
+async fn typed_ledger_client__typed_validator_rejects_bad_redeemer() {
+    // given
+    let counters: TypedLedgerClient<i64, i64, _> = TypedLedgerClient::new(untyped_ledger());
+    let script_address = Address::from_bech32(COUNTER_SCRIPT).unwrap();
+    let lock = TxActions::v2()
+        .with_script_init(7, lovelace(2_000_000), script_address.clone())
+        .to_unbuilt_tx()
+        .unwrap();
+    counters.issue(lock).await.unwrap();
+    let output = counters
+        .all_outputs_at_address(&script_address)
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+
+    // when
+    let redeem = TxActions::v2()
+        .with_script_redeem(output, 4, Box::new(CounterValidator))
+        .to_unbuilt_tx()
+        .unwrap();
+    let res = counters.issue(redeem).await;
+
+    // then
+    assert!(res.is_err());
+}
+
+#[tokio::test]
+async fn untyped_tx__spends_outputs_of_contracts_with_different_types() {
+    // given
+    let untyped = Arc::new(untyped_ledger());
+    let counters: TypedLedgerClient<i64, i64, _> = TypedLedgerClient::from_shared(untyped.clone());
+    let counter_address = Address::from_bech32(COUNTER_SCRIPT).unwrap();
+    let name_address = Address::from_bech32(NAME_SCRIPT).unwrap();
+    let name = PlutusData::BoundedBytes(b"naumachia".to_vec());
+    let lock = TxActions::v2()
+        .with_script_init(
+            PlutusData::from(9),
+            lovelace(2_000_000),
+            counter_address.clone(),
+        )
+        .with_script_init(name, lovelace(2_000_000), name_address.clone())
+        .to_unbuilt_tx()
+        .unwrap();
+    untyped.issue(lock).await.unwrap();
+
+    // when
+    let counter = counters
+        .all_outputs_at_address(&counter_address)
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let named = untyped
+        .all_outputs_at_address(&name_address)
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let redeem = TxActions::v2()
+        .with_typed_script_redeem(counter, 1, Box::new(CounterValidator))
+        .with_typed_script_redeem(named, (), Box::new(AlwaysTrueNameValidator))
+        .to_unbuilt_tx()
+        .unwrap();
+    untyped.issue(redeem).await.unwrap();
+
+    // then
+    let counter_balance = untyped
+        .balance_at_address(&counter_address, &PolicyId::Lovelace)
+        .await
+        .unwrap();
+    let name_balance = untyped
+        .balance_at_address(&name_address, &PolicyId::Lovelace)
+        .await
+        .unwrap();
+    assert_eq!(counter_balance, 0);
+    assert_eq!(name_balance, 0);
+}
@@ -1,21 +1,27 @@
-use crate::scripts::raw_validator_script::plutus_data::PlutusData;
+use crate::scripts::{raw_validator_script::plutus_data::PlutusData, ReferenceScript};
 use crate::PolicyId;
 use pallas_addresses::Address;
 use serde::{Deserialize, Serialize};
 
 use crate::values::Values;
 
+pub mod min_ada;
+
+pub use min_ada::DatumEncoding;
+
 // TODO: Does this need to be separated?
 #[derive(Clone, PartialEq, Debug, Eq, Deserialize, Serialize)]
 pub enum UnbuiltOutput<Datum> {
     Wallet {
         owner: String,
         values: Values,
+        reference_script: Option<ReferenceScript>,
     },
     Validator {
         script_address: String,
         values: Values,
         datum: Datum,
+        reference_script: Option<ReferenceScript>,
     },
 }
 
@@ -24,6 +30,7 @@ impl<Datum> UnbuiltOutput<Datum> {
         UnbuiltOutput::Wallet {
             owner: owner.to_bech32().expect("already validated"),
             values,
+            reference_script: None,
         }
     }
 
@@ -32,7 +39,20 @@ impl<Datum> UnbuiltOutput<Datum> {
             script_address: script_address.to_bech32().expect("Already validated"),
             values,
             datum,
+            reference_script: None,
+        }
+    }
+
+    pub fn with_reference_script(mut self, script: ReferenceScript) -> Self {
+        match &mut self {
+            UnbuiltOutput::Wallet {
+                reference_script, ..
+            } => *reference_script = Some(script),
+            UnbuiltOutput::Validator {
+                reference_script, ..
+            } => *reference_script = Some(script),
         }
+        self
     }
 
     pub fn owner(&self) -> Address {
@@ -59,6 +79,45 @@ impl<Datum> UnbuiltOutput<Datum> {
             UnbuiltOutput::Validator { datum, .. } => Some(datum),
         }
     }
+
+    pub fn reference_script(&self) -> Option<&ReferenceScript> {
+        match self {
+            UnbuiltOutput::Wallet {
+                reference_script, ..
+            } => reference_script.as_ref(),
+            UnbuiltOutput::Validator {
+                reference_script, ..
+            } => reference_script.as_ref(),
+        }
+    }
+}
+
+impl<Datum: Into<PlutusData>> UnbuiltOutput<Datum> {
+    /// Same output with its datum as raw `PlutusData`
+    pub fn into_untyped(self) -> UnbuiltOutput<PlutusData> {
+        match self {
+            UnbuiltOutput::Wallet {
+                owner,
+                values,
+                reference_script,
+            } => UnbuiltOutput::Wallet {
+                owner,
+                values,
+                reference_script,
+            },
+            UnbuiltOutput::Validator {
+                script_address,
+                values,
+                datum,
+                reference_script,
+            } => UnbuiltOutput::Validator {
+                script_address,
+                values,
+                datum: datum.into(),
+                reference_script,
+            },
+        }
+    }
 }
 
 #[derive(Clone, PartialEq, Debug, Eq, Deserialize, Serialize)]
@@ -99,6 +158,8 @@ pub struct Output<Datum> {
     owner: String,
     values: Values,
     datum: DatumKind<Datum>,
+    datum_encoding: Option<DatumEncoding>,
+    reference_script: Option<ReferenceScript>,
 }
 
 #[derive(Clone, PartialEq, Debug, Eq, Deserialize, Serialize)]
@@ -132,6 +193,8 @@ impl<Datum> Output<Datum> {
             owner: addr,
             values,
             datum: DatumKind::None,
+            datum_encoding: None,
+            reference_script: None,
         }
     }
 
@@ -149,6 +212,8 @@ impl<Datum> Output<Datum> {
             owner: addr,
             values,
             datum: DatumKind::Typed(datum),
+            datum_encoding: None,
+            reference_script: None,
         }
     }
 
@@ -166,6 +231,8 @@ impl<Datum> Output<Datum> {
             owner: addr,
             values,
             datum: DatumKind::UnTyped(datum),
+            datum_encoding: None,
+            reference_script: None,
         }
     }
 
@@ -184,6 +251,25 @@ impl<Datum> Output<Datum> {
     pub fn datum(&self) -> &DatumKind<Datum> {
         &self.datum
     }
+
+    /// Whether the datum is inline or hashed, if the ledger reported it
+    pub fn datum_encoding(&self) -> Option<DatumEncoding> {
+        self.datum_encoding
+    }
+
+    pub fn with_datum_encoding(mut self, datum_encoding: DatumEncoding) -> Self {
+        self.datum_encoding = Some(datum_encoding);
+        self
+    }
+
+    pub fn reference_script(&self) -> Option<&ReferenceScript> {
+        self.reference_script.as_ref()
+    }
+
+    pub fn with_reference_script(mut self, script: ReferenceScript) -> Self {
+        self.reference_script = Some(script);
+        self
+    }
 }
 
 impl<Datum: Clone> Output<Datum> {
@@ -208,6 +294,8 @@ impl<Datum: Clone + Into<PlutusData>> Output<Datum> {
             owner: self.owner.clone(),
             values: self.values.clone(),
             datum: new_datum,
+            datum_encoding: self.datum_encoding,
+            reference_script: self.reference_script.clone(),
         }
     }
 
@@ -218,6 +306,23 @@ impl<Datum: Clone + Into<PlutusData>> Output<Datum> {
             DatumKind::None => None,
         }
     }
+
+    /// Same output with its datum as raw `PlutusData`
+    pub fn into_untyped(self) -> Output<PlutusData> {
+        let datum = match self.datum {
+            DatumKind::Typed(datum) => DatumKind::Typed(datum.into()),
+            DatumKind::UnTyped(data) => DatumKind::Typed(data),
+            DatumKind::None => DatumKind::None,
+        };
+        Output {
+            id: self.id,
+            owner: self.owner,
+            values: self.values,
+            datum,
+            datum_encoding: self.datum_encoding,
+            reference_script: self.reference_script,
+        }
+    }
 }
 
 impl<Datum: Clone + TryFrom<PlutusData>> Output<Datum> {
@@ -239,6 +344,27 @@ impl<Datum: Clone + TryFrom<PlutusData>> Output<Datum> {
             owner: self.owner.clone(),
             values: self.values.clone(),
             datum: new_datum,
+            datum_encoding: self.datum_encoding,
+            reference_script: self.reference_script.clone(),
+        }
+    }
+}
+
+impl Output<PlutusData> {
+    /// Decode the datum as `Datum`. Datums that don't decode are kept untyped.
+    pub fn into_typed<Datum: Clone + TryFrom<PlutusData>>(self) -> Output<Datum> {
+        let datum = match self.datum {
+            DatumKind::Typed(data) | DatumKind::UnTyped(data) => DatumKind::UnTyped(data),
+            DatumKind::None => DatumKind::None,
+        };
+        Output {
+            id: self.id,
+            owner: self.owner,
+            values: self.values,
+            datum,
+            datum_encoding: self.datum_encoding,
+            reference_script: self.reference_script,
         }
+        .with_typed_datum_if_possible()
     }
 }
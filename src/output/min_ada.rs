@@ -0,0 +1,172 @@
+use crate::{
+    output::UnbuiltOutput,
+    scripts::{raw_validator_script::plutus_data::PlutusData, ReferenceScript},
+    values::Values,
+    PolicyId,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[cfg(test)]
+mod tests;
+
+/// Bytes the ledger adds to every output's serialized size for the UTxO entry itself
+pub const MIN_UTXO_OVERHEAD_BYTES: u64 = 160;
+
+/// `coinsPerUTxOByte` of the current mainnet and testnet protocol parameters
+pub const DEFAULT_COINS_PER_UTXO_BYTE: u64 = 4310;
+
+const DATUM_HASH_LEN: u64 = 32;
+
+/// How a validator output's datum is attached: in the output itself, or as a hash of a datum
+/// supplied by the spending tx
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DatumEncoding {
+    Inline,
+    Hash,
+}
+
+/// Minimum lovelace an output must hold, following the Babbage rule:
+/// `(160 + serialized output size) * coinsPerUTxOByte`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MinAdaCalculator {
+    coins_per_utxo_byte: u64,
+    datum_encoding: DatumEncoding,
+}
+
+impl Default for MinAdaCalculator {
+    fn default() -> Self {
+        MinAdaCalculator::new(DEFAULT_COINS_PER_UTXO_BYTE)
+    }
+}
+
+impl MinAdaCalculator {
+    pub fn new(coins_per_utxo_byte: u64) -> Self {
+        MinAdaCalculator {
+            coins_per_utxo_byte,
+            datum_encoding: DatumEncoding::Hash,
+        }
+    }
+
+    /// Size datums as inline datums instead of datum hashes, which the CML client attaches
+    pub fn with_datum_encoding(mut self, datum_encoding: DatumEncoding) -> Self {
+        self.datum_encoding = datum_encoding;
+        self
+    }
+
+    pub fn coins_per_utxo_byte(&self) -> u64 {
+        self.coins_per_utxo_byte
+    }
+
+    pub fn min_ada<Datum: Into<PlutusData> + Clone>(&self, output: &UnbuiltOutput<Datum>) -> u64 {
+        self.min_ada_for_size(self.output_size(output))
+    }
+
+    /// Minimum for an output that is already serialized, e.g. by a backend's tx builder
+    pub fn min_ada_for_size(&self, serialized_size: u64) -> u64 {
+        (MIN_UTXO_OVERHEAD_BYTES + serialized_size) * self.coins_per_utxo_byte
+    }
+
+    /// Raise the lovelace of `output` to its minimum. Outputs already holding enough are unchanged.
+    pub fn top_up<Datum: Into<PlutusData> + Clone>(
+        &self,
+        mut output: UnbuiltOutput<Datum>,
+    ) -> UnbuiltOutput<Datum> {
+        // Adding lovelace can grow the encoded coin, which can raise the minimum again
+        loop {
+            let required = self.min_ada(&output);
+            let values = values_mut(&mut output);
+            let current = values.get(&PolicyId::Lovelace).unwrap_or_default();
+            if current >= required {
+                return output;
+            }
+            values.take(&PolicyId::Lovelace);
+            values.add_one_value(&PolicyId::Lovelace, required);
+        }
+    }
+
+    /// Estimated size of the output in its map (post-Alonzo) CBOR form
+    fn output_size<Datum: Into<PlutusData> + Clone>(&self, output: &UnbuiltOutput<Datum>) -> u64 {
+        let address_len = output.owner().to_vec().len() as u64;
+        let mut entries = 2;
+        let mut size = 1 + bytes_len(address_len) + 1 + value_size(output.values());
+        if let Some(datum) = output.datum() {
+            entries += 1;
+            size += 1 + self.datum_size(datum);
+        }
+        if let Some(script) = output.reference_script() {
+            entries += 1;
+            size += 1 + script_ref_size(script);
+        }
+        head_len(entries) + size
+    }
+
+    fn datum_size<Datum: Into<PlutusData> + Clone>(&self, datum: &Datum) -> u64 {
+        // [0, hash] or [1, #6.24(bytes)]
+        match self.datum_encoding {
+            DatumEncoding::Hash => 1 + 1 + bytes_len(DATUM_HASH_LEN),
+            DatumEncoding::Inline => {
+                let data: PlutusData = datum.clone().into();
+                1 + 1 + 2 + bytes_len(data.bytes().len() as u64)
+            }
+        }
+    }
+}
+
+fn values_mut<Datum>(output: &mut UnbuiltOutput<Datum>) -> &mut Values {
+    match output {
+        UnbuiltOutput::Wallet { values, .. } => values,
+        UnbuiltOutput::Validator { values, .. } => values,
+    }
+}
+
+fn value_size(values: &Values) -> u64 {
+    let coin = values.get(&PolicyId::Lovelace).unwrap_or_default();
+    let mut policies: BTreeMap<&str, Vec<(u64, u64)>> = BTreeMap::new();
+    for (policy_id, amount) in values.as_iter() {
+        if let PolicyId::NativeToken(id, asset_name) = policy_id {
+            let name_len = asset_name.as_ref().map(|name| name.len()).unwrap_or(0) as u64;
+            policies
+                .entry(id.as_str())
+                .or_default()
+                .push((name_len, *amount));
+        }
+    }
+    if policies.is_empty() {
+        return head_len(coin);
+    }
+    // [coin, { policy_id => { asset_name => amount } }]
+    let multi_asset_size: u64 = policies
+        .iter()
+        .map(|(id, assets)| {
+            let policy_len = (id.len() / 2) as u64;
+            let assets_size: u64 = assets
+                .iter()
+                .map(|(name_len, amount)| bytes_len(*name_len) + head_len(*amount))
+                .sum();
+            bytes_len(policy_len) + head_len(assets.len() as u64) + assets_size
+        })
+        .sum();
+    1 + head_len(coin) + head_len(policies.len() as u64) + multi_asset_size
+}
+
+fn script_ref_size(script: &ReferenceScript) -> u64 {
+    // #6.24(bytes([language, script]))
+    let script_len = (script.script_hex().len() / 2) as u64;
+    2 + bytes_len(1 + 1 + script_len)
+}
+
+fn bytes_len(len: u64) -> u64 {
+    head_len(len) + len
+}
+
+/// Length of a CBOR major type header carrying `n`
+fn head_len(n: u64) -> u64 {
+    match n {
+        0..=23 => 1,
+        24..=0xff => 2,
+        0x100..=0xffff => 3,
+        0x1_0000..=0xffff_ffff => 5,
+        _ => 9,
+    }
+}
@@ -0,0 +1,113 @@
+#![allow(non_snake_case)]
+
+use super::*;
+use crate::transaction::TransactionVersion;
+use pallas_addresses::Address;
+
+const ALICE: &str = "addr_test1qrmezjhpelwzvz83wjl0e6mx766de7j3nksu2338s00yzx870xyxfa97xyz2zn5rknyntu5g0c66s7ktjnx0p6f0an6s3dyxwr";
+const POLICY: &str = "3f1b5974f4f09f0974be655e4ce94f8a2d087df378b79ef3916c26b2";
+
+fn wallet_output(values: Values) -> UnbuiltOutput<PlutusData> {
+    UnbuiltOutput::new_wallet(Address::from_bech32(ALICE).unwrap(), values)
+}
+
+#[test]
+fn min_ada__lovelace_only_output() {
+    let output = wallet_output(Values::default());
+
+    let actual = MinAdaCalculator::default().min_ada(&output);
+
+    // map header + address (key, 2 byte header, 57 bytes) + value (key, 1 byte coin)
+    let expected = (MIN_UTXO_OVERHEAD_BYTES + 63) * DEFAULT_COINS_PER_UTXO_BYTE;
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn min_ada__grows_with_native_assets() {
+    // given
+    let calculator = MinAdaCalculator::default();
+    let mut one_asset = Values::default();
+    one_asset.add_one_value(&PolicyId::native_token(POLICY, &Some("A".to_string())), 1);
+    let mut two_assets = one_asset.clone();
+    two_assets.add_one_value(&PolicyId::native_token(POLICY, &Some("B".to_string())), 1);
+
+    // when
+    let ada_only = calculator.min_ada(&wallet_output(Values::default()));
+    let with_one = calculator.min_ada(&wallet_output(one_asset));
+    let with_two = calculator.min_ada(&wallet_output(two_assets));
+
+    // then
+    assert!(ada_only < with_one);
+    assert!(with_one < with_two);
+}
+
+#[test]
+fn min_ada__inline_datum_is_sized_but_datum_hash_is_fixed() {
+    // given
+    let address = Address::from_bech32(ALICE).unwrap();
+    let small = UnbuiltOutput::new_validator(
+        address.clone(),
+        Values::default(),
+        PlutusData::BoundedBytes(vec![0; 4]),
+    );
+    let large = UnbuiltOutput::new_validator(
+        address,
+        Values::default(),
+        PlutusData::BoundedBytes(vec![0; 40]),
+    );
+    let inline = MinAdaCalculator::default().with_datum_encoding(DatumEncoding::Inline);
+    let hash = MinAdaCalculator::default();
+
+    // then
+    assert_eq!(
+        inline.min_ada(&large) - inline.min_ada(&small),
+        38 * DEFAULT_COINS_PER_UTXO_BYTE
+    );
+    assert_eq!(hash.min_ada(&large), hash.min_ada(&small));
+}
+
+#[test]
+fn min_ada__counts_reference_script() {
+    // given
+    let calculator = MinAdaCalculator::default();
+    let script = ReferenceScript::new(TransactionVersion::V2, &"00".repeat(100));
+    let without_script = wallet_output(Values::default());
+    let with_script = without_script.clone().with_reference_script(script);
+
+    // when
+    let difference = calculator.min_ada(&with_script) - calculator.min_ada(&without_script);
+
+    // then
+    // key, tag, 2 byte header, array header, language and 100 bytes of script
+    assert_eq!(difference, 107 * DEFAULT_COINS_PER_UTXO_BYTE);
+}
+
+#[test]
+fn top_up__raises_lovelace_to_min_ada() {
+    // given
+    let calculator = MinAdaCalculator::default();
+    let mut values = Values::default();
+    values.add_one_value(&PolicyId::native_token(POLICY, &None), 1);
+    let output = wallet_output(values);
+
+    // when
+    let topped_up = calculator.top_up(output);
+
+    // then
+    let lovelace = topped_up.values().get(&PolicyId::Lovelace).unwrap();
+    assert_eq!(lovelace, calculator.min_ada(&topped_up));
+}
+
+#[test]
+fn top_up__keeps_outputs_that_hold_enough() {
+    // given
+    let mut values = Values::default();
+    values.add_one_value(&PolicyId::Lovelace, 5_000_000);
+    let output = wallet_output(values);
+
+    // when
+    let topped_up = MinAdaCalculator::default().top_up(output.clone());
+
+    // then
+    assert_eq!(topped_up, output);
+}
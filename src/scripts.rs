@@ -1,11 +1,16 @@
+use crate::transaction::TransactionVersion;
+use cardano_multiplatform_lib::plutus::{PlutusScript, PlutusV1Script, PlutusV2Script};
 use context::TxContext;
 use pallas_addresses::{Address, Network};
+use serde::{Deserialize, Serialize};
 use std::fmt::Debug;
 use thiserror::Error;
 
 pub mod raw_policy_script;
 pub mod raw_script;
+pub mod raw_staking_script;
 pub mod raw_validator_script;
+pub mod untyped;
 
 pub mod context;
 
@@ -21,6 +26,73 @@ pub trait MintingPolicy<R>: Send + Sync {
     fn script_hex(&self) -> ScriptResult<String>;
 }
 
+/// Script that authorizes certificates and reward withdrawals for a script stake credential
+pub trait StakingValidator<R>: Send + Sync {
+    fn execute(&self, redeemer: R, ctx: TxContext) -> ScriptResult<ExecutionCost>;
+    /// Hex encoded script hash, i.e. the stake credential
+    fn hash(&self) -> ScriptResult<String>;
+    fn script_hex(&self) -> ScriptResult<String>;
+}
+
+/// Script stored on an output so later transactions can use it by reference (CIP-33)
+#[derive(Clone, PartialEq, Debug, Eq, Deserialize, Serialize)]
+pub struct ReferenceScript {
+    version: TransactionVersion,
+    script_hex: String,
+}
+
+impl ReferenceScript {
+    pub fn new(version: TransactionVersion, script_hex: &str) -> Self {
+        ReferenceScript {
+            version,
+            script_hex: script_hex.to_string(),
+        }
+    }
+
+    pub fn from_validator<D, R>(
+        version: TransactionVersion,
+        validator: &(dyn ValidatorCode<D, R> + '_),
+    ) -> ScriptResult<Self> {
+        let script_hex = validator.script_hex()?;
+        Ok(ReferenceScript::new(version, &script_hex))
+    }
+
+    pub fn from_policy<R>(
+        version: TransactionVersion,
+        policy: &(dyn MintingPolicy<R> + '_),
+    ) -> ScriptResult<Self> {
+        let script_hex = policy.script_hex()?;
+        Ok(ReferenceScript::new(version, &script_hex))
+    }
+
+    pub fn version(&self) -> &TransactionVersion {
+        &self.version
+    }
+
+    pub fn script_hex(&self) -> &str {
+        &self.script_hex
+    }
+
+    // TODO: I'd prefer not to be taking a dep on CML here
+    pub fn hash(&self) -> ScriptResult<Vec<u8>> {
+        let script_bytes =
+            hex::decode(&self.script_hex).map_err(|e| ScriptError::IdRetrieval(e.to_string()))?;
+        let script = match self.version {
+            TransactionVersion::V1 => {
+                let v1 = PlutusV1Script::from_bytes(script_bytes)
+                    .map_err(|e| ScriptError::IdRetrieval(e.to_string()))?;
+                PlutusScript::from_v1(&v1)
+            }
+            TransactionVersion::V2 => {
+                let v2 = PlutusV2Script::from_bytes(script_bytes)
+                    .map_err(|e| ScriptError::IdRetrieval(e.to_string()))?;
+                PlutusScript::from_v2(&v2)
+            }
+        };
+        Ok(script.hash().to_bytes())
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct ExecutionCost {
     mem: i64,
@@ -61,6 +133,8 @@ pub enum ScriptError {
     IdRetrieval(String),
     #[error("Failed to retrieve script Cbor Hex")]
     ScriptHexRetrieval(String),
+    #[error("Invalid script context: {0:?}")]
+    InvalidContext(String),
 }
 
 pub fn as_failed_to_execute<E: Debug>(e: E) -> ScriptError {
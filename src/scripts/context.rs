@@ -1,10 +1,10 @@
 use crate::{
-    output::Output, scripts::raw_validator_script::plutus_data::PlutusData, values::Values,
-    PolicyId,
+    output::Output, scripts::raw_validator_script::plutus_data::PlutusData,
+    transaction::staking::StakeCredential, values::Values, PolicyId,
 };
-use pallas_addresses::Address;
+use pallas_addresses::{Address, ShelleyDelegationPart};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::{collections::HashMap, ops::AddAssign};
 
 // TODO: Flesh out and probably move https://github.com/MitchTurner/naumachia/issues/39
 // TODO: This should be shaped like the real one actually. That will be extra useful because we can
@@ -15,17 +15,35 @@ pub struct TxContext {
     pub signer: PubKeyHash,
     pub range: ValidRange,
     pub inputs: Vec<Input>,
+    pub reference_inputs: Vec<Input>,
     pub outputs: Vec<CtxOutput>,
+    /// Lovelace
+    pub fee: u64,
+    pub mint: CtxMintValue,
+    pub dcert: Vec<CtxDCert>,
+    pub wdrl: Vec<(StakeCredential, u64)>,
     pub extra_signatories: Vec<PubKeyHash>,
+    /// Redeemer of every script run by the tx, keyed by what it's run for
+    pub redeemers: Vec<(CtxScriptPurpose, PlutusData)>,
     pub datums: Vec<(Vec<u8>, PlutusData)>,
+    /// Hash of the tx body
+    pub id: Vec<u8>,
 }
 
 #[derive(Clone, Debug)]
 pub enum CtxScriptPurpose {
     Mint(Vec<u8>),
     Spend(CtxOutputReference),
-    WithdrawFrom,
-    Publish,
+    WithdrawFrom(StakeCredential),
+    Publish(CtxDCert),
+}
+
+/// Certificates a script can see. Only the stake credential ones are supported.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CtxDCert {
+    DelegRegKey(StakeCredential),
+    DelegDeRegKey(StakeCredential),
+    DelegDelegate(StakeCredential, Vec<u8>),
 }
 
 #[derive(Clone, Debug)]
@@ -68,6 +86,17 @@ pub fn pub_key_hash_from_address_if_available(address: &Address) -> Option<PubKe
     }
 }
 
+/// Retrieves stake key hash if Address is a Shelley with a key delegation part
+pub fn stake_key_hash_from_address_if_available(address: &Address) -> Option<PubKeyHash> {
+    match address {
+        Address::Shelley(shelley_address) => match shelley_address.delegation() {
+            ShelleyDelegationPart::Key(hash) => Some(PubKeyHash::new(&hash.to_vec())),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
 /// valid range of tx in milliseconds
 #[derive(Clone, Debug)]
 pub struct ValidRange {
@@ -118,6 +147,18 @@ impl From<Values> for CtxValue {
     }
 }
 
+/// Minted (positive) and burned (negative) amounts of a tx, keyed by policy id and asset name
+#[derive(Clone, Debug, Default)]
+pub struct CtxMintValue {
+    pub inner: HashMap<String, HashMap<String, i64>>,
+}
+
+impl CtxMintValue {
+    pub fn add(&mut self, policy_id: &str, asset_name: &str, amt: i64) {
+        add_to_nested(&mut self.inner, policy_id, asset_name, amt);
+    }
+}
+
 #[derive(Clone, Debug)]
 pub enum CtxDatum {
     NoDatum,
@@ -138,9 +179,16 @@ pub struct ContextBuilder {
     signer: PubKeyHash,
     range: Option<ValidRange>,
     inputs: Vec<Input>,
+    reference_inputs: Vec<Input>,
     outputs: Vec<CtxOutput>,
+    fee: u64,
+    mint: CtxMintValue,
+    dcert: Vec<CtxDCert>,
+    wdrl: Vec<(StakeCredential, u64)>,
     extra_signatories: Vec<PubKeyHash>,
+    redeemers: Vec<(CtxScriptPurpose, PlutusData)>,
     datums: Vec<(Vec<u8>, PlutusData)>,
+    id: Vec<u8>,
 }
 
 impl ContextBuilder {
@@ -149,9 +197,16 @@ impl ContextBuilder {
             signer,
             range: None,
             inputs: vec![],
+            reference_inputs: vec![],
             outputs: vec![],
+            fee: 0,
+            mint: CtxMintValue::default(),
+            dcert: vec![],
+            wdrl: vec![],
             extra_signatories: vec![],
+            redeemers: vec![],
             datums: vec![],
+            id: vec![],
         }
     }
 
@@ -175,6 +230,7 @@ impl ContextBuilder {
             datum: CtxDatum::NoDatum,
             reference_script: None,
             output_index,
+            is_reference: false,
         }
     }
 
@@ -184,21 +240,32 @@ impl ContextBuilder {
     }
 
     pub fn add_specific_input<D: Clone + Into<PlutusData>>(mut self, input: &Output<D>) -> Self {
-        let id = input.id();
-        let transaction_id = id.tx_hash().to_vec();
-        let output_index = id.index();
-        let address = input.owner();
-        let value = CtxValue::from(input.values().to_owned());
-        let datum = input.typed_datum().into();
-        let ctx_input = Input {
-            transaction_id,
-            output_index,
-            address,
-            value,
-            datum,
-            reference_script: None,
-        };
-        self.inputs.push(ctx_input);
+        self.inputs.push(input_from_output(input));
+        self
+    }
+
+    pub fn with_reference_input(
+        self,
+        transaction_id: &[u8],
+        output_index: u64,
+        address: &Address,
+    ) -> InputBuilder {
+        InputBuilder {
+            is_reference: true,
+            ..self.with_input(transaction_id, output_index, address)
+        }
+    }
+
+    fn add_reference_input(mut self, input: Input) -> ContextBuilder {
+        self.reference_inputs.push(input);
+        self
+    }
+
+    pub fn add_specific_reference_input<D: Clone + Into<PlutusData>>(
+        mut self,
+        input: &Output<D>,
+    ) -> Self {
+        self.reference_inputs.push(input_from_output(input));
         self
     }
 
@@ -221,48 +288,83 @@ impl ContextBuilder {
         let address = input.owner();
         let value = CtxValue::from(input.values().to_owned());
         let datum = input.typed_datum().into();
+        let reference_script = input
+            .reference_script()
+            .and_then(|script| script.hash().ok());
         let ctx_input = CtxOutput {
             address,
             value,
             datum,
-            reference_script: None,
+            reference_script,
         };
         self.outputs.push(ctx_input);
         self
     }
 
+    pub fn with_fee(mut self, fee: u64) -> Self {
+        self.fee = fee;
+        self
+    }
+
+    /// Negative `amt` is a burn
+    pub fn with_mint(mut self, policy_id: &str, asset_name: &str, amt: i64) -> Self {
+        self.mint.add(policy_id, asset_name, amt);
+        self
+    }
+
+    pub fn add_certificate(mut self, dcert: CtxDCert) -> Self {
+        self.dcert.push(dcert);
+        self
+    }
+
+    pub fn add_withdrawal(mut self, credential: StakeCredential, amount: u64) -> Self {
+        self.wdrl.push((credential, amount));
+        self
+    }
+
     pub fn add_signatory(mut self, signer: PubKeyHash) -> Self {
         self.extra_signatories.push(signer);
         self
     }
 
+    pub fn add_redeemer<Redeemer: Into<PlutusData>>(
+        mut self,
+        purpose: CtxScriptPurpose,
+        redeemer: Redeemer,
+    ) -> Self {
+        self.redeemers.push((purpose, redeemer.into()));
+        self
+    }
+
     pub fn add_datum<Datum: Into<PlutusData>>(mut self, datum: Datum) -> Self {
         let data = datum.into();
         self.datums.push((data.hash(), data));
         self
     }
 
+    pub fn with_tx_id(mut self, tx_id: &[u8]) -> Self {
+        self.id = tx_id.to_vec();
+        self
+    }
+
     pub fn build_spend(&self, tx_id: &[u8], index: u64) -> TxContext {
-        let range = if let Some(range) = self.range.clone() {
-            range
-        } else {
-            ValidRange {
-                lower: None,
-                upper: None,
-            }
-        };
-        TxContext {
-            purpose: CtxScriptPurpose::Spend(CtxOutputReference::new(tx_id.to_vec(), index)),
-            signer: self.signer.clone(),
-            range,
-            inputs: self.inputs.clone(),
-            outputs: self.outputs.clone(),
-            extra_signatories: self.extra_signatories.clone(),
-            datums: self.datums.clone(),
-        }
+        let out_ref = CtxOutputReference::new(tx_id.to_vec(), index);
+        self.build(CtxScriptPurpose::Spend(out_ref))
     }
 
     pub fn build_mint(&self, policy_id: &[u8]) -> TxContext {
+        self.build(CtxScriptPurpose::Mint(policy_id.to_vec()))
+    }
+
+    pub fn build_withdraw(&self, credential: &StakeCredential) -> TxContext {
+        self.build(CtxScriptPurpose::WithdrawFrom(credential.clone()))
+    }
+
+    pub fn build_publish(&self, dcert: &CtxDCert) -> TxContext {
+        self.build(CtxScriptPurpose::Publish(dcert.clone()))
+    }
+
+    fn build(&self, purpose: CtxScriptPurpose) -> TxContext {
         let range = if let Some(range) = self.range.clone() {
             range
         } else {
@@ -272,13 +374,20 @@ impl ContextBuilder {
             }
         };
         TxContext {
-            purpose: CtxScriptPurpose::Mint(policy_id.to_vec()),
+            purpose,
             signer: self.signer.clone(),
             range,
             inputs: self.inputs.clone(),
+            reference_inputs: self.reference_inputs.clone(),
             outputs: self.outputs.clone(),
+            fee: self.fee,
+            mint: self.mint.clone(),
+            dcert: self.dcert.clone(),
+            wdrl: self.wdrl.clone(),
             extra_signatories: self.extra_signatories.clone(),
+            redeemers: self.redeemers.clone(),
             datums: self.datums.clone(),
+            id: self.id.clone(),
         }
     }
 }
@@ -291,6 +400,7 @@ pub struct InputBuilder {
     value: HashMap<String, HashMap<String, u64>>,
     datum: CtxDatum,
     reference_script: Option<Vec<u8>>,
+    is_reference: bool,
 }
 
 impl InputBuilder {
@@ -317,6 +427,11 @@ impl InputBuilder {
         self
     }
 
+    pub fn with_reference_script(mut self, script_hash: Vec<u8>) -> InputBuilder {
+        self.reference_script = Some(script_hash);
+        self
+    }
+
     pub fn finish_input(self) -> ContextBuilder {
         let value = CtxValue { inner: self.value };
         let input = Input {
@@ -327,7 +442,11 @@ impl InputBuilder {
             datum: self.datum,
             reference_script: self.reference_script,
         };
-        self.outer.add_input(input)
+        if self.is_reference {
+            self.outer.add_reference_input(input)
+        } else {
+            self.outer.add_input(input)
+        }
     }
 }
 
@@ -360,6 +479,11 @@ impl CtxOutputBuilder {
         self
     }
 
+    pub fn with_reference_script(mut self, script_hash: Vec<u8>) -> Self {
+        self.reference_script = Some(script_hash);
+        self
+    }
+
     pub fn finish_output(self) -> ContextBuilder {
         let value = CtxValue { inner: self.value };
         let output = CtxOutput {
@@ -372,11 +496,25 @@ impl CtxOutputBuilder {
     }
 }
 
-fn add_to_nested(
-    values: &mut HashMap<String, HashMap<String, u64>>,
+fn input_from_output<D: Clone + Into<PlutusData>>(output: &Output<D>) -> Input {
+    let id = output.id();
+    Input {
+        transaction_id: id.tx_hash().to_vec(),
+        output_index: id.index(),
+        address: output.owner(),
+        value: CtxValue::from(output.values().to_owned()),
+        datum: output.typed_datum().into(),
+        reference_script: output
+            .reference_script()
+            .and_then(|script| script.hash().ok()),
+    }
+}
+
+fn add_to_nested<T: AddAssign>(
+    values: &mut HashMap<String, HashMap<String, T>>,
     policy_id: &str,
     asset_name: &str,
-    amt: u64,
+    amt: T,
 ) {
     let new_assets = if let Some(mut assets) = values.remove(policy_id) {
         if let Some(mut total_amt) = assets.remove(asset_name) {
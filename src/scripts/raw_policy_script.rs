@@ -210,7 +210,7 @@ where
         let redeemer_data: PlutusData = redeemer.into();
         let redeemer_term = Term::Constant(Rc::new(Constant::Data(redeemer_data.into())));
         let program = program.apply_term(&redeemer_term);
-        let ctx_data: PlutusData = ctx.into();
+        let ctx_data = PlutusData::try_from(ctx)?;
         let ctx_term = Term::Constant(Rc::new(Constant::Data(ctx_data.into())));
         let program = program.apply_term(&ctx_term);
         let mut eval_result = match self.version {
@@ -0,0 +1,122 @@
+use crate::scripts::ExecutionCost;
+use crate::{
+    scripts::context::TxContext,
+    scripts::raw_script::ValidatorBlueprint,
+    scripts::ScriptError,
+    scripts::{
+        as_failed_to_execute,
+        raw_script::{PlutusScriptFile, RawPlutusScriptError, RawPlutusScriptResult},
+        raw_validator_script::plutus_data::PlutusData,
+        ReferenceScript, ScriptResult, StakingValidator,
+    },
+    transaction::TransactionVersion,
+};
+use minicbor::{Decoder, Encoder};
+use std::marker::PhantomData;
+use std::rc::Rc;
+use uplc::{
+    ast::{Constant, FakeNamedDeBruijn, NamedDeBruijn, Program, Term},
+    machine::cost_model::ExBudget,
+};
+
+pub struct RawStakingValidator<Redeemer> {
+    version: TransactionVersion,
+    cbor: Vec<u8>,
+    _redeemer: PhantomData<Redeemer>,
+}
+
+impl<R> RawStakingValidator<R> {
+    pub fn new_v1(script_file: PlutusScriptFile) -> RawPlutusScriptResult<Self> {
+        let cbor = hex::decode(script_file.cborHex)
+            .map_err(|e| RawPlutusScriptError::AikenApply(e.to_string()))?;
+        let mut outer_decoder = Decoder::new(&cbor);
+        let outer = outer_decoder
+            .bytes()
+            .map_err(|e| RawPlutusScriptError::AikenApply(e.to_string()))?;
+        let v1_validator = RawStakingValidator {
+            version: TransactionVersion::V1,
+            cbor: outer.to_vec(),
+            _redeemer: Default::default(),
+        };
+        Ok(v1_validator)
+    }
+
+    pub fn new_v2(script_file: PlutusScriptFile) -> RawPlutusScriptResult<Self> {
+        let cbor = hex::decode(script_file.cborHex)
+            .map_err(|e| RawPlutusScriptError::AikenApply(e.to_string()))?;
+        let mut outer_decoder = Decoder::new(&cbor);
+        let outer = outer_decoder
+            .bytes()
+            .map_err(|e| RawPlutusScriptError::AikenApply(e.to_string()))?;
+        let v2_validator = RawStakingValidator {
+            version: TransactionVersion::V2,
+            cbor: outer.to_vec(),
+            _redeemer: Default::default(),
+        };
+        Ok(v2_validator)
+    }
+
+    pub fn from_blueprint(blueprint: ValidatorBlueprint) -> RawPlutusScriptResult<Self> {
+        let cbor = hex::decode(blueprint.compiled_code())
+            .map_err(|e| RawPlutusScriptError::AikenApply(e.to_string()))?;
+        let v2_validator = RawStakingValidator {
+            version: TransactionVersion::V2,
+            cbor,
+            _redeemer: Default::default(),
+        };
+        Ok(v2_validator)
+    }
+
+    pub fn version(&self) -> TransactionVersion {
+        self.version.clone()
+    }
+}
+
+impl<Redeemer> StakingValidator<Redeemer> for RawStakingValidator<Redeemer>
+where
+    Redeemer: Into<PlutusData> + Send + Sync,
+{
+    fn execute(&self, redeemer: Redeemer, ctx: TxContext) -> ScriptResult<ExecutionCost> {
+        let program: Program<NamedDeBruijn> =
+            Program::<FakeNamedDeBruijn>::from_cbor(&self.cbor, &mut Vec::new())
+                .map_err(as_failed_to_execute)?
+                .into();
+        let redeemer_data: PlutusData = redeemer.into();
+        let redeemer_term = Term::Constant(Rc::new(Constant::Data(redeemer_data.into())));
+        let program = program.apply_term(&redeemer_term);
+        let ctx_data = PlutusData::try_from(ctx)?;
+        let ctx_term = Term::Constant(Rc::new(Constant::Data(ctx_data.into())));
+        let program = program.apply_term(&ctx_term);
+        let mut eval_result = match self.version {
+            TransactionVersion::V1 => program.eval_v1(),
+            TransactionVersion::V2 => program.eval(ExBudget::default()), // TODO: parameterize
+        };
+        let logs = eval_result.logs();
+        let cost = eval_result.cost();
+        eval_result
+            .result()
+            .map_err(|e| RawPlutusScriptError::AikenEval {
+                error: format!("{e:?}"),
+                logs,
+            })
+            .map_err(as_failed_to_execute)?;
+        Ok(cost.into())
+    }
+
+    fn hash(&self) -> ScriptResult<String> {
+        let script_hex = self.script_hex()?;
+        let hash = ReferenceScript::new(self.version.clone(), &script_hex).hash()?;
+        Ok(hex::encode(hash))
+    }
+
+    fn script_hex(&self) -> ScriptResult<String> {
+        let wrap = Encoder::new(Vec::new())
+            .bytes(&self.cbor)
+            .map_err(|e| ScriptError::ScriptHexRetrieval(e.to_string()))?
+            .clone()
+            .into_writer();
+
+        let hex = hex::encode(wrap);
+        Ok(hex)
+    }
+}
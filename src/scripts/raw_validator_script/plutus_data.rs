@@ -1,14 +1,16 @@
+use crate::address::policy_id_bytes;
 use crate::scripts::context::{
-    CtxDatum, CtxOutput, CtxOutputReference, CtxScriptPurpose, CtxValue, Input, PubKeyHash,
-    TxContext, ValidRange,
+    CtxDCert, CtxDatum, CtxMintValue, CtxOutput, CtxOutputReference, CtxScriptPurpose, CtxValue,
+    Input, PubKeyHash, TxContext, ValidRange,
 };
 use crate::scripts::ScriptError;
+use crate::transaction::staking::StakeCredential;
 use cardano_multiplatform_lib::ledger::common::hash::hash_plutus_data;
 use pallas_addresses::{Address, ShelleyDelegationPart, ShelleyPaymentPart};
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Serialize, Deserialize)]
 pub enum PlutusData {
     Constr(Constr<PlutusData>),
     Map(BTreeMap<PlutusData, PlutusData>),
@@ -35,13 +37,13 @@ impl PlutusData {
     }
 }
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Serialize, Deserialize)]
 pub struct Constr<T> {
     pub constr: u64,
     pub fields: Vec<T>,
 }
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Serialize, Deserialize)]
 pub enum BigInt {
     Int { neg: bool, val: u64 },
     BigUInt(Vec<u8>),
@@ -109,42 +111,47 @@ impl TryFrom<PlutusData> for i64 {
     }
 }
 
-// TODO: Don't hardcode values!
 // TODO: THIS IS V2 only right now! Add V1!
-impl From<TxContext> for PlutusData {
-    fn from(ctx: TxContext) -> Self {
+impl TryFrom<TxContext> for PlutusData {
+    type Error = ScriptError;
+
+    fn try_from(ctx: TxContext) -> Result<Self, Self::Error> {
         let inputs = PlutusData::Array(ctx.inputs.into_iter().map(Into::into).collect());
-        let reference_inputs = PlutusData::Array(vec![]);
+        let reference_inputs =
+            PlutusData::Array(ctx.reference_inputs.into_iter().map(Into::into).collect());
         let outputs = PlutusData::Array(ctx.outputs.into_iter().map(Into::into).collect());
         let fee = PlutusData::Map(BTreeMap::from([(
             PlutusData::BoundedBytes(Vec::new()),
             PlutusData::Map(BTreeMap::from([(
                 PlutusData::BoundedBytes(Vec::new()),
-                PlutusData::BigInt(999_i64.into()),
+                ctx.fee.into(),
             )])),
         )]));
-        let mint = PlutusData::Map(BTreeMap::from([(
-            PlutusData::BoundedBytes(Vec::new()),
-            PlutusData::Map(BTreeMap::from([(
-                PlutusData::BoundedBytes(Vec::new()),
-                PlutusData::BigInt(0_i64.into()),
-            )])),
-        )]));
-        let dcert = PlutusData::Array(vec![]);
-        let wdrl = PlutusData::Map(BTreeMap::new());
+        let mint = ctx.mint.into();
+        let dcert = PlutusData::Array(ctx.dcert.into_iter().map(Into::into).collect());
+        let wdrl = PlutusData::Map(
+            ctx.wdrl
+                .into_iter()
+                .map(|(credential, amount)| (credential.into(), amount.into()))
+                .collect(),
+        );
         let valid_range = ctx.range.into();
         let mut signers: Vec<_> = ctx.extra_signatories.into_iter().map(Into::into).collect();
         signers.push(ctx.signer.into());
         let signatories = PlutusData::Array(signers);
-        let redeemers = PlutusData::Map(BTreeMap::new());
+        let redeemers = PlutusData::Map(
+            ctx.redeemers
+                .into_iter()
+                .map(|(purpose, redeemer)| (purpose.into(), redeemer))
+                .collect(),
+        );
         let data = PlutusData::Map(
             ctx.datums
                 .into_iter()
                 .map(|(hash, data)| (PlutusData::BoundedBytes(hash), data))
                 .collect(),
         );
-        // TODO this id should be computed!
-        let id = wrap_with_constr(0, PlutusData::BoundedBytes(Vec::new()));
+        let id = wrap_with_constr(0, PlutusData::BoundedBytes(ctx.id));
         let tx_info = PlutusData::Constr(Constr {
             constr: 0,
             fields: vec![
@@ -162,8 +169,18 @@ impl From<TxContext> for PlutusData {
                 id,
             ],
         });
-        // Spending
-        let purpose = match ctx.purpose {
+        let purpose = ctx.purpose.into();
+
+        Ok(PlutusData::Constr(Constr {
+            constr: 0,
+            fields: vec![tx_info, purpose],
+        }))
+    }
+}
+
+impl From<CtxScriptPurpose> for PlutusData {
+    fn from(value: CtxScriptPurpose) -> Self {
+        match value {
             CtxScriptPurpose::Mint(policy_id) => {
                 let policy_id_data = PlutusData::BoundedBytes(policy_id);
                 wrap_with_constr(0, policy_id_data)
@@ -172,15 +189,33 @@ impl From<TxContext> for PlutusData {
                 let out_ref_data = out_ref.into();
                 wrap_with_constr(1, out_ref_data)
             }
-            _ => {
-                todo!()
-            }
+            CtxScriptPurpose::WithdrawFrom(credential) => wrap_with_constr(2, credential.into()),
+            CtxScriptPurpose::Publish(dcert) => wrap_with_constr(3, dcert.into()),
+        }
+    }
+}
+
+// StakingHash wrapping a PubKeyCredential or ScriptCredential
+impl From<StakeCredential> for PlutusData {
+    fn from(value: StakeCredential) -> Self {
+        let credential = match value {
+            StakeCredential::Key(pkh) => wrap_with_constr(0, pkh.into()),
+            StakeCredential::Script(hash) => wrap_with_constr(1, PlutusData::BoundedBytes(hash)),
         };
+        wrap_with_constr(0, credential)
+    }
+}
 
-        PlutusData::Constr(Constr {
-            constr: 0,
-            fields: vec![tx_info, purpose],
-        })
+impl From<CtxDCert> for PlutusData {
+    fn from(value: CtxDCert) -> Self {
+        match value {
+            CtxDCert::DelegRegKey(credential) => wrap_with_constr(0, credential.into()),
+            CtxDCert::DelegDeRegKey(credential) => wrap_with_constr(1, credential.into()),
+            CtxDCert::DelegDelegate(credential, pool_key_hash) => wrap_multiple_with_constr(
+                2,
+                vec![credential.into(), PlutusData::BoundedBytes(pool_key_hash)],
+            ),
+        }
     }
 }
 
@@ -268,98 +303,33 @@ fn constr_index(index: u64) -> u64 {
 
 impl From<ValidRange> for PlutusData {
     fn from(value: ValidRange) -> Self {
-        match (value.lower, value.upper) {
-            (None, None) => no_time_bound(),
-            (Some((bound, is_inclusive)), None) => lower_bound(bound, is_inclusive),
-            (None, Some(_)) => todo!(),
-            (Some(_), Some(_)) => todo!(),
-        }
-    }
-}
-
-fn no_time_bound() -> PlutusData {
-    PlutusData::Constr(Constr {
-        constr: 0,
-        fields: vec![
-            PlutusData::Constr(Constr {
-                constr: 0,
-                fields: vec![
-                    // NegInf
-                    PlutusData::Constr(Constr {
-                        constr: 0,
-                        fields: vec![],
-                    }),
-                    // Closure
-                    PlutusData::Constr(Constr {
-                        constr: 1,
-                        fields: vec![],
-                    }),
-                ],
-            }),
-            PlutusData::Constr(Constr {
-                constr: 0,
-                fields: vec![
-                    // PosInf
-                    PlutusData::Constr(Constr {
-                        constr: 2,
-                        fields: vec![],
-                    }),
-                    // Closure
-                    PlutusData::Constr(Constr {
-                        constr: 1,
-                        fields: vec![],
-                    }),
-                ],
-            }),
-        ],
-    })
-}
-
-fn lower_bound(bound: i64, is_inclusive: bool) -> PlutusData {
-    let closure = if is_inclusive {
-        // True
-        PlutusData::Constr(Constr {
-            constr: 1,
-            fields: vec![],
-        })
-    } else {
-        // False
+        // NegInf and PosInf
+        let lower = interval_bound(value.lower, 0);
+        let upper = interval_bound(value.upper, 2);
         PlutusData::Constr(Constr {
             constr: 0,
-            fields: vec![],
+            fields: vec![lower, upper],
         })
+    }
+}
+
+/// `Finite` time if bounded, otherwise the infinity at `infinite_index`, which is always closed
+fn interval_bound(bound: Option<(i64, bool)>, infinite_index: u64) -> PlutusData {
+    let (extended, is_inclusive) = match bound {
+        Some((time, is_inclusive)) => {
+            let finite = PlutusData::Constr(Constr {
+                constr: 1,
+                fields: vec![PlutusData::BigInt(time.into())],
+            });
+            (finite, is_inclusive)
+        }
+        None => (empty_constr(infinite_index), true),
     };
+    // Closure as False or True
+    let closure = empty_constr(u64::from(is_inclusive));
     PlutusData::Constr(Constr {
         constr: 0,
-        fields: vec![
-            PlutusData::Constr(Constr {
-                constr: 0,
-                fields: vec![
-                    // Finite
-                    PlutusData::Constr(Constr {
-                        constr: 1,
-                        fields: vec![PlutusData::BigInt(bound.into())],
-                    }),
-                    // Closure
-                    closure,
-                ],
-            }),
-            PlutusData::Constr(Constr {
-                constr: 0,
-                fields: vec![
-                    // PosInf
-                    PlutusData::Constr(Constr {
-                        constr: 2,
-                        fields: vec![],
-                    }),
-                    // Closure
-                    PlutusData::Constr(Constr {
-                        constr: 1,
-                        fields: vec![],
-                    }),
-                ],
-            }),
-        ],
+        fields: vec![extended, closure],
     })
 }
 
@@ -415,7 +385,7 @@ impl From<CtxValue> for PlutusData {
             .inner
             .iter()
             .map(|(p, a)| {
-                let policy_id = PlutusData::BoundedBytes(hex::decode(p).unwrap()); // TODO
+                let policy_id = PlutusData::BoundedBytes(policy_id_bytes(p));
                 let assets = a
                     .iter()
                     .map(|(an, amt)| {
@@ -431,6 +401,32 @@ impl From<CtxValue> for PlutusData {
     }
 }
 
+// The ledger always includes a zero ADA entry in the mint value
+impl From<CtxMintValue> for PlutusData {
+    fn from(value: CtxMintValue) -> Self {
+        let mut converted_inner = BTreeMap::from([(
+            PlutusData::BoundedBytes(Vec::new()),
+            PlutusData::Map(BTreeMap::from([(
+                PlutusData::BoundedBytes(Vec::new()),
+                PlutusData::BigInt(0_i64.into()),
+            )])),
+        )]);
+        for (p, a) in value.inner.iter() {
+            let policy_id = PlutusData::BoundedBytes(policy_id_bytes(p));
+            let assets = a
+                .iter()
+                .map(|(an, amt)| {
+                    let asset_name = PlutusData::BoundedBytes(an.as_bytes().to_vec());
+                    let amount = PlutusData::BigInt((*amt).into());
+                    (asset_name, amount)
+                })
+                .collect();
+            converted_inner.insert(policy_id, PlutusData::Map(assets));
+        }
+        PlutusData::Map(converted_inner)
+    }
+}
+
 impl From<CtxDatum> for PlutusData {
     fn from(value: CtxDatum) -> Self {
         match value {
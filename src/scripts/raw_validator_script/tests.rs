@@ -1,5 +1,9 @@
 use super::*;
-use crate::scripts::context::{pub_key_hash_from_address_if_available, ContextBuilder};
+use crate::scripts::context::{
+    pub_key_hash_from_address_if_available, ContextBuilder, CtxMintValue, CtxOutputReference,
+    CtxScriptPurpose, ValidRange,
+};
+use std::collections::BTreeMap;
 
 mod game;
 mod hello;
@@ -20,3 +24,94 @@ fn execute_always_succeeds() {
 
     script.execute((), (), ctx).unwrap();
 }
+
+#[test]
+fn tx_info_holds_fee_redeemers_and_id() {
+    let signer = Address::from_bech32("addr_test1qrksjmprvgcedgdt6rhg40590vr6exdzdc2hm5wc6pyl9ymkyskmqs55usm57gflrumk9kd63f3ty6r0l2tdfwfm28qs0rurdr").unwrap();
+    let signer_pkh = pub_key_hash_from_address_if_available(&signer).unwrap();
+    let tx_id = vec![7; 32];
+    let purpose = CtxScriptPurpose::Spend(CtxOutputReference::new(vec![1; 32], 0));
+
+    let ctx = ContextBuilder::new(signer_pkh)
+        .with_fee(170_000)
+        .add_redeemer(purpose.clone(), 42_i64)
+        .with_tx_id(&tx_id)
+        .build_spend(&[1; 32], 0);
+    let data = PlutusData::try_from(ctx).unwrap();
+
+    let fields = |data: &PlutusData| match data {
+        PlutusData::Constr(constr) => constr.fields.clone(),
+        _ => panic!("expected a constr"),
+    };
+    let tx_info = fields(&fields(&data)[0]);
+    let lovelace = |amount: i64| {
+        PlutusData::Map(BTreeMap::from([(
+            PlutusData::BoundedBytes(vec![]),
+            PlutusData::Map(BTreeMap::from([(
+                PlutusData::BoundedBytes(vec![]),
+                amount.into(),
+            )])),
+        )]))
+    };
+    assert_eq!(tx_info[3], lovelace(170_000));
+    assert_eq!(
+        tx_info[9],
+        PlutusData::Map(BTreeMap::from([(purpose.into(), 42_i64.into())]))
+    );
+    assert_eq!(
+        tx_info[11],
+        PlutusData::Constr(Constr {
+            constr: 0,
+            fields: vec![PlutusData::BoundedBytes(tx_id)],
+        })
+    );
+}
+
+#[test]
+fn valid_range_encodes_finite_bounds() {
+    let valid_range = ValidRange {
+        lower: Some((1_000, true)),
+        upper: Some((2_000, false)),
+    };
+
+    let data = PlutusData::from(valid_range);
+
+    let bound = |time: i64, is_inclusive: bool| {
+        PlutusData::Constr(Constr {
+            constr: 0,
+            fields: vec![
+                PlutusData::Constr(Constr {
+                    constr: 1,
+                    fields: vec![PlutusData::BigInt(time.into())],
+                }),
+                PlutusData::Constr(Constr {
+                    constr: u64::from(is_inclusive),
+                    fields: vec![],
+                }),
+            ],
+        })
+    };
+    assert_eq!(
+        data,
+        PlutusData::Constr(Constr {
+            constr: 0,
+            fields: vec![bound(1_000, true), bound(2_000, false)],
+        })
+    );
+}
+
+#[test]
+fn mint_value_takes_non_hex_policy_ids_as_their_bytes() {
+    let mut mint = CtxMintValue::default();
+    mint.add("abcd", "", 1);
+    mint.add("not hex", "", 2);
+
+    let data = PlutusData::from(mint);
+
+    let policies = match data {
+        PlutusData::Map(policies) => policies,
+        _ => panic!("expected a map"),
+    };
+    assert!(policies.contains_key(&PlutusData::BoundedBytes(vec![0xab, 0xcd])));
+    assert!(policies.contains_key(&PlutusData::BoundedBytes(b"not hex".to_vec())));
+}
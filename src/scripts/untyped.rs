@@ -0,0 +1,106 @@
+use crate::scripts::{
+    context::TxContext, raw_validator_script::plutus_data::PlutusData, ExecutionCost,
+    MintingPolicy, ScriptError, ScriptResult, StakingValidator, ValidatorCode,
+};
+use pallas_addresses::{Address, Network};
+
+/// Runs a typed validator on raw `PlutusData`, decoding the datum and redeemer first. Lets
+/// scripts with different datum and redeemer types share one untyped transaction.
+pub struct UntypedValidator<D, R> {
+    inner: Box<dyn ValidatorCode<D, R>>,
+}
+
+impl<D, R> UntypedValidator<D, R> {
+    pub fn new(inner: Box<dyn ValidatorCode<D, R>>) -> Self {
+        UntypedValidator { inner }
+    }
+}
+
+impl<D, R> ValidatorCode<PlutusData, PlutusData> for UntypedValidator<D, R>
+where
+    D: TryFrom<PlutusData> + Send + Sync,
+    R: TryFrom<PlutusData> + Send + Sync,
+{
+    fn execute(
+        &self,
+        datum: PlutusData,
+        redeemer: PlutusData,
+        ctx: TxContext,
+    ) -> ScriptResult<ExecutionCost> {
+        let datum = D::try_from(datum.clone())
+            .map_err(|_| ScriptError::DatumDeserialization(format!("{datum:?}")))?;
+        let redeemer = decode_redeemer(redeemer)?;
+        self.inner.execute(datum, redeemer, ctx)
+    }
+
+    fn address(&self, network: Network) -> ScriptResult<Address> {
+        self.inner.address(network)
+    }
+
+    fn script_hex(&self) -> ScriptResult<String> {
+        self.inner.script_hex()
+    }
+}
+
+/// `UntypedValidator` for minting policies
+pub struct UntypedMintingPolicy<R> {
+    inner: Box<dyn MintingPolicy<R>>,
+}
+
+impl<R> UntypedMintingPolicy<R> {
+    pub fn new(inner: Box<dyn MintingPolicy<R>>) -> Self {
+        UntypedMintingPolicy { inner }
+    }
+}
+
+impl<R> MintingPolicy<PlutusData> for UntypedMintingPolicy<R>
+where
+    R: TryFrom<PlutusData> + Send + Sync,
+{
+    fn execute(&self, redeemer: PlutusData, ctx: TxContext) -> ScriptResult<ExecutionCost> {
+        let redeemer = decode_redeemer(redeemer)?;
+        self.inner.execute(redeemer, ctx)
+    }
+
+    fn id(&self) -> ScriptResult<String> {
+        self.inner.id()
+    }
+
+    fn script_hex(&self) -> ScriptResult<String> {
+        self.inner.script_hex()
+    }
+}
+
+/// `UntypedValidator` for staking validators
+pub struct UntypedStakingValidator<R> {
+    inner: Box<dyn StakingValidator<R>>,
+}
+
+impl<R> UntypedStakingValidator<R> {
+    pub fn new(inner: Box<dyn StakingValidator<R>>) -> Self {
+        UntypedStakingValidator { inner }
+    }
+}
+
+impl<R> StakingValidator<PlutusData> for UntypedStakingValidator<R>
+where
+    R: TryFrom<PlutusData> + Send + Sync,
+{
+    fn execute(&self, redeemer: PlutusData, ctx: TxContext) -> ScriptResult<ExecutionCost> {
+        let redeemer = decode_redeemer(redeemer)?;
+        self.inner.execute(redeemer, ctx)
+    }
+
+    fn hash(&self) -> ScriptResult<String> {
+        self.inner.hash()
+    }
+
+    fn script_hex(&self) -> ScriptResult<String> {
+        self.inner.script_hex()
+    }
+}
+
+fn decode_redeemer<R: TryFrom<PlutusData>>(redeemer: PlutusData) -> ScriptResult<R> {
+    R::try_from(redeemer.clone())
+        .map_err(|_| ScriptError::RedeemerDeserialization(format!("{redeemer:?}")))
+}
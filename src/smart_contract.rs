@@ -1,14 +1,14 @@
 use async_trait::async_trait;
 use std::fmt::Debug;
 
-use crate::{error::Result, ledger_client::LedgerClient, logic::SCLogic};
+use crate::{error::Result, ledger_client::LedgerClient, logic::SCLogic, transaction::TxId};
 
 #[async_trait]
 pub trait SmartContractTrait {
     type Endpoint;
     type Lookup;
     type LookupResponse;
-    async fn hit_endpoint(&self, endpoint: Self::Endpoint) -> Result<()>;
+    async fn hit_endpoint(&self, endpoint: Self::Endpoint) -> Result<TxId>;
     async fn lookup(&self, lookup: Self::Lookup) -> Result<Self::LookupResponse>;
 }
 
@@ -53,12 +53,11 @@ where
     type Lookup = Logic::Lookups;
     type LookupResponse = Logic::LookupResponses;
 
-    async fn hit_endpoint(&self, endpoint: Logic::Endpoints) -> Result<()> {
+    async fn hit_endpoint(&self, endpoint: Logic::Endpoints) -> Result<TxId> {
         let tx_actions = Logic::handle_endpoint(endpoint, &self.ledger_client).await?;
         let tx = tx_actions.to_unbuilt_tx()?;
         let tx_id = self.ledger_client.issue(tx).await?;
-        println!("Transaction Submitted: {:?}", &tx_id);
-        Ok(())
+        Ok(tx_id)
     }
 
     async fn lookup(&self, lookup: Self::Lookup) -> Result<Self::LookupResponse> {
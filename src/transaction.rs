@@ -1,18 +1,30 @@
+use crate::transaction::metadata::{Metadatum, TransactionMetadata};
 use crate::transaction::nested_value_map::{add_amount_to_nested_map, nested_map_to_vecs};
+use crate::transaction::staking::{StakeCertificate, StakeCredential, StakeWitness, Withdrawal};
+use crate::transaction::validity::ValidityInterval;
 use crate::{
     address::PolicyId,
     backend::RedemptionDetails,
     error::*,
-    output::{Output, UnbuiltOutput},
-    scripts::{MintingPolicy, ValidatorCode},
+    output::{Output, OutputId, UnbuiltOutput},
+    scripts::{
+        context::PubKeyHash,
+        raw_validator_script::plutus_data::PlutusData,
+        untyped::{UntypedMintingPolicy, UntypedValidator},
+        ExecutionCost, MintingPolicy, ReferenceScript, ScriptResult, ValidatorCode,
+    },
     values::Values,
 };
 use pallas_addresses::Address;
+use serde::{Deserialize, Serialize};
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::fmt::Debug;
 
+pub mod metadata;
 pub(crate) mod nested_value_map;
+pub mod staking;
+pub mod validity;
 
 pub enum Action<Datum, Redeemer> {
     Transfer {
@@ -21,8 +33,8 @@ pub enum Action<Datum, Redeemer> {
         policy_id: PolicyId,
     },
     Mint {
-        amount: u64,
-        asset_name: Option<String>,
+        /// Asset names with their amounts. Negative amounts are burns
+        assets: Vec<(Option<String>, i64)>,
         redeemer: Redeemer,
         policy: Box<dyn MintingPolicy<Redeemer>>,
     },
@@ -31,6 +43,11 @@ pub enum Action<Datum, Redeemer> {
         values: Values,
         address: Address,
     },
+    InitReferenceScript {
+        script: ReferenceScript,
+        values: Values,
+        address: Address,
+    },
     RedeemScriptOutput {
         output: Output<Datum>,
         redeemer: Redeemer,
@@ -39,6 +56,30 @@ pub enum Action<Datum, Redeemer> {
     SpecificInput {
         input: Output<Datum>,
     },
+    ReferenceInput {
+        output: Output<Datum>,
+    },
+    ReferenceScriptInput {
+        output: Output<Datum>,
+        script_hex: ScriptResult<String>,
+    },
+    Metadata {
+        label: u64,
+        value: Metadatum,
+    },
+    StakeCertificate {
+        certificate: StakeCertificate<Redeemer>,
+    },
+    Withdrawal {
+        withdrawal: Withdrawal<Redeemer>,
+    },
+    RequiredSigner {
+        signer: PubKeyHash,
+    },
+    /// Builder input that can't be turned into a tx, reported by `to_unbuilt_tx`
+    Invalid {
+        error: Error,
+    },
 }
 
 // TODO: Maybe we should make V1 and V2 TxActions be completely different types,
@@ -46,7 +87,8 @@ pub enum Action<Datum, Redeemer> {
 pub struct TxActions<Datum, Redeemer> {
     pub script_version: TransactionVersion,
     pub actions: Vec<Action<Datum, Redeemer>>,
-    pub valid_range: Range,
+    pub valid_range: ValidityInterval,
+    pub min_ada_top_up: bool,
 }
 
 impl<Datum, Redeemer> TxActions<Datum, Redeemer> {
@@ -54,7 +96,8 @@ impl<Datum, Redeemer> TxActions<Datum, Redeemer> {
         TxActions {
             script_version: TransactionVersion::V1,
             actions: Vec::new(),
-            valid_range: (None, None),
+            valid_range: ValidityInterval::default(),
+            min_ada_top_up: false,
         }
     }
 
@@ -62,11 +105,45 @@ impl<Datum, Redeemer> TxActions<Datum, Redeemer> {
         TxActions {
             script_version: TransactionVersion::V2,
             actions: Vec::new(),
-            valid_range: (None, None),
+            valid_range: ValidityInterval::default(),
+            min_ada_top_up: false,
         }
     }
 }
 
+impl TxActions<PlutusData, PlutusData> {
+    /// `with_script_redeem` for a validator with its own datum and redeemer types, so one tx can
+    /// spend from contracts with different types
+    pub fn with_typed_script_redeem<D, R>(
+        self,
+        output: Output<D>,
+        redeemer: R,
+        script: Box<dyn ValidatorCode<D, R>>,
+    ) -> Self
+    where
+        D: Clone + Into<PlutusData> + TryFrom<PlutusData> + Send + Sync + 'static,
+        R: Into<PlutusData> + TryFrom<PlutusData> + Send + Sync + 'static,
+    {
+        let script = Box::new(UntypedValidator::new(script));
+        self.with_script_redeem(output.into_untyped(), redeemer.into(), script)
+    }
+
+    /// `with_mint` for a policy with its own redeemer type
+    pub fn with_typed_mint<R>(
+        self,
+        amount: u64,
+        asset_name: Option<String>,
+        redeemer: R,
+        policy: Box<dyn MintingPolicy<R>>,
+    ) -> Self
+    where
+        R: Into<PlutusData> + TryFrom<PlutusData> + Send + Sync + 'static,
+    {
+        let policy = Box::new(UntypedMintingPolicy::new(policy));
+        self.with_mint(amount, asset_name, redeemer.into(), policy)
+    }
+}
+
 impl<Datum: Clone, Redeemer> TxActions<Datum, Redeemer> {
     pub fn with_transfer(mut self, amount: u64, recipient: Address, policy_id: PolicyId) -> Self {
         let action = Action::Transfer {
@@ -78,16 +155,44 @@ impl<Datum: Clone, Redeemer> TxActions<Datum, Redeemer> {
         self
     }
 
+    /// `to_unbuilt_tx` errors if `amount` doesn't fit in an `i64`
     pub fn with_mint(
-        mut self,
+        self,
         amount: u64,
         asset_name: Option<String>,
         redeemer: Redeemer,
         policy: Box<dyn MintingPolicy<Redeemer>>,
+    ) -> Self {
+        match i64::try_from(amount) {
+            Ok(amount) => self.with_mint_assets(vec![(asset_name, amount)], redeemer, policy),
+            Err(_) => self.with_invalid(Error::MintAmountOutOfRange(amount)),
+        }
+    }
+
+    /// `to_unbuilt_tx` errors if `amount` doesn't fit in an `i64`
+    pub fn with_burn(
+        self,
+        amount: u64,
+        asset_name: Option<String>,
+        redeemer: Redeemer,
+        policy: Box<dyn MintingPolicy<Redeemer>>,
+    ) -> Self {
+        match i64::try_from(amount) {
+            Ok(amount) => self.with_mint_assets(vec![(asset_name, -amount)], redeemer, policy),
+            Err(_) => self.with_invalid(Error::MintAmountOutOfRange(amount)),
+        }
+    }
+
+    /// Mint (or burn, with negative amounts) several assets under one policy. The policy is run
+    /// once for all of them, like the ledger does.
+    pub fn with_mint_assets(
+        mut self,
+        assets: Vec<(Option<String>, i64)>,
+        redeemer: Redeemer,
+        policy: Box<dyn MintingPolicy<Redeemer>>,
     ) -> Self {
         let action = Action::Mint {
-            amount,
-            asset_name,
+            assets,
             redeemer,
             policy,
         };
@@ -105,6 +210,22 @@ impl<Datum: Clone, Redeemer> TxActions<Datum, Redeemer> {
         self
     }
 
+    /// Create an output at `address` that carries `script` as a reference script (CIP-33)
+    pub fn with_reference_script_output(
+        mut self,
+        script: ReferenceScript,
+        values: Values,
+        address: Address,
+    ) -> Self {
+        let action = Action::InitReferenceScript {
+            script,
+            values,
+            address,
+        };
+        self.actions.push(action);
+        self
+    }
+
     // TODO: This can prolly just take the Output ID
     pub fn with_script_redeem(
         mut self,
@@ -121,6 +242,97 @@ impl<Datum: Clone, Redeemer> TxActions<Datum, Redeemer> {
         self
     }
 
+    /// Same as `with_script_redeem`, but `script_output` is added as a reference input so the
+    /// validator is read from its reference script instead of being attached to the tx.
+    /// `to_unbuilt_tx` errors if `script_output` doesn't carry the validator as reference script.
+    pub fn with_script_redeem_by_reference(
+        mut self,
+        output: Output<Datum>,
+        redeemer: Redeemer,
+        script: Box<dyn ValidatorCode<Datum, Redeemer>>,
+        script_output: Output<Datum>,
+    ) -> Self {
+        let action = Action::ReferenceScriptInput {
+            output: script_output,
+            script_hex: script.script_hex(),
+        };
+        self.actions.push(action);
+        self.with_script_redeem(output, redeemer, script)
+    }
+
+    /// Same as `with_mint`, but `policy_output` is added as a reference input so the policy is
+    /// read from its reference script instead of being attached to the tx.
+    /// `to_unbuilt_tx` errors if `policy_output` doesn't carry the policy as reference script.
+    pub fn with_mint_by_reference(
+        mut self,
+        amount: u64,
+        asset_name: Option<String>,
+        redeemer: Redeemer,
+        policy: Box<dyn MintingPolicy<Redeemer>>,
+        policy_output: Output<Datum>,
+    ) -> Self {
+        let action = Action::ReferenceScriptInput {
+            output: policy_output,
+            script_hex: policy.script_hex(),
+        };
+        self.actions.push(action);
+        self.with_mint(amount, asset_name, redeemer, policy)
+    }
+
+    /// Attach `value` to the tx's auxiliary data under `label`. A later value for the same label
+    /// replaces an earlier one.
+    pub fn with_metadata(mut self, label: u64, value: Metadatum) -> Self {
+        let action = Action::Metadata { label, value };
+        self.actions.push(action);
+        self
+    }
+
+    /// Register `credential` so it can be delegated and earn rewards. Takes the stake deposit.
+    pub fn with_stake_registration(mut self, credential: StakeCredential) -> Self {
+        let certificate = StakeCertificate::Registration(credential);
+        let action = Action::StakeCertificate { certificate };
+        self.actions.push(action);
+        self
+    }
+
+    /// Deregister the witnessed credential and get the deposit back. Rewards must be withdrawn
+    /// first.
+    pub fn with_stake_deregistration(mut self, witness: StakeWitness<Redeemer>) -> Self {
+        let certificate = StakeCertificate::Deregistration(witness);
+        let action = Action::StakeCertificate { certificate };
+        self.actions.push(action);
+        self
+    }
+
+    pub fn with_stake_delegation(
+        mut self,
+        witness: StakeWitness<Redeemer>,
+        pool_key_hash: Vec<u8>,
+    ) -> Self {
+        let certificate = StakeCertificate::Delegation {
+            witness,
+            pool_key_hash,
+        };
+        let action = Action::StakeCertificate { certificate };
+        self.actions.push(action);
+        self
+    }
+
+    /// Withdraw rewards of the witnessed credential. `amount` has to be the full reward balance.
+    pub fn with_withdrawal(mut self, witness: StakeWitness<Redeemer>, amount: u64) -> Self {
+        let withdrawal = Withdrawal { witness, amount };
+        let action = Action::Withdrawal { withdrawal };
+        self.actions.push(action);
+        self
+    }
+
+    /// Require a signature from `signer`. Validators see it in `extra_signatories`.
+    pub fn with_required_signer(mut self, signer: PubKeyHash) -> Self {
+        let action = Action::RequiredSigner { signer };
+        self.actions.push(action);
+        self
+    }
+
     // **NOTE**: if you are using CML, this can break if your input is too small and you don't
     // specify any specific OUTPUTs: https://github.com/MitchTurner/naumachia/issues/73
     pub fn with_specific_input(mut self, input: Output<Datum>) -> Self {
@@ -129,9 +341,32 @@ impl<Datum: Clone, Redeemer> TxActions<Datum, Redeemer> {
         self
     }
 
+    /// Include `output` as a reference input (CIP-31). Validators can read it, but it isn't spent.
+    pub fn with_reference_input(mut self, output: Output<Datum>) -> Self {
+        let action = Action::ReferenceInput { output };
+        self.actions.push(action);
+        self
+    }
+
+    /// Raise the lovelace of every output to the ledger's minimum instead of failing on it
+    pub fn with_min_ada_top_up(mut self) -> Self {
+        self.min_ada_top_up = true;
+        self
+    }
+
     // Specify valid range in seconds since the Unix epoch
-    pub fn with_valid_range_secs(mut self, lower: Option<i64>, upper: Option<i64>) -> Self {
-        self.valid_range = (lower, upper);
+    pub fn with_valid_range_secs(self, lower: Option<i64>, upper: Option<i64>) -> Self {
+        self.with_validity_interval(ValidityInterval::posix_secs(lower, upper))
+    }
+
+    pub fn with_validity_interval(mut self, valid_range: ValidityInterval) -> Self {
+        self.valid_range = valid_range;
+        self
+    }
+
+    fn with_invalid(mut self, error: Error) -> Self {
+        let action = Action::Invalid { error };
+        self.actions.push(action);
         self
     }
 
@@ -146,6 +381,11 @@ impl<Datum: Clone, Redeemer> TxActions<Datum, Redeemer> {
         let mut script_inputs: Vec<RedemptionDetails<Datum, Redeemer>> = Vec::new();
         let mut specific_outputs: Vec<UnbuiltOutput<Datum>> = Vec::new();
         let mut specific_wallet_inputs: Vec<Output<Datum>> = Vec::new();
+        let mut reference_inputs: Vec<Output<Datum>> = Vec::new();
+        let mut metadata = TransactionMetadata::default();
+        let mut certificates = Vec::new();
+        let mut withdrawals = Vec::new();
+        let mut required_signers = Vec::new();
 
         for action in actions {
             match action {
@@ -157,12 +397,11 @@ impl<Datum: Clone, Redeemer> TxActions<Datum, Redeemer> {
                     add_amount_to_nested_map(&mut min_output_values, amount, &recipient, &policy);
                 }
                 Action::Mint {
-                    amount,
-                    asset_name,
+                    assets,
                     redeemer,
                     policy,
                 } => {
-                    minting.push((amount, asset_name, redeemer, policy));
+                    minting.push((assets, redeemer, policy));
                 }
                 Action::InitScript {
                     datum,
@@ -174,9 +413,19 @@ impl<Datum: Clone, Redeemer> TxActions<Datum, Redeemer> {
                         script_address: owner,
                         values,
                         datum,
+                        reference_script: None,
                     };
                     specific_outputs.push(output);
                 }
+                Action::InitReferenceScript {
+                    script,
+                    values,
+                    address,
+                } => {
+                    let output =
+                        UnbuiltOutput::new_wallet(address, values).with_reference_script(script);
+                    specific_outputs.push(output);
+                }
                 Action::RedeemScriptOutput {
                     output,
                     redeemer,
@@ -185,6 +434,25 @@ impl<Datum: Clone, Redeemer> TxActions<Datum, Redeemer> {
                     script_inputs.push((output.clone(), redeemer, script));
                 }
                 Action::SpecificInput { input } => specific_wallet_inputs.push(input),
+                Action::ReferenceInput { output } => reference_inputs.push(output),
+                Action::ReferenceScriptInput { output, script_hex } => {
+                    let reference_script = output
+                        .reference_script()
+                        .ok_or_else(|| Error::MissingReferenceScript(output.id().to_owned()))?;
+                    if reference_script.script_hex() != script_hex? {
+                        return Err(Error::ReferenceScriptMismatch(output.id().to_owned()));
+                    }
+                    reference_inputs.push(output)
+                }
+                Action::Metadata { label, value } => metadata.insert(label, value),
+                Action::StakeCertificate { certificate } => certificates.push(certificate),
+                Action::Withdrawal { withdrawal } => withdrawals.push(withdrawal),
+                Action::RequiredSigner { signer } => {
+                    if !required_signers.contains(&signer) {
+                        required_signers.push(signer)
+                    }
+                }
+                Action::Invalid { error } => return Err(error),
             }
         }
 
@@ -198,6 +466,12 @@ impl<Datum: Clone, Redeemer> TxActions<Datum, Redeemer> {
             unbuilt_outputs: outputs,
             minting,
             specific_wallet_inputs,
+            reference_inputs,
+            metadata,
+            certificates,
+            withdrawals,
+            required_signers,
+            min_ada_top_up: self.min_ada_top_up,
             valid_range: self.valid_range,
         };
         Ok(tx)
@@ -224,28 +498,31 @@ fn create_outputs_for<Datum>(
     outputs
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
 pub enum TransactionVersion {
     V1,
     V2,
 }
 
-/// Range of times in seconds since the Unix epoch
-type Range = (Option<i64>, Option<i64>);
-
 pub struct UnbuiltTransaction<Datum, Redeemer> {
     pub script_version: TransactionVersion,
     pub script_inputs: Vec<RedemptionDetails<Datum, Redeemer>>,
     pub unbuilt_outputs: Vec<UnbuiltOutput<Datum>>,
     #[allow(clippy::type_complexity)]
     pub minting: Vec<(
-        u64,
-        Option<String>,
+        Vec<(Option<String>, i64)>,
         Redeemer,
         Box<dyn MintingPolicy<Redeemer>>,
     )>,
     pub specific_wallet_inputs: Vec<Output<Datum>>,
-    pub valid_range: Range,
+    pub reference_inputs: Vec<Output<Datum>>,
+    pub metadata: TransactionMetadata,
+    pub certificates: Vec<StakeCertificate<Redeemer>>,
+    pub withdrawals: Vec<Withdrawal<Redeemer>>,
+    pub required_signers: Vec<PubKeyHash>,
+    /// Whether the ledger client should top up outputs below the minimum ADA
+    pub min_ada_top_up: bool,
+    pub valid_range: ValidityInterval,
 }
 
 impl<Datum, Redeemer> UnbuiltTransaction<Datum, Redeemer> {
@@ -256,9 +533,148 @@ impl<Datum, Redeemer> UnbuiltTransaction<Datum, Redeemer> {
     pub fn script_inputs(&self) -> &Vec<RedemptionDetails<Datum, Redeemer>> {
         &self.script_inputs
     }
+
+    pub fn reference_inputs(&self) -> &Vec<Output<Datum>> {
+        &self.reference_inputs
+    }
+
+    pub fn metadata(&self) -> &TransactionMetadata {
+        &self.metadata
+    }
+
+    pub fn certificates(&self) -> &Vec<StakeCertificate<Redeemer>> {
+        &self.certificates
+    }
+
+    pub fn withdrawals(&self) -> &Vec<Withdrawal<Redeemer>> {
+        &self.withdrawals
+    }
+
+    pub fn required_signers(&self) -> &Vec<PubKeyHash> {
+        &self.required_signers
+    }
+}
+
+impl<Datum, Redeemer> UnbuiltTransaction<Datum, Redeemer>
+where
+    Datum: Clone + Into<PlutusData> + TryFrom<PlutusData> + Send + Sync + 'static,
+    Redeemer: Into<PlutusData> + TryFrom<PlutusData> + Send + Sync + 'static,
+{
+    /// Same tx with every datum and redeemer as raw `PlutusData`. Scripts are wrapped so they
+    /// still see their own types.
+    pub fn into_untyped(self) -> UnbuiltTransaction<PlutusData, PlutusData> {
+        let script_inputs = self
+            .script_inputs
+            .into_iter()
+            .map(|(output, redeemer, script)| {
+                let script: Box<dyn ValidatorCode<PlutusData, PlutusData>> =
+                    Box::new(UntypedValidator::new(script));
+                (output.into_untyped(), redeemer.into(), script)
+            })
+            .collect();
+        let minting = self
+            .minting
+            .into_iter()
+            .map(|(assets, redeemer, policy)| {
+                let policy: Box<dyn MintingPolicy<PlutusData>> =
+                    Box::new(UntypedMintingPolicy::new(policy));
+                (assets, redeemer.into(), policy)
+            })
+            .collect();
+        UnbuiltTransaction {
+            script_version: self.script_version,
+            script_inputs,
+            unbuilt_outputs: self
+                .unbuilt_outputs
+                .into_iter()
+                .map(UnbuiltOutput::into_untyped)
+                .collect(),
+            minting,
+            specific_wallet_inputs: self
+                .specific_wallet_inputs
+                .into_iter()
+                .map(Output::into_untyped)
+                .collect(),
+            reference_inputs: self
+                .reference_inputs
+                .into_iter()
+                .map(Output::into_untyped)
+                .collect(),
+            metadata: self.metadata,
+            certificates: self
+                .certificates
+                .into_iter()
+                .map(StakeCertificate::into_untyped)
+                .collect(),
+            withdrawals: self
+                .withdrawals
+                .into_iter()
+                .map(Withdrawal::into_untyped)
+                .collect(),
+            required_signers: self.required_signers,
+            min_ada_top_up: self.min_ada_top_up,
+            valid_range: self.valid_range,
+        }
+    }
+}
+
+/// Balanced tx that still needs key witnesses, e.g. to be reviewed and signed on another machine
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct UnsignedTransaction {
+    cbor_hex: String,
+    summary: TxSummary,
+}
+
+impl UnsignedTransaction {
+    pub fn new(cbor_hex: String, summary: TxSummary) -> Self {
+        UnsignedTransaction { cbor_hex, summary }
+    }
+
+    /// Full tx CBOR, including any script witnesses but no key witnesses
+    pub fn cbor_hex(&self) -> &str {
+        &self.cbor_hex
+    }
+
+    pub fn summary(&self) -> &TxSummary {
+        &self.summary
+    }
+}
+
+/// What an unsigned tx will do once submitted
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct TxSummary {
+    pub tx_id: String,
+    pub inputs: Vec<OutputId>,
+    /// Bech32 owner address and the value sent to it
+    pub outputs: Vec<(String, Values)>,
+    pub fee: u64,
+}
+
+/// Dry-run result of a tx. Nothing is committed to the ledger
+#[derive(Clone, Debug)]
+pub struct TxSimulation {
+    pub summary: TxSummary,
+    pub execution_costs: Vec<RedeemerCost>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RedeemerKind {
+    Spend,
+    Mint,
+    Certificate,
+    Withdrawal,
+}
+
+/// Cost of running the script behind one redeemer of a tx
+#[derive(Clone, Debug)]
+pub struct RedeemerCost {
+    pub kind: RedeemerKind,
+    /// Position of the redeemer among the redeemers of the same kind
+    pub index: u64,
+    pub cost: ExecutionCost,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct TxId(String);
 
 impl TxId {
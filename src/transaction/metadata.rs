@@ -0,0 +1,150 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[cfg(test)]
+mod tests;
+
+/// Label for CIP-20 transaction messages
+pub const CIP20_LABEL: u64 = 674;
+/// Label for CIP-25 NFT metadata
+pub const CIP25_LABEL: u64 = 721;
+
+/// Strings and bytes in tx metadata are limited to 64 bytes each
+pub const MAX_METADATUM_LEN: usize = 64;
+
+#[derive(Clone, PartialEq, Debug, Eq, Deserialize, Serialize)]
+pub enum Metadatum {
+    Map(Vec<(Metadatum, Metadatum)>),
+    List(Vec<Metadatum>),
+    Int(i64),
+    Bytes(Vec<u8>),
+    Text(String),
+}
+
+impl Metadatum {
+    pub fn text(text: &str) -> Self {
+        Metadatum::Text(text.to_string())
+    }
+
+    /// Single `Text` if `text` fits, otherwise a `List` of chunks that each fit
+    pub fn chunked_text(text: &str) -> Self {
+        if text.len() <= MAX_METADATUM_LEN {
+            return Metadatum::text(text);
+        }
+        let mut chunks = Vec::new();
+        let mut current = String::new();
+        for c in text.chars() {
+            if current.len() + c.len_utf8() > MAX_METADATUM_LEN {
+                chunks.push(Metadatum::Text(std::mem::take(&mut current)));
+            }
+            current.push(c);
+        }
+        if !current.is_empty() {
+            chunks.push(Metadatum::Text(current));
+        }
+        Metadatum::List(chunks)
+    }
+}
+
+/// Auxiliary data attached to a transaction, keyed by label
+#[derive(Clone, PartialEq, Debug, Eq, Default, Deserialize, Serialize)]
+pub struct TransactionMetadata {
+    entries: BTreeMap<u64, Metadatum>,
+}
+
+impl TransactionMetadata {
+    pub fn insert(&mut self, label: u64, value: Metadatum) {
+        self.entries.insert(label, value);
+    }
+
+    pub fn get(&self, label: u64) -> Option<&Metadatum> {
+        self.entries.get(&label)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&u64, &Metadatum)> {
+        self.entries.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// CIP-20 message: `{ "msg": [lines...] }`. Long lines are split to fit the metadatum limit.
+pub fn cip20_message(lines: &[&str]) -> Metadatum {
+    let msg = lines
+        .iter()
+        .flat_map(|line| match Metadatum::chunked_text(line) {
+            Metadatum::List(chunks) => chunks,
+            single => vec![single],
+        })
+        .collect();
+    Metadatum::Map(vec![(Metadatum::text("msg"), Metadatum::List(msg))])
+}
+
+/// Fields for a single asset in CIP-25 metadata
+#[derive(Clone, PartialEq, Debug, Eq)]
+pub struct Cip25Asset {
+    pub name: String,
+    pub image: String,
+    pub media_type: Option<String>,
+    pub description: Option<String>,
+}
+
+impl Cip25Asset {
+    pub fn new(name: &str, image: &str) -> Self {
+        Cip25Asset {
+            name: name.to_string(),
+            image: image.to_string(),
+            media_type: None,
+            description: None,
+        }
+    }
+
+    pub fn with_media_type(mut self, media_type: &str) -> Self {
+        self.media_type = Some(media_type.to_string());
+        self
+    }
+
+    pub fn with_description(mut self, description: &str) -> Self {
+        self.description = Some(description.to_string());
+        self
+    }
+}
+
+impl From<Cip25Asset> for Metadatum {
+    fn from(asset: Cip25Asset) -> Self {
+        let mut fields = vec![
+            (
+                Metadatum::text("name"),
+                Metadatum::chunked_text(&asset.name),
+            ),
+            (
+                Metadatum::text("image"),
+                Metadatum::chunked_text(&asset.image),
+            ),
+        ];
+        if let Some(media_type) = asset.media_type {
+            fields.push((Metadatum::text("mediaType"), Metadatum::Text(media_type)));
+        }
+        if let Some(description) = asset.description {
+            fields.push((
+                Metadatum::text("description"),
+                Metadatum::chunked_text(&description),
+            ));
+        }
+        Metadatum::Map(fields)
+    }
+}
+
+/// CIP-25 (version 1.0) metadata for assets under `policy_id`, to be put under [`CIP25_LABEL`]
+pub fn cip25_metadata(policy_id: &str, assets: Vec<(String, Cip25Asset)>) -> Metadatum {
+    let assets = assets
+        .into_iter()
+        .map(|(asset_name, asset)| (Metadatum::Text(asset_name), asset.into()))
+        .collect();
+    Metadatum::Map(vec![
+        (Metadatum::text(policy_id), Metadatum::Map(assets)),
+        (Metadatum::text("version"), Metadatum::text("1.0")),
+    ])
+}
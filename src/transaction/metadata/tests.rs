@@ -0,0 +1,71 @@
+#![allow(non_snake_case)]
+
+use super::*;
+
+#[test]
+fn chunked_text__short_text_is_single_text() {
+    let text = "hello";
+    let actual = Metadatum::chunked_text(text);
+    assert_eq!(actual, Metadatum::text(text));
+}
+
+#[test]
+fn chunked_text__long_text_is_split_into_chunks_that_fit() {
+    // given
+    let text = "é".repeat(50); // 100 bytes, and no chunk boundary may split a char
+
+    // when
+    let actual = Metadatum::chunked_text(&text);
+
+    // then
+    if let Metadatum::List(chunks) = actual {
+        let joined: String = chunks
+            .iter()
+            .map(|chunk| match chunk {
+                Metadatum::Text(inner) => {
+                    assert!(inner.len() <= MAX_METADATUM_LEN);
+                    inner.clone()
+                }
+                _ => panic!("expected text chunk"),
+            })
+            .collect();
+        assert_eq!(joined, text);
+    } else {
+        panic!("expected list")
+    }
+}
+
+#[test]
+fn cip20_message__wraps_lines_under_msg() {
+    let actual = cip20_message(&["first", "second"]);
+    let expected = Metadatum::Map(vec![(
+        Metadatum::text("msg"),
+        Metadatum::List(vec![Metadatum::text("first"), Metadatum::text("second")]),
+    )]);
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn cip25_metadata__nests_assets_under_policy_id() {
+    // given
+    let policy_id = "abcd";
+    let asset = Cip25Asset::new("My NFT", "ipfs://Qm").with_media_type("image/png");
+
+    // when
+    let actual = cip25_metadata(policy_id, vec![("MyNFT".to_string(), asset)]);
+
+    // then
+    let expected_asset = Metadatum::Map(vec![
+        (Metadatum::text("name"), Metadatum::text("My NFT")),
+        (Metadatum::text("image"), Metadatum::text("ipfs://Qm")),
+        (Metadatum::text("mediaType"), Metadatum::text("image/png")),
+    ]);
+    let expected = Metadatum::Map(vec![
+        (
+            Metadatum::text(policy_id),
+            Metadatum::Map(vec![(Metadatum::text("MyNFT"), expected_asset)]),
+        ),
+        (Metadatum::text("version"), Metadatum::text("1.0")),
+    ]);
+    assert_eq!(actual, expected);
+}
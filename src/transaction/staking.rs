@@ -0,0 +1,152 @@
+use crate::scripts::{
+    context::{CtxDCert, PubKeyHash},
+    raw_validator_script::plutus_data::PlutusData,
+    untyped::UntypedStakingValidator,
+    ScriptError, ScriptResult, StakingValidator,
+};
+use serde::{Deserialize, Serialize};
+
+/// Lovelace locked by the ledger while a stake credential is registered, per the current mainnet
+/// and testnet `keyDeposit`
+pub const STAKE_KEY_DEPOSIT: u64 = 2_000_000;
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum StakeCredential {
+    Key(PubKeyHash),
+    Script(Vec<u8>),
+}
+
+impl StakeCredential {
+    pub fn from_validator<R>(validator: &(dyn StakingValidator<R> + '_)) -> ScriptResult<Self> {
+        let hash = validator.hash()?;
+        let bytes = hex::decode(hash).map_err(|e| ScriptError::IdRetrieval(e.to_string()))?;
+        Ok(StakeCredential::Script(bytes))
+    }
+
+    pub fn hash(&self) -> Vec<u8> {
+        match self {
+            StakeCredential::Key(pkh) => pkh.bytes(),
+            StakeCredential::Script(hash) => hash.clone(),
+        }
+    }
+}
+
+/// Authorization for an operation on a stake credential
+pub enum StakeWitness<Redeemer> {
+    /// Signed with the stake key of the signer
+    Key(PubKeyHash),
+    Script {
+        redeemer: Redeemer,
+        validator: Box<dyn StakingValidator<Redeemer>>,
+    },
+}
+
+impl<Redeemer> StakeWitness<Redeemer> {
+    pub fn credential(&self) -> ScriptResult<StakeCredential> {
+        match self {
+            StakeWitness::Key(pkh) => Ok(StakeCredential::Key(pkh.clone())),
+            StakeWitness::Script { validator, .. } => {
+                StakeCredential::from_validator(validator.as_ref())
+            }
+        }
+    }
+}
+
+impl<Redeemer> StakeWitness<Redeemer>
+where
+    Redeemer: Into<PlutusData> + TryFrom<PlutusData> + Send + Sync + 'static,
+{
+    /// Same witness with its redeemer as raw `PlutusData`
+    pub fn into_untyped(self) -> StakeWitness<PlutusData> {
+        match self {
+            StakeWitness::Key(pkh) => StakeWitness::Key(pkh),
+            StakeWitness::Script {
+                redeemer,
+                validator,
+            } => StakeWitness::Script {
+                redeemer: redeemer.into(),
+                validator: Box::new(UntypedStakingValidator::new(validator)),
+            },
+        }
+    }
+}
+
+pub enum StakeCertificate<Redeemer> {
+    /// Registration takes a deposit but needs no witness
+    Registration(StakeCredential),
+    Deregistration(StakeWitness<Redeemer>),
+    Delegation {
+        witness: StakeWitness<Redeemer>,
+        pool_key_hash: Vec<u8>,
+    },
+}
+
+impl<Redeemer> StakeCertificate<Redeemer> {
+    pub fn credential(&self) -> ScriptResult<StakeCredential> {
+        match self {
+            StakeCertificate::Registration(credential) => Ok(credential.clone()),
+            StakeCertificate::Deregistration(witness) => witness.credential(),
+            StakeCertificate::Delegation { witness, .. } => witness.credential(),
+        }
+    }
+
+    pub fn witness(&self) -> Option<&StakeWitness<Redeemer>> {
+        match self {
+            StakeCertificate::Registration(_) => None,
+            StakeCertificate::Deregistration(witness) => Some(witness),
+            StakeCertificate::Delegation { witness, .. } => Some(witness),
+        }
+    }
+
+    pub fn to_ctx(&self) -> ScriptResult<CtxDCert> {
+        let credential = self.credential()?;
+        let dcert = match self {
+            StakeCertificate::Registration(_) => CtxDCert::DelegRegKey(credential),
+            StakeCertificate::Deregistration(_) => CtxDCert::DelegDeRegKey(credential),
+            StakeCertificate::Delegation { pool_key_hash, .. } => {
+                CtxDCert::DelegDelegate(credential, pool_key_hash.clone())
+            }
+        };
+        Ok(dcert)
+    }
+}
+
+impl<Redeemer> StakeCertificate<Redeemer>
+where
+    Redeemer: Into<PlutusData> + TryFrom<PlutusData> + Send + Sync + 'static,
+{
+    pub fn into_untyped(self) -> StakeCertificate<PlutusData> {
+        match self {
+            StakeCertificate::Registration(credential) => {
+                StakeCertificate::Registration(credential)
+            }
+            StakeCertificate::Deregistration(witness) => {
+                StakeCertificate::Deregistration(witness.into_untyped())
+            }
+            StakeCertificate::Delegation {
+                witness,
+                pool_key_hash,
+            } => StakeCertificate::Delegation {
+                witness: witness.into_untyped(),
+                pool_key_hash,
+            },
+        }
+    }
+}
+
+pub struct Withdrawal<Redeemer> {
+    pub witness: StakeWitness<Redeemer>,
+    pub amount: u64,
+}
+
+impl<Redeemer> Withdrawal<Redeemer>
+where
+    Redeemer: Into<PlutusData> + TryFrom<PlutusData> + Send + Sync + 'static,
+{
+    pub fn into_untyped(self) -> Withdrawal<PlutusData> {
+        Withdrawal {
+            witness: self.witness.into_untyped(),
+            amount: self.amount,
+        }
+    }
+}
@@ -0,0 +1,41 @@
+/// One end of a `ValidityInterval`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ValidityBound {
+    /// Milliseconds since the Unix epoch, converted to the slot containing it
+    PosixMillis(i64),
+    Slot(u64),
+}
+
+/// Interval a transaction can be added to the chain in, like the ledger's: the lower bound is
+/// inclusive and the upper bound exclusive. A missing bound leaves that end open.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ValidityInterval {
+    pub lower: Option<ValidityBound>,
+    pub upper: Option<ValidityBound>,
+}
+
+impl ValidityInterval {
+    pub fn posix_millis(lower: Option<i64>, upper: Option<i64>) -> Self {
+        ValidityInterval {
+            lower: lower.map(ValidityBound::PosixMillis),
+            upper: upper.map(ValidityBound::PosixMillis),
+        }
+    }
+
+    /// Bounds too far out to hold in milliseconds saturate instead of overflowing
+    pub fn posix_secs(lower: Option<i64>, upper: Option<i64>) -> Self {
+        let millis = |secs: i64| secs.saturating_mul(1000);
+        ValidityInterval::posix_millis(lower.map(millis), upper.map(millis))
+    }
+
+    pub fn slots(lower: Option<u64>, upper: Option<u64>) -> Self {
+        ValidityInterval {
+            lower: lower.map(ValidityBound::Slot),
+            upper: upper.map(ValidityBound::Slot),
+        }
+    }
+
+    pub fn is_unbounded(&self) -> bool {
+        self.lower.is_none() && self.upper.is_none()
+    }
+}
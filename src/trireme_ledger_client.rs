@@ -4,22 +4,24 @@ use crate::{
         test_ledger_client::local_persisted_storage::LocalPersistedStorage,
         test_ledger_client::TestLedgerClient, LedgerClient, LedgerClientError, LedgerClientResult,
     },
-    output::Output,
+    output::{Output, OutputId},
     scripts::raw_validator_script::plutus_data::PlutusData,
-    transaction::TxId,
-    trireme_ledger_client::cml_client::blockfrost_ledger::BlockfrostApiKey,
+    transaction::{TxId, TxSimulation, UnsignedTransaction},
+    trireme_ledger_client::cml_client::blockfrost_ledger::{blockfrost_url, BlockfrostApiKey},
     trireme_ledger_client::raw_secret_phrase::RawSecretPhraseKeys,
-    UnbuiltTransaction,
+    PolicyId, UnbuiltTransaction,
 };
 
+use crate::trireme_ledger_client::cml_client::cached_ledger::{CachedLedger, LedgerCacheConfig};
+use crate::trireme_ledger_client::cml_client::kupo_ogmios_ledger::KupoOgmiosLedger;
 use crate::trireme_ledger_client::cml_client::network_settings::NetworkSettings;
+use crate::trireme_ledger_client::cml_client::ogmios_ledger::{OgmiosLedger, OgmiosWsClient};
 use crate::trireme_ledger_client::cml_client::ogmios_scrolls_ledger::OgmiosScrollsLedger;
 use crate::trireme_ledger_client::cml_client::Keys;
 use crate::trireme_ledger_client::terminal_password_phrase::{
     PasswordProtectedPhraseKeys, TerminalPasswordUpfront,
 };
 use async_trait::async_trait;
-use blockfrost_http_client::{MAINNET_URL, PREPROD_NETWORK_URL};
 use cardano_multiplatform_lib::address::BaseAddress;
 use cardano_multiplatform_lib::crypto::PrivateKey;
 use cml_client::{
@@ -30,7 +32,7 @@ use ogmios_client::OgmiosClient;
 use pallas_addresses::Address;
 use scrolls_client::ScrollsClient;
 use serde::{de::DeserializeOwned, ser, Deserialize, Serialize};
-use std::{fmt::Debug, hash::Hash, marker::PhantomData, path::PathBuf};
+use std::{fmt::Debug, hash::Hash, marker::PhantomData, path::PathBuf, time::Duration};
 use thiserror::Error;
 use tokio::{fs, io::AsyncWriteExt};
 
@@ -43,6 +45,7 @@ pub mod terminal_password_phrase;
 pub const TRIREME_CONFIG_FOLDER: &str = ".trireme";
 pub const TRIREME_CONFIG_FILE: &str = "config.toml";
 pub const CLIENT_CONFIG_FILE: &str = "config.toml";
+pub const PROTOCOL_PARAMETERS_FILE: &str = "protocol_parameters.json";
 
 pub fn path_to_trireme_config_dir() -> Result<PathBuf> {
     let mut dir =
@@ -64,6 +67,13 @@ pub fn path_to_client_config_file(sub_dir: &str) -> Result<PathBuf> {
     Ok(dir)
 }
 
+pub fn path_to_protocol_parameters_file(sub_dir: &str) -> Result<PathBuf> {
+    let mut dir = path_to_trireme_config_dir()?;
+    dir.push(sub_dir);
+    dir.push(PROTOCOL_PARAMETERS_FILE);
+    Ok(dir)
+}
+
 pub async fn get_current_client_config_from_file() -> Result<Option<ClientConfig>> {
     let trireme_config = get_trireme_config_from_file().await?;
     let current_env = trireme_config
@@ -88,7 +98,15 @@ pub async fn get_trireme_ledger_client_from_file<
         + Sync
         + Into<PlutusData>
         + TryFrom<PlutusData>,
-    Redeemer: PlutusDataInterop + Clone + Eq + Debug + Hash + Send + Sync + DeserializeOwned,
+    Redeemer: PlutusDataInterop
+        + Clone
+        + Eq
+        + Debug
+        + Hash
+        + Send
+        + Sync
+        + DeserializeOwned
+        + Into<PlutusData>,
 >() -> Result<TriremeLedgerClient<Datum, Redeemer>> {
     if let Some(config) = get_current_client_config_from_file().await? {
         config.to_client().await
@@ -107,6 +125,9 @@ pub async fn get_trireme_config_from_file() -> Result<Option<TriremeConfig>> {
 pub enum LedgerSource {
     BlockFrost {
         api_key_file: PathBuf,
+        /// Blockfrost-compatible endpoint to use instead of Blockfrost's own for the network
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        base_url: Option<String>,
     },
     OgmiosAndScrolls {
         scrolls_ip: String,
@@ -114,6 +135,15 @@ pub enum LedgerSource {
         ogmios_ip: String,
         ogmios_port: String,
     },
+    KupoOgmios {
+        kupo_url: String,
+        ogmios_ip: String,
+        ogmios_port: String,
+    },
+    Ogmios {
+        ogmios_ip: String,
+        ogmios_port: String,
+    },
 }
 
 #[derive(Deserialize, Serialize, Clone)]
@@ -129,19 +159,29 @@ pub enum KeySource {
     },
 }
 
-#[derive(Deserialize, Serialize, Clone)]
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
 #[serde(tag = "type")]
 pub enum Network {
     Preprod,
     Preview,
     Mainnet,
+    /// Private network, such as a local devnet, whose settings are loaded from its genesis files
+    Custom {
+        byron_genesis_file: PathBuf,
+        shelley_genesis_file: PathBuf,
+        /// Epoch the network hard forked into Shelley at, `0` for most devnets
+        #[serde(default)]
+        shelley_start_epoch: u64,
+    },
 }
 
 impl From<Network> for u8 {
+    /// Custom networks are taken to be testnets here; their `NetworkSettings` read the actual id
+    /// from the Shelley genesis
     fn from(network: Network) -> Self {
         match network {
             Network::Mainnet => 1,
-            Network::Preprod | Network::Preview => 0,
+            Network::Preprod | Network::Preview | Network::Custom { .. } => 0,
         }
     }
 }
@@ -226,6 +266,8 @@ pub struct CMLClientConfig {
     ledger_source: LedgerSource,
     key_source: KeySource,
     network: Network,
+    #[serde(default)]
+    ledger_cache: LedgerCacheConfig,
 }
 
 #[derive(Deserialize, Serialize, Clone)]
@@ -250,6 +292,7 @@ impl ClientConfig {
             ledger_source,
             key_source,
             network,
+            ledger_cache: LedgerCacheConfig::default(),
         };
         let variant = ClientVariant::CML(inner);
         ClientConfig {
@@ -269,6 +312,14 @@ impl ClientConfig {
         }
     }
 
+    /// Tune caching, concurrency and retries of CML ledger calls. Ignored by test clients.
+    pub fn with_ledger_cache(mut self, ledger_cache: LedgerCacheConfig) -> Self {
+        if let ClientVariant::CML(inner) = &mut self.variant {
+            inner.ledger_cache = ledger_cache;
+        }
+        self
+    }
+
     pub fn name(&self) -> String {
         self.name.clone()
     }
@@ -288,16 +339,21 @@ impl ClientConfig {
             + Sync
             + Into<PlutusData>
             + TryFrom<PlutusData>,
-        Redeemer: PlutusDataInterop + Clone + Eq + PartialEq + Debug + Hash + Send + Sync,
+        Redeemer: PlutusDataInterop + Clone + Eq + Debug + Hash + Send + Sync + Into<PlutusData>,
     >(
         self,
     ) -> Result<TriremeLedgerClient<Datum, Redeemer>> {
         match self.variant {
             ClientVariant::CML(inner) => {
                 let network = inner.network;
+                let network_settings = NetworkSettings::try_from(network.clone())
+                    .map_err(|e| Error::Trireme(format!("Invalid network settings: {e}")))?;
+                let ledger_cache = inner.ledger_cache;
+                let protocol_parameters_cache = path_to_protocol_parameters_file(&self.name)?;
                 let keys = match inner.key_source {
                     KeySource::RawSecretPhrase { phrase_file } => {
-                        let keys = RawSecretPhraseKeys::new(phrase_file, network.clone().into());
+                        let keys =
+                            RawSecretPhraseKeys::new(phrase_file, network_settings.network());
                         SecretPhraseKeys::RawSecretPhraseKeys(keys)
                     }
                     KeySource::TerminalPasswordUpfrontSecretPhrase {
@@ -309,14 +365,17 @@ impl ClientConfig {
                         let keys = PasswordProtectedPhraseKeys::new(
                             password,
                             phrase_file,
-                            network.clone().into(),
+                            network_settings.network(),
                             encrpytion_nonce,
                         );
                         SecretPhraseKeys::PasswordProtectedPhraseKeys(keys)
                     }
                 };
                 let inner_client = match inner.ledger_source {
-                    LedgerSource::BlockFrost { api_key_file } => {
+                    LedgerSource::BlockFrost {
+                        api_key_file,
+                        base_url,
+                    } => {
                         let blockfrost_key =
                             read_toml_struct_from_file::<BlockfrostApiKey>(&api_key_file)
                                 .await?
@@ -327,22 +386,19 @@ impl ClientConfig {
                             )
                                 })?;
                         let key: String = blockfrost_key.into();
-                        let url = match &network {
-                            Network::Preprod => PREPROD_NETWORK_URL,
-                            Network::Mainnet => MAINNET_URL,
-                            Network::Preview => {
-                                return Err(Error::Trireme(
-                                    "Preview network not supported yet".to_string(),
-                                ))
-                            }
-                        };
-                        let ledger = BlockFrostLedger::new(url, &key);
-                        let network_settings = network.clone().into();
-                        InnerClient::BlockFrost(CMLLedgerCLient::new(
-                            ledger,
-                            keys,
-                            network_settings,
-                        ))
+                        let url = base_url
+                            .or_else(|| blockfrost_url(&network).map(str::to_string))
+                            .ok_or_else(|| {
+                                Error::Trireme(
+                                    "Blockfrost needs a base URL for custom networks".to_string(),
+                                )
+                            })?;
+                        let ledger = BlockFrostLedger::new(&url, &key);
+                        let ledger = CachedLedger::new(ledger, ledger_cache);
+                        InnerClient::BlockFrost(
+                            CMLLedgerCLient::new(ledger, keys, network_settings)
+                                .with_protocol_parameters_cache(protocol_parameters_cache),
+                        )
                     }
                     LedgerSource::OgmiosAndScrolls {
                         scrolls_ip,
@@ -351,18 +407,51 @@ impl ClientConfig {
                         ogmios_port,
                     } => {
                         let scrolls_client = ScrollsClient::new_redis(scrolls_ip, scrolls_port);
+                        let ogmios_ws_url = format!("ws://{ogmios_ip}:{ogmios_port}");
                         let ogmios_client = OgmiosClient::new(ogmios_ip, ogmios_port);
-                        let network_settings: NetworkSettings = network.into();
                         let ledger = OgmiosScrollsLedger::new(
                             scrolls_client,
                             ogmios_client,
                             network_settings,
-                        );
-                        InnerClient::OgmiosScrolls(CMLLedgerCLient::new(
-                            ledger,
-                            keys,
+                        )
+                        .with_ogmios_ws_url(&ogmios_ws_url);
+                        let ledger = CachedLedger::new(ledger, ledger_cache);
+                        InnerClient::OgmiosScrolls(
+                            CMLLedgerCLient::new(ledger, keys, network_settings)
+                                .with_protocol_parameters_cache(protocol_parameters_cache),
+                        )
+                    }
+                    LedgerSource::KupoOgmios {
+                        kupo_url,
+                        ogmios_ip,
+                        ogmios_port,
+                    } => {
+                        let ogmios_ws_url = format!("ws://{ogmios_ip}:{ogmios_port}");
+                        let ogmios_client = OgmiosClient::new(ogmios_ip, ogmios_port);
+                        let ledger = KupoOgmiosLedger::new(
+                            &kupo_url,
+                            ogmios_client,
+                            &ogmios_ws_url,
                             network_settings,
-                        ))
+                        );
+                        let ledger = CachedLedger::new(ledger, ledger_cache);
+                        InnerClient::KupoOgmios(
+                            CMLLedgerCLient::new(ledger, keys, network_settings)
+                                .with_protocol_parameters_cache(protocol_parameters_cache),
+                        )
+                    }
+                    LedgerSource::Ogmios {
+                        ogmios_ip,
+                        ogmios_port,
+                    } => {
+                        let client =
+                            OgmiosWsClient::new(&format!("ws://{ogmios_ip}:{ogmios_port}"));
+                        let ledger = OgmiosLedger::new(client, network_settings);
+                        let ledger = CachedLedger::new(ledger, ledger_cache);
+                        InnerClient::Ogmios(
+                            CMLLedgerCLient::new(ledger, keys, network_settings)
+                                .with_protocol_parameters_cache(protocol_parameters_cache),
+                        )
                     }
                 };
 
@@ -408,6 +497,13 @@ impl Keys for SecretPhraseKeys {
             SecretPhraseKeys::PasswordProtectedPhraseKeys(keys) => keys.private_key().await,
         }
     }
+
+    async fn stake_private_key(&self) -> cml_client::error::Result<PrivateKey> {
+        match self {
+            SecretPhraseKeys::RawSecretPhraseKeys(keys) => keys.stake_private_key().await,
+            SecretPhraseKeys::PasswordProtectedPhraseKeys(keys) => keys.stake_private_key().await,
+        }
+    }
 }
 
 enum InnerClient<Datum, Redeemer>
@@ -421,8 +517,12 @@ where
         + TryFrom<PlutusData>,
     Redeemer: PlutusDataInterop,
 {
-    BlockFrost(CMLLedgerCLient<BlockFrostLedger, SecretPhraseKeys, Datum, Redeemer>),
-    OgmiosScrolls(CMLLedgerCLient<OgmiosScrollsLedger, SecretPhraseKeys, Datum, Redeemer>),
+    BlockFrost(CMLLedgerCLient<CachedLedger<BlockFrostLedger>, SecretPhraseKeys, Datum, Redeemer>),
+    OgmiosScrolls(
+        CMLLedgerCLient<CachedLedger<OgmiosScrollsLedger>, SecretPhraseKeys, Datum, Redeemer>,
+    ),
+    KupoOgmios(CMLLedgerCLient<CachedLedger<KupoOgmiosLedger>, SecretPhraseKeys, Datum, Redeemer>),
+    Ogmios(CMLLedgerCLient<CachedLedger<OgmiosLedger>, SecretPhraseKeys, Datum, Redeemer>),
     Mocked(TestLedgerClient<Datum, Redeemer, LocalPersistedStorage<PathBuf, Datum>>),
 }
 
@@ -462,6 +562,12 @@ where
             InnerClient::OgmiosScrolls(_) => Err(LedgerClientError::CurrentTime(Box::new(
                 Error::Trireme("Not implemented for Ogmios/Scrolls client".to_string()),
             ))),
+            InnerClient::KupoOgmios(_) => Err(LedgerClientError::CurrentTime(Box::new(
+                Error::Trireme("Not implemented for Kupo/Ogmios client".to_string()),
+            ))),
+            InnerClient::Ogmios(_) => Err(LedgerClientError::CurrentTime(Box::new(
+                Error::Trireme("Not implemented for Ogmios client".to_string()),
+            ))),
         }
     }
 
@@ -474,6 +580,12 @@ where
             InnerClient::OgmiosScrolls(_) => Err(LedgerClientError::CurrentTime(Box::new(
                 Error::Trireme("Not implemented for Ogmios/Scrolls client".to_string()),
             ))),
+            InnerClient::KupoOgmios(_) => Err(LedgerClientError::CurrentTime(Box::new(
+                Error::Trireme("Not implemented for Kupo/Ogmios client".to_string()),
+            ))),
+            InnerClient::Ogmios(_) => Err(LedgerClientError::CurrentTime(Box::new(
+                Error::Trireme("Not implemented for Ogmios client".to_string()),
+            ))),
         }
     }
 }
@@ -489,13 +601,16 @@ where
         + PartialEq
         + Into<PlutusData>
         + TryFrom<PlutusData>,
-    Redeemer: PlutusDataInterop + Send + Sync + Clone + Eq + PartialEq + Debug + Hash,
+    Redeemer:
+        PlutusDataInterop + Send + Sync + Clone + Eq + PartialEq + Debug + Hash + Into<PlutusData>,
 {
     async fn signer_base_address(&self) -> LedgerClientResult<Address> {
         match &self.inner_client {
             InnerClient::BlockFrost(cml_client) => cml_client.signer_base_address(),
             InnerClient::Mocked(test_client) => test_client.signer_base_address(),
             InnerClient::OgmiosScrolls(cml_client) => cml_client.signer_base_address(),
+            InnerClient::KupoOgmios(cml_client) => cml_client.signer_base_address(),
+            InnerClient::Ogmios(cml_client) => cml_client.signer_base_address(),
         }
         .await
     }
@@ -509,6 +624,8 @@ where
             InnerClient::BlockFrost(cml_client) => cml_client.outputs_at_address(address, count),
             InnerClient::Mocked(test_client) => test_client.outputs_at_address(address, count),
             InnerClient::OgmiosScrolls(cml_client) => cml_client.outputs_at_address(address, count),
+            InnerClient::KupoOgmios(cml_client) => cml_client.outputs_at_address(address, count),
+            InnerClient::Ogmios(cml_client) => cml_client.outputs_at_address(address, count),
         }
         .await
     }
@@ -521,6 +638,30 @@ where
             InnerClient::BlockFrost(cml_client) => cml_client.all_outputs_at_address(address),
             InnerClient::Mocked(test_client) => test_client.all_outputs_at_address(address),
             InnerClient::OgmiosScrolls(cml_client) => cml_client.all_outputs_at_address(address),
+            InnerClient::KupoOgmios(cml_client) => cml_client.all_outputs_at_address(address),
+            InnerClient::Ogmios(cml_client) => cml_client.all_outputs_at_address(address),
+        }
+        .await
+    }
+
+    async fn output_by_id(&self, id: &OutputId) -> LedgerClientResult<Option<Output<Datum>>> {
+        match &self.inner_client {
+            InnerClient::BlockFrost(cml_client) => cml_client.output_by_id(id),
+            InnerClient::Mocked(test_client) => test_client.output_by_id(id),
+            InnerClient::OgmiosScrolls(cml_client) => cml_client.output_by_id(id),
+            InnerClient::KupoOgmios(cml_client) => cml_client.output_by_id(id),
+            InnerClient::Ogmios(cml_client) => cml_client.output_by_id(id),
+        }
+        .await
+    }
+
+    async fn outputs_with_asset(&self, asset: &PolicyId) -> LedgerClientResult<Vec<Output<Datum>>> {
+        match &self.inner_client {
+            InnerClient::BlockFrost(cml_client) => cml_client.outputs_with_asset(asset),
+            InnerClient::Mocked(test_client) => test_client.outputs_with_asset(asset),
+            InnerClient::OgmiosScrolls(cml_client) => cml_client.outputs_with_asset(asset),
+            InnerClient::KupoOgmios(cml_client) => cml_client.outputs_with_asset(asset),
+            InnerClient::Ogmios(cml_client) => cml_client.outputs_with_asset(asset),
         }
         .await
     }
@@ -530,6 +671,80 @@ where
             InnerClient::BlockFrost(cml_client) => cml_client.issue(tx),
             InnerClient::Mocked(test_client) => test_client.issue(tx),
             InnerClient::OgmiosScrolls(cml_client) => cml_client.issue(tx),
+            InnerClient::KupoOgmios(cml_client) => cml_client.issue(tx),
+            InnerClient::Ogmios(cml_client) => cml_client.issue(tx),
+        }
+        .await
+    }
+
+    async fn build_unsigned(
+        &self,
+        tx: UnbuiltTransaction<Datum, Redeemer>,
+    ) -> LedgerClientResult<UnsignedTransaction> {
+        match &self.inner_client {
+            InnerClient::BlockFrost(cml_client) => cml_client.build_unsigned(tx),
+            InnerClient::Mocked(test_client) => test_client.build_unsigned(tx),
+            InnerClient::OgmiosScrolls(cml_client) => cml_client.build_unsigned(tx),
+            InnerClient::KupoOgmios(cml_client) => cml_client.build_unsigned(tx),
+            InnerClient::Ogmios(cml_client) => cml_client.build_unsigned(tx),
+        }
+        .await
+    }
+
+    async fn simulate(
+        &self,
+        tx: UnbuiltTransaction<Datum, Redeemer>,
+    ) -> LedgerClientResult<TxSimulation> {
+        match &self.inner_client {
+            InnerClient::BlockFrost(cml_client) => cml_client.simulate(tx),
+            InnerClient::Mocked(test_client) => test_client.simulate(tx),
+            InnerClient::OgmiosScrolls(cml_client) => cml_client.simulate(tx),
+            InnerClient::KupoOgmios(cml_client) => cml_client.simulate(tx),
+            InnerClient::Ogmios(cml_client) => cml_client.simulate(tx),
+        }
+        .await
+    }
+
+    async fn submit_signed(&self, cbor_hex: &str) -> LedgerClientResult<TxId> {
+        match &self.inner_client {
+            InnerClient::BlockFrost(cml_client) => cml_client.submit_signed(cbor_hex),
+            InnerClient::Mocked(test_client) => test_client.submit_signed(cbor_hex),
+            InnerClient::OgmiosScrolls(cml_client) => cml_client.submit_signed(cbor_hex),
+            InnerClient::KupoOgmios(cml_client) => cml_client.submit_signed(cbor_hex),
+            InnerClient::Ogmios(cml_client) => cml_client.submit_signed(cbor_hex),
+        }
+        .await
+    }
+
+    async fn tx_confirmations(&self, tx_id: &TxId) -> LedgerClientResult<Option<u64>> {
+        match &self.inner_client {
+            InnerClient::BlockFrost(cml_client) => cml_client.tx_confirmations(tx_id),
+            InnerClient::Mocked(test_client) => test_client.tx_confirmations(tx_id),
+            InnerClient::OgmiosScrolls(cml_client) => cml_client.tx_confirmations(tx_id),
+            InnerClient::KupoOgmios(cml_client) => cml_client.tx_confirmations(tx_id),
+            InnerClient::Ogmios(cml_client) => cml_client.tx_confirmations(tx_id),
+        }
+        .await
+    }
+
+    async fn await_tx(
+        &self,
+        tx_id: &TxId,
+        confirmations: u64,
+        timeout: Duration,
+    ) -> LedgerClientResult<()> {
+        match &self.inner_client {
+            InnerClient::BlockFrost(cml_client) => {
+                cml_client.await_tx(tx_id, confirmations, timeout)
+            }
+            InnerClient::Mocked(test_client) => test_client.await_tx(tx_id, confirmations, timeout),
+            InnerClient::OgmiosScrolls(cml_client) => {
+                cml_client.await_tx(tx_id, confirmations, timeout)
+            }
+            InnerClient::KupoOgmios(cml_client) => {
+                cml_client.await_tx(tx_id, confirmations, timeout)
+            }
+            InnerClient::Ogmios(cml_client) => cml_client.await_tx(tx_id, confirmations, timeout),
         }
         .await
     }
@@ -539,6 +754,8 @@ where
             InnerClient::BlockFrost(cml_client) => cml_client.network(),
             InnerClient::Mocked(test_client) => test_client.network(),
             InnerClient::OgmiosScrolls(cml_client) => cml_client.network(),
+            InnerClient::KupoOgmios(cml_client) => cml_client.network(),
+            InnerClient::Ogmios(cml_client) => cml_client.network(),
         }
         .await
     }
@@ -548,6 +765,8 @@ where
             InnerClient::BlockFrost(cml_client) => cml_client.last_block_time_secs(),
             InnerClient::Mocked(test_client) => test_client.last_block_time_secs(),
             InnerClient::OgmiosScrolls(cml_client) => cml_client.last_block_time_secs(),
+            InnerClient::KupoOgmios(cml_client) => cml_client.last_block_time_secs(),
+            InnerClient::Ogmios(cml_client) => cml_client.last_block_time_secs(),
         }
         .await
     }
@@ -563,6 +782,8 @@ where
                 test_client
             ),
             InnerClient::OgmiosScrolls(cml_client) => cml_client.current_time_secs(),
+            InnerClient::KupoOgmios(cml_client) => cml_client.current_time_secs(),
+            InnerClient::Ogmios(cml_client) => cml_client.current_time_secs(),
         }
         .await
     }
@@ -1,57 +1,74 @@
 use crate::trireme_ledger_client::cml_client::network_settings::NetworkSettings;
 use crate::{
-    ledger_client::{LedgerClient, LedgerClientError, LedgerClientResult},
-    output::{Output, UnbuiltOutput},
-    scripts::ValidatorCode,
-    transaction::{TransactionVersion, TxId},
+    ledger_client::{LedgerClient, LedgerClientError, LedgerClientResult, AWAIT_TX_POLL_INTERVAL},
+    output::{DatumEncoding, Output, OutputId, UnbuiltOutput},
+    scripts::{ExecutionCost as ScriptExecutionCost, ValidatorCode},
+    transaction::{
+        staking::{StakeCertificate, StakeWitness},
+        validity::ValidityBound,
+        RedeemerCost, RedeemerKind, TransactionVersion, TxId, TxSimulation, UnsignedTransaction,
+    },
     trireme_ledger_client::cml_client::issuance_helpers::{
-        cml_v1_script_from_nau_policy, cml_v2_script_from_nau_policy,
-        cml_v2_script_from_nau_script, vasil_v2_tx_builder,
+        cml_v1_script_from_nau_policy, cml_v2_script_from_nau_policy, cml_v2_script_from_nau_script,
     },
     trireme_ledger_client::cml_client::{
         issuance_helpers::{
-            add_collateral, build_tx_for_signing, cml_v1_script_from_nau_script, input_tx_hash,
-            partial_script_witness, select_inputs_from_utxos, sign_tx,
-            specify_utxos_available_for_input_selection, utxo_to_nau_utxo, vasil_v1_tx_builder,
+            add_collateral, build_tx_for_signing, cml_auxiliary_data, cml_mint_assets,
+            cml_script_from_nau_staking_validator, cml_script_ref, cml_stake_credential,
+            cml_v1_script_from_nau_script, input_tx_hash, needs_stake_key_signature,
+            partial_script_witness, plutus_script_witness, reference_script_hashes,
+            select_inputs_from_utxos, sign_tx, specify_utxos_available_for_input_selection,
+            tx_summary, utxo_to_nau_utxo,
         },
         plutus_data_interop::PlutusDataInterop,
+        protocol_parameters::ProtocolParameters,
     },
-    UnbuiltTransaction,
+    PolicyId, UnbuiltTransaction,
 };
 use async_trait::async_trait;
 use cardano_multiplatform_lib::{
     address::{Address as CMLAddress, BaseAddress, EnterpriseAddress, StakeCredential},
     builders::{
+        certificate_builder::SingleCertificateBuilder,
         input_builder::{InputBuilderResult, SingleInputBuilder},
         mint_builder::{MintBuilderResult, SingleMintBuilder},
         output_builder::SingleOutputBuilderResult,
         redeemer_builder::RedeemerWitnessKey,
-        tx_builder::{ChangeSelectionAlgo, TransactionBuilder},
-        witness_builder::{PartialPlutusWitness, PlutusScriptWitness},
+        tx_builder::{ChangeSelectionAlgo, SignedTxBuilder, TransactionBuilder},
+        withdrawal_builder::SingleWithdrawalBuilder,
+        witness_builder::PartialPlutusWitness,
     },
-    crypto::{PrivateKey, TransactionHash},
-    ledger::common::{hash::hash_plutus_data, value::BigNum, value::Int, value::Value as CMLValue},
+    crypto::{Ed25519KeyHash, PrivateKey, TransactionHash},
+    ledger::common::{hash::hash_plutus_data, value::BigNum, value::Value as CMLValue},
     plutus::{ExUnits, PlutusData, PlutusScript, RedeemerTag},
-    AssetName, Datum as CMLDatum, MintAssets, RequiredSigners, Transaction as CMLTransaction,
-    TransactionInput, TransactionOutput,
+    Certificate, Datum as CMLDatum, RequiredSigners, RewardAddress, ScriptRef, StakeDelegation,
+    StakeDeregistration, StakeRegistration, Transaction as CMLTransaction, TransactionInput,
+    TransactionOutput, TransactionUnspentOutput,
 };
 use error::*;
 use pallas_addresses::{Address, Network as CMLNetwork};
-use std::time::UNIX_EPOCH;
-use std::{collections::HashMap, fmt::Debug, marker::PhantomData, ops::Deref};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant, UNIX_EPOCH};
+use std::{collections::HashMap, fmt::Debug, marker::PhantomData, ops::Deref, path::PathBuf};
 
 pub mod blockfrost_ledger;
+pub mod cached_ledger;
 pub mod error;
 pub mod issuance_helpers;
 pub mod key_manager;
+pub mod kupo_ogmios_ledger;
 pub mod network_settings;
+pub mod ogmios_ledger;
 pub mod ogmios_scrolls_ledger;
 pub mod plutus_data_interop;
+pub mod protocol_parameters;
+pub mod recorded_ledger;
 
+#[cfg(test)]
+mod test_utils;
 #[cfg(test)]
 mod tests;
 
-// TODO: Add minimum ADA https://github.com/MitchTurner/naumachia/issues/41
 pub struct CMLLedgerCLient<L, K, Datum, Redeemer>
 where
     L: Ledger,
@@ -61,7 +78,9 @@ where
 {
     ledger: L,
     keys: K,
+    cosigners: Vec<Box<dyn Keys + Send + Sync>>,
     network_settings: NetworkSettings,
+    protocol_parameters_cache: Option<PathBuf>,
     _datum: PhantomData<Datum>,
     _redeemer: PhantomData<Redeemer>,
 }
@@ -70,6 +89,10 @@ where
 pub trait Keys {
     async fn base_addr(&self) -> Result<BaseAddress>;
     async fn private_key(&self) -> Result<PrivateKey>;
+    /// Only needed for stake certificates and withdrawals witnessed by the wallet's stake key
+    async fn stake_private_key(&self) -> Result<PrivateKey> {
+        Err(CMLLCError::NoStakeKey)
+    }
 }
 
 fn addr_from_bech_32(addr: &str) -> Result<CMLAddress> {
@@ -78,15 +101,66 @@ fn addr_from_bech_32(addr: &str) -> Result<CMLAddress> {
     Ok(cml_address)
 }
 
-#[derive(Debug)]
+fn cml_output(
+    address: &CMLAddress,
+    value: &CMLValue,
+    reference_script: &Option<ScriptRef>,
+    data: &Option<PlutusData>,
+) -> TransactionOutput {
+    let mut output = TransactionOutput::new(address, value);
+    if let Some(script_ref) = reference_script {
+        output.set_script_ref(script_ref);
+    }
+    if let Some(data) = data {
+        let data_hash = hash_plutus_data(data);
+        output.set_datum(&CMLDatum::new_data_hash(&data_hash));
+    }
+    output
+}
+
+/// Slot for the `which` bound of a validity range, which must lie between the start of the
+/// network and its stable slot horizon, past which the slot length may still change
+fn validity_slot(
+    network_settings: &NetworkSettings,
+    tip_posix: i64,
+    bound: ValidityBound,
+    which: &str,
+) -> LedgerClientResult<u64> {
+    let slot = match bound {
+        ValidityBound::PosixMillis(posix_ms) => network_settings
+            .slot_from_posix_ms(posix_ms)
+            .ok_or(LedgerClientError::ValidityRange(format!(
+                "Invalid {which} bound; Must be after the network's start time: {:?}",
+                network_settings.system_start()
+            )))?,
+        ValidityBound::Slot(slot) => slot,
+    };
+    let horizon =
+        network_settings
+            .horizon_slot(tip_posix)
+            .ok_or(LedgerClientError::ValidityRange(format!(
+                "Last block time {tip_posix:?} is before the network's start time"
+            )))?;
+    if slot > horizon {
+        return Err(LedgerClientError::ValidityRange(format!(
+            "Invalid {which} bound; Must be within the network's stable slot horizon: slot {horizon:?}"
+        )));
+    }
+    Ok(slot)
+}
+
+#[derive(Clone, Debug)]
 pub struct UTxO {
     tx_hash: TransactionHash,
     output_index: BigNum,
     amount: CMLValue,
     datum: Option<PlutusData>,
+    datum_encoding: DatumEncoding,
+    reference_script_hash: Option<Vec<u8>>,
 }
 
 impl UTxO {
+    /// Datums are taken to be hashed unless set otherwise with `with_datum_encoding`
     pub fn new(
         tx_hash: TransactionHash,
         output_index: BigNum,
@@ -98,9 +172,21 @@ impl UTxO {
             output_index,
             amount,
             datum,
+            datum_encoding: DatumEncoding::Hash,
+            reference_script_hash: None,
         }
     }
 
+    pub fn with_datum_encoding(mut self, datum_encoding: DatumEncoding) -> Self {
+        self.datum_encoding = datum_encoding;
+        self
+    }
+
+    pub fn with_reference_script_hash(mut self, script_hash: Vec<u8>) -> Self {
+        self.reference_script_hash = Some(script_hash);
+        self
+    }
+
     pub fn tx_hash(&self) -> &TransactionHash {
         &self.tx_hash
     }
@@ -116,16 +202,26 @@ impl UTxO {
     pub fn datum(&self) -> &Option<PlutusData> {
         &self.datum
     }
+
+    /// `None` if there's no datum
+    pub fn datum_encoding(&self) -> Option<DatumEncoding> {
+        self.datum.as_ref().map(|_| self.datum_encoding)
+    }
+
+    /// Hash of the script this output carries for reference inputs
+    pub fn reference_script_hash(&self) -> Option<&[u8]> {
+        self.reference_script_hash.as_deref()
+    }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ExecutionCost {
     execution_type: ExecutionType,
     memory: u64,
     steps: u64,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ExecutionType {
     Spend,
     Mint,
@@ -188,6 +284,17 @@ pub trait Ledger {
     async fn get_utxos_for_addr(&self, addr: &CMLAddress, count: usize) -> Result<Vec<UTxO>>;
     async fn get_all_utxos_for_addr(&self, addr: &CMLAddress) -> Result<Vec<UTxO>>;
     async fn calculate_ex_units(&self, tx: &CMLTransaction) -> Result<HashMap<u64, ExecutionCost>>;
+    /// Blocks from the one including `tx_id` up to the tip, or `None` if it isn't on chain yet
+    async fn tx_confirmations(&self, tx_id: &str) -> Result<Option<u64>>;
+    /// Unspent output `index` of tx `tx_hash` along with its owner
+    async fn utxo_by_id(&self, tx_hash: &str, index: u64) -> Result<Option<(Address, UTxO)>>;
+    /// Addresses holding `policy_id` tokens, limited to `asset_name` (hex) if given
+    async fn addresses_with_asset(
+        &self,
+        policy_id: &str,
+        asset_name: Option<&str>,
+    ) -> Result<Vec<Address>>;
+    async fn protocol_parameters(&self) -> Result<ProtocolParameters>;
     async fn submit_transaction(&self, tx: &CMLTransaction) -> Result<String>;
 }
 
@@ -202,19 +309,115 @@ where
         CMLLedgerCLient {
             ledger,
             keys,
+            cosigners: Vec::new(),
             network_settings,
+            protocol_parameters_cache: None,
             _datum: Default::default(),
             _redeemer: Default::default(),
         }
     }
 
+    /// Collect a witness from `cosigner` for txs that list its key hash as a required signer
+    pub fn with_cosigner<C: Keys + Send + Sync + 'static>(mut self, cosigner: C) -> Self {
+        self.cosigners.push(Box::new(cosigner));
+        self
+    }
+
+    /// Save fetched protocol parameters to `path` and fall back to them when the ledger can't
+    /// provide any, e.g. when running offline. Without a cache there's no fallback, so ledger
+    /// errors surface as is; clients built from a trireme `ClientConfig` always set one.
+    pub fn with_protocol_parameters_cache(mut self, path: PathBuf) -> Self {
+        self.protocol_parameters_cache = Some(path);
+        self
+    }
+
+    async fn tx_landed_at_my_address(&self, tx_id: &TxId) -> LedgerClientResult<bool> {
+        let my_address = self
+            .keys
+            .base_addr()
+            .await
+            .map_err(|e| LedgerClientError::FailedToLookUpTx(Box::new(e)))?
+            .to_address();
+        let landed = self
+            .ledger
+            .get_all_utxos_for_addr(&my_address)
+            .await
+            .map_err(|e| LedgerClientError::FailedToLookUpTx(Box::new(e)))?
+            .iter()
+            .any(|utxo| utxo.tx_hash().to_string() == tx_id.as_str());
+        Ok(landed)
+    }
+
+    /// Current protocol parameters from the ledger, or from the cache set with
+    /// `with_protocol_parameters_cache` if the ledger fails.
+    ///
+    /// The cache is only rewritten when the parameters change. Failing to write it isn't fatal,
+    /// e.g. in a read-only directory, since the fresh parameters are already in hand.
+    pub async fn protocol_parameters(&self) -> LedgerClientResult<ProtocolParameters> {
+        match self.ledger.protocol_parameters().await {
+            Ok(params) => {
+                if let Some(path) = &self.protocol_parameters_cache {
+                    let cached = ProtocolParameters::load(path).ok();
+                    if cached.as_ref() != Some(&params) {
+                        let _ = params.save(path);
+                    }
+                }
+                Ok(params)
+            }
+            Err(e) => match &self.protocol_parameters_cache {
+                Some(path) if path.exists() => {
+                    ProtocolParameters::load(path).map_err(as_failed_to_issue_tx)
+                }
+                _ => Err(as_failed_to_issue_tx(e)),
+            },
+        }
+    }
+
+    async fn cosigner_keys<Datum, Redeemer>(
+        &self,
+        tx: &UnbuiltTransaction<Datum, Redeemer>,
+    ) -> LedgerClientResult<Vec<PrivateKey>> {
+        let mut keys = Vec::new();
+        for cosigner in self.cosigners.iter() {
+            let priv_key = cosigner
+                .private_key()
+                .await
+                .map_err(as_failed_to_issue_tx)?;
+            let key_hash = priv_key.to_public().hash().to_bytes();
+            if tx
+                .required_signers()
+                .iter()
+                .any(|signer| signer.bytes() == key_hash)
+            {
+                keys.push(priv_key);
+            }
+        }
+        Ok(keys)
+    }
+
+    async fn add_required_signers<Datum, Redeemer>(
+        &self,
+        tx_builder: &mut TransactionBuilder,
+        tx: &UnbuiltTransaction<Datum, Redeemer>,
+    ) -> LedgerClientResult<()> {
+        for signer in tx.required_signers() {
+            let key_hash = Ed25519KeyHash::from_bytes(signer.bytes())
+                .map_err(|e| CMLLCError::Deserialize(e.to_string()))
+                .map_err(as_failed_to_issue_tx)?;
+            tx_builder.add_required_signer(&key_hash);
+        }
+        Ok(())
+    }
+
     async fn add_outputs_for_tx<Datum: PlutusDataInterop + Debug, Redeemer: PlutusDataInterop>(
         &self,
         tx_builder: &mut TransactionBuilder,
         tx: &UnbuiltTransaction<Datum, Redeemer>,
+        params: &ProtocolParameters,
     ) -> LedgerClientResult<()> {
+        let calculator = params.min_ada_calculator();
         for unbuilt_output in tx.unbuilt_outputs().iter() {
-            let cml_values: CMLValue = unbuilt_output
+            let mut cml_values: CMLValue = unbuilt_output
                 .values()
                 .to_owned()
                 .try_into()
@@ -222,18 +425,34 @@ where
             let recipient = unbuilt_output.owner();
             let recp_addr =
                 addr_from_bech_32(&recipient.to_string()).map_err(as_failed_to_issue_tx)?;
-            let mut output = TransactionOutput::new(&recp_addr, &cml_values);
-            let res = if let UnbuiltOutput::Validator { datum, .. } = unbuilt_output {
-                let data = datum.to_plutus_data();
-                let data_hash = hash_plutus_data(&data);
-                let cml_datum = CMLDatum::new_data_hash(&data_hash);
-                output.set_datum(&cml_datum);
-                let mut res = SingleOutputBuilderResult::new(&output);
-                res.set_communication_datum(&data);
-                res
+            let data = if let UnbuiltOutput::Validator { datum, .. } = unbuilt_output {
+                Some(datum.to_plutus_data())
             } else {
-                SingleOutputBuilderResult::new(&output)
+                None
             };
+            let reference_script = unbuilt_output
+                .reference_script()
+                .map(cml_script_ref)
+                .transpose()?;
+            let mut output = cml_output(&recp_addr, &cml_values, &reference_script, &data);
+            let has_lovelace = unbuilt_output.values().get(&PolicyId::Lovelace).is_some();
+            if tx.min_ada_top_up || !has_lovelace {
+                // Adding lovelace can grow the output, so repeat until it holds its own minimum
+                loop {
+                    let size = output.to_bytes().len() as u64;
+                    let required = calculator.min_ada_for_size(size);
+                    let current: u64 = cml_values.coin().into();
+                    if current >= required {
+                        break;
+                    }
+                    cml_values.set_coin(&required.into());
+                    output = cml_output(&recp_addr, &cml_values, &reference_script, &data);
+                }
+            }
+            let mut res = SingleOutputBuilderResult::new(&output);
+            if let Some(data) = &data {
+                res.set_communication_datum(data);
+            }
             tx_builder
                 .add_output(&res)
                 .map_err(|e| CMLLCError::JsError(e.to_string()))
@@ -260,7 +479,7 @@ where
     ) -> LedgerClientResult<InputBuilderResult> {
         let tx_hash = input_tx_hash(input).await?;
         let cml_script = cml_v1_script_from_nau_script(script).await?;
-        let partial_witness = partial_script_witness(&cml_script, redeemer).await;
+        let partial_witness = partial_script_witness(&cml_script, redeemer, &[]).await;
         let cml_script_address = self.cml_script_address(&cml_script).await;
         let required_signers = RequiredSigners::new();
 
@@ -293,10 +512,12 @@ where
         input: &Output<Datum>,
         redeemer: &Redeemer,
         script: &(dyn ValidatorCode<Datum, Redeemer> + '_),
+        reference_script_hashes: &[Vec<u8>],
     ) -> LedgerClientResult<InputBuilderResult> {
         let tx_hash = input_tx_hash(input).await?;
         let cml_script = cml_v2_script_from_nau_script(script).await?;
-        let partial_witness = partial_script_witness(&cml_script, redeemer).await;
+        let partial_witness =
+            partial_script_witness(&cml_script, redeemer, reference_script_hashes).await;
         let cml_script_address = self.cml_script_address(&cml_script).await;
         let required_signers = RequiredSigners::new();
 
@@ -309,16 +530,24 @@ where
             .clone()
             .try_into()
             .map_err(as_failed_to_issue_tx)?;
-        let utxo_info = TransactionOutput::new(&cml_script_address, &value);
-        let input_builder = SingleInputBuilder::new(&script_input, &utxo_info);
         let maybe_data: Option<Datum> = input.datum().to_owned().into();
         let data = maybe_data.ok_or(LedgerClientError::NoDatumOnScriptInput)?;
         let datum = data.to_plutus_data();
-        let cml_input = input_builder
-            .plutus_script(&partial_witness, &required_signers, &datum)
+        let mut utxo_info = TransactionOutput::new(&cml_script_address, &value);
+        // An inline datum is read from the spent output, and supplying it again is rejected
+        let inline_datum = input.datum_encoding() == Some(DatumEncoding::Inline);
+        if inline_datum {
+            utxo_info.set_datum(&CMLDatum::new_data(&datum));
+        }
+        let input_builder = SingleInputBuilder::new(&script_input, &utxo_info);
+        let cml_input = if inline_datum {
+            input_builder.plutus_script_inline_datum(&partial_witness, &required_signers)
+        } else {
+            input_builder.plutus_script(&partial_witness, &required_signers, &datum)
+        };
+        cml_input
             .map_err(|e| CMLLCError::JsError(e.to_string()))
-            .map_err(as_failed_to_issue_tx)?;
-        Ok(cml_input)
+            .map_err(as_failed_to_issue_tx)
     }
 
     async fn add_v1_script_input<Datum: PlutusDataInterop + Clone, Redeemer: PlutusDataInterop>(
@@ -344,9 +573,10 @@ where
         input: &Output<Datum>,
         redeemer: &Redeemer,
         script: &(dyn ValidatorCode<Datum, Redeemer> + '_),
+        reference_script_hashes: &[Vec<u8>],
     ) -> LedgerClientResult<()> {
         let cml_input = self
-            .build_v2_cml_script_input(input, redeemer, script)
+            .build_v2_cml_script_input(input, redeemer, script, reference_script_hashes)
             .await?;
         tx_builder
             .add_input(&cml_input)
@@ -360,11 +590,9 @@ where
         tx_builder: &mut TransactionBuilder,
         tx: &UnbuiltTransaction<Datum, Redeemer>,
     ) -> LedgerClientResult<()> {
-        for (amount, asset_name, redeemer, policy) in tx.minting.iter() {
+        for (assets, redeemer, policy) in tx.minting.iter() {
             let script = cml_v1_script_from_nau_policy(policy.deref()).await?;
-            let mint_builder_res = self
-                .build_mint_res(*amount, asset_name, redeemer, script)
-                .await?;
+            let mint_builder_res = self.build_mint_res(assets, redeemer, script, &[]).await?;
             tx_builder.add_mint(&mint_builder_res);
         }
         Ok(())
@@ -375,10 +603,11 @@ where
         tx_builder: &mut TransactionBuilder,
         tx: &UnbuiltTransaction<Datum, Redeemer>,
     ) -> LedgerClientResult<()> {
-        for (amount, asset_name, redeemer, policy) in tx.minting.iter() {
+        let reference_script_hashes = reference_script_hashes(tx)?;
+        for (assets, redeemer, policy) in tx.minting.iter() {
             let script = cml_v2_script_from_nau_policy(policy.deref()).await?;
             let mint_builder_res = self
-                .build_mint_res(*amount, asset_name, redeemer, script)
+                .build_mint_res(assets, redeemer, script, &reference_script_hashes)
                 .await?;
             tx_builder.add_mint(&mint_builder_res);
         }
@@ -387,24 +616,14 @@ where
 
     async fn build_mint_res<Redeemer: PlutusDataInterop>(
         &self,
-        amount: u64,
-        asset_name: &Option<String>,
+        assets: &[(Option<String>, i64)],
         redeemer: &Redeemer,
         script: PlutusScript,
+        reference_script_hashes: &[Vec<u8>],
     ) -> LedgerClientResult<MintBuilderResult> {
-        let inner_key = if let Some(name) = asset_name {
-            name.as_bytes().to_vec()
-        } else {
-            Vec::new()
-        };
-        let key = AssetName::new(inner_key)
-            .map_err(|e| CMLLCError::JsError(e.to_string()))
-            .map_err(as_failed_to_issue_tx)?;
-        let big_num = BigNum::from(amount);
-        let value = Int::new(&big_num);
-        let mint_assets = MintAssets::new_from_entry(&key, value);
+        let mint_assets = cml_mint_assets(assets)?;
         let mint_builder = SingleMintBuilder::new(&mint_assets);
-        let script_witness = PlutusScriptWitness::from_script(script);
+        let script_witness = plutus_script_witness(&script, reference_script_hashes);
         let redeemer = redeemer.to_plutus_data();
         let partial_witness = PartialPlutusWitness::new(&script_witness, &redeemer);
         let required_signers = RequiredSigners::new();
@@ -429,9 +648,16 @@ where
         tx_builder: &mut TransactionBuilder,
         tx: &UnbuiltTransaction<Datum, Redeemer>,
     ) -> LedgerClientResult<()> {
+        let reference_script_hashes = reference_script_hashes(tx)?;
         for (input, redeemer, script) in tx.script_inputs() {
-            self.add_v2_script_input(tx_builder, input, redeemer, script.deref())
-                .await?
+            self.add_v2_script_input(
+                tx_builder,
+                input,
+                redeemer,
+                script.deref(),
+                &reference_script_hashes,
+            )
+            .await?
         }
         Ok(())
     }
@@ -440,7 +666,7 @@ where
         &self,
         tx_builder: &mut TransactionBuilder,
         my_address: &CMLAddress,
-    ) -> LedgerClientResult<()> {
+    ) -> LedgerClientResult<Vec<RedeemerCost>> {
         let algo = ChangeSelectionAlgo::Default;
         let tx_redeemer_builder = tx_builder
             .build_for_evaluation(algo, my_address)
@@ -454,19 +680,25 @@ where
             .await
             .map_err(|e| CMLLCError::JsError(e.to_string()))
             .map_err(as_failed_to_issue_tx)?;
+        let mut costs = Vec::new();
         for (index, spend) in res.iter() {
-            let tag = match spend.execution_type {
-                ExecutionType::Spend => RedeemerTag::new_spend(),
-                ExecutionType::Mint => RedeemerTag::new_mint(),
-                ExecutionType::Withdrawal => RedeemerTag::new_reward(),
-                ExecutionType::Certificate => RedeemerTag::new_cert(),
+            let (tag, kind) = match spend.execution_type {
+                ExecutionType::Spend => (RedeemerTag::new_spend(), RedeemerKind::Spend),
+                ExecutionType::Mint => (RedeemerTag::new_mint(), RedeemerKind::Mint),
+                ExecutionType::Withdrawal => (RedeemerTag::new_reward(), RedeemerKind::Withdrawal),
+                ExecutionType::Certificate => (RedeemerTag::new_cert(), RedeemerKind::Certificate),
             };
             tx_builder.set_exunits(
                 &RedeemerWitnessKey::new(&tag, &BigNum::from(*index)),
                 &ExUnits::new(&spend.memory().into(), &spend.steps().into()),
             );
+            costs.push(RedeemerCost {
+                kind,
+                index: *index,
+                cost: ScriptExecutionCost::new(spend.memory() as i64, spend.steps() as i64),
+            });
         }
-        Ok(())
+        Ok(costs)
     }
 
     async fn submit_tx(&self, tx: &CMLTransaction) -> LedgerClientResult<TxId> {
@@ -478,50 +710,198 @@ where
         Ok(TxId::new(&submit_res))
     }
 
-    async fn issue_v1_tx<Datum: PlutusDataInterop + Debug + Clone, Redeemer: PlutusDataInterop>(
+    async fn build_v1_tx<Datum: PlutusDataInterop + Debug + Clone, Redeemer: PlutusDataInterop>(
         &self,
         tx: UnbuiltTransaction<Datum, Redeemer>,
         my_utxos: Vec<UTxO>,
         my_address: CMLAddress,
-        priv_key: PrivateKey,
-    ) -> LedgerClientResult<TxId> {
-        let mut tx_builder = vasil_v1_tx_builder()?;
+        params: &ProtocolParameters,
+    ) -> LedgerClientResult<(SignedTxBuilder, Vec<RedeemerCost>)> {
+        let mut tx_builder = params
+            .tx_builder(&TransactionVersion::V1)
+            .map_err(as_failed_to_issue_tx)?;
+        self.set_valid_range(&mut tx_builder, &tx).await?;
         self.add_v1_script_inputs(&mut tx_builder, &tx).await?;
         self.add_tokens_for_v1_minting(&mut tx_builder, &tx).await?;
+        self.add_certificates(&mut tx_builder, &tx).await?;
+        self.add_withdrawals(&mut tx_builder, &tx).await?;
+        self.add_required_signers(&mut tx_builder, &tx).await?;
         specify_utxos_available_for_input_selection(&mut tx_builder, &my_address, &my_utxos)
             .await?;
-        self.add_outputs_for_tx(&mut tx_builder, &tx).await?;
+        self.add_outputs_for_tx(&mut tx_builder, &tx, params)
+            .await?;
+        self.add_metadata(&mut tx_builder, &tx).await?;
         add_collateral(&mut tx_builder, &my_address, &my_utxos).await?;
         select_inputs_from_utxos(&mut tx_builder).await?;
-        self.update_ex_units(&mut tx_builder, &my_address).await?;
-        let mut signed_tx_builder = build_tx_for_signing(&mut tx_builder, &my_address).await?;
-        let tx = sign_tx(&mut signed_tx_builder, &priv_key).await?;
-        let tx_id = self.submit_tx(&tx).await?;
-        Ok(tx_id)
+        let costs = self.update_ex_units(&mut tx_builder, &my_address).await?;
+        let signed_tx_builder = build_tx_for_signing(&mut tx_builder, &my_address).await?;
+        Ok((signed_tx_builder, costs))
     }
 
-    async fn issue_v2_tx<Datum: PlutusDataInterop + Debug + Clone, Redeemer: PlutusDataInterop>(
+    async fn build_v2_tx<Datum: PlutusDataInterop + Debug + Clone, Redeemer: PlutusDataInterop>(
         &self,
         tx: UnbuiltTransaction<Datum, Redeemer>,
         my_utxos: Vec<UTxO>,
         my_address: CMLAddress,
-        priv_key: PrivateKey,
-    ) -> LedgerClientResult<TxId> {
-        let mut tx_builder = vasil_v2_tx_builder()?;
+        params: &ProtocolParameters,
+    ) -> LedgerClientResult<(SignedTxBuilder, Vec<RedeemerCost>)> {
+        let mut tx_builder = params
+            .tx_builder(&TransactionVersion::V2)
+            .map_err(as_failed_to_issue_tx)?;
         self.set_valid_range(&mut tx_builder, &tx).await?;
         self.add_v2_script_inputs(&mut tx_builder, &tx).await?;
         self.add_tokens_for_v2_minting(&mut tx_builder, &tx).await?;
+        self.add_certificates(&mut tx_builder, &tx).await?;
+        self.add_withdrawals(&mut tx_builder, &tx).await?;
+        self.add_required_signers(&mut tx_builder, &tx).await?;
         specify_utxos_available_for_input_selection(&mut tx_builder, &my_address, &my_utxos)
             .await?;
         self.add_specific_inputs(&mut tx_builder, &tx).await?;
-        self.add_outputs_for_tx(&mut tx_builder, &tx).await?;
+        self.add_reference_inputs(&mut tx_builder, &tx).await?;
+        self.add_outputs_for_tx(&mut tx_builder, &tx, params)
+            .await?;
+        self.add_metadata(&mut tx_builder, &tx).await?;
         add_collateral(&mut tx_builder, &my_address, &my_utxos).await?;
         select_inputs_from_utxos(&mut tx_builder).await?;
-        self.update_ex_units(&mut tx_builder, &my_address).await?;
-        let mut signed_tx_builder = build_tx_for_signing(&mut tx_builder, &my_address).await?;
-        let tx = sign_tx(&mut signed_tx_builder, &priv_key).await?;
-        let tx_id = self.submit_tx(&tx).await?;
-        Ok(tx_id)
+        let costs = self.update_ex_units(&mut tx_builder, &my_address).await?;
+        let signed_tx_builder = build_tx_for_signing(&mut tx_builder, &my_address).await?;
+        Ok((signed_tx_builder, costs))
+    }
+
+    async fn build_tx<Datum: PlutusDataInterop + Debug + Clone, Redeemer: PlutusDataInterop>(
+        &self,
+        tx: UnbuiltTransaction<Datum, Redeemer>,
+    ) -> LedgerClientResult<(SignedTxBuilder, Vec<RedeemerCost>)> {
+        if tx.script_version == TransactionVersion::V1 && !tx.reference_inputs().is_empty() {
+            return Err(as_failed_to_issue_tx(CMLLCError::ReferenceInputsInV1));
+        }
+        let spends_inline_datum = tx
+            .script_inputs()
+            .iter()
+            .any(|(input, _, _)| input.datum_encoding() == Some(DatumEncoding::Inline));
+        if tx.script_version == TransactionVersion::V1 && spends_inline_datum {
+            return Err(as_failed_to_issue_tx(CMLLCError::InlineDatumInV1));
+        }
+        let my_address = self
+            .keys
+            .base_addr()
+            .await
+            .map_err(as_failed_to_issue_tx)?
+            .to_address();
+        let my_utxos = self
+            .ledger
+            .get_all_utxos_for_addr(&my_address)
+            .await
+            .map_err(as_failed_to_issue_tx)?;
+
+        let params = self.protocol_parameters().await?;
+
+        match tx.script_version {
+            TransactionVersion::V1 => self.build_v1_tx(tx, my_utxos, my_address, &params).await,
+            TransactionVersion::V2 => self.build_v2_tx(tx, my_utxos, my_address, &params).await,
+        }
+    }
+
+    async fn add_certificates<Datum, Redeemer: PlutusDataInterop>(
+        &self,
+        tx_builder: &mut TransactionBuilder,
+        tx: &UnbuiltTransaction<Datum, Redeemer>,
+    ) -> LedgerClientResult<()> {
+        for certificate in tx.certificates() {
+            let credential = certificate.credential().map_err(as_failed_to_issue_tx)?;
+            let cml_credential = cml_stake_credential(&credential)?;
+            let cml_certificate = match certificate {
+                StakeCertificate::Registration(_) => {
+                    Certificate::new_stake_registration(&StakeRegistration::new(&cml_credential))
+                }
+                StakeCertificate::Deregistration(_) => Certificate::new_stake_deregistration(
+                    &StakeDeregistration::new(&cml_credential),
+                ),
+                StakeCertificate::Delegation { pool_key_hash, .. } => {
+                    let pool_key_hash = Ed25519KeyHash::from_bytes(pool_key_hash.clone())
+                        .map_err(|e| CMLLCError::Deserialize(e.to_string()))
+                        .map_err(as_failed_to_issue_tx)?;
+                    Certificate::new_stake_delegation(&StakeDelegation::new(
+                        &cml_credential,
+                        &pool_key_hash,
+                    ))
+                }
+            };
+            let certificate_builder = SingleCertificateBuilder::new(&cml_certificate);
+            let res = match certificate.witness() {
+                None => certificate_builder.skip_witness(),
+                Some(StakeWitness::Key(_)) => certificate_builder
+                    .payment_key()
+                    .map_err(|e| CMLLCError::JsError(e.to_string()))
+                    .map_err(as_failed_to_issue_tx)?,
+                Some(StakeWitness::Script {
+                    redeemer,
+                    validator,
+                }) => {
+                    let script = cml_script_from_nau_staking_validator(
+                        &tx.script_version,
+                        validator.deref(),
+                    )
+                    .await?;
+                    let partial_witness = partial_script_witness(&script, redeemer, &[]).await;
+                    certificate_builder
+                        .plutus_script(&partial_witness, &RequiredSigners::new())
+                        .map_err(|e| CMLLCError::JsError(e.to_string()))
+                        .map_err(as_failed_to_issue_tx)?
+                }
+            };
+            tx_builder.add_cert(&res);
+        }
+        Ok(())
+    }
+
+    async fn add_withdrawals<Datum, Redeemer: PlutusDataInterop>(
+        &self,
+        tx_builder: &mut TransactionBuilder,
+        tx: &UnbuiltTransaction<Datum, Redeemer>,
+    ) -> LedgerClientResult<()> {
+        for withdrawal in tx.withdrawals() {
+            let credential = withdrawal
+                .witness
+                .credential()
+                .map_err(as_failed_to_issue_tx)?;
+            let cml_credential = cml_stake_credential(&credential)?;
+            let reward_address =
+                RewardAddress::new(self.network_settings.network(), &cml_credential);
+            let withdrawal_builder =
+                SingleWithdrawalBuilder::new(&reward_address, &BigNum::from(withdrawal.amount));
+            let res = match &withdrawal.witness {
+                StakeWitness::Key(_) => withdrawal_builder.payment_key(),
+                StakeWitness::Script {
+                    redeemer,
+                    validator,
+                } => {
+                    let script = cml_script_from_nau_staking_validator(
+                        &tx.script_version,
+                        validator.deref(),
+                    )
+                    .await?;
+                    let partial_witness = partial_script_witness(&script, redeemer, &[]).await;
+                    withdrawal_builder.plutus_script(&partial_witness, &RequiredSigners::new())
+                }
+            }
+            .map_err(|e| CMLLCError::JsError(e.to_string()))
+            .map_err(as_failed_to_issue_tx)?;
+            tx_builder.add_withdrawal(&res);
+        }
+        Ok(())
+    }
+
+    async fn add_metadata<Datum, Redeemer>(
+        &self,
+        tx_builder: &mut TransactionBuilder,
+        tx: &UnbuiltTransaction<Datum, Redeemer>,
+    ) -> LedgerClientResult<()> {
+        if !tx.metadata().is_empty() {
+            let auxiliary_data = cml_auxiliary_data(tx.metadata())?;
+            tx_builder.set_auxiliary_data(&auxiliary_data);
+        }
+        Ok(())
     }
 
     async fn set_valid_range<Datum: PlutusDataInterop + Debug, Redeemer: PlutusDataInterop>(
@@ -529,23 +909,20 @@ where
         tx_builder: &mut TransactionBuilder,
         tx: &UnbuiltTransaction<Datum, Redeemer>,
     ) -> LedgerClientResult<()> {
-        let (lower, upper) = tx.valid_range;
-        if let Some(posix) = lower {
-            let slot = self.network_settings.slot_from_posix(posix).ok_or(
-                LedgerClientError::ValidityRange(format!(
-                    "Invalid lower bounds; Must be after starting slot time: {:?}",
-                    self.network_settings.starting_slot_time()
-                )),
-            )?;
+        if tx.valid_range.is_unbounded() {
+            return Ok(());
+        }
+        let tip_posix = self
+            .ledger
+            .last_block_time_secs()
+            .await
+            .map_err(as_failed_to_get_block_time)?;
+        if let Some(bound) = tx.valid_range.lower {
+            let slot = validity_slot(&self.network_settings, tip_posix, bound, "lower")?;
             tx_builder.set_validity_start_interval(&slot.into());
         }
-        if let Some(posix) = upper {
-            let slot = self.network_settings.slot_from_posix(posix).ok_or(
-                LedgerClientError::ValidityRange(format!(
-                    "Invalid upper bounds; Must be after starting slot time: {:?}",
-                    self.network_settings.starting_slot_time()
-                )),
-            )?;
+        if let Some(bound) = tx.valid_range.upper {
+            let slot = validity_slot(&self.network_settings, tip_posix, bound, "upper")?;
             tx_builder.set_ttl(&slot.into());
         }
         Ok(())
@@ -581,6 +958,41 @@ where
         }
         Ok(())
     }
+
+    async fn add_reference_inputs<Datum: PlutusDataInterop + Clone, Redeemer: PlutusDataInterop>(
+        &self,
+        tx_builder: &mut TransactionBuilder,
+        tx: &UnbuiltTransaction<Datum, Redeemer>,
+    ) -> LedgerClientResult<()> {
+        for reference_input in tx.reference_inputs() {
+            let transaction_id = input_tx_hash(reference_input).await?;
+            let index = reference_input.id().index().into();
+            let input = TransactionInput::new(&transaction_id, &index);
+            let address = addr_from_bech_32(&reference_input.owner().to_string())
+                .map_err(|e| CMLLCError::JsError(e.to_string()))
+                .map_err(as_failed_to_issue_tx)?;
+            let amount = reference_input
+                .values()
+                .clone()
+                .try_into()
+                .map_err(as_failed_to_issue_tx)?;
+            let mut output = TransactionOutput::new(&address, &amount);
+            if let Some(datum) = reference_input.typed_datum() {
+                let datum = datum.to_plutus_data();
+                let cml_datum = match reference_input.datum_encoding() {
+                    Some(DatumEncoding::Inline) => CMLDatum::new_data(&datum),
+                    _ => CMLDatum::new_data_hash(&hash_plutus_data(&datum)),
+                };
+                output.set_datum(&cml_datum);
+            }
+            if let Some(reference_script) = reference_input.reference_script() {
+                output.set_script_ref(&cml_script_ref(reference_script)?);
+            }
+            let utxo = TransactionUnspentOutput::new(&input, &output);
+            tx_builder.add_reference_input(&utxo);
+        }
+        Ok(())
+    }
 }
 
 #[async_trait]
@@ -632,6 +1044,50 @@ where
         Ok(utxos)
     }
 
+    async fn output_by_id(&self, id: &OutputId) -> LedgerClientResult<Option<Output<Datum>>> {
+        let tx_hash = hex::encode(id.tx_hash());
+        let utxo = self
+            .ledger
+            .utxo_by_id(&tx_hash, id.index())
+            .await
+            .map_err(|e| {
+                LedgerClientError::FailedToRetrieveOutputWithId(id.clone(), Box::new(e))
+            })?;
+        utxo.map(|(owner, utxo)| utxo_to_nau_utxo(&utxo, &owner))
+            .transpose()
+    }
+
+    async fn outputs_with_asset(&self, asset: &PolicyId) -> LedgerClientResult<Vec<Output<Datum>>> {
+        let (policy_id, asset_name) = match asset {
+            PolicyId::NativeToken(id, name) => {
+                (id, name.as_ref().map(|name| hex::encode(name.as_bytes())))
+            }
+            PolicyId::Lovelace => {
+                return Err(LedgerClientError::FailedToRetrieveOutputsWithAsset(
+                    asset.clone(),
+                    Box::new(CMLLCError::InvalidPolicyId("lovelace".to_string())),
+                ))
+            }
+        };
+        let addresses = self
+            .ledger
+            .addresses_with_asset(policy_id, asset_name.as_deref())
+            .await
+            .map_err(|e| {
+                LedgerClientError::FailedToRetrieveOutputsWithAsset(asset.clone(), Box::new(e))
+            })?;
+        let mut outputs = Vec::new();
+        for address in addresses {
+            let holding = self
+                .all_outputs_at_address(&address)
+                .await?
+                .into_iter()
+                .filter(|output| output.values().contains_asset(asset));
+            outputs.extend(holding);
+        }
+        Ok(outputs)
+    }
+
     async fn all_outputs_at_address(
         &self,
         address: &Address,
@@ -655,28 +1111,57 @@ where
     }
 
     async fn issue(&self, tx: UnbuiltTransaction<Datum, Redeemer>) -> LedgerClientResult<TxId> {
-        let my_address = self
-            .keys
-            .base_addr()
-            .await
-            .map_err(as_failed_to_issue_tx)?
-            .to_address();
         let priv_key = self
             .keys
             .private_key()
             .await
             .map_err(as_failed_to_issue_tx)?;
+        let mut priv_keys = vec![priv_key];
+        if needs_stake_key_signature(&tx) {
+            let stake_key = self
+                .keys
+                .stake_private_key()
+                .await
+                .map_err(as_failed_to_issue_tx)?;
+            priv_keys.push(stake_key);
+        }
+        priv_keys.extend(self.cosigner_keys(&tx).await?);
 
-        let my_utxos = self
-            .ledger
-            .get_all_utxos_for_addr(&my_address)
-            .await
-            .map_err(as_failed_to_issue_tx)?;
+        let (mut signed_tx_builder, _) = self.build_tx(tx).await?;
+        let tx = sign_tx(&mut signed_tx_builder, &priv_keys).await?;
+        self.submit_tx(&tx).await
+    }
 
-        match tx.script_version {
-            TransactionVersion::V1 => self.issue_v1_tx(tx, my_utxos, my_address, priv_key).await,
-            TransactionVersion::V2 => self.issue_v2_tx(tx, my_utxos, my_address, priv_key).await,
-        }
+    async fn build_unsigned(
+        &self,
+        tx: UnbuiltTransaction<Datum, Redeemer>,
+    ) -> LedgerClientResult<UnsignedTransaction> {
+        let (signed_tx_builder, _) = self.build_tx(tx).await?;
+        let unsigned_tx = signed_tx_builder.build_unchecked();
+        let summary = tx_summary(&unsigned_tx)?;
+        let cbor_hex = hex::encode(unsigned_tx.to_bytes());
+        Ok(UnsignedTransaction::new(cbor_hex, summary))
+    }
+
+    async fn simulate(
+        &self,
+        tx: UnbuiltTransaction<Datum, Redeemer>,
+    ) -> LedgerClientResult<TxSimulation> {
+        let (signed_tx_builder, execution_costs) = self.build_tx(tx).await?;
+        let summary = tx_summary(&signed_tx_builder.build_unchecked())?;
+        let simulation = TxSimulation {
+            summary,
+            execution_costs,
+        };
+        Ok(simulation)
+    }
+
+    async fn submit_signed(&self, cbor_hex: &str) -> LedgerClientResult<TxId> {
+        let bytes = hex::decode(cbor_hex).map_err(as_failed_to_issue_tx)?;
+        let tx = CMLTransaction::from_bytes(bytes)
+            .map_err(|e| CMLLCError::Deserialize(e.to_string()))
+            .map_err(as_failed_to_issue_tx)?;
+        self.submit_tx(&tx).await
     }
 
     async fn network(&self) -> LedgerClientResult<CMLNetwork> {
@@ -688,6 +1173,51 @@ where
         Ok(network)
     }
 
+    /// Blocks from the one including `tx_id` up to the tip, or `None` if it hasn't landed yet.
+    /// Errors with `Unsupported` for ledgers that can't look up a tx by hash or measure its depth.
+    async fn tx_confirmations(&self, tx_id: &TxId) -> LedgerClientResult<Option<u64>> {
+        match self.ledger.tx_confirmations(&tx_id.as_str()).await {
+            Err(CMLLCError::TxLookupUnsupported) => Err(LedgerClientError::Unsupported(
+                "Counting confirmations with this ledger".to_string(),
+            )),
+            res => res.map_err(|e| LedgerClientError::FailedToLookUpTx(Box::new(e))),
+        }
+    }
+
+    /// Ledgers that can't look up a tx by hash only see the signer's own txs, and count them as
+    /// one confirmation once their change shows up at the signer's address, so a single
+    /// confirmation can be awaited with them, but no more.
+    async fn await_tx(
+        &self,
+        tx_id: &TxId,
+        confirmations: u64,
+        timeout: Duration,
+    ) -> LedgerClientResult<()> {
+        let start = Instant::now();
+        loop {
+            let current = match self.ledger.tx_confirmations(&tx_id.as_str()).await {
+                Err(CMLLCError::TxLookupUnsupported) if confirmations <= 1 => {
+                    self.tx_landed_at_my_address(tx_id).await?.then_some(1)
+                }
+                Err(CMLLCError::TxLookupUnsupported) => {
+                    return Err(LedgerClientError::Unsupported(format!(
+                        "Awaiting {confirmations} confirmations with this ledger"
+                    )))
+                }
+                res => res.map_err(|e| LedgerClientError::FailedToLookUpTx(Box::new(e)))?,
+            };
+            if let Some(current) = current {
+                if current >= confirmations {
+                    return Ok(());
+                }
+            }
+            if start.elapsed() >= timeout {
+                return Err(LedgerClientError::TxNotConfirmed(tx_id.as_str(), timeout));
+            }
+            tokio::time::sleep(AWAIT_TX_POLL_INTERVAL).await;
+        }
+    }
+
     async fn last_block_time_secs(&self) -> LedgerClientResult<i64> {
         self.ledger
             .last_block_time_secs()
@@ -1,68 +1,190 @@
 use super::error::*;
-use crate::trireme_ledger_client::cml_client::{error::CMLLCError, ExecutionCost, Ledger, UTxO};
+use crate::output::DatumEncoding;
+use crate::transaction::TransactionVersion;
+use crate::trireme_ledger_client::cml_client::{
+    error::CMLLCError,
+    protocol_parameters::{ProtocolParameters, Ratio},
+    ExecutionCost, Ledger, UTxO,
+};
+use crate::trireme_ledger_client::Network;
 use async_trait::async_trait;
-use blockfrost_http_client::error::Error;
 use blockfrost_http_client::{
-    models::ExecutionType, models::UTxO as BFUTxO, models::Value as BFValue, BlockFrostHttp,
-    BlockFrostHttpTrait,
+    models::ExecutionType, models::Value as BFValue, BlockFrostHttp, BlockFrostHttpTrait,
+    MAINNET_URL, PREPROD_NETWORK_URL,
 };
 use cardano_multiplatform_lib::{
     address::Address as CMLAddress, crypto::TransactionHash, ledger::common::value::BigNum,
-    ledger::common::value::Value as CMLValue, plutus::encode_json_str_to_plutus_datum,
-    plutus::PlutusDatumSchema, AssetName, Assets, MultiAsset, PolicyID,
-    Transaction as CMLTransaction,
+    ledger::common::value::Value as CMLValue, plutus::PlutusData, AssetName, Assets, MultiAsset,
+    PolicyID, Transaction as CMLTransaction,
+};
+use pallas_addresses::Address;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    str::FromStr,
 };
-use futures::future;
-use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, str::FromStr};
 use thiserror::Error;
 
+#[cfg(test)]
+mod tests;
+
+/// Blockfrost endpoint for the Preview testnet
+pub const PREVIEW_NETWORK_URL: &str = "https://cardano-preview.blockfrost.io/api/v0";
+
+/// Blockfrost's hosted endpoint for `network`, if Blockfrost hosts one
+pub fn blockfrost_url(network: &Network) -> Option<&'static str> {
+    match network {
+        Network::Preprod => Some(PREPROD_NETWORK_URL),
+        Network::Preview => Some(PREVIEW_NETWORK_URL),
+        Network::Mainnet => Some(MAINNET_URL),
+        Network::Custom { .. } => None,
+    }
+}
+
+/// Items Blockfrost returns per page of a paginated endpoint
+const PAGE_SIZE: usize = 100;
+
 pub struct BlockFrostLedger {
     client: BlockFrostHttp,
+    /// For the endpoints `client` doesn't cover, shared so connections are pooled
+    http: reqwest::Client,
+    url: String,
+    key: String,
 }
 
 impl BlockFrostLedger {
     pub fn new(url: &str, key: &str) -> Self {
         let client = BlockFrostHttp::new(url, key);
-        BlockFrostLedger { client }
+        BlockFrostLedger {
+            client,
+            http: reqwest::Client::new(),
+            url: url.to_string(),
+            key: key.to_string(),
+        }
     }
 
-    // TODO: Handle V2 outputs (with inline datums)
-    async fn bfutxo_to_utxo(&self, bf_utxo: &BFUTxO) -> Result<UTxO> {
-        let tx_hash = TransactionHash::from_hex(bf_utxo.tx_hash())
-            .map_err(|e| CMLLCError::JsError(e.to_string()))?;
-        let output_index = bf_utxo.output_index().into();
-        let amount = cmlvalue_from_bfvalues(bf_utxo.amount())?;
-        let datum = if let Some(data_hash) = bf_utxo.data_hash() {
-            let json_datum = self
-                .client
-                .datum(data_hash)
-                .await
-                .map_err(|e| CMLLCError::LedgerError(Box::new(e)))?;
-            if let Some(inner) = json_datum.as_object() {
-                if inner.get("error").is_none() {
-                    let ser = json_datum["json_value"].to_string();
-                    let plutus_data = encode_json_str_to_plutus_datum(
-                        &ser, // TODO: Make this safer!
-                        PlutusDatumSchema::DetailedSchema,
-                    )
-                    .map_err(|e| CMLLCError::JsError(e.to_string()))?;
-                    Some(plutus_data)
-                } else {
-                    None // TODO: Add debug msg
-                }
-            } else {
-                None // TODO: Add debug msg
+    /// GET an endpoint the http client doesn't cover. `None` if Blockfrost doesn't know it.
+    async fn get_endpoint<T: DeserializeOwned>(&self, path: &str) -> Result<Option<T>> {
+        let url = format!("{}/{}", self.url.trim_end_matches('/'), path);
+        let res = self
+            .http
+            .get(url)
+            .header("project_id", &self.key)
+            .send()
+            .await
+            .map_err(|e| CMLLCError::LedgerError(Box::new(e)))?;
+        let status = res.status();
+        if status == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !status.is_success() {
+            let message = res.text().await.unwrap_or_default();
+            return Err(CMLLCError::HttpStatus {
+                status: status.as_u16(),
+                message,
+            });
+        }
+        let body = res
+            .json()
+            .await
+            .map_err(|e| CMLLCError::LedgerError(Box::new(e)))?;
+        Ok(Some(body))
+    }
+
+    /// Every page of a paginated endpoint, or nothing if it doesn't exist
+    async fn get_all_pages<T: DeserializeOwned>(&self, path: &str) -> Result<Vec<T>> {
+        self.get_pages(path, usize::MAX).await
+    }
+
+    /// Every page of a paginated endpoint up to `limit` items, or nothing if it doesn't exist
+    async fn get_pages<T: DeserializeOwned>(&self, path: &str, limit: usize) -> Result<Vec<T>> {
+        let mut items = Vec::new();
+        let mut page = 1;
+        while items.len() < limit {
+            let mut page_items = match self
+                .get_endpoint::<Vec<T>>(&format!("{path}?page={page}"))
+                .await?
+            {
+                Some(page_items) => page_items,
+                None => break,
+            };
+            let last_page = page_items.len() < PAGE_SIZE;
+            items.append(&mut page_items);
+            if last_page {
+                break;
             }
+            page += 1;
+        }
+        items.truncate(limit);
+        Ok(items)
+    }
+
+    /// `None` if Blockfrost hasn't seen the datum, e.g. because no tx has published it yet
+    async fn datum_from_hash(&self, data_hash: &str) -> Result<Option<PlutusData>> {
+        let datum: Option<BFDatumCbor> = self
+            .get_endpoint(&format!("scripts/datum/{data_hash}/cbor"))
+            .await?;
+        datum
+            .map(|datum| decode_datum(data_hash, &datum.cbor))
+            .transpose()
+    }
+
+    async fn utxo_from_bf_output(
+        &self,
+        tx_hash: &str,
+        output_index: u64,
+        output: &BFOutput,
+    ) -> Result<UTxO> {
+        let cml_tx_hash =
+            TransactionHash::from_hex(tx_hash).map_err(|e| CMLLCError::JsError(e.to_string()))?;
+        let amount = cmlvalue_from_bfvalues(&output.amount)?;
+        // Blockfrost sets `data_hash` for inline datums too, so check for those first
+        let (datum, datum_encoding) = if let Some(cbor) = &output.inline_datum {
+            (Some(decode_datum(cbor, cbor)?), DatumEncoding::Inline)
+        } else if let Some(data_hash) = &output.data_hash {
+            (self.datum_from_hash(data_hash).await?, DatumEncoding::Hash)
         } else {
-            None
+            (None, DatumEncoding::Hash)
         };
+        let utxo = UTxO::new(cml_tx_hash, output_index.into(), amount, datum)
+            .with_datum_encoding(datum_encoding);
+        if let Some(script_hash) = &output.reference_script_hash {
+            Ok(utxo.with_reference_script_hash(hex::decode(script_hash)?))
+        } else {
+            Ok(utxo)
+        }
+    }
 
-        let utxo = UTxO::new(tx_hash, output_index, amount, datum);
-        Ok(utxo)
+    /// Datums are looked up one at a time, so a call makes one request at once and
+    /// `CachedLedger` alone bounds how many are in flight
+    async fn utxos_at(&self, addr: &CMLAddress, limit: usize) -> Result<Vec<UTxO>> {
+        let addr_string = addr
+            .to_bech32(None)
+            .map_err(|e| CMLLCError::JsError(e.to_string()))?;
+        let bf_utxos: Vec<BFAddressUtxo> = self
+            .get_pages(&format!("addresses/{addr_string}/utxos"), limit)
+            .await?;
+        let mut utxos = Vec::with_capacity(bf_utxos.len());
+        for bf_utxo in &bf_utxos {
+            let utxo = self
+                .utxo_from_bf_output(&bf_utxo.tx_hash, bf_utxo.output_index, &bf_utxo.output)
+                .await?;
+            utxos.push(utxo);
+        }
+        Ok(utxos)
     }
 }
 
+/// `datum` is what to name the datum by in errors, its hash or CBOR
+fn decode_datum(datum: &str, cbor: &str) -> Result<PlutusData> {
+    let decode_error = |reason: String| CMLLCError::DatumDecode {
+        datum: datum.to_string(),
+        reason,
+    };
+    let bytes = hex::decode(cbor).map_err(|e| decode_error(e.to_string()))?;
+    PlutusData::from_bytes(bytes).map_err(|e| decode_error(e.to_string()))
+}
+
 pub fn cmlvalue_from_bfvalues(values: &[BFValue]) -> Result<CMLValue> {
     let mut cml_value = CMLValue::zero();
     for value in values.iter() {
@@ -109,61 +231,11 @@ impl Ledger for BlockFrostLedger {
     }
 
     async fn get_utxos_for_addr(&self, addr: &CMLAddress, count: usize) -> Result<Vec<UTxO>> {
-        let addr_string = addr
-            .to_bech32(None)
-            .map_err(|e| CMLLCError::JsError(e.to_string()))?;
-        let bf_utxos_res = self.client.utxos(&addr_string, Some(count)).await;
-        let bf_utxos = match bf_utxos_res {
-            Ok(bf_utxos) => Ok(bf_utxos),
-            Err(e) => match e {
-                Error::HttpError { status_code, .. } => {
-                    if status_code == 404 {
-                        Ok(Vec::new())
-                    } else {
-                        Err(CMLLCError::LedgerError(Box::new(e)))
-                    }
-                }
-                _ => Err(CMLLCError::LedgerError(Box::new(e))),
-            },
-        }?;
-        let utxos = future::join_all(
-            bf_utxos
-                .iter()
-                .map(|bf_utxo| async move { self.bfutxo_to_utxo(bf_utxo).await }),
-        )
-        .await
-        .into_iter()
-        .collect::<Result<Vec<_>>>()?;
-        Ok(utxos)
+        self.utxos_at(addr, count).await
     }
 
     async fn get_all_utxos_for_addr(&self, addr: &CMLAddress) -> Result<Vec<UTxO>> {
-        let addr_string = addr
-            .to_bech32(None)
-            .map_err(|e| CMLLCError::JsError(e.to_string()))?;
-        let bf_utxos_res = self.client.utxos(&addr_string, None).await;
-        let bf_utxos = match bf_utxos_res {
-            Ok(bf_utxos) => Ok(bf_utxos),
-            Err(e) => match e {
-                Error::HttpError { status_code, .. } => {
-                    if status_code == 404 {
-                        Ok(Vec::new())
-                    } else {
-                        Err(CMLLCError::LedgerError(Box::new(e)))
-                    }
-                }
-                _ => Err(CMLLCError::LedgerError(Box::new(e))),
-            },
-        }?;
-        let utxos = future::join_all(
-            bf_utxos
-                .iter()
-                .map(|bf_utxo| async move { self.bfutxo_to_utxo(bf_utxo).await }),
-        )
-        .await
-        .into_iter()
-        .collect::<Result<Vec<_>>>()?;
-        Ok(utxos)
+        self.utxos_at(addr, usize::MAX).await
     }
 
     async fn calculate_ex_units(&self, tx: &CMLTransaction) -> Result<HashMap<u64, ExecutionCost>> {
@@ -185,6 +257,74 @@ impl Ledger for BlockFrostLedger {
         Ok(spends)
     }
 
+    async fn tx_confirmations(&self, tx_id: &str) -> Result<Option<u64>> {
+        let tx: Option<BFTxInfo> = self.get_endpoint(&format!("txs/{tx_id}")).await?;
+        if let Some(tx) = tx {
+            let tip: BFBlockInfo = self
+                .get_endpoint("blocks/latest")
+                .await?
+                .ok_or(CMLLCError::LedgerError("No latest block".into()))?;
+            Ok(Some(tip.height.saturating_sub(tx.block_height) + 1))
+        } else {
+            Ok(None)
+        }
+    }
+
+    async fn utxo_by_id(&self, tx_hash: &str, index: u64) -> Result<Option<(Address, UTxO)>> {
+        let tx_utxos: Option<BFTxUtxos> =
+            self.get_endpoint(&format!("txs/{tx_hash}/utxos")).await?;
+        let output = tx_utxos.and_then(|utxos| {
+            utxos
+                .outputs
+                .into_iter()
+                .find(|output| output.output_index == index && output.consumed_by_tx.is_none())
+        });
+        if let Some(output) = output {
+            let address = Address::from_bech32(&output.address)?;
+            let utxo = self
+                .utxo_from_bf_output(tx_hash, index, &output.output)
+                .await?;
+            Ok(Some((address, utxo)))
+        } else {
+            Ok(None)
+        }
+    }
+
+    async fn addresses_with_asset(
+        &self,
+        policy_id: &str,
+        asset_name: Option<&str>,
+    ) -> Result<Vec<Address>> {
+        let units = if let Some(asset_name) = asset_name {
+            vec![format!("{policy_id}{asset_name}")]
+        } else {
+            self.get_all_pages::<BFPolicyAsset>(&format!("assets/policy/{policy_id}"))
+                .await?
+                .into_iter()
+                .map(|policy_asset| policy_asset.asset)
+                .collect()
+        };
+        let mut addresses = BTreeSet::new();
+        for unit in units {
+            let holders: Vec<BFAssetAddress> = self
+                .get_all_pages(&format!("assets/{unit}/addresses"))
+                .await?;
+            addresses.extend(holders.into_iter().map(|holder| holder.address));
+        }
+        addresses
+            .iter()
+            .map(|address| Address::from_bech32(address).map_err(CMLLCError::from))
+            .collect()
+    }
+
+    async fn protocol_parameters(&self) -> Result<ProtocolParameters> {
+        let params: BFProtocolParameters = self
+            .get_endpoint("epochs/latest/parameters")
+            .await?
+            .ok_or(CMLLCError::LedgerError("No protocol parameters".into()))?;
+        params.try_into()
+    }
+
     async fn submit_transaction(&self, tx: &CMLTransaction) -> Result<String> {
         println!("{}", &tx.to_json().unwrap());
         let res = self
@@ -207,11 +347,142 @@ fn spend_from_bf_spend(
     }
 }
 
+#[derive(Deserialize)]
+struct BFTxInfo {
+    block_height: u64,
+}
+
+#[derive(Deserialize)]
+struct BFBlockInfo {
+    height: u64,
+}
+
+#[derive(Deserialize)]
+struct BFPolicyAsset {
+    asset: String,
+}
+
+#[derive(Deserialize)]
+struct BFAssetAddress {
+    address: String,
+}
+
+#[derive(Deserialize)]
+struct BFTxUtxos {
+    outputs: Vec<BFTxOutput>,
+}
+
+#[derive(Deserialize)]
+struct BFTxOutput {
+    address: String,
+    output_index: u64,
+    /// Only set once the output is spent
+    #[serde(default)]
+    consumed_by_tx: Option<String>,
+    #[serde(flatten)]
+    output: BFOutput,
+}
+
+#[derive(Deserialize)]
+struct BFAddressUtxo {
+    tx_hash: String,
+    output_index: u64,
+    #[serde(flatten)]
+    output: BFOutput,
+}
+
+/// Fields of an output shared by the address and tx UTxO endpoints
+#[derive(Deserialize)]
+struct BFOutput {
+    amount: Vec<BFValue>,
+    data_hash: Option<String>,
+    /// CBOR hex
+    #[serde(default)]
+    inline_datum: Option<String>,
+    #[serde(default)]
+    reference_script_hash: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct BFDatumCbor {
+    cbor: String,
+}
+
+#[derive(Deserialize)]
+struct BFProtocolParameters {
+    min_fee_a: u64,
+    min_fee_b: u64,
+    pool_deposit: String,
+    key_deposit: String,
+    max_val_size: String,
+    max_tx_size: u32,
+    coins_per_utxo_size: String,
+    price_mem: serde_json::Number,
+    price_step: serde_json::Number,
+    collateral_percent: u32,
+    max_collateral_inputs: u32,
+    cost_models: HashMap<String, BTreeMap<String, i64>>,
+    /// Cost models already in their on-chain order, so parameters added at hard forks need no
+    /// name table
+    #[serde(default)]
+    cost_models_raw: Option<HashMap<String, Vec<i64>>>,
+}
+
+impl TryFrom<BFProtocolParameters> for ProtocolParameters {
+    type Error = CMLLCError;
+
+    fn try_from(params: BFProtocolParameters) -> Result<Self> {
+        let parse = |field: &str| {
+            field
+                .parse()
+                .map_err(|_| CMLLCError::Deserialize(format!("Not a number: {field:?}")))
+        };
+        let cost_model = |language: &str, version: TransactionVersion| {
+            if let Some(raw) = &params.cost_models_raw {
+                return Ok(raw.get(language).cloned().unwrap_or_default());
+            }
+            ProtocolParameters::cost_model_from_named(&version, params.cost_models.get(language))
+        };
+        Ok(ProtocolParameters {
+            min_fee_coefficient: params.min_fee_a,
+            min_fee_constant: params.min_fee_b,
+            pool_deposit: parse(&params.pool_deposit)?,
+            key_deposit: parse(&params.key_deposit)?,
+            max_value_size: u32::try_from(parse(&params.max_val_size)?).map_err(|_| {
+                CMLLCError::Deserialize(format!("Out of range: {:?}", params.max_val_size))
+            })?,
+            max_tx_size: params.max_tx_size,
+            coins_per_utxo_byte: parse(&params.coins_per_utxo_size)?,
+            memory_price: Ratio::from_decimal_str(&params.price_mem.to_string())?,
+            step_price: Ratio::from_decimal_str(&params.price_step.to_string())?,
+            collateral_percentage: params.collateral_percent,
+            max_collateral_inputs: params.max_collateral_inputs,
+            plutus_v1_cost_model: cost_model("PlutusV1", TransactionVersion::V1)?,
+            plutus_v2_cost_model: cost_model("PlutusV2", TransactionVersion::V2)?,
+        })
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct BlockfrostApiKey {
     inner: String,
 }
 
+impl BlockfrostApiKey {
+    /// Blockfrost project ids start with the network they're for, e.g. `preprodAbC...`
+    pub fn network(&self) -> Option<Network> {
+        if self.inner.starts_with("mainnet") {
+            Some(Network::Mainnet)
+        } else if self.inner.starts_with("preprod") {
+            Some(Network::Preprod)
+        } else if self.inner.starts_with("preview") {
+            Some(Network::Preview)
+        } else {
+            None
+        }
+    }
+}
+
 impl From<BlockfrostApiKey> for String {
     fn from(secret_phrase: BlockfrostApiKey) -> Self {
         secret_phrase.inner
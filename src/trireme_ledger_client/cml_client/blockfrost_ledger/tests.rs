@@ -0,0 +1,175 @@
+#![allow(non_snake_case)]
+
+use super::*;
+use crate::trireme_ledger_client::cml_client::test_utils::{
+    alice, serve, ALICE, DATUM_HASH, TX_HASH,
+};
+
+const SCRIPT_HASH: &str = "67f33146617a5e61936081db3b2117cbf59bd2123748f58ac9678656";
+
+fn bf_utxo(index: u64, data_hash: Option<&str>, inline_datum: Option<&str>) -> String {
+    let quoted = |field: Option<&str>| {
+        field
+            .map(|value| format!("\"{value}\""))
+            .unwrap_or_else(|| "null".to_string())
+    };
+    format!(
+        r#"{{"address":"{ALICE}","tx_hash":"{TX_HASH}","output_index":{index},"amount":[{{"unit":"lovelace","quantity":"5000000"}}],"block":"00","data_hash":{},"inline_datum":{},"reference_script_hash":null}}"#,
+        quoted(data_hash),
+        quoted(inline_datum),
+    )
+}
+
+#[tokio::test]
+async fn get_all_utxos_for_addr__decodes_inline_datums_and_resolves_hashed_ones() {
+    // given
+    let with_reference_script = bf_utxo(2, None, None).replace(
+        r#""reference_script_hash":null"#,
+        &format!(r#""reference_script_hash":"{SCRIPT_HASH}""#),
+    );
+    let utxos = format!(
+        "[{},{},{}]",
+        // Blockfrost sets the hash of inline datums too, but it mustn't be looked up
+        bf_utxo(0, Some(DATUM_HASH), Some("d87980")),
+        bf_utxo(1, Some(DATUM_HASH), None),
+        with_reference_script,
+    );
+    let url = serve(vec![
+        (format!("addresses/{ALICE}/utxos?page=1"), utxos),
+        (
+            format!("scripts/datum/{DATUM_HASH}/cbor"),
+            r#"{"cbor":"d87a80"}"#.to_string(),
+        ),
+    ])
+    .await;
+    let ledger = BlockFrostLedger::new(&url, "key");
+
+    // when
+    let utxos = ledger.get_all_utxos_for_addr(&alice()).await.unwrap();
+
+    // then
+    assert_eq!(utxos.len(), 3);
+    assert_eq!(
+        utxos[0].datum().as_ref().map(|datum| datum.to_bytes()),
+        Some(hex::decode("d87980").unwrap())
+    );
+    assert_eq!(utxos[0].datum_encoding(), Some(DatumEncoding::Inline));
+    assert_eq!(
+        utxos[1].datum().as_ref().map(|datum| datum.to_bytes()),
+        Some(hex::decode("d87a80").unwrap())
+    );
+    assert_eq!(utxos[1].datum_encoding(), Some(DatumEncoding::Hash));
+    assert_eq!(utxos[2].datum_encoding(), None);
+    assert_eq!(
+        utxos[2].reference_script_hash(),
+        Some(hex::decode(SCRIPT_HASH).unwrap().as_slice())
+    );
+}
+
+#[tokio::test]
+async fn get_all_utxos_for_addr__reports_undecodable_inline_datums() {
+    // given
+    let utxos = format!("[{}]", bf_utxo(0, None, Some("ff00")));
+    let url = serve(vec![(format!("addresses/{ALICE}/utxos?page=1"), utxos)]).await;
+    let ledger = BlockFrostLedger::new(&url, "key");
+
+    // when
+    let res = ledger.get_all_utxos_for_addr(&alice()).await;
+
+    // then
+    assert!(matches!(res, Err(CMLLCError::DatumDecode { .. })));
+}
+
+#[tokio::test]
+async fn get_utxos_for_addr__leaves_unpublished_hashed_datums_empty() {
+    // given
+    let utxos = format!(
+        "[{},{}]",
+        bf_utxo(0, Some(DATUM_HASH), None),
+        bf_utxo(1, None, None)
+    );
+    let url = serve(vec![(format!("addresses/{ALICE}/utxos?page=1"), utxos)]).await;
+    let ledger = BlockFrostLedger::new(&url, "key");
+
+    // when
+    let utxos = ledger.get_utxos_for_addr(&alice(), 1).await.unwrap();
+
+    // then
+    assert_eq!(utxos.len(), 1);
+    assert!(utxos[0].datum().is_none());
+}
+
+#[test]
+fn network__inferred_from_api_key_prefix() {
+    let network = |key: &str| BlockfrostApiKey::from_str(key).unwrap().network();
+    assert_eq!(network("mainnetAbC123"), Some(Network::Mainnet));
+    assert_eq!(network("preprodAbC123"), Some(Network::Preprod));
+    assert_eq!(network("previewAbC123"), Some(Network::Preview));
+    assert_eq!(network("testnetAbC123"), None);
+}
+
+#[test]
+fn blockfrost_url__per_network() {
+    let custom = Network::Custom {
+        byron_genesis_file: "byron-genesis.json".into(),
+        shelley_genesis_file: "shelley-genesis.json".into(),
+        shelley_start_epoch: 0,
+    };
+    assert_eq!(blockfrost_url(&Network::Preprod), Some(PREPROD_NETWORK_URL));
+    assert_eq!(blockfrost_url(&Network::Preview), Some(PREVIEW_NETWORK_URL));
+    assert_eq!(blockfrost_url(&Network::Mainnet), Some(MAINNET_URL));
+    assert_eq!(blockfrost_url(&custom), None);
+}
+
+fn bf_protocol_parameters() -> BFProtocolParameters {
+    BFProtocolParameters {
+        min_fee_a: 44,
+        min_fee_b: 155381,
+        pool_deposit: "500000000".to_string(),
+        key_deposit: "2000000".to_string(),
+        max_val_size: "5000".to_string(),
+        max_tx_size: 16384,
+        coins_per_utxo_size: "4310".to_string(),
+        price_mem: serde_json::Number::from_f64(0.0577).unwrap(),
+        price_step: serde_json::Number::from_f64(0.0000721).unwrap(),
+        collateral_percent: 150,
+        max_collateral_inputs: 3,
+        cost_models: HashMap::new(),
+        cost_models_raw: None,
+    }
+}
+
+#[test]
+fn protocol_parameters__rejects_max_value_size_beyond_u32() {
+    // given
+    let params = BFProtocolParameters {
+        max_val_size: (u64::from(u32::MAX) + 1).to_string(),
+        ..bf_protocol_parameters()
+    };
+
+    // when
+    let res = ProtocolParameters::try_from(params);
+
+    // then
+    assert!(matches!(res, Err(CMLLCError::Deserialize(_))));
+}
+
+#[test]
+fn protocol_parameters__take_raw_cost_models_in_on_chain_order() {
+    // given
+    let plutus_v2: Vec<i64> = (0..185).collect();
+    let mut named_v2 = BTreeMap::new();
+    named_v2.insert("integerToByteString-cpu-arguments-c0".to_string(), 1);
+    let params = BFProtocolParameters {
+        cost_models: HashMap::from([("PlutusV2".to_string(), named_v2)]),
+        cost_models_raw: Some(HashMap::from([("PlutusV2".to_string(), plutus_v2.clone())])),
+        ..bf_protocol_parameters()
+    };
+
+    // when
+    let params = ProtocolParameters::try_from(params).unwrap();
+
+    // then
+    assert_eq!(params.plutus_v2_cost_model, plutus_v2);
+    assert!(params.plutus_v1_cost_model.is_empty());
+}
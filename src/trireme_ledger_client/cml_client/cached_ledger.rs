@@ -0,0 +1,253 @@
+use super::{error::*, ExecutionCost, Ledger, UTxO};
+use crate::trireme_ledger_client::cml_client::protocol_parameters::ProtocolParameters;
+use async_trait::async_trait;
+use cardano_multiplatform_lib::{address::Address as CMLAddress, Transaction as CMLTransaction};
+use pallas_addresses::Address;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+use tokio::sync::Semaphore;
+
+#[cfg(test)]
+mod tests;
+
+/// How `CachedLedger` treats the ledger it wraps
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LedgerCacheConfig {
+    /// Seconds an address's UTxOs are served from the cache. `0` turns caching off.
+    pub utxo_ttl_secs: u64,
+    /// Calls to the wrapped ledger in flight at once. The wrapped ledgers make one request at a
+    /// time per call, so this also bounds the requests in flight.
+    pub max_concurrent_requests: usize,
+    /// Retries of a call rejected for rate limiting (429) or a server error (5xx)
+    pub max_retries: u32,
+    /// Wait before the first retry, doubled for each one after
+    pub initial_backoff_ms: u64,
+}
+
+impl Default for LedgerCacheConfig {
+    fn default() -> Self {
+        LedgerCacheConfig {
+            utxo_ttl_secs: 0,
+            max_concurrent_requests: 8,
+            max_retries: 3,
+            initial_backoff_ms: 500,
+        }
+    }
+}
+
+struct CachedUTxOs {
+    fetched_at: Instant,
+    utxos: Vec<UTxO>,
+}
+
+#[derive(Default)]
+struct UTxOCache {
+    by_address: HashMap<String, CachedUTxOs>,
+    /// Bumped by every eviction, so a fetch that raced a submission isn't cached
+    generation: u64,
+}
+
+/// Wraps a `Ledger` to cache UTxOs per address, bound concurrent calls, and retry calls that
+/// fail with 429 or 5xx responses. Submitting a tx evicts the addresses it spends from or pays
+/// to.
+pub struct CachedLedger<L> {
+    inner: L,
+    config: LedgerCacheConfig,
+    permits: Semaphore,
+    utxos: Mutex<UTxOCache>,
+}
+
+impl<L: Ledger> CachedLedger<L> {
+    pub fn new(inner: L, config: LedgerCacheConfig) -> Self {
+        let permits = Semaphore::new(config.max_concurrent_requests.max(1));
+        CachedLedger {
+            inner,
+            config,
+            permits,
+            utxos: Mutex::new(UTxOCache::default()),
+        }
+    }
+
+    async fn call<T, F, Fut>(&self, call: F) -> Result<T>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let _permit = self
+            .permits
+            .acquire()
+            .await
+            .map_err(|e| CMLLCError::LedgerError(Box::new(e)))?;
+        let mut backoff = Duration::from_millis(self.config.initial_backoff_ms);
+        let mut retries = 0;
+        loop {
+            match call().await {
+                Err(e) if retries < self.config.max_retries && is_retryable(&e) => {
+                    retries += 1;
+                    tokio::time::sleep(backoff).await;
+                    backoff *= 2;
+                }
+                res => return res,
+            }
+        }
+    }
+
+    fn lock_utxos(&self) -> Result<MutexGuard<'_, UTxOCache>> {
+        self.utxos
+            .lock()
+            .map_err(|e| CMLLCError::LedgerError(format!("{e:?}").into()))
+    }
+
+    fn cached_utxos(&self, address: &str) -> Result<Option<Vec<UTxO>>> {
+        let ttl = Duration::from_secs(self.config.utxo_ttl_secs);
+        let utxos = self
+            .lock_utxos()?
+            .by_address
+            .get(address)
+            .filter(|cached| cached.fetched_at.elapsed() < ttl)
+            .map(|cached| cached.utxos.clone());
+        Ok(utxos)
+    }
+
+    /// Skipped if anything was evicted since `generation`, as the UTxOs may predate a submission
+    fn cache_utxos(&self, address: String, utxos: &[UTxO], generation: u64) -> Result<()> {
+        if self.config.utxo_ttl_secs > 0 {
+            let mut cache = self.lock_utxos()?;
+            if cache.generation == generation {
+                let cached = CachedUTxOs {
+                    fetched_at: Instant::now(),
+                    utxos: utxos.to_vec(),
+                };
+                cache.by_address.insert(address, cached);
+            }
+        }
+        Ok(())
+    }
+
+    fn evict_addresses_touched_by(&self, tx: &CMLTransaction) -> Result<()> {
+        let body = tx.body();
+        let inputs = body.inputs();
+        let spent: Vec<(Vec<u8>, u64)> = (0..inputs.len())
+            .map(|i| {
+                let input = inputs.get(i);
+                (input.transaction_id().to_bytes(), input.index().into())
+            })
+            .collect();
+        let outputs = body.outputs();
+        let paid_to = (0..outputs.len())
+            .map(|i| outputs.get(i).address().to_bech32(None))
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| CMLLCError::JsError(e.to_string()))?;
+        let mut cache = self.lock_utxos()?;
+        cache.generation += 1;
+        cache.by_address.retain(|address, cached| {
+            let spends_from = cached.utxos.iter().any(|utxo| {
+                spent.contains(&(utxo.tx_hash().to_bytes(), utxo.output_index().into()))
+            });
+            !spends_from && !paid_to.contains(address)
+        });
+        Ok(())
+    }
+}
+
+fn is_retryable(error: &CMLLCError) -> bool {
+    match error {
+        CMLLCError::HttpStatus { status, .. } => is_retryable_status(*status),
+        CMLLCError::LedgerError(inner) => {
+            if let Some(e) = inner.downcast_ref::<reqwest::Error>() {
+                e.status().map_or(e.is_timeout(), |status| {
+                    is_retryable_status(status.as_u16())
+                })
+            } else if let Some(blockfrost_http_client::error::Error::HttpError {
+                status_code,
+                ..
+            }) = inner.downcast_ref::<blockfrost_http_client::error::Error>()
+            {
+                is_retryable_status(*status_code)
+            } else {
+                false
+            }
+        }
+        _ => false,
+    }
+}
+
+fn is_retryable_status(status: u16) -> bool {
+    status == 429 || (500..600).contains(&status)
+}
+
+fn cml_address_string(addr: &CMLAddress) -> Result<String> {
+    addr.to_bech32(None)
+        .map_err(|e| CMLLCError::JsError(e.to_string()))
+}
+
+#[async_trait]
+impl<L: Ledger + Send + Sync> Ledger for CachedLedger<L> {
+    async fn last_block_time_secs(&self) -> Result<i64> {
+        self.call(|| self.inner.last_block_time_secs()).await
+    }
+
+    async fn get_utxos_for_addr(&self, addr: &CMLAddress, count: usize) -> Result<Vec<UTxO>> {
+        if let Some(utxos) = self.cached_utxos(&cml_address_string(addr)?)? {
+            return Ok(utxos.into_iter().take(count).collect());
+        }
+        self.call(|| self.inner.get_utxos_for_addr(addr, count))
+            .await
+    }
+
+    async fn get_all_utxos_for_addr(&self, addr: &CMLAddress) -> Result<Vec<UTxO>> {
+        let address = cml_address_string(addr)?;
+        if let Some(utxos) = self.cached_utxos(&address)? {
+            return Ok(utxos);
+        }
+        let generation = self.lock_utxos()?.generation;
+        let utxos = self
+            .call(|| self.inner.get_all_utxos_for_addr(addr))
+            .await?;
+        self.cache_utxos(address, &utxos, generation)?;
+        Ok(utxos)
+    }
+
+    async fn calculate_ex_units(&self, tx: &CMLTransaction) -> Result<HashMap<u64, ExecutionCost>> {
+        self.call(|| self.inner.calculate_ex_units(tx)).await
+    }
+
+    async fn tx_confirmations(&self, tx_id: &str) -> Result<Option<u64>> {
+        self.call(|| self.inner.tx_confirmations(tx_id)).await
+    }
+
+    async fn utxo_by_id(&self, tx_hash: &str, index: u64) -> Result<Option<(Address, UTxO)>> {
+        self.call(|| self.inner.utxo_by_id(tx_hash, index)).await
+    }
+
+    async fn addresses_with_asset(
+        &self,
+        policy_id: &str,
+        asset_name: Option<&str>,
+    ) -> Result<Vec<Address>> {
+        self.call(|| self.inner.addresses_with_asset(policy_id, asset_name))
+            .await
+    }
+
+    async fn protocol_parameters(&self) -> Result<ProtocolParameters> {
+        self.call(|| self.inner.protocol_parameters()).await
+    }
+
+    /// Not retried, since a submission that timed out may still have gone through
+    async fn submit_transaction(&self, tx: &CMLTransaction) -> Result<String> {
+        let _permit = self
+            .permits
+            .acquire()
+            .await
+            .map_err(|e| CMLLCError::LedgerError(Box::new(e)))?;
+        let tx_id = self.inner.submit_transaction(tx).await?;
+        self.evict_addresses_touched_by(tx)?;
+        Ok(tx_id)
+    }
+}
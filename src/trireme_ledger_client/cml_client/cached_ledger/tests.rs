@@ -0,0 +1,260 @@
+#![allow(non_snake_case)]
+
+use super::*;
+use crate::trireme_ledger_client::cml_client::test_utils::{alice, ALICE, TX_HASH};
+use cardano_multiplatform_lib::{
+    crypto::TransactionHash,
+    ledger::common::value::{BigNum, Value as CMLValue},
+    TransactionBody, TransactionInput, TransactionInputs, TransactionOutputs,
+    TransactionWitnessSet,
+};
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+
+/// Fails with `failure_status` for the first `failures` calls, then answers
+struct CountingLedger {
+    calls: AtomicU32,
+    failures: u32,
+    failure_status: u16,
+    in_flight: AtomicUsize,
+    max_in_flight: AtomicUsize,
+}
+
+impl CountingLedger {
+    fn new() -> Self {
+        Self::failing(0, 500)
+    }
+
+    fn failing(failures: u32, failure_status: u16) -> Self {
+        CountingLedger {
+            calls: AtomicU32::new(0),
+            failures,
+            failure_status,
+            in_flight: AtomicUsize::new(0),
+            max_in_flight: AtomicUsize::new(0),
+        }
+    }
+
+    fn next_call(&self) -> Result<()> {
+        let call = self.calls.fetch_add(1, Ordering::SeqCst);
+        if call < self.failures {
+            Err(CMLLCError::HttpStatus {
+                status: self.failure_status,
+                message: "nope".to_string(),
+            })
+        } else {
+            Ok(())
+        }
+    }
+}
+
+fn utxo() -> UTxO {
+    UTxO::new(
+        TransactionHash::from_hex(TX_HASH).unwrap(),
+        BigNum::from(0),
+        CMLValue::new(&BigNum::from(10_000_000)),
+        None,
+    )
+}
+
+#[async_trait]
+impl Ledger for CountingLedger {
+    /// Takes a while, so concurrent calls overlap
+    async fn last_block_time_secs(&self) -> Result<i64> {
+        self.next_call()?;
+        let in_flight = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+        self.max_in_flight.fetch_max(in_flight, Ordering::SeqCst);
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+        Ok(1_666_000_000)
+    }
+
+    async fn get_utxos_for_addr(&self, _addr: &CMLAddress, _count: usize) -> Result<Vec<UTxO>> {
+        self.next_call()?;
+        Ok(vec![utxo()])
+    }
+
+    async fn get_all_utxos_for_addr(&self, _addr: &CMLAddress) -> Result<Vec<UTxO>> {
+        self.next_call()?;
+        Ok(vec![utxo()])
+    }
+
+    async fn calculate_ex_units(
+        &self,
+        _tx: &CMLTransaction,
+    ) -> Result<HashMap<u64, ExecutionCost>> {
+        self.next_call()?;
+        Ok(HashMap::new())
+    }
+
+    async fn tx_confirmations(&self, _tx_id: &str) -> Result<Option<u64>> {
+        self.next_call()?;
+        Ok(None)
+    }
+
+    async fn utxo_by_id(&self, _tx_hash: &str, _index: u64) -> Result<Option<(Address, UTxO)>> {
+        self.next_call()?;
+        Ok(None)
+    }
+
+    async fn addresses_with_asset(
+        &self,
+        _policy_id: &str,
+        _asset_name: Option<&str>,
+    ) -> Result<Vec<Address>> {
+        self.next_call()?;
+        Ok(Vec::new())
+    }
+
+    async fn protocol_parameters(&self) -> Result<ProtocolParameters> {
+        self.next_call()?;
+        Err(CMLLCError::LedgerError("No protocol parameters".into()))
+    }
+
+    async fn submit_transaction(&self, _tx: &CMLTransaction) -> Result<String> {
+        self.next_call()?;
+        Ok(TX_HASH.to_string())
+    }
+}
+
+/// Spends the UTxO `CountingLedger` reports and pays to no one
+fn spend_utxo_tx() -> CMLTransaction {
+    let mut inputs = TransactionInputs::new();
+    inputs.add(&TransactionInput::new(
+        &TransactionHash::from_hex(TX_HASH).unwrap(),
+        &BigNum::from(0),
+    ));
+    let body = TransactionBody::new(
+        &inputs,
+        &TransactionOutputs::new(),
+        &BigNum::from(200_000),
+        None,
+    );
+    CMLTransaction::new(&body, &TransactionWitnessSet::new(), None)
+}
+
+fn config(utxo_ttl_secs: u64) -> LedgerCacheConfig {
+    LedgerCacheConfig {
+        utxo_ttl_secs,
+        initial_backoff_ms: 1,
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn get_all_utxos_for_addr__serves_cached_utxos_within_ttl() {
+    // given
+    let ledger = CachedLedger::new(CountingLedger::new(), config(60));
+    ledger.get_all_utxos_for_addr(&alice()).await.unwrap();
+
+    // when
+    let utxos = ledger.get_all_utxos_for_addr(&alice()).await.unwrap();
+    let some_utxos = ledger.get_utxos_for_addr(&alice(), 1).await.unwrap();
+
+    // then
+    assert_eq!(utxos.len(), 1);
+    assert_eq!(some_utxos.len(), 1);
+    assert_eq!(ledger.inner.calls.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn get_all_utxos_for_addr__refetches_without_ttl() {
+    // given
+    let ledger = CachedLedger::new(CountingLedger::new(), config(0));
+    ledger.get_all_utxos_for_addr(&alice()).await.unwrap();
+
+    // when
+    ledger.get_all_utxos_for_addr(&alice()).await.unwrap();
+
+    // then
+    assert_eq!(ledger.inner.calls.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn submit_transaction__evicts_addresses_it_spends_from() {
+    // given
+    let ledger = CachedLedger::new(CountingLedger::new(), config(60));
+    ledger.get_all_utxos_for_addr(&alice()).await.unwrap();
+
+    // when
+    ledger.submit_transaction(&spend_utxo_tx()).await.unwrap();
+    ledger.get_all_utxos_for_addr(&alice()).await.unwrap();
+
+    // then
+    assert_eq!(ledger.inner.calls.load(Ordering::SeqCst), 3);
+}
+
+#[test]
+fn get_all_utxos_for_addr__does_not_cache_a_fetch_that_raced_an_eviction() {
+    // given
+    let ledger = CachedLedger::new(CountingLedger::new(), config(60));
+    let generation = ledger.lock_utxos().unwrap().generation;
+    ledger.evict_addresses_touched_by(&spend_utxo_tx()).unwrap();
+
+    // when
+    ledger
+        .cache_utxos(ALICE.to_string(), &[utxo()], generation)
+        .unwrap();
+
+    // then
+    assert!(ledger.cached_utxos(ALICE).unwrap().is_none());
+}
+
+#[tokio::test]
+async fn call__retries_rate_limited_calls() {
+    // given
+    let ledger = CachedLedger::new(CountingLedger::failing(2, 429), config(0));
+
+    // when
+    let time = ledger.last_block_time_secs().await.unwrap();
+
+    // then
+    assert_eq!(time, 1_666_000_000);
+    assert_eq!(ledger.inner.calls.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn call__gives_up_after_max_retries() {
+    // given
+    let ledger = CachedLedger::new(CountingLedger::failing(10, 503), config(0));
+
+    // when
+    let res = ledger.last_block_time_secs().await;
+
+    // then
+    assert!(matches!(
+        res,
+        Err(CMLLCError::HttpStatus { status: 503, .. })
+    ));
+    assert_eq!(ledger.inner.calls.load(Ordering::SeqCst), 4);
+}
+
+#[tokio::test]
+async fn call__does_not_retry_client_errors() {
+    // given
+    let ledger = CachedLedger::new(CountingLedger::failing(1, 400), config(0));
+
+    // when
+    let res = ledger.last_block_time_secs().await;
+
+    // then
+    assert!(res.is_err());
+    assert_eq!(ledger.inner.calls.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn call__caps_calls_in_flight() {
+    // given
+    let config = LedgerCacheConfig {
+        max_concurrent_requests: 2,
+        ..config(0)
+    };
+    let ledger = CachedLedger::new(CountingLedger::new(), config);
+
+    // when
+    let times = futures::future::join_all((0..6).map(|_| ledger.last_block_time_secs())).await;
+
+    // then
+    assert!(times.iter().all(|time| time.is_ok()));
+    assert_eq!(ledger.inner.calls.load(Ordering::SeqCst), 6);
+    assert_eq!(ledger.inner.max_in_flight.load(Ordering::SeqCst), 2);
+}
@@ -1,4 +1,6 @@
 use crate::ledger_client::LedgerClientError;
+use crate::trireme_ledger_client::cml_client::protocol_parameters::ProtocolParametersError;
+use crate::trireme_ledger_client::cml_client::recorded_ledger::FixtureError;
 use pallas_addresses::Address;
 use thiserror::Error;
 
@@ -20,6 +22,8 @@ pub enum CMLLCError {
     LedgerError(Box<dyn std::error::Error + Send + Sync>),
     #[error("Error in key manager implementation: {0:?}")]
     KeyError(Box<dyn std::error::Error + Send + Sync>),
+    #[error("Key manager doesn't provide a stake key")]
+    NoStakeKey,
     #[error("Unbuilt output does not have sufficient ADA")]
     InsufficientADA,
     #[error("Error while deserializing: {0:?}")]
@@ -29,6 +33,24 @@ pub enum CMLLCError {
     Hex(#[from] hex::FromHexError),
     #[error("Invalid Policy Id: {0:?}")]
     InvalidPolicyId(String),
+    #[error("Mint amount of asset {0:?} overflows")]
+    MintAmountOverflow(String),
+    #[error("Reference inputs aren't allowed in PlutusV1 transactions")]
+    ReferenceInputsInV1,
+    #[error("PlutusV1 scripts can't spend outputs with inline datums")]
+    InlineDatumInV1,
+    #[error("Ledger can't look up transactions by hash")]
+    TxLookupUnsupported,
+    #[error("Ledger can't look up outputs by asset")]
+    AssetLookupUnsupported,
+    #[error("Protocol parameters: {0:?}")]
+    ProtocolParameters(#[from] ProtocolParametersError),
+    #[error("Ledger responded with HTTP {status}: {message}")]
+    HttpStatus { status: u16, message: String },
+    #[error("Failed to decode datum {datum}: {reason}")]
+    DatumDecode { datum: String, reason: String },
+    #[error("Ledger fixture: {0:?}")]
+    Fixture(#[from] FixtureError),
 }
 
 pub fn as_failed_to_retrieve_by_address(
@@ -1,156 +1,47 @@
 use super::error::*;
-use crate::scripts::MintingPolicy;
+use crate::scripts::{MintingPolicy, ReferenceScript, StakingValidator};
+use crate::transaction::{
+    metadata::{Metadatum, TransactionMetadata},
+    staking::{StakeCredential, StakeWitness},
+    TransactionVersion, TxSummary,
+};
 use crate::{
     ledger_client::{LedgerClientError, LedgerClientResult},
-    output::Output,
+    output::{Output, OutputId},
     scripts::ValidatorCode,
     trireme_ledger_client::cml_client::{
         error::CMLLCError::JsError, plutus_data_interop::PlutusDataInterop, UTxO,
     },
     values::Values,
-    PolicyId,
+    PolicyId, UnbuiltTransaction,
 };
 use cardano_multiplatform_lib::{
-    address::Address as CMLAddress,
+    address::{Address as CMLAddress, StakeCredential as CMLStakeCredential},
     builders::{
         input_builder::{InputBuilderResult, SingleInputBuilder},
         output_builder::TransactionOutputBuilder,
+        tx_builder::TransactionBuilder,
         tx_builder::{ChangeSelectionAlgo, CoinSelectionStrategyCIP2, SignedTxBuilder},
-        tx_builder::{TransactionBuilder, TransactionBuilderConfigBuilder},
         witness_builder::{PartialPlutusWitness, PlutusScriptWitness},
     },
     crypto::ScriptHash,
-    crypto::{PrivateKey, TransactionHash},
+    crypto::{Ed25519KeyHash, PrivateKey, TransactionHash},
     ledger::{
-        alonzo::fees::LinearFee,
         common::hash::hash_transaction,
-        common::value::{Int, Value as CMLValue},
+        common::value::{BigNum, Int, Value as CMLValue},
         shelley::witness::make_vkey_witness,
     },
+    metadata::{
+        AuxiliaryData, GeneralTransactionMetadata, MetadataList, MetadataMap, TransactionMetadatum,
+    },
     plutus::PlutusV2Script,
-    plutus::{CostModel, Costmdls, ExUnitPrices, Language},
     plutus::{PlutusScript, PlutusV1Script},
-    AssetName, Assets, MultiAsset, Transaction as CMLTransaction, TransactionInput,
-    TransactionOutput, UnitInterval,
+    AssetName, Assets, MintAssets, MultiAsset, Script, ScriptRef, Transaction as CMLTransaction,
+    TransactionInput, TransactionOutput,
 };
 use pallas_addresses::Address;
 use std::collections::BTreeMap;
 
-// TODO: I think some of these values might be dynamic, in which case we should query them
-//   rather than hard-coding them
-pub fn vasil_v1_tx_builder() -> LedgerClientResult<TransactionBuilder> {
-    let coefficient = 44.into();
-    let constant = 155381.into();
-    let linear_fee = LinearFee::new(&coefficient, &constant);
-
-    let pool_deposit = 500000000.into();
-    let key_deposit = 2000000.into();
-
-    let coins_per_utxo_byte = 4310.into();
-    let mem_num = 577.into();
-    let mem_den = 10000.into();
-    let mem_price = UnitInterval::new(&mem_num, &mem_den);
-    let step_num = 721.into();
-    let step_den = 10000000.into();
-    let step_price = UnitInterval::new(&step_num, &step_den);
-    let ex_unit_prices = ExUnitPrices::new(&mem_price, &step_price);
-    let vasil_v1_cost_models = vec![
-        205665, 812, 1, 1, 1000, 571, 0, 1, 1000, 24177, 4, 1, 1000, 32, 117366, 10475, 4, 23000,
-        100, 23000, 100, 23000, 100, 23000, 100, 23000, 100, 23000, 100, 100, 100, 23000, 100,
-        19537, 32, 175354, 32, 46417, 4, 221973, 511, 0, 1, 89141, 32, 497525, 14068, 4, 2, 196500,
-        453240, 220, 0, 1, 1, 1000, 28662, 4, 2, 245000, 216773, 62, 1, 1060367, 12586, 1, 208512,
-        421, 1, 187000, 1000, 52998, 1, 80436, 32, 43249, 32, 1000, 32, 80556, 1, 57667, 4, 1000,
-        10, 197145, 156, 1, 197145, 156, 1, 204924, 473, 1, 208896, 511, 1, 52467, 32, 64832, 32,
-        65493, 32, 22558, 32, 16563, 32, 76511, 32, 196500, 453240, 220, 0, 1, 1, 69522, 11687, 0,
-        1, 60091, 32, 196500, 453240, 220, 0, 1, 1, 196500, 453240, 220, 0, 1, 1, 806990, 30482, 4,
-        1927926, 82523, 4, 265318, 0, 4, 0, 85931, 32, 205665, 812, 1, 1, 41182, 32, 212342, 32,
-        31220, 32, 32696, 32, 43357, 32, 32247, 32, 38314, 32, 9462713, 1021, 10,
-    ];
-    let cm = CostModel::new(
-        &Language::new_plutus_v1(),
-        &vasil_v1_cost_models.iter().map(|&i| Int::from(i)).collect(),
-    );
-    let mut cost_models = Costmdls::new();
-    cost_models.insert(&cm);
-
-    let tx_builder_cfg = TransactionBuilderConfigBuilder::new()
-        .fee_algo(&linear_fee)
-        .pool_deposit(&pool_deposit)
-        .key_deposit(&key_deposit)
-        .max_value_size(5000)
-        .max_tx_size(16384)
-        .coins_per_utxo_byte(&coins_per_utxo_byte)
-        .ex_unit_prices(&ex_unit_prices)
-        .collateral_percentage(150)
-        .max_collateral_inputs(3)
-        .costmdls(&cost_models)
-        .build()
-        .map_err(|e| CMLLCError::JsError(e.to_string()))
-        .map_err(as_failed_to_issue_tx)?;
-    Ok(TransactionBuilder::new(&tx_builder_cfg))
-}
-
-// TODO: I think some of these values might be dynamic, in which case we should query them
-//   rather than hard-coding them
-pub fn vasil_v2_tx_builder() -> LedgerClientResult<TransactionBuilder> {
-    let coefficient = 44.into();
-    let constant = 155381.into();
-    let linear_fee = LinearFee::new(&coefficient, &constant);
-
-    let pool_deposit = 500000000.into();
-    let key_deposit = 2000000.into();
-
-    let coins_per_utxo_byte = 4310.into();
-    let mem_num = 577.into();
-    let mem_den = 10000.into();
-    let mem_price = UnitInterval::new(&mem_num, &mem_den);
-    let step_num = 721.into();
-    let step_den = 10000000.into();
-    let step_price = UnitInterval::new(&step_num, &step_den);
-    let ex_unit_prices = ExUnitPrices::new(&mem_price, &step_price);
-    let vasil_v2_cost_models: Vec<i64> = vec![
-        205665, 812, 1, 1, 1000, 571, 0, 1, 1000, 24177, 4, 1, 1000, 32, 117366, 10475, 4, 23000,
-        100, 23000, 100, 23000, 100, 23000, 100, 23000, 100, 23000, 100, 100, 100, 23000, 100,
-        19537, 32, 175354, 32, 46417, 4, 221973, 511, 0, 1, 89141, 32, 497525, 14068, 4, 2, 196500,
-        453240, 220, 0, 1, 1, 1000, 28662, 4, 2, 245000, 216773, 62, 1, 1060367, 12586, 1, 208512,
-        421, 1, 187000, 1000, 52998, 1, 80436, 32, 43249, 32, 1000, 32, 80556, 1, 57667, 4, 1000,
-        10, 197145, 156, 1, 197145, 156, 1, 204924, 473, 1, 208896, 511, 1, 52467, 32, 64832, 32,
-        65493, 32, 22558, 32, 16563, 32, 76511, 32, 196500, 453240, 220, 0, 1, 1, 69522, 11687, 0,
-        1, 60091, 32, 196500, 453240, 220, 0, 1, 1, 196500, 453240, 220, 0, 1, 1, 1159724, 392670,
-        0, 2, 806990, 30482, 4, 1927926, 82523, 4, 265318, 0, 4, 0, 85931, 32, 205665, 812, 1, 1,
-        41182, 32, 212342, 32, 31220, 32, 32696, 32, 43357, 32, 32247, 32, 38314, 32, 35892428, 10,
-        57996947, 18975, 10, 38887044, 32947, 10,
-    ];
-    let cm = CostModel::new(
-        &Language::new_plutus_v2(),
-        &vasil_v2_cost_models
-            .iter()
-            .map(|&i| Int::from_str(&i.to_string()))
-            .collect::<Result<Vec<_>, _>>()
-            .map_err(|e| {
-                LedgerClientError::ConfigError(format!("Cost models misconfigured: {e:?}"))
-            })?,
-    );
-    let mut cost_models = Costmdls::new();
-    cost_models.insert(&cm);
-
-    let tx_builder_cfg = TransactionBuilderConfigBuilder::new()
-        .fee_algo(&linear_fee)
-        .pool_deposit(&pool_deposit)
-        .key_deposit(&key_deposit)
-        .max_value_size(5000)
-        .max_tx_size(16384)
-        .coins_per_utxo_byte(&coins_per_utxo_byte)
-        .ex_unit_prices(&ex_unit_prices)
-        .collateral_percentage(150)
-        .max_collateral_inputs(3)
-        .costmdls(&cost_models)
-        .build()
-        .map_err(|e| CMLLCError::JsError(e.to_string()))
-        .map_err(as_failed_to_issue_tx)?;
-    Ok(TransactionBuilder::new(&tx_builder_cfg))
-}
-
 pub(crate) fn input_from_utxo(
     my_address: &CMLAddress,
     utxo: &UTxO,
@@ -176,10 +67,8 @@ impl TryFrom<Values> for CMLValue {
     type Error = CMLLCError;
 
     fn try_from(vals: Values) -> Result<Self> {
-        // TODO: This is broken :((( I got an error message complaining about 1120600 and wanting
-        //   1155080. Please look into
-        // let mut ada = 1120600u64; // TODO: This is kinda buried. Maybe ref the CML value
-        let mut ada = 1155080u64; // TODO: This is kinda buried. Maybe ref the CML value
+        // Outputs without lovelace are topped up to the minimum when they are built
+        let mut ada = 0;
         let mut nau_assets: BTreeMap<String, BTreeMap<Option<String>, u64>> = BTreeMap::new();
         for (policy_id, amount) in vals.as_iter() {
             match policy_id {
@@ -234,7 +123,11 @@ pub(crate) fn utxo_to_nau_utxo<Datum: PlutusDataInterop>(
         .to_owned()
         .and_then(|data| Datum::from_plutus_data(&data).ok())
     {
-        Output::new_validator(tx_hash, index, owner.to_owned(), values, datum)
+        let output = Output::new_validator(tx_hash, index, owner.to_owned(), values, datum);
+        match utxo.datum_encoding() {
+            Some(datum_encoding) => output.with_datum_encoding(datum_encoding),
+            None => output,
+        }
     } else {
         Output::new_wallet(tx_hash, index, owner.to_owned(), values)
     };
@@ -366,13 +259,15 @@ pub(crate) async fn build_tx_for_signing(
 
 pub(crate) async fn sign_tx(
     signed_tx_builder: &mut SignedTxBuilder,
-    priv_key: &PrivateKey,
+    priv_keys: &[PrivateKey],
 ) -> LedgerClientResult<CMLTransaction> {
     let unchecked_tx = signed_tx_builder.build_unchecked();
     let tx_body = unchecked_tx.body();
     let tx_hash = hash_transaction(&tx_body);
-    let vkey_witness = make_vkey_witness(&tx_hash, priv_key);
-    signed_tx_builder.add_vkey(&vkey_witness);
+    for priv_key in priv_keys {
+        let vkey_witness = make_vkey_witness(&tx_hash, priv_key);
+        signed_tx_builder.add_vkey(&vkey_witness);
+    }
     let tx = signed_tx_builder
         .build_checked()
         .map_err(|e| CMLLCError::JsError(e.to_string()))
@@ -380,6 +275,40 @@ pub(crate) async fn sign_tx(
     Ok(tx)
 }
 
+pub(crate) fn tx_summary(tx: &CMLTransaction) -> LedgerClientResult<TxSummary> {
+    let body = tx.body();
+    let tx_id = hash_transaction(&body).to_string();
+
+    let cml_inputs = body.inputs();
+    let mut inputs = Vec::new();
+    for i in 0..cml_inputs.len() {
+        let input = cml_inputs.get(i);
+        let tx_hash = input.transaction_id().to_bytes();
+        inputs.push(OutputId::new(tx_hash, input.index().into()));
+    }
+
+    let cml_outputs = body.outputs();
+    let mut outputs = Vec::new();
+    for i in 0..cml_outputs.len() {
+        let output = cml_outputs.get(i);
+        let owner = output
+            .address()
+            .to_bech32(None)
+            .map_err(|e| CMLLCError::JsError(e.to_string()))
+            .map_err(as_failed_to_issue_tx)?;
+        let values = as_nau_values(&output.amount())?;
+        outputs.push((owner, values));
+    }
+
+    let summary = TxSummary {
+        tx_id,
+        inputs,
+        outputs,
+        fee: body.fee().into(),
+    };
+    Ok(summary)
+}
+
 pub(crate) async fn input_tx_hash<Datum>(
     input: &Output<Datum>,
 ) -> LedgerClientResult<TransactionHash> {
@@ -437,10 +366,186 @@ pub(crate) async fn cml_v2_script_from_nau_policy<Redeemer>(
     Ok(cml_script)
 }
 
+pub(crate) async fn cml_script_from_nau_staking_validator<Redeemer>(
+    version: &TransactionVersion,
+    validator: &(dyn StakingValidator<Redeemer> + '_),
+) -> LedgerClientResult<PlutusScript> {
+    let script_hex = validator.script_hex().map_err(as_failed_to_issue_tx)?;
+    let script_bytes = hex::decode(script_hex).map_err(as_failed_to_issue_tx)?;
+    let cml_script = match version {
+        TransactionVersion::V1 => {
+            let v1 = PlutusV1Script::from_bytes(script_bytes)
+                .map_err(|e| CMLLCError::Deserialize(e.to_string()))
+                .map_err(as_failed_to_issue_tx)?;
+            PlutusScript::from_v1(&v1)
+        }
+        TransactionVersion::V2 => {
+            let v2 = PlutusV2Script::from_bytes(script_bytes)
+                .map_err(|e| CMLLCError::Deserialize(e.to_string()))
+                .map_err(as_failed_to_issue_tx)?;
+            PlutusScript::from_v2(&v2)
+        }
+    };
+    Ok(cml_script)
+}
+
+pub(crate) fn cml_stake_credential(
+    credential: &StakeCredential,
+) -> LedgerClientResult<CMLStakeCredential> {
+    let cml_credential = match credential {
+        StakeCredential::Key(pkh) => {
+            let key_hash = Ed25519KeyHash::from_bytes(pkh.bytes())
+                .map_err(|e| CMLLCError::Deserialize(e.to_string()))
+                .map_err(as_failed_to_issue_tx)?;
+            CMLStakeCredential::from_keyhash(&key_hash)
+        }
+        StakeCredential::Script(hash) => {
+            let script_hash = ScriptHash::from_bytes(hash.clone())
+                .map_err(|e| CMLLCError::Deserialize(e.to_string()))
+                .map_err(as_failed_to_issue_tx)?;
+            CMLStakeCredential::from_scripthash(&script_hash)
+        }
+    };
+    Ok(cml_credential)
+}
+
+/// The stake key has to sign if any certificate or withdrawal is witnessed by a key
+pub(crate) fn needs_stake_key_signature<Datum, Redeemer>(
+    tx: &UnbuiltTransaction<Datum, Redeemer>,
+) -> bool {
+    let is_key = |witness: &StakeWitness<Redeemer>| matches!(witness, StakeWitness::Key(_));
+    tx.certificates()
+        .iter()
+        .filter_map(|certificate| certificate.witness())
+        .any(is_key)
+        || tx
+            .withdrawals()
+            .iter()
+            .any(|withdrawal| is_key(&withdrawal.witness))
+}
+
 pub(crate) async fn partial_script_witness<Redeemer: PlutusDataInterop>(
     cml_script: &PlutusScript,
     redeemer: &Redeemer,
+    reference_script_hashes: &[Vec<u8>],
 ) -> PartialPlutusWitness {
-    let script_witness = PlutusScriptWitness::from_script(cml_script.clone());
+    let script_witness = plutus_script_witness(cml_script, reference_script_hashes);
     PartialPlutusWitness::new(&script_witness, &redeemer.to_plutus_data())
 }
+
+/// Point at the script by hash if one of the reference inputs carries it, otherwise attach it
+pub(crate) fn plutus_script_witness(
+    cml_script: &PlutusScript,
+    reference_script_hashes: &[Vec<u8>],
+) -> PlutusScriptWitness {
+    let script_hash = cml_script.hash();
+    if reference_script_hashes.contains(&script_hash.to_bytes()) {
+        PlutusScriptWitness::from_ref(script_hash)
+    } else {
+        PlutusScriptWitness::from_script(cml_script.clone())
+    }
+}
+
+pub(crate) fn reference_script_hashes<Datum, Redeemer>(
+    tx: &UnbuiltTransaction<Datum, Redeemer>,
+) -> LedgerClientResult<Vec<Vec<u8>>> {
+    tx.reference_inputs()
+        .iter()
+        .filter_map(|output| output.reference_script())
+        .map(|script| script.hash().map_err(as_failed_to_issue_tx))
+        .collect()
+}
+
+pub(crate) fn cml_script_ref(reference_script: &ReferenceScript) -> LedgerClientResult<ScriptRef> {
+    let script_bytes = hex::decode(reference_script.script_hex()).map_err(as_failed_to_issue_tx)?;
+    let script = match reference_script.version() {
+        TransactionVersion::V1 => {
+            let v1 = PlutusV1Script::from_bytes(script_bytes)
+                .map_err(|e| CMLLCError::Deserialize(e.to_string()))
+                .map_err(as_failed_to_issue_tx)?;
+            Script::new_plutus_v1(&v1)
+        }
+        TransactionVersion::V2 => {
+            let v2 = PlutusV2Script::from_bytes(script_bytes)
+                .map_err(|e| CMLLCError::Deserialize(e.to_string()))
+                .map_err(as_failed_to_issue_tx)?;
+            Script::new_plutus_v2(&v2)
+        }
+    };
+    Ok(ScriptRef::new(&script))
+}
+
+/// Amounts for the same asset name are summed, since `MintAssets` holds one amount per name
+pub(crate) fn cml_mint_assets(assets: &[(Option<String>, i64)]) -> LedgerClientResult<MintAssets> {
+    let mut totals: BTreeMap<Vec<u8>, i64> = BTreeMap::new();
+    for (asset_name, amount) in assets {
+        let name = asset_name.clone().unwrap_or_default().into_bytes();
+        let total = totals.entry(name).or_default();
+        *total = total.checked_add(*amount).ok_or_else(|| {
+            as_failed_to_issue_tx(CMLLCError::MintAmountOverflow(
+                asset_name.clone().unwrap_or_default(),
+            ))
+        })?;
+    }
+    let mut mint_assets = MintAssets::new();
+    for (name, amount) in totals {
+        let key = AssetName::new(name)
+            .map_err(|e| CMLLCError::JsError(e.to_string()))
+            .map_err(as_failed_to_issue_tx)?;
+        let big_num = BigNum::from(amount.unsigned_abs());
+        let value = if amount < 0 {
+            Int::new_negative(&big_num)
+        } else {
+            Int::new(&big_num)
+        };
+        mint_assets.insert(&key, value);
+    }
+    Ok(mint_assets)
+}
+
+pub(crate) fn cml_auxiliary_data(
+    metadata: &TransactionMetadata,
+) -> LedgerClientResult<AuxiliaryData> {
+    let mut general_metadata = GeneralTransactionMetadata::new();
+    for (label, value) in metadata.iter() {
+        general_metadata.insert(&BigNum::from(*label), &cml_metadatum(value)?);
+    }
+    let mut auxiliary_data = AuxiliaryData::new();
+    auxiliary_data.set_metadata(&general_metadata);
+    Ok(auxiliary_data)
+}
+
+fn cml_metadatum(metadatum: &Metadatum) -> LedgerClientResult<TransactionMetadatum> {
+    let cml_metadatum = match metadatum {
+        Metadatum::Map(entries) => {
+            let mut map = MetadataMap::new();
+            for (key, value) in entries {
+                map.insert(&cml_metadatum(key)?, &cml_metadatum(value)?);
+            }
+            TransactionMetadatum::new_map(&map)
+        }
+        Metadatum::List(items) => {
+            let mut list = MetadataList::new();
+            for item in items {
+                list.add(&cml_metadatum(item)?);
+            }
+            TransactionMetadatum::new_list(&list)
+        }
+        Metadatum::Int(int) => {
+            let big_num = BigNum::from(int.unsigned_abs());
+            let int = if *int < 0 {
+                Int::new_negative(&big_num)
+            } else {
+                Int::new(&big_num)
+            };
+            TransactionMetadatum::new_int(&int)
+        }
+        Metadatum::Bytes(bytes) => TransactionMetadatum::new_bytes(bytes.clone())
+            .map_err(|e| CMLLCError::JsError(e.to_string()))
+            .map_err(as_failed_to_issue_tx)?,
+        Metadatum::Text(text) => TransactionMetadatum::new_text(text.clone())
+            .map_err(|e| CMLLCError::JsError(e.to_string()))
+            .map_err(as_failed_to_issue_tx)?,
+    };
+    Ok(cml_metadatum)
+}
@@ -0,0 +1,257 @@
+use crate::output::DatumEncoding;
+use crate::trireme_ledger_client::cml_client::{
+    error::{CMLLCError, Result},
+    network_settings::NetworkSettings,
+    ogmios_scrolls_ledger::{
+        check_for_error, cml_value_from_ogmios_value, parse_evaluation_results, query_ogmios,
+        OgmiosProtocolParameters, OgmiosValue,
+    },
+    protocol_parameters::ProtocolParameters,
+    ExecutionCost, Ledger, UTxO,
+};
+use async_trait::async_trait;
+use cardano_multiplatform_lib::{
+    address::Address as CMLAddress, crypto::TransactionHash, plutus::PlutusData,
+    Transaction as CMLTransaction,
+};
+use ogmios_client::{OgmiosClient, OgmiosLocalTxSubmission};
+use pallas_addresses::Address;
+use serde::{de::DeserializeOwned, Deserialize};
+use std::collections::{BTreeSet, HashMap};
+
+#[cfg(test)]
+mod tests;
+
+/// Reads the chain from a [Kupo](https://cardanosolutions.github.io/kupo) index, and evaluates
+/// and submits txs through Ogmios
+pub struct KupoOgmiosLedger {
+    kupo_url: String,
+    /// Shared across Kupo requests so connections are pooled
+    http: reqwest::Client,
+    ogmios_client: OgmiosClient,
+    ogmios_ws_url: String,
+    network_settings: NetworkSettings,
+}
+
+impl KupoOgmiosLedger {
+    /// `kupo_url` is Kupo's HTTP root, e.g. `http://localhost:1442`, and `ogmios_ws_url` the
+    /// Ogmios websocket, e.g. `ws://localhost:1337`
+    pub fn new(
+        kupo_url: &str,
+        ogmios_client: OgmiosClient,
+        ogmios_ws_url: &str,
+        network_settings: NetworkSettings,
+    ) -> Self {
+        KupoOgmiosLedger {
+            kupo_url: kupo_url.trim_end_matches('/').to_string(),
+            http: reqwest::Client::new(),
+            ogmios_client,
+            ogmios_ws_url: ogmios_ws_url.to_string(),
+            network_settings,
+        }
+    }
+
+    async fn get_kupo<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+        let url = format!("{}/{}", self.kupo_url, path);
+        let res = self
+            .http
+            .get(url)
+            .header("Accept", "application/json")
+            .send()
+            .await
+            .map_err(|e| CMLLCError::LedgerError(Box::new(e)))?;
+        let status = res.status();
+        if !status.is_success() {
+            let message = res.text().await.unwrap_or_default();
+            return Err(CMLLCError::HttpStatus {
+                status: status.as_u16(),
+                message,
+            });
+        }
+        res.json()
+            .await
+            .map_err(|e| CMLLCError::LedgerError(Box::new(e)))
+    }
+
+    async fn matches(&self, pattern: &str, unspent: bool) -> Result<Vec<KupoMatch>> {
+        let path = if unspent {
+            format!("matches/{pattern}?unspent")
+        } else {
+            format!("matches/{pattern}")
+        };
+        self.get_kupo(&path).await
+    }
+
+    /// Kupo indexes inline datums by hash too, so both kinds are looked up the same way
+    async fn datum(&self, datum_hash: &str) -> Result<Option<PlutusData>> {
+        let datum: Option<KupoDatum> = self.get_kupo(&format!("datums/{datum_hash}")).await?;
+        datum
+            .map(|datum| {
+                PlutusData::from_bytes(hex::decode(datum.datum)?)
+                    .map_err(|e| CMLLCError::Deserialize(e.to_string()))
+            })
+            .transpose()
+    }
+
+    /// Script with `script_hash`, if Kupo has seen it in a tx
+    pub async fn script(&self, script_hash: &str) -> Result<Option<KupoScript>> {
+        self.get_kupo(&format!("scripts/{script_hash}")).await
+    }
+
+    async fn utxo_from_match(&self, kupo_match: &KupoMatch) -> Result<UTxO> {
+        let tx_hash = TransactionHash::from_hex(&kupo_match.transaction_id)
+            .map_err(|e| CMLLCError::JsError(e.to_string()))?;
+        let amount = cml_value_from_ogmios_value(&kupo_match.value)?;
+        let datum = if let Some(datum_hash) = &kupo_match.datum_hash {
+            self.datum(datum_hash).await?
+        } else {
+            None
+        };
+        let datum_encoding = match kupo_match.datum_type.as_deref() {
+            Some("inline") => DatumEncoding::Inline,
+            _ => DatumEncoding::Hash,
+        };
+        let utxo = UTxO::new(tx_hash, kupo_match.output_index.into(), amount, datum)
+            .with_datum_encoding(datum_encoding);
+        if let Some(script_hash) = &kupo_match.script_hash {
+            Ok(utxo.with_reference_script_hash(hex::decode(script_hash)?))
+        } else {
+            Ok(utxo)
+        }
+    }
+
+    async fn utxos_at(&self, addr: &CMLAddress) -> Result<Vec<UTxO>> {
+        let address = addr
+            .to_bech32(None)
+            .map_err(|e| CMLLCError::JsError(e.to_string()))?;
+        let mut utxos = Vec::new();
+        for kupo_match in self.matches(&address, true).await? {
+            utxos.push(self.utxo_from_match(&kupo_match).await?);
+        }
+        Ok(utxos)
+    }
+}
+
+#[async_trait]
+impl Ledger for KupoOgmiosLedger {
+    async fn last_block_time_secs(&self) -> Result<i64> {
+        let health: KupoHealth = self.get_kupo("health").await?;
+        let slot = health
+            .most_recent_checkpoint
+            .ok_or(CMLLCError::LedgerError("Kupo has no checkpoint yet".into()))?;
+        Ok(self.network_settings.posix_from_slot(slot))
+    }
+
+    async fn get_utxos_for_addr(&self, addr: &CMLAddress, count: usize) -> Result<Vec<UTxO>> {
+        let utxos = self.utxos_at(addr).await?.into_iter().take(count).collect();
+        Ok(utxos)
+    }
+
+    async fn get_all_utxos_for_addr(&self, addr: &CMLAddress) -> Result<Vec<UTxO>> {
+        self.utxos_at(addr).await
+    }
+
+    async fn calculate_ex_units(&self, tx: &CMLTransaction) -> Result<HashMap<u64, ExecutionCost>> {
+        let bytes = tx.to_bytes();
+        let res = self.ogmios_client.evaluate_tx(&bytes, vec![]).await?;
+        check_for_error(&res)?;
+        parse_evaluation_results(&res)
+    }
+
+    /// Kupo only knows which slot a tx landed in, not block heights, so the depth of a tx can't
+    /// be measured
+    async fn tx_confirmations(&self, _tx_id: &str) -> Result<Option<u64>> {
+        Err(CMLLCError::TxLookupUnsupported)
+    }
+
+    async fn utxo_by_id(&self, tx_hash: &str, index: u64) -> Result<Option<(Address, UTxO)>> {
+        let kupo_match = self
+            .matches(&format!("{index}@{tx_hash}"), true)
+            .await?
+            .into_iter()
+            .next();
+        if let Some(kupo_match) = kupo_match {
+            let address = Address::from_bech32(&kupo_match.address)?;
+            let utxo = self.utxo_from_match(&kupo_match).await?;
+            Ok(Some((address, utxo)))
+        } else {
+            Ok(None)
+        }
+    }
+
+    async fn addresses_with_asset(
+        &self,
+        policy_id: &str,
+        asset_name: Option<&str>,
+    ) -> Result<Vec<Address>> {
+        let pattern = format!("{policy_id}.{}", asset_name.unwrap_or("*"));
+        let addresses: BTreeSet<String> = self
+            .matches(&pattern, true)
+            .await?
+            .into_iter()
+            .map(|kupo_match| kupo_match.address)
+            .collect();
+        addresses
+            .iter()
+            .map(|address| Address::from_bech32(address).map_err(CMLLCError::from))
+            .collect()
+    }
+
+    async fn protocol_parameters(&self) -> Result<ProtocolParameters> {
+        let result = query_ogmios(
+            &self.ogmios_ws_url,
+            serde_json::json!("currentProtocolParameters"),
+        )
+        .await?;
+        let params: OgmiosProtocolParameters =
+            serde_json::from_value(result).map_err(|e| CMLLCError::Deserialize(e.to_string()))?;
+        params.try_into()
+    }
+
+    async fn submit_transaction(&self, tx: &CMLTransaction) -> Result<String> {
+        let bytes = tx.to_bytes();
+        let res = self.ogmios_client.submit_tx(&bytes).await?;
+        let tx_hash = res
+            .result()
+            .ok_or(CMLLCError::OgmiosResponse(
+                "No transaction hash in response".to_string(),
+            ))?
+            .tx_id()
+            .to_string();
+        Ok(tx_hash)
+    }
+}
+
+#[derive(Deserialize)]
+struct KupoMatch {
+    transaction_id: String,
+    output_index: u64,
+    address: String,
+    value: OgmiosValue,
+    datum_hash: Option<String>,
+    /// `hash` or `inline`
+    #[serde(default)]
+    datum_type: Option<String>,
+    /// Hash of the reference script the output carries
+    #[serde(default)]
+    script_hash: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct KupoDatum {
+    /// CBOR hex
+    datum: String,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Eq)]
+pub struct KupoScript {
+    /// e.g. `plutus:v2`
+    pub language: String,
+    /// CBOR hex
+    pub script: String,
+}
+
+#[derive(Deserialize)]
+struct KupoHealth {
+    most_recent_checkpoint: Option<u64>,
+}
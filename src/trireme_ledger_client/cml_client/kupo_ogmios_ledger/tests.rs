@@ -0,0 +1,143 @@
+#![allow(non_snake_case)]
+
+use super::*;
+use crate::trireme_ledger_client::{
+    cml_client::test_utils::{alice, serve, ALICE, BOB, DATUM_HASH, TX_HASH},
+    Network,
+};
+
+const POLICY: &str = "57fca08abbaddee36da742a839f7d83a7e1d2419f1507fcbf3916522";
+
+fn kupo_match(address: &str, index: u64, value: &str, datum_hash: Option<&str>) -> String {
+    let datum_hash = datum_hash
+        .map(|hash| format!("\"{hash}\""))
+        .unwrap_or_else(|| "null".to_string());
+    format!(
+        r#"{{"transaction_index":0,"transaction_id":"{TX_HASH}","output_index":{index},"address":"{address}","value":{value},"datum_hash":{datum_hash},"script_hash":null,"created_at":{{"slot_no":1000,"header_hash":"00"}},"spent_at":null}}"#
+    )
+}
+
+fn ledger(kupo_url: &str) -> KupoOgmiosLedger {
+    let ogmios_client = OgmiosClient::new("127.0.0.1".to_string(), "1337".to_string());
+    KupoOgmiosLedger::new(
+        kupo_url,
+        ogmios_client,
+        "ws://127.0.0.1:1337",
+        Network::Preprod.try_into().unwrap(),
+    )
+}
+
+#[tokio::test]
+async fn get_all_utxos_for_addr__reads_unspent_matches_and_their_datums() {
+    // given
+    let token_value = format!(r#"{{"coins":2000000,"assets":{{"{POLICY}.54455354":1}}}}"#);
+    let matches = format!(
+        "[{},{}]",
+        kupo_match(ALICE, 0, r#"{"coins":5000000}"#, Some(DATUM_HASH)),
+        kupo_match(ALICE, 1, &token_value, None),
+    );
+    let url = serve(vec![
+        (format!("matches/{ALICE}?unspent"), matches),
+        (
+            format!("datums/{DATUM_HASH}"),
+            r#"{"datum":"d87980"}"#.to_string(),
+        ),
+    ])
+    .await;
+    let ledger = ledger(&url);
+
+    // when
+    let utxos = ledger.get_all_utxos_for_addr(&alice()).await.unwrap();
+
+    // then
+    assert_eq!(utxos.len(), 2);
+    assert_eq!(u64::from(utxos[0].amount().coin()), 5_000_000);
+    assert_eq!(
+        utxos[0].datum().as_ref().map(|datum| datum.to_bytes()),
+        Some(hex::decode("d87980").unwrap())
+    );
+    assert!(utxos[1].datum().is_none());
+    assert!(utxos[1].amount().multiasset().is_some());
+}
+
+#[tokio::test]
+async fn utxo_by_id__finds_owner_or_nothing() {
+    // given
+    let url = serve(vec![
+        (
+            format!("matches/1@{TX_HASH}?unspent"),
+            format!("[{}]", kupo_match(BOB, 1, r#"{"coins":3000000}"#, None)),
+        ),
+        (format!("matches/2@{TX_HASH}?unspent"), "[]".to_string()),
+    ])
+    .await;
+    let ledger = ledger(&url);
+
+    // when
+    let found = ledger.utxo_by_id(TX_HASH, 1).await.unwrap();
+    let missing = ledger.utxo_by_id(TX_HASH, 2).await.unwrap();
+
+    // then
+    let (owner, utxo) = found.unwrap();
+    assert_eq!(owner, Address::from_bech32(BOB).unwrap());
+    assert_eq!(u64::from(utxo.output_index()), 1);
+    assert!(missing.is_none());
+}
+
+#[tokio::test]
+async fn addresses_with_asset__collects_each_holder_once() {
+    // given
+    let value = format!(r#"{{"coins":2000000,"assets":{{"{POLICY}.54455354":1}}}}"#);
+    let matches = format!(
+        "[{},{},{}]",
+        kupo_match(ALICE, 0, &value, None),
+        kupo_match(BOB, 1, &value, None),
+        kupo_match(ALICE, 2, &value, None),
+    );
+    let url = serve(vec![(format!("matches/{POLICY}.*?unspent"), matches)]).await;
+    let ledger = ledger(&url);
+
+    // when
+    let addresses = ledger.addresses_with_asset(POLICY, None).await.unwrap();
+
+    // then
+    assert_eq!(addresses.len(), 2);
+    assert!(addresses.contains(&Address::from_bech32(ALICE).unwrap()));
+    assert!(addresses.contains(&Address::from_bech32(BOB).unwrap()));
+}
+
+#[tokio::test]
+async fn tx_confirmations__reports_depth_as_unsupported() {
+    // given
+    let url = serve(vec![(
+        format!("matches/*@{TX_HASH}"),
+        format!("[{}]", kupo_match(BOB, 0, r#"{"coins":3000000}"#, None)),
+    )])
+    .await;
+    let ledger = ledger(&url);
+
+    // when
+    let confirmations = ledger.tx_confirmations(TX_HASH).await;
+
+    // then
+    assert!(matches!(
+        confirmations,
+        Err(CMLLCError::TxLookupUnsupported)
+    ));
+}
+
+#[tokio::test]
+async fn last_block_time_secs__errors_on_kupo_failure() {
+    // given
+    let url = serve(vec![]).await;
+    let ledger = ledger(&url);
+
+    // when
+    let res = ledger.last_block_time_secs().await;
+
+    // then
+    assert!(matches!(
+        res,
+        Err(CMLLCError::HttpStatus { status: 404, .. })
+    ));
+}
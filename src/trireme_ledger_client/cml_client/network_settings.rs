@@ -1,11 +1,53 @@
 use crate::trireme_ledger_client::Network;
+use serde::Deserialize;
+use std::path::Path;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum NetworkSettingsError {
+    #[error("Failed to read genesis file: {0:?}")]
+    File(#[from] std::io::Error),
+    #[error("Failed to deserialize genesis file: {0:?}")]
+    Serde(#[from] serde_json::Error),
+    #[error("Invalid genesis: {0:?}")]
+    InvalidGenesis(String),
+}
+
+pub type Result<T, E = NetworkSettingsError> = std::result::Result<T, E>;
+
+/// Byron epochs are always `10 * k` slots long, which is `21600` for all public networks
+const BYRON_EPOCH_LENGTH_PER_K: u64 = 10;
+/// `3k/f` slots, for `k = 2160` and `f = 0.05` as on mainnet and preprod
+const DEFAULT_STABILITY_WINDOW: u64 = 129600;
+
+/// Slot timing of a single era, which lasts until the next era starts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct EraSettings {
+    start_slot: u64,
+    start_time_ms: i64,
+    slot_length_ms: i64,
+}
+
+impl EraSettings {
+    fn slot_from_posix_ms(&self, posix_ms: i64) -> u64 {
+        let elapsed = posix_ms - self.start_time_ms;
+        self.start_slot + (elapsed / self.slot_length_ms) as u64
+    }
 
-#[derive(Debug, Clone, Copy)]
+    fn posix_ms_from_slot(&self, slot: u64) -> i64 {
+        let elapsed = slot as i64 - self.start_slot as i64;
+        self.start_time_ms + elapsed * self.slot_length_ms
+    }
+}
+
+/// Slot timing of a network. Slots before the Shelley era are converted with the Byron era's
+/// slot length, if the network had one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct NetworkSettings {
     network: u8,
-    slot_length: i64,
-    starting_slot_time: i64,
-    starting_slot_number: u64,
+    byron: Option<EraSettings>,
+    shelley: EraSettings,
+    stability_window: u64,
 }
 
 impl NetworkSettings {
@@ -17,78 +59,257 @@ impl NetworkSettings {
     ) -> Self {
         NetworkSettings {
             network,
-            slot_length,
-            starting_slot_time,
-            starting_slot_number,
+            byron: None,
+            shelley: EraSettings {
+                start_slot: starting_slot_number,
+                start_time_ms: starting_slot_time * 1000,
+                slot_length_ms: slot_length * 1000,
+            },
+            stability_window: DEFAULT_STABILITY_WINDOW,
         }
     }
 
+    /// Sets how many slots past the tip slots can be converted to and from POSIX time reliably,
+    /// since a hard fork can't change the slot length any sooner
+    pub fn with_stability_window(mut self, stability_window: u64) -> Self {
+        self.stability_window = stability_window;
+        self
+    }
+
+    /// Adds a Byron era starting at slot `0` and `system_start`, lasting until the Shelley era
+    pub fn with_byron_era(mut self, system_start: i64, slot_length_ms: i64) -> Self {
+        self.byron = Some(EraSettings {
+            start_slot: 0,
+            start_time_ms: system_start * 1000,
+            slot_length_ms,
+        });
+        self
+    }
+
+    /// Loads the settings of a network from its genesis files. `shelley_start_epoch` is the epoch
+    /// the network hard forked into Shelley at, which is `0` for most devnets.
+    pub fn from_genesis_files(
+        byron_genesis_file: &Path,
+        shelley_genesis_file: &Path,
+        shelley_start_epoch: u64,
+    ) -> Result<Self> {
+        let byron = serde_json::from_str(&std::fs::read_to_string(byron_genesis_file)?)?;
+        let shelley = serde_json::from_str(&std::fs::read_to_string(shelley_genesis_file)?)?;
+        NetworkSettings::from_genesis(byron, shelley, shelley_start_epoch)
+    }
+
+    fn from_genesis(
+        byron: ByronGenesis,
+        shelley: ShelleyGenesis,
+        shelley_start_epoch: u64,
+    ) -> Result<Self> {
+        let byron_slot_length_ms: i64 = byron
+            .block_version_data
+            .slot_duration
+            .parse()
+            .ok()
+            .filter(|length| *length > 0)
+            .ok_or_else(|| {
+                NetworkSettingsError::InvalidGenesis(format!(
+                    "Byron slot duration: {}",
+                    byron.block_version_data.slot_duration
+                ))
+            })?;
+        let shelley_slot_length_ms = (shelley.slot_length * 1000.0).round() as i64;
+        if shelley_slot_length_ms <= 0 {
+            return Err(NetworkSettingsError::InvalidGenesis(format!(
+                "Shelley slot length: {}",
+                shelley.slot_length
+            )));
+        }
+        if shelley.active_slots_coeff <= 0.0 || shelley.active_slots_coeff > 1.0 {
+            return Err(NetworkSettingsError::InvalidGenesis(format!(
+                "Active slots coefficient: {}",
+                shelley.active_slots_coeff
+            )));
+        }
+        let stability_window =
+            (3.0 * shelley.security_param as f64 / shelley.active_slots_coeff).ceil() as u64;
+        let network = match shelley.network_id.as_str() {
+            "Mainnet" => 1,
+            "Testnet" => 0,
+            other => {
+                return Err(NetworkSettingsError::InvalidGenesis(format!(
+                    "Network id: {other}"
+                )))
+            }
+        };
+        let shelley_start_slot =
+            shelley_start_epoch * BYRON_EPOCH_LENGTH_PER_K * byron.protocol_consts.k;
+        let byron = EraSettings {
+            start_slot: 0,
+            start_time_ms: byron.start_time * 1000,
+            slot_length_ms: byron_slot_length_ms,
+        };
+        let shelley = EraSettings {
+            start_slot: shelley_start_slot,
+            start_time_ms: byron.posix_ms_from_slot(shelley_start_slot),
+            slot_length_ms: shelley_slot_length_ms,
+        };
+        Ok(NetworkSettings {
+            network,
+            byron: (shelley_start_slot > 0).then_some(byron),
+            shelley,
+            stability_window,
+        })
+    }
+
     pub fn network(&self) -> u8 {
         self.network
     }
 
+    /// Shelley era slot length, in seconds
     pub fn slot_length(&self) -> i64 {
-        self.slot_length
+        self.shelley.slot_length_ms / 1000
     }
 
+    /// Shelley era slot length, in milliseconds
+    pub fn slot_length_ms(&self) -> i64 {
+        self.shelley.slot_length_ms
+    }
+
+    /// POSIX time, in seconds, of the first Shelley era slot
     pub fn starting_slot_time(&self) -> i64 {
-        self.starting_slot_time
+        self.shelley.start_time_ms / 1000
     }
 
+    /// First Shelley era slot
     pub fn starting_slot_number(&self) -> u64 {
-        self.starting_slot_number
+        self.shelley.start_slot
+    }
+
+    /// POSIX time, in seconds, of the first slot of the network
+    pub fn system_start(&self) -> i64 {
+        self.byron.unwrap_or(self.shelley).start_time_ms / 1000
+    }
+
+    pub fn stability_window(&self) -> u64 {
+        self.stability_window
+    }
+
+    /// Last slot whose POSIX time is certain while the tip of the chain is at `tip_posix`
+    pub fn horizon_slot(&self, tip_posix: i64) -> Option<u64> {
+        let tip_slot = self.slot_from_posix(tip_posix)?;
+        Some(tip_slot + self.stability_window)
     }
 
     pub fn slot_from_posix(&self, posix: i64) -> Option<u64> {
-        let time_s = posix.checked_sub(self.starting_slot_time())?;
-        let abs_slot = (time_s / self.slot_length()) as u64 + self.starting_slot_number();
-        Some(abs_slot)
+        self.slot_from_posix_ms(posix.checked_mul(1000)?)
+    }
+
+    /// Slot containing the POSIX time `posix_ms`, given in milliseconds
+    pub fn slot_from_posix_ms(&self, posix_ms: i64) -> Option<u64> {
+        if posix_ms >= self.shelley.start_time_ms {
+            Some(self.shelley.slot_from_posix_ms(posix_ms))
+        } else {
+            self.byron
+                .filter(|byron| posix_ms >= byron.start_time_ms)
+                .map(|byron| byron.slot_from_posix_ms(posix_ms))
+        }
     }
 
     pub fn posix_from_slot(&self, slot: u64) -> i64 {
-        let slot = slot;
-        let time_s = (slot - self.starting_slot_number()) as i64 * self.slot_length();
-        time_s + self.starting_slot_time()
+        let era = match self.byron {
+            Some(byron) if slot < self.shelley.start_slot => byron,
+            _ => self.shelley,
+        };
+        era.posix_ms_from_slot(slot) / 1000
     }
 }
 
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ByronGenesis {
+    start_time: i64,
+    protocol_consts: ByronProtocolConsts,
+    block_version_data: ByronBlockVersionData,
+}
+
+#[derive(Deserialize)]
+struct ByronProtocolConsts {
+    k: u64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ByronBlockVersionData {
+    /// Milliseconds, as a string
+    slot_duration: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ShelleyGenesis {
+    network_id: String,
+    /// Seconds, possibly fractional
+    slot_length: f64,
+    security_param: u64,
+    active_slots_coeff: f64,
+}
+
 const MAINNET_NETWORK: u8 = 1;
 const MAINNET_SLOT_LENGTH: i64 = 1;
 const MAINNET_STARTING_SLOT_TIME: i64 = 1596059091;
 const MAINNET_STARTING_SLOT_NUMBER: u64 = 4492800;
+const MAINNET_SYSTEM_START: i64 = 1506203091;
+const MAINNET_BYRON_SLOT_LENGTH_MS: i64 = 20000;
 
 const PRE_PROD_NETWORK: u8 = 0;
 const PRE_PROD_SLOT_LENGTH: i64 = 1;
 const PRE_PROD_STARTING_SLOT_TIME: i64 = 1655769600;
 const PRE_PROD_STARTING_SLOT_NUMBER: u64 = 86400;
+const PRE_PROD_SYSTEM_START: i64 = 1654041600;
+const PRE_PROD_BYRON_SLOT_LENGTH_MS: i64 = 20000;
 
 const PREVIEW_NETWORK: u8 = 0;
 const PREVIEW_SLOT_LENGTH: i64 = 1;
 const PREVIEW_STARTING_SLOT_TIME: i64 = 1666656000;
 const PREVIEW_STARTING_SLOT_NUMBER: u64 = 0;
+/// Preview has `k = 432`
+const PREVIEW_STABILITY_WINDOW: u64 = 25920;
 
-impl From<Network> for NetworkSettings {
-    fn from(network: Network) -> Self {
-        match network {
+impl TryFrom<Network> for NetworkSettings {
+    type Error = NetworkSettingsError;
+
+    fn try_from(network: Network) -> Result<Self> {
+        let settings = match network {
             Network::Preprod => NetworkSettings::new(
                 PRE_PROD_NETWORK,
                 PRE_PROD_SLOT_LENGTH,
                 PRE_PROD_STARTING_SLOT_TIME,
                 PRE_PROD_STARTING_SLOT_NUMBER,
-            ),
+            )
+            .with_byron_era(PRE_PROD_SYSTEM_START, PRE_PROD_BYRON_SLOT_LENGTH_MS),
             Network::Mainnet => NetworkSettings::new(
                 MAINNET_NETWORK,
                 MAINNET_SLOT_LENGTH,
                 MAINNET_STARTING_SLOT_TIME,
                 MAINNET_STARTING_SLOT_NUMBER,
-            ),
+            )
+            .with_byron_era(MAINNET_SYSTEM_START, MAINNET_BYRON_SLOT_LENGTH_MS),
             Network::Preview => NetworkSettings::new(
                 PREVIEW_NETWORK,
                 PREVIEW_SLOT_LENGTH,
                 PREVIEW_STARTING_SLOT_TIME,
                 PREVIEW_STARTING_SLOT_NUMBER,
-            ),
-        }
+            )
+            .with_stability_window(PREVIEW_STABILITY_WINDOW),
+            Network::Custom {
+                byron_genesis_file,
+                shelley_genesis_file,
+                shelley_start_epoch,
+            } => NetworkSettings::from_genesis_files(
+                &byron_genesis_file,
+                &shelley_genesis_file,
+                shelley_start_epoch,
+            )?,
+        };
+        Ok(settings)
     }
 }
 
@@ -97,12 +318,29 @@ mod tests {
     #![allow(non_snake_case)]
 
     use super::*;
+    use std::path::PathBuf;
+
+    const DEVNET_SYSTEM_START: i64 = 1700000000;
+
+    fn fixture(name: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("src/trireme_ledger_client/cml_client/network_settings/fixtures")
+            .join(name)
+    }
+
+    fn devnet(shelley_start_epoch: u64) -> Network {
+        Network::Custom {
+            byron_genesis_file: fixture("byron-genesis.json"),
+            shelley_genesis_file: fixture("shelley-genesis.json"),
+            shelley_start_epoch,
+        }
+    }
 
     #[test]
     fn slot_from_posix__mainnet() {
         // given
         let network = Network::Mainnet;
-        let network_settings = NetworkSettings::from(network);
+        let network_settings = NetworkSettings::try_from(network).unwrap();
 
         // when
         let posix = 1693686614;
@@ -117,7 +355,7 @@ mod tests {
     fn slot_from_posix__preprod() {
         // given
         let network = Network::Preprod;
-        let network_settings = NetworkSettings::from(network);
+        let network_settings = NetworkSettings::try_from(network).unwrap();
 
         // when
         let posix = 1693686777;
@@ -132,7 +370,7 @@ mod tests {
     fn slot_from_posix__preview() {
         // given
         let network = Network::Preview;
-        let network_settings = NetworkSettings::from(network);
+        let network_settings = NetworkSettings::try_from(network).unwrap();
 
         // when
         let posix = 1693686823;
@@ -147,7 +385,7 @@ mod tests {
     fn posix_from_slot__mainnet() {
         // given
         let network = Network::Mainnet;
-        let network_settings = NetworkSettings::from(network);
+        let network_settings = NetworkSettings::try_from(network).unwrap();
 
         // when
         let posix = 102120323;
@@ -162,7 +400,7 @@ mod tests {
     fn posix_from_slot__preprod() {
         // given
         let network = Network::Preprod;
-        let network_settings = NetworkSettings::from(network);
+        let network_settings = NetworkSettings::try_from(network).unwrap();
 
         // when
         let posix = 38003577;
@@ -177,7 +415,7 @@ mod tests {
     fn posix_from_slot__preview() {
         // given
         let network = Network::Preview;
-        let network_settings = NetworkSettings::from(network);
+        let network_settings = NetworkSettings::try_from(network).unwrap();
 
         // when
         let posix = 27030823;
@@ -187,4 +425,155 @@ mod tests {
         let actual = network_settings.posix_from_slot(posix);
         assert_eq!(expected, actual);
     }
+
+    #[test]
+    fn slot_from_posix__mainnet_byron_era() {
+        // given
+        let network_settings = NetworkSettings::try_from(Network::Mainnet).unwrap();
+
+        // then
+        assert_eq!(network_settings.slot_from_posix(1506203091), Some(0));
+        assert_eq!(network_settings.slot_from_posix(1506203131), Some(2));
+        assert_eq!(network_settings.slot_from_posix(1596059071), Some(4492799));
+        assert_eq!(network_settings.slot_from_posix(1506203090), None);
+    }
+
+    #[test]
+    fn posix_from_slot__mainnet_byron_era() {
+        // given
+        let network_settings = NetworkSettings::try_from(Network::Mainnet).unwrap();
+
+        // then
+        assert_eq!(network_settings.posix_from_slot(0), 1506203091);
+        assert_eq!(network_settings.posix_from_slot(4492799), 1596059071);
+        assert_eq!(network_settings.posix_from_slot(4492800), 1596059091);
+    }
+
+    #[test]
+    fn try_from__custom_network_starting_in_shelley() {
+        // when
+        let network_settings = NetworkSettings::try_from(devnet(0)).unwrap();
+
+        // then
+        assert_eq!(network_settings.network(), 0);
+        assert_eq!(network_settings.system_start(), DEVNET_SYSTEM_START);
+        assert_eq!(network_settings.starting_slot_number(), 0);
+        assert_eq!(network_settings.stability_window(), 240);
+        assert_eq!(
+            network_settings.slot_from_posix(DEVNET_SYSTEM_START + 100),
+            Some(100)
+        );
+        assert_eq!(
+            network_settings.posix_from_slot(100),
+            DEVNET_SYSTEM_START + 100
+        );
+        assert_eq!(
+            network_settings.slot_from_posix(DEVNET_SYSTEM_START - 1),
+            None
+        );
+    }
+
+    #[test]
+    fn try_from__custom_network_with_byron_era() {
+        // given
+        let shelley_start_slot = 2 * 21600;
+        let shelley_start_time = DEVNET_SYSTEM_START + shelley_start_slot as i64 * 20;
+
+        // when
+        let network_settings = NetworkSettings::try_from(devnet(2)).unwrap();
+
+        // then
+        assert_eq!(network_settings.starting_slot_number(), shelley_start_slot);
+        assert_eq!(network_settings.starting_slot_time(), shelley_start_time);
+        assert_eq!(
+            network_settings.slot_from_posix(DEVNET_SYSTEM_START + 2000),
+            Some(100)
+        );
+        assert_eq!(
+            network_settings.posix_from_slot(100),
+            DEVNET_SYSTEM_START + 2000
+        );
+        assert_eq!(
+            network_settings.slot_from_posix(shelley_start_time + 5),
+            Some(shelley_start_slot + 5)
+        );
+        assert_eq!(
+            network_settings.posix_from_slot(shelley_start_slot + 5),
+            shelley_start_time + 5
+        );
+    }
+
+    #[test]
+    fn from_genesis__sub_second_slots() {
+        // given
+        let byron = serde_json::from_str(
+            r#"{"startTime":1700000000,"protocolConsts":{"k":10},"blockVersionData":{"slotDuration":"1000"}}"#,
+        )
+        .unwrap();
+        let shelley = serde_json::from_str(
+            r#"{"networkId":"Testnet","slotLength":0.2,"securityParam":10,"activeSlotsCoeff":0.5}"#,
+        )
+        .unwrap();
+
+        // when
+        let network_settings = NetworkSettings::from_genesis(byron, shelley, 0).unwrap();
+
+        // then
+        assert_eq!(network_settings.slot_length_ms(), 200);
+        assert_eq!(
+            network_settings.slot_from_posix(DEVNET_SYSTEM_START + 3),
+            Some(15)
+        );
+        assert_eq!(
+            network_settings.posix_from_slot(15),
+            DEVNET_SYSTEM_START + 3
+        );
+    }
+
+    #[test]
+    fn from_genesis__rejects_unknown_network_id() {
+        // given
+        let byron = serde_json::from_str(
+            r#"{"startTime":1700000000,"protocolConsts":{"k":10},"blockVersionData":{"slotDuration":"1000"}}"#,
+        )
+        .unwrap();
+        let shelley = serde_json::from_str(
+            r#"{"networkId":"Devnet","slotLength":1,"securityParam":10,"activeSlotsCoeff":0.5}"#,
+        )
+        .unwrap();
+
+        // when
+        let res = NetworkSettings::from_genesis(byron, shelley, 0);
+
+        // then
+        assert!(matches!(res, Err(NetworkSettingsError::InvalidGenesis(_))));
+    }
+
+    #[test]
+    fn try_from__custom_network_missing_genesis_file() {
+        // given
+        let network = Network::Custom {
+            byron_genesis_file: fixture("missing.json"),
+            shelley_genesis_file: fixture("shelley-genesis.json"),
+            shelley_start_epoch: 0,
+        };
+
+        // when
+        let res = NetworkSettings::try_from(network);
+
+        // then
+        assert!(matches!(res, Err(NetworkSettingsError::File(_))));
+    }
+
+    #[test]
+    fn horizon_slot__stability_window_past_the_tip() {
+        // given
+        let network_settings = NetworkSettings::try_from(Network::Preview).unwrap();
+
+        // when
+        let horizon = network_settings.horizon_slot(1693686823);
+
+        // then
+        assert_eq!(horizon, Some(27030823 + 25920));
+    }
 }
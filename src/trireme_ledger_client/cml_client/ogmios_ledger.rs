@@ -0,0 +1,410 @@
+use crate::output::DatumEncoding;
+use crate::scripts::ReferenceScript;
+use crate::transaction::TransactionVersion;
+use crate::trireme_ledger_client::cml_client::{
+    error::{CMLLCError, Result},
+    network_settings::NetworkSettings,
+    ogmios_scrolls_ledger::cml_value_from_units,
+    protocol_parameters::{ProtocolParameters, Ratio},
+    ExecutionCost, Ledger, UTxO,
+};
+use async_trait::async_trait;
+use cardano_multiplatform_lib::{
+    address::Address as CMLAddress, crypto::TransactionHash,
+    ledger::common::value::Value as CMLValue, plutus::PlutusData, Transaction as CMLTransaction,
+};
+use futures::{SinkExt, StreamExt};
+use pallas_addresses::Address;
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use tokio_tungstenite::{connect_async, tungstenite::Message};
+
+#[cfg(test)]
+mod tests;
+
+/// JSON-RPC client for the Ogmios v6 websocket. Each request gets its own connection.
+pub struct OgmiosWsClient {
+    url: String,
+}
+
+impl OgmiosWsClient {
+    /// `url` is the Ogmios websocket, e.g. `ws://localhost:1337`
+    pub fn new(url: &str) -> Self {
+        OgmiosWsClient {
+            url: url.to_string(),
+        }
+    }
+
+    pub async fn request<T: DeserializeOwned>(&self, method: &str, params: Value) -> Result<T> {
+        let (mut socket, _) = connect_async(&self.url)
+            .await
+            .map_err(|e| CMLLCError::LedgerError(Box::new(e)))?;
+        socket
+            .send(Message::Text(rpc_request(method, params).to_string()))
+            .await
+            .map_err(|e| CMLLCError::LedgerError(Box::new(e)))?;
+        while let Some(message) = socket.next().await {
+            let message = message.map_err(|e| CMLLCError::LedgerError(Box::new(e)))?;
+            if let Message::Text(text) = message {
+                let _ = socket.close(None).await;
+                return parse_rpc_response(&text);
+            }
+        }
+        Err(CMLLCError::OgmiosResponse(
+            "Connection closed before a response".to_string(),
+        ))
+    }
+}
+
+fn rpc_request(method: &str, params: Value) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "method": method,
+        "params": params,
+        "id": method,
+    })
+}
+
+fn parse_rpc_response<T: DeserializeOwned>(text: &str) -> Result<T> {
+    let response: RpcResponse<T> =
+        serde_json::from_str(text).map_err(|e| CMLLCError::Deserialize(e.to_string()))?;
+    match response {
+        RpcResponse {
+            error: Some(error), ..
+        } => Err(CMLLCError::OgmiosResponse(format!(
+            "{} ({}): {}",
+            error.message,
+            error.code,
+            error.data.unwrap_or_default()
+        ))),
+        RpcResponse {
+            result: Some(result),
+            ..
+        } => Ok(result),
+        _ => Err(CMLLCError::OgmiosResponse("No result".to_string())),
+    }
+}
+
+/// Ledger backed only by Ogmios v6. Ogmios doesn't index txs or assets, so
+/// `tx_confirmations` and `addresses_with_asset` are unsupported.
+pub struct OgmiosLedger {
+    client: OgmiosWsClient,
+    network_settings: NetworkSettings,
+}
+
+impl OgmiosLedger {
+    pub fn new(client: OgmiosWsClient, network_settings: NetworkSettings) -> Self {
+        OgmiosLedger {
+            client,
+            network_settings,
+        }
+    }
+
+    async fn query_utxos(&self, params: Value) -> Result<Vec<OgmiosUtxo>> {
+        self.client.request("queryLedgerState/utxo", params).await
+    }
+
+    async fn utxos_at(&self, addr: &CMLAddress) -> Result<Vec<UTxO>> {
+        let address = addr
+            .to_bech32(None)
+            .map_err(|e| CMLLCError::JsError(e.to_string()))?;
+        self.query_utxos(json!({ "addresses": [address] }))
+            .await?
+            .iter()
+            .map(utxo_from_ogmios_utxo)
+            .collect()
+    }
+}
+
+#[async_trait]
+impl Ledger for OgmiosLedger {
+    async fn last_block_time_secs(&self) -> Result<i64> {
+        let tip: OgmiosTip = self.client.request("queryNetwork/tip", json!({})).await?;
+        Ok(self.network_settings.posix_from_slot(tip_slot(tip)?))
+    }
+
+    async fn get_utxos_for_addr(&self, addr: &CMLAddress, count: usize) -> Result<Vec<UTxO>> {
+        let utxos = self.utxos_at(addr).await?.into_iter().take(count).collect();
+        Ok(utxos)
+    }
+
+    async fn get_all_utxos_for_addr(&self, addr: &CMLAddress) -> Result<Vec<UTxO>> {
+        self.utxos_at(addr).await
+    }
+
+    async fn calculate_ex_units(&self, tx: &CMLTransaction) -> Result<HashMap<u64, ExecutionCost>> {
+        let params = json!({ "transaction": { "cbor": hex::encode(tx.to_bytes()) } });
+        let evaluations: Vec<OgmiosEvaluation> =
+            self.client.request("evaluateTransaction", params).await?;
+        Ok(ex_units_from_evaluations(&evaluations))
+    }
+
+    async fn tx_confirmations(&self, _tx_id: &str) -> Result<Option<u64>> {
+        Err(CMLLCError::TxLookupUnsupported)
+    }
+
+    async fn utxo_by_id(&self, tx_hash: &str, index: u64) -> Result<Option<(Address, UTxO)>> {
+        let params = json!({
+            "outputReferences": [{ "transaction": { "id": tx_hash }, "index": index }]
+        });
+        if let Some(output) = self.query_utxos(params).await?.first() {
+            let address = Address::from_bech32(&output.address)?;
+            let utxo = utxo_from_ogmios_utxo(output)?;
+            Ok(Some((address, utxo)))
+        } else {
+            Ok(None)
+        }
+    }
+
+    async fn addresses_with_asset(
+        &self,
+        _policy_id: &str,
+        _asset_name: Option<&str>,
+    ) -> Result<Vec<Address>> {
+        Err(CMLLCError::AssetLookupUnsupported)
+    }
+
+    async fn protocol_parameters(&self) -> Result<ProtocolParameters> {
+        let params: OgmiosParameters = self
+            .client
+            .request("queryLedgerState/protocolParameters", json!({}))
+            .await?;
+        params.try_into()
+    }
+
+    async fn submit_transaction(&self, tx: &CMLTransaction) -> Result<String> {
+        let params = json!({ "transaction": { "cbor": hex::encode(tx.to_bytes()) } });
+        let submitted: OgmiosSubmitted = self.client.request("submitTransaction", params).await?;
+        Ok(submitted.transaction.id)
+    }
+}
+
+fn tip_slot(tip: OgmiosTip) -> Result<u64> {
+    match tip {
+        OgmiosTip::Point { slot } => Ok(slot),
+        OgmiosTip::Origin(_) => Err(CMLLCError::OgmiosResponse(
+            "Chain is still at origin".to_string(),
+        )),
+    }
+}
+
+/// Ogmios can't resolve datum hashes, so outputs with a hashed datum come back without it
+fn utxo_from_ogmios_utxo(utxo: &OgmiosUtxo) -> Result<UTxO> {
+    let tx_hash = TransactionHash::from_hex(&utxo.transaction.id)
+        .map_err(|e| CMLLCError::JsError(e.to_string()))?;
+    let amount = cml_value_from_ogmios_assets(&utxo.value)?;
+    let index = utxo.index.into();
+    let utxo_out = match (&utxo.datum, &utxo.datum_hash) {
+        (Some(datum), _) => {
+            let datum = PlutusData::from_bytes(hex::decode(datum)?)
+                .map_err(|e| CMLLCError::Deserialize(e.to_string()))?;
+            UTxO::new(tx_hash, index, amount, Some(datum))
+                .with_datum_encoding(DatumEncoding::Inline)
+        }
+        (None, Some(_)) => {
+            UTxO::new(tx_hash, index, amount, None).with_datum_encoding(DatumEncoding::Hash)
+        }
+        (None, None) => UTxO::new(tx_hash, index, amount, None),
+    };
+    let script_hash = utxo
+        .script
+        .as_ref()
+        .map(reference_script_hash)
+        .transpose()?
+        .flatten();
+    match script_hash {
+        Some(script_hash) => Ok(utxo_out.with_reference_script_hash(script_hash)),
+        None => Ok(utxo_out),
+    }
+}
+
+/// `None` for native and Plutus V3 scripts, which can't be used as reference scripts yet
+fn reference_script_hash(script: &OgmiosScript) -> Result<Option<Vec<u8>>> {
+    let version = match script.language.as_str() {
+        "plutus:v1" => TransactionVersion::V1,
+        "plutus:v2" => TransactionVersion::V2,
+        _ => return Ok(None),
+    };
+    let hash = ReferenceScript::new(version, &script.cbor)
+        .hash()
+        .map_err(|e| CMLLCError::Deserialize(e.to_string()))?;
+    Ok(Some(hash))
+}
+
+/// Ogmios v6 nests asset quantities by policy id, with ADA under `ada.lovelace`
+fn cml_value_from_ogmios_assets(value: &OgmiosAssets) -> Result<CMLValue> {
+    let units: Vec<(String, u64)> = value
+        .iter()
+        .flat_map(|(policy_id, assets)| {
+            assets.iter().map(move |(asset_name, quantity)| {
+                if policy_id == "ada" {
+                    (asset_name.clone(), *quantity)
+                } else {
+                    (format!("{policy_id}{asset_name}"), *quantity)
+                }
+            })
+        })
+        .collect();
+    cml_value_from_units(
+        units
+            .iter()
+            .map(|(unit, quantity)| (unit.as_str(), *quantity)),
+    )
+}
+
+fn ex_units_from_evaluations(evaluations: &[OgmiosEvaluation]) -> HashMap<u64, ExecutionCost> {
+    evaluations
+        .iter()
+        .filter_map(|evaluation| {
+            let memory = evaluation.budget.memory;
+            let steps = evaluation.budget.cpu;
+            let ex_cost = match evaluation.validator.purpose.as_str() {
+                "spend" => Some(ExecutionCost::new_spend(memory, steps)),
+                "mint" => Some(ExecutionCost::new_mint(memory, steps)),
+                "withdraw" => Some(ExecutionCost::new_withdrawal(memory, steps)),
+                "publish" => Some(ExecutionCost::new_certificate(memory, steps)),
+                _ => None,
+            }?;
+            Some((evaluation.validator.index, ex_cost))
+        })
+        .collect()
+}
+
+#[derive(Deserialize)]
+struct RpcResponse<T> {
+    result: Option<T>,
+    error: Option<RpcError>,
+}
+
+#[derive(Deserialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+    data: Option<Value>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum OgmiosTip {
+    Point { slot: u64 },
+    Origin(String),
+}
+
+#[derive(Deserialize)]
+struct OgmiosEvaluation {
+    validator: OgmiosValidator,
+    budget: OgmiosBudget,
+}
+
+#[derive(Deserialize)]
+struct OgmiosValidator {
+    index: u64,
+    purpose: String,
+}
+
+#[derive(Deserialize)]
+struct OgmiosBudget {
+    memory: u64,
+    cpu: u64,
+}
+
+#[derive(Deserialize)]
+struct OgmiosSubmitted {
+    transaction: OgmiosTxId,
+}
+
+#[derive(Deserialize)]
+struct OgmiosTxId {
+    id: String,
+}
+
+/// Quantities by policy id, then by hex asset name
+type OgmiosAssets = BTreeMap<String, BTreeMap<String, u64>>;
+
+#[derive(Deserialize)]
+struct OgmiosUtxo {
+    transaction: OgmiosTxId,
+    index: u64,
+    address: String,
+    value: OgmiosAssets,
+    /// CBOR hex of an inline datum
+    datum: Option<String>,
+    #[serde(rename = "datumHash")]
+    datum_hash: Option<String>,
+    script: Option<OgmiosScript>,
+}
+
+#[derive(Deserialize)]
+struct OgmiosScript {
+    /// e.g. `plutus:v2` or `native`
+    language: String,
+    cbor: String,
+}
+
+#[derive(Deserialize)]
+struct OgmiosLovelace {
+    lovelace: u64,
+}
+
+#[derive(Deserialize)]
+struct OgmiosAda {
+    ada: OgmiosLovelace,
+}
+
+#[derive(Deserialize)]
+struct OgmiosBytes {
+    bytes: u32,
+}
+
+#[derive(Deserialize)]
+struct OgmiosPrices {
+    memory: String,
+    cpu: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct OgmiosParameters {
+    min_fee_coefficient: u64,
+    min_fee_constant: OgmiosAda,
+    stake_pool_deposit: OgmiosAda,
+    stake_credential_deposit: OgmiosAda,
+    max_value_size: OgmiosBytes,
+    max_transaction_size: OgmiosBytes,
+    min_utxo_deposit_coefficient: u64,
+    script_execution_prices: OgmiosPrices,
+    collateral_percentage: u32,
+    max_collateral_inputs: u32,
+    plutus_cost_models: HashMap<String, Vec<i64>>,
+}
+
+impl TryFrom<OgmiosParameters> for ProtocolParameters {
+    type Error = CMLLCError;
+
+    fn try_from(params: OgmiosParameters) -> Result<Self> {
+        // Unlike v5, v6 lists cost model parameters in on-chain order already
+        let cost_model = |language: &str| {
+            params
+                .plutus_cost_models
+                .get(language)
+                .cloned()
+                .unwrap_or_default()
+        };
+        Ok(ProtocolParameters {
+            min_fee_coefficient: params.min_fee_coefficient,
+            min_fee_constant: params.min_fee_constant.ada.lovelace,
+            pool_deposit: params.stake_pool_deposit.ada.lovelace,
+            key_deposit: params.stake_credential_deposit.ada.lovelace,
+            max_value_size: params.max_value_size.bytes,
+            max_tx_size: params.max_transaction_size.bytes,
+            coins_per_utxo_byte: params.min_utxo_deposit_coefficient,
+            memory_price: Ratio::from_fraction_str(&params.script_execution_prices.memory)?,
+            step_price: Ratio::from_fraction_str(&params.script_execution_prices.cpu)?,
+            collateral_percentage: params.collateral_percentage,
+            max_collateral_inputs: params.max_collateral_inputs,
+            plutus_v1_cost_model: cost_model("plutus:v1"),
+            plutus_v2_cost_model: cost_model("plutus:v2"),
+        })
+    }
+}
@@ -0,0 +1,185 @@
+#![allow(non_snake_case)]
+
+use super::*;
+use crate::trireme_ledger_client::cml_client::test_utils::TX_HASH;
+use tokio::net::TcpListener;
+use tokio_tungstenite::accept_async;
+
+// Payloads in `fixtures/` are synthetic: they follow the Ogmios v6 JSON-RPC response shapes, but
+// were written by hand rather than captured from a node, and reuse the made-up tx hash and
+// addresses of the other ledger tests. Replace them with captures from a v6 node when one is at
+// hand.
+const EVALUATE_TRANSACTION: &str = include_str!("fixtures/evaluate_transaction.json");
+const EVALUATE_TRANSACTION_FAILURE: &str =
+    include_str!("fixtures/evaluate_transaction_failure.json");
+const SUBMIT_TRANSACTION: &str = include_str!("fixtures/submit_transaction.json");
+const QUERY_UTXO: &str = include_str!("fixtures/query_utxo.json");
+const QUERY_TIP: &str = include_str!("fixtures/query_tip.json");
+const QUERY_TIP_ORIGIN: &str = include_str!("fixtures/query_tip_origin.json");
+const PROTOCOL_PARAMETERS: &str = include_str!("fixtures/protocol_parameters.json");
+
+#[test]
+fn parse_rpc_response__evaluation_into_ex_units() {
+    // when
+    let evaluations: Vec<OgmiosEvaluation> = parse_rpc_response(EVALUATE_TRANSACTION).unwrap();
+    let ex_units = ex_units_from_evaluations(&evaluations);
+
+    // then
+    assert_eq!(ex_units.len(), 2);
+    assert_eq!(
+        ex_units.get(&0),
+        Some(&ExecutionCost::new_spend(1700, 476468))
+    );
+    assert_eq!(
+        ex_units.get(&1),
+        Some(&ExecutionCost::new_mint(5236, 1318672))
+    );
+}
+
+#[test]
+fn parse_rpc_response__surfaces_rpc_errors() {
+    // when
+    let res = parse_rpc_response::<Vec<OgmiosEvaluation>>(EVALUATE_TRANSACTION_FAILURE);
+
+    // then
+    match res {
+        Err(CMLLCError::OgmiosResponse(message)) => {
+            assert!(message.contains("3010"));
+            assert!(message.contains("validationError"));
+        }
+        _ => panic!("Expected an Ogmios error"),
+    }
+}
+
+#[test]
+fn parse_rpc_response__submitted_tx_id() {
+    // when
+    let submitted: OgmiosSubmitted = parse_rpc_response(SUBMIT_TRANSACTION).unwrap();
+
+    // then
+    assert_eq!(submitted.transaction.id, TX_HASH);
+}
+
+#[test]
+fn utxo_from_ogmios_utxo__reads_values_and_inline_datums() {
+    // given
+    let utxos: Vec<OgmiosUtxo> = parse_rpc_response(QUERY_UTXO).unwrap();
+
+    // when
+    let utxos: Vec<UTxO> = utxos
+        .iter()
+        .map(utxo_from_ogmios_utxo)
+        .collect::<Result<_>>()
+        .unwrap();
+
+    // then
+    assert_eq!(utxos.len(), 3);
+    assert_eq!(u64::from(utxos[0].amount().coin()), 5_000_000);
+    assert_eq!(
+        utxos[0].datum().as_ref().map(|datum| datum.to_bytes()),
+        Some(hex::decode("d87980").unwrap())
+    );
+    assert_eq!(u64::from(utxos[1].amount().coin()), 2_000_000);
+    assert!(utxos[1].amount().multiasset().is_some());
+    assert!(utxos[2].datum().is_none());
+    assert_eq!(u64::from(utxos[2].output_index()), 2);
+}
+
+#[test]
+fn utxo_from_ogmios_utxo__marks_inline_and_hashed_datums() {
+    // given
+    let utxos: Vec<OgmiosUtxo> = parse_rpc_response(QUERY_UTXO).unwrap();
+
+    // when
+    let utxos: Vec<UTxO> = utxos
+        .iter()
+        .map(utxo_from_ogmios_utxo)
+        .collect::<Result<_>>()
+        .unwrap();
+
+    // then
+    assert_eq!(utxos[0].datum_encoding(), Some(DatumEncoding::Inline));
+    assert!(utxos[1].datum_encoding().is_none());
+    assert_eq!(utxos[2].datum_encoding, DatumEncoding::Hash);
+}
+
+#[test]
+fn utxo_from_ogmios_utxo__reads_reference_script_hashes() {
+    // given
+    let utxos: Vec<OgmiosUtxo> = parse_rpc_response(QUERY_UTXO).unwrap();
+    let expected = ReferenceScript::new(TransactionVersion::V2, "4e4d01000033222220051200120011")
+        .hash()
+        .unwrap();
+
+    // when
+    let utxos: Vec<UTxO> = utxos
+        .iter()
+        .map(utxo_from_ogmios_utxo)
+        .collect::<Result<_>>()
+        .unwrap();
+
+    // then
+    assert!(utxos[0].reference_script_hash().is_none());
+    assert_eq!(utxos[1].reference_script_hash(), Some(expected.as_slice()));
+    assert!(utxos[2].reference_script_hash().is_none());
+}
+
+#[test]
+fn tip_slot__from_point_or_origin() {
+    // given
+    let tip: OgmiosTip = parse_rpc_response(QUERY_TIP).unwrap();
+    let origin: OgmiosTip = parse_rpc_response(QUERY_TIP_ORIGIN).unwrap();
+
+    // then
+    assert_eq!(tip_slot(tip).unwrap(), 22005410);
+    assert!(tip_slot(origin).is_err());
+}
+
+#[test]
+fn protocol_parameters__match_the_vasil_parameters() {
+    // given
+    let vasil = ProtocolParameters::load(
+        &std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("src/trireme_ledger_client/cml_client/protocol_parameters/vasil.json"),
+    )
+    .unwrap();
+    let params: OgmiosParameters = parse_rpc_response(PROTOCOL_PARAMETERS).unwrap();
+
+    // when
+    let params: ProtocolParameters = params.try_into().unwrap();
+
+    // then
+    assert_eq!(params, vasil);
+}
+
+#[tokio::test]
+async fn request__round_trips_over_websocket() {
+    // given
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("ws://{}", listener.local_addr().unwrap());
+    let server = tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut socket = accept_async(stream).await.unwrap();
+        let request = socket.next().await.unwrap().unwrap();
+        socket
+            .send(Message::Text(SUBMIT_TRANSACTION.to_string()))
+            .await
+            .unwrap();
+        serde_json::from_str::<Value>(request.to_text().unwrap()).unwrap()
+    });
+    let client = OgmiosWsClient::new(&url);
+
+    // when
+    let params = json!({ "transaction": { "cbor": "84a0" } });
+    let submitted: OgmiosSubmitted = client
+        .request("submitTransaction", params.clone())
+        .await
+        .unwrap();
+
+    // then
+    let request = server.await.unwrap();
+    assert_eq!(request["jsonrpc"], "2.0");
+    assert_eq!(request["method"], "submitTransaction");
+    assert_eq!(request["params"], params);
+    assert_eq!(submitted.transaction.id, TX_HASH);
+}
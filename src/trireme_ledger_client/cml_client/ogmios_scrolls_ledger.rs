@@ -1,6 +1,9 @@
+use crate::output::DatumEncoding;
+use crate::transaction::TransactionVersion;
 use crate::trireme_ledger_client::cml_client::network_settings::NetworkSettings;
 use crate::trireme_ledger_client::cml_client::{
     error::{CMLLCError, Result},
+    protocol_parameters::{ProtocolParameters, Ratio},
     ExecutionCost, Ledger, UTxO,
 };
 use async_trait::async_trait;
@@ -9,13 +12,16 @@ use cardano_multiplatform_lib::{
     ledger::common::value::Value as CMLValue, plutus::PlutusData, AssetName, Assets, MultiAsset,
     PolicyID, Transaction as CMLTransaction,
 };
+use futures::{SinkExt, StreamExt};
 use ogmios_client::{EvaluationResult, OgmiosClient, OgmiosLocalTxSubmission, OgmiosResponse};
 use pallas_addresses::Address;
 use scrolls_client::{
     Amount as ScrollClientAmount, LastBlockInfo, ScrollsClient, UTxO as ScrollsClientUTxO,
     UTxOsByAddress,
 };
-use std::collections::HashMap;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use tokio_tungstenite::{connect_async, tungstenite::Message};
 
 fn utxo_from_scrolls_utxo(utxo: &ScrollsClientUTxO) -> Result<UTxO> {
     let tx_hash = TransactionHash::from_hex(utxo.tx_hash())
@@ -34,10 +40,28 @@ fn utxo_from_scrolls_utxo(utxo: &ScrollsClientUTxO) -> Result<UTxO> {
 }
 
 fn cml_value_from_scroll_amount(amount: &[ScrollClientAmount]) -> Result<CMLValue> {
+    cml_value_from_units(amount.iter().map(|value| (value.unit(), value.quantity())))
+}
+
+/// Ogmios keys assets as `policy_id.asset_name` and keeps ADA separate
+pub(super) fn cml_value_from_ogmios_value(value: &OgmiosValue) -> Result<CMLValue> {
+    let units: Vec<(String, u64)> = value
+        .assets
+        .iter()
+        .map(|(asset, quantity)| (asset.replace('.', ""), *quantity))
+        .collect();
+    let lovelace = std::iter::once(("lovelace", value.coins));
+    let assets = units
+        .iter()
+        .map(|(unit, quantity)| (unit.as_str(), *quantity));
+    cml_value_from_units(lovelace.chain(assets))
+}
+
+pub(super) fn cml_value_from_units<'a>(
+    units: impl Iterator<Item = (&'a str, u64)>,
+) -> Result<CMLValue> {
     let mut cml_value = CMLValue::zero();
-    for value in amount.iter() {
-        let unit = value.unit();
-        let quantity = value.quantity();
+    for (unit, quantity) in units {
         let add_value = match unit {
             "lovelace" => CMLValue::new(&quantity.into()),
             _ => {
@@ -72,11 +96,48 @@ fn plutus_data_from_scroll_datum(datum: &str) -> Result<Option<PlutusData>> {
     Ok(PlutusData::from_bytes(bytes).ok())
 }
 
+/// Run an Ogmios state query over the websocket at `url`
+pub(super) async fn query_ogmios(url: &str, query: serde_json::Value) -> Result<serde_json::Value> {
+    let (mut socket, _) = connect_async(url)
+        .await
+        .map_err(|e| CMLLCError::LedgerError(Box::new(e)))?;
+    let request = serde_json::json!({
+        "type": "jsonwsp/request",
+        "version": "1.0",
+        "servicename": "ogmios",
+        "methodname": "Query",
+        "args": { "query": query },
+    });
+    socket
+        .send(Message::Text(request.to_string()))
+        .await
+        .map_err(|e| CMLLCError::LedgerError(Box::new(e)))?;
+    while let Some(message) = socket.next().await {
+        let message = message.map_err(|e| CMLLCError::LedgerError(Box::new(e)))?;
+        if let Message::Text(text) = message {
+            let response: serde_json::Value =
+                serde_json::from_str(&text).map_err(|e| CMLLCError::Deserialize(e.to_string()))?;
+            if let Some(fault) = response.get("fault") {
+                return Err(CMLLCError::OgmiosResponse(fault.to_string()));
+            }
+            return response
+                .get("result")
+                .cloned()
+                .ok_or(CMLLCError::OgmiosResponse("No query result".to_string()));
+        }
+    }
+    Err(CMLLCError::OgmiosResponse(
+        "Connection closed before a response".to_string(),
+    ))
+}
+
+/// Reads UTxOs from Scrolls and talks to Ogmios v5. For Ogmios v6 on its own, see
+/// [`OgmiosLedger`](super::ogmios_ledger::OgmiosLedger).
 pub struct OgmiosScrollsLedger {
     scrolls_client: ScrollsClient,
     ogmios_client: OgmiosClient,
     network_settings: NetworkSettings,
-    // TODO: WS Client for Ogmios data
+    ogmios_ws_url: Option<String>,
 }
 
 impl OgmiosScrollsLedger {
@@ -89,9 +150,47 @@ impl OgmiosScrollsLedger {
             scrolls_client,
             ogmios_client,
             network_settings,
+            ogmios_ws_url: None,
         }
     }
 
+    /// Websocket URL, e.g. `ws://localhost:1337`, used for Ogmios state queries
+    pub fn with_ogmios_ws_url(mut self, url: &str) -> Self {
+        self.ogmios_ws_url = Some(url.to_string());
+        self
+    }
+
+    async fn query_ogmios(&self, query: serde_json::Value) -> Result<serde_json::Value> {
+        let url = self
+            .ogmios_ws_url
+            .as_ref()
+            .ok_or(CMLLCError::OgmiosResponse(
+                "No Ogmios websocket URL configured".to_string(),
+            ))?;
+        query_ogmios(url, query).await
+    }
+
+    /// Ogmios only knows the hash of non-inline datums, but Scrolls indexes the datums of the
+    /// outputs at each address
+    async fn scrolls_datum(
+        &self,
+        address: &str,
+        tx_hash: &str,
+        index: u64,
+    ) -> Result<Option<PlutusData>> {
+        let address =
+            CMLAddress::from_bech32(address).map_err(|e| CMLLCError::JsError(e.to_string()))?;
+        let datum = self
+            .get_utxos(&address)
+            .await?
+            .into_iter()
+            .find(|utxo| {
+                utxo.tx_hash().to_string() == tx_hash && u64::from(utxo.output_index()) == index
+            })
+            .and_then(|utxo| utxo.datum().clone());
+        Ok(datum)
+    }
+
     pub async fn get_utxos(&self, addr: &CMLAddress) -> Result<Vec<UTxO>> {
         let address_str = addr
             .to_bech32(None)
@@ -134,6 +233,54 @@ impl Ledger for OgmiosScrollsLedger {
         parse_evaluation_results(&res)
     }
 
+    async fn tx_confirmations(&self, _tx_id: &str) -> Result<Option<u64>> {
+        Err(CMLLCError::TxLookupUnsupported)
+    }
+
+    async fn utxo_by_id(&self, tx_hash: &str, index: u64) -> Result<Option<(Address, UTxO)>> {
+        let query = serde_json::json!({ "utxo": [{ "txId": tx_hash, "index": index }] });
+        let result = self.query_ogmios(query).await?;
+        let utxos: Vec<(serde_json::Value, OgmiosOutput)> =
+            serde_json::from_value(result).map_err(|e| CMLLCError::Deserialize(e.to_string()))?;
+        if let Some((_, output)) = utxos.into_iter().next() {
+            let address = Address::from_bech32(&output.address)?;
+            let cml_tx_hash = TransactionHash::from_hex(tx_hash)
+                .map_err(|e| CMLLCError::JsError(e.to_string()))?;
+            let amount = cml_value_from_ogmios_value(&output.value)?;
+            let utxo = if let Some(datum) = &output.datum {
+                let datum = plutus_data_from_scroll_datum(datum)?;
+                UTxO::new(cml_tx_hash, index.into(), amount, datum)
+                    .with_datum_encoding(DatumEncoding::Inline)
+            } else {
+                let datum = match &output.datum_hash {
+                    Some(_) => self.scrolls_datum(&output.address, tx_hash, index).await?,
+                    None => None,
+                };
+                UTxO::new(cml_tx_hash, index.into(), amount, datum)
+            };
+            Ok(Some((address, utxo)))
+        } else {
+            Ok(None)
+        }
+    }
+
+    async fn addresses_with_asset(
+        &self,
+        _policy_id: &str,
+        _asset_name: Option<&str>,
+    ) -> Result<Vec<Address>> {
+        Err(CMLLCError::AssetLookupUnsupported)
+    }
+
+    async fn protocol_parameters(&self) -> Result<ProtocolParameters> {
+        let result = self
+            .query_ogmios(serde_json::json!("currentProtocolParameters"))
+            .await?;
+        let params: OgmiosProtocolParameters =
+            serde_json::from_value(result).map_err(|e| CMLLCError::Deserialize(e.to_string()))?;
+        params.try_into()
+    }
+
     async fn submit_transaction(&self, tx: &CMLTransaction) -> Result<String> {
         let bytes = tx.to_bytes();
         let res = self.ogmios_client.submit_tx(&bytes).await?;
@@ -148,7 +295,7 @@ impl Ledger for OgmiosScrollsLedger {
     }
 }
 
-fn check_for_error(res: &OgmiosResponse<EvaluationResult>) -> Result<()> {
+pub(super) fn check_for_error(res: &OgmiosResponse<EvaluationResult>) -> Result<()> {
     if let Some(err) = res.fault() {
         Err(CMLLCError::OgmiosResponse(err.to_string()))
     } else {
@@ -156,7 +303,7 @@ fn check_for_error(res: &OgmiosResponse<EvaluationResult>) -> Result<()> {
     }
 }
 
-fn parse_evaluation_results(
+pub(super) fn parse_evaluation_results(
     res: &OgmiosResponse<EvaluationResult>,
 ) -> Result<HashMap<u64, ExecutionCost>> {
     let eval_res = if let Some(eval_res) = res.result() {
@@ -196,3 +343,68 @@ fn parse_pair(key: &str, value: &serde_json::Value) -> Option<(u64, ExecutionCos
     }?;
     Some((index, ex_cost))
 }
+
+#[derive(Deserialize)]
+struct OgmiosOutput {
+    address: String,
+    value: OgmiosValue,
+    #[serde(default, rename = "datumHash")]
+    datum_hash: Option<String>,
+    /// Hex encoded inline datum
+    #[serde(default)]
+    datum: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub(super) struct OgmiosValue {
+    coins: u64,
+    #[serde(default)]
+    assets: HashMap<String, u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(super) struct OgmiosProtocolParameters {
+    min_fee_coefficient: u64,
+    min_fee_constant: u64,
+    pool_deposit: u64,
+    stake_key_deposit: u64,
+    max_value_size: u32,
+    max_tx_size: u32,
+    coins_per_utxo_byte: u64,
+    prices: OgmiosPrices,
+    collateral_percentage: u32,
+    max_collateral_inputs: u32,
+    cost_models: HashMap<String, BTreeMap<String, i64>>,
+}
+
+#[derive(Deserialize)]
+struct OgmiosPrices {
+    memory: String,
+    steps: String,
+}
+
+impl TryFrom<OgmiosProtocolParameters> for ProtocolParameters {
+    type Error = CMLLCError;
+
+    fn try_from(params: OgmiosProtocolParameters) -> Result<Self> {
+        let cost_model = |language: &str, version: TransactionVersion| {
+            ProtocolParameters::cost_model_from_named(&version, params.cost_models.get(language))
+        };
+        Ok(ProtocolParameters {
+            min_fee_coefficient: params.min_fee_coefficient,
+            min_fee_constant: params.min_fee_constant,
+            pool_deposit: params.pool_deposit,
+            key_deposit: params.stake_key_deposit,
+            max_value_size: params.max_value_size,
+            max_tx_size: params.max_tx_size,
+            coins_per_utxo_byte: params.coins_per_utxo_byte,
+            memory_price: Ratio::from_fraction_str(&params.prices.memory)?,
+            step_price: Ratio::from_fraction_str(&params.prices.steps)?,
+            collateral_percentage: params.collateral_percentage,
+            max_collateral_inputs: params.max_collateral_inputs,
+            plutus_v1_cost_model: cost_model("plutus:v1", TransactionVersion::V1)?,
+            plutus_v2_cost_model: cost_model("plutus:v2", TransactionVersion::V2)?,
+        })
+    }
+}
@@ -0,0 +1,604 @@
+use crate::{output::min_ada::MinAdaCalculator, transaction::TransactionVersion};
+use cardano_multiplatform_lib::{
+    builders::tx_builder::{TransactionBuilder, TransactionBuilderConfigBuilder},
+    ledger::{alonzo::fees::LinearFee, common::value::Int},
+    plutus::{CostModel, Costmdls, ExUnitPrices, Language},
+    UnitInterval,
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, path::Path};
+use thiserror::Error;
+
+#[cfg(test)]
+mod tests;
+
+#[derive(Debug, Error)]
+pub enum ProtocolParametersError {
+    #[error("Invalid ratio: {0:?}")]
+    InvalidRatio(String),
+    #[error("Missing cost model for {0:?}")]
+    MissingCostModel(TransactionVersion),
+    #[error("Missing cost model parameter {1:?} for {0:?}")]
+    MissingCostModelParameter(TransactionVersion, String),
+    #[error("Unknown cost model parameter {1:?} for {0:?}")]
+    UnknownCostModelParameter(TransactionVersion, String),
+    #[error("Failed to read or write protocol parameters file: {0:?}")]
+    File(#[from] std::io::Error),
+    #[error("Failed to (de)serialize protocol parameters: {0:?}")]
+    Serde(#[from] serde_json::Error),
+    #[error("CML JsError: {0:?}")]
+    JsError(String),
+}
+
+pub type Result<T, E = ProtocolParametersError> = std::result::Result<T, E>;
+
+/// Exact fraction used for ex-unit prices, e.g. `577/10000`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Ratio {
+    pub numerator: u64,
+    pub denominator: u64,
+}
+
+impl Ratio {
+    pub fn new(numerator: u64, denominator: u64) -> Self {
+        Ratio {
+            numerator,
+            denominator,
+        }
+    }
+
+    /// Parse a `numerator/denominator` string, like Ogmios returns
+    pub fn from_fraction_str(fraction: &str) -> Result<Self> {
+        let invalid = || ProtocolParametersError::InvalidRatio(fraction.to_string());
+        let (numerator, denominator) = fraction.split_once('/').ok_or_else(invalid)?;
+        let numerator = numerator.trim().parse().map_err(|_| invalid())?;
+        let denominator = denominator.trim().parse().map_err(|_| invalid())?;
+        Ok(Ratio::new(numerator, denominator))
+    }
+
+    /// Parse a decimal string like `0.0577` or `7.21e-5`, like Blockfrost returns
+    pub fn from_decimal_str(decimal: &str) -> Result<Self> {
+        let invalid = || ProtocolParametersError::InvalidRatio(decimal.to_string());
+        let decimal = decimal.trim().to_lowercase();
+        let (mantissa, exponent) = decimal.split_once('e').unwrap_or((decimal.as_str(), "0"));
+        let exponent: i64 = exponent.parse().map_err(|_| invalid())?;
+        let (whole, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        let mut numerator: u64 = format!("{whole}{fraction}")
+            .parse()
+            .map_err(|_| invalid())?;
+        let scale = fraction.len() as i64 - exponent;
+        let power = |n: i64| {
+            u32::try_from(n)
+                .ok()
+                .and_then(|n| 10u64.checked_pow(n))
+                .ok_or_else(invalid)
+        };
+        let denominator = if scale >= 0 {
+            power(scale)?
+        } else {
+            numerator = numerator.checked_mul(power(-scale)?).ok_or_else(invalid)?;
+            1
+        };
+        Ok(Ratio::new(numerator, denominator))
+    }
+}
+
+/// Ledger rules txs are built against. They change at hard forks, so they're queried from the
+/// ledger rather than hard-coded.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProtocolParameters {
+    pub min_fee_coefficient: u64,
+    pub min_fee_constant: u64,
+    pub pool_deposit: u64,
+    pub key_deposit: u64,
+    pub max_value_size: u32,
+    pub max_tx_size: u32,
+    pub coins_per_utxo_byte: u64,
+    pub memory_price: Ratio,
+    pub step_price: Ratio,
+    pub collateral_percentage: u32,
+    pub max_collateral_inputs: u32,
+    pub plutus_v1_cost_model: Vec<i64>,
+    pub plutus_v2_cost_model: Vec<i64>,
+}
+
+impl ProtocolParameters {
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = std::fs::read_to_string(path)?;
+        let params = serde_json::from_str(&json)?;
+        Ok(params)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, json)?;
+        Ok(())
+    }
+
+    pub fn min_ada_calculator(&self) -> MinAdaCalculator {
+        MinAdaCalculator::new(self.coins_per_utxo_byte)
+    }
+
+    /// Order cost model parameters `named` like their ledger representation, as providers that
+    /// return them by name don't keep that order. Languages missing from the provider yield an
+    /// empty cost model, so building txs for them fails with `MissingCostModel`.
+    pub fn cost_model_from_named(
+        version: &TransactionVersion,
+        named: Option<&BTreeMap<String, i64>>,
+    ) -> Result<Vec<i64>> {
+        let named = match named {
+            Some(named) => named,
+            None => return Ok(Vec::new()),
+        };
+        let names: &[&str] = match version {
+            TransactionVersion::V1 => &PLUTUS_V1_COST_MODEL_PARAMETERS,
+            TransactionVersion::V2 => &PLUTUS_V2_COST_MODEL_PARAMETERS,
+        };
+        let canonical = |name: &str| {
+            LEGACY_COST_MODEL_PARAMETERS
+                .iter()
+                .find(|(legacy, _)| *legacy == name)
+                .map(|(_, current)| *current)
+                .unwrap_or(name)
+                .to_string()
+        };
+        let named: BTreeMap<String, i64> = named
+            .iter()
+            .map(|(name, value)| (canonical(name), *value))
+            .collect();
+        if let Some(unknown) = named.keys().find(|name| !names.contains(&name.as_str())) {
+            return Err(ProtocolParametersError::UnknownCostModelParameter(
+                version.clone(),
+                unknown.to_string(),
+            ));
+        }
+        names
+            .iter()
+            .map(|name| {
+                named.get(*name).copied().ok_or_else(|| {
+                    ProtocolParametersError::MissingCostModelParameter(
+                        version.clone(),
+                        name.to_string(),
+                    )
+                })
+            })
+            .collect()
+    }
+
+    pub fn cost_model(&self, version: &TransactionVersion) -> Result<&Vec<i64>> {
+        let cost_model = match version {
+            TransactionVersion::V1 => &self.plutus_v1_cost_model,
+            TransactionVersion::V2 => &self.plutus_v2_cost_model,
+        };
+        if cost_model.is_empty() {
+            Err(ProtocolParametersError::MissingCostModel(version.clone()))
+        } else {
+            Ok(cost_model)
+        }
+    }
+
+    /// CML tx builder for txs running scripts of `version`
+    pub fn tx_builder(&self, version: &TransactionVersion) -> Result<TransactionBuilder> {
+        let linear_fee = LinearFee::new(
+            &self.min_fee_coefficient.into(),
+            &self.min_fee_constant.into(),
+        );
+        let mem_price = UnitInterval::new(
+            &self.memory_price.numerator.into(),
+            &self.memory_price.denominator.into(),
+        );
+        let step_price = UnitInterval::new(
+            &self.step_price.numerator.into(),
+            &self.step_price.denominator.into(),
+        );
+        let ex_unit_prices = ExUnitPrices::new(&mem_price, &step_price);
+
+        let language = match version {
+            TransactionVersion::V1 => Language::new_plutus_v1(),
+            TransactionVersion::V2 => Language::new_plutus_v2(),
+        };
+        let cm = CostModel::new(
+            &language,
+            &self
+                .cost_model(version)?
+                .iter()
+                .map(|&i| Int::from_str(&i.to_string()))
+                .collect::<std::result::Result<Vec<_>, _>>()
+                .map_err(|e| ProtocolParametersError::JsError(e.to_string()))?,
+        );
+        let mut cost_models = Costmdls::new();
+        cost_models.insert(&cm);
+
+        let tx_builder_cfg = TransactionBuilderConfigBuilder::new()
+            .fee_algo(&linear_fee)
+            .pool_deposit(&self.pool_deposit.into())
+            .key_deposit(&self.key_deposit.into())
+            .max_value_size(self.max_value_size)
+            .max_tx_size(self.max_tx_size)
+            .coins_per_utxo_byte(&self.coins_per_utxo_byte.into())
+            .ex_unit_prices(&ex_unit_prices)
+            .collateral_percentage(self.collateral_percentage)
+            .max_collateral_inputs(self.max_collateral_inputs)
+            .costmdls(&cost_models)
+            .build()
+            .map_err(|e| ProtocolParametersError::JsError(e.to_string()))?;
+        Ok(TransactionBuilder::new(&tx_builder_cfg))
+    }
+}
+
+/// Plutus V1 cost model parameters in the order of their ledger representation
+const PLUTUS_V1_COST_MODEL_PARAMETERS: [&str; 166] = [
+    "addInteger-cpu-arguments-intercept",
+    "addInteger-cpu-arguments-slope",
+    "addInteger-memory-arguments-intercept",
+    "addInteger-memory-arguments-slope",
+    "appendByteString-cpu-arguments-intercept",
+    "appendByteString-cpu-arguments-slope",
+    "appendByteString-memory-arguments-intercept",
+    "appendByteString-memory-arguments-slope",
+    "appendString-cpu-arguments-intercept",
+    "appendString-cpu-arguments-slope",
+    "appendString-memory-arguments-intercept",
+    "appendString-memory-arguments-slope",
+    "bData-cpu-arguments",
+    "bData-memory-arguments",
+    "blake2b_256-cpu-arguments-intercept",
+    "blake2b_256-cpu-arguments-slope",
+    "blake2b_256-memory-arguments",
+    "cekApplyCost-exBudgetCPU",
+    "cekApplyCost-exBudgetMemory",
+    "cekBuiltinCost-exBudgetCPU",
+    "cekBuiltinCost-exBudgetMemory",
+    "cekConstCost-exBudgetCPU",
+    "cekConstCost-exBudgetMemory",
+    "cekDelayCost-exBudgetCPU",
+    "cekDelayCost-exBudgetMemory",
+    "cekForceCost-exBudgetCPU",
+    "cekForceCost-exBudgetMemory",
+    "cekLamCost-exBudgetCPU",
+    "cekLamCost-exBudgetMemory",
+    "cekStartupCost-exBudgetCPU",
+    "cekStartupCost-exBudgetMemory",
+    "cekVarCost-exBudgetCPU",
+    "cekVarCost-exBudgetMemory",
+    "chooseData-cpu-arguments",
+    "chooseData-memory-arguments",
+    "chooseList-cpu-arguments",
+    "chooseList-memory-arguments",
+    "chooseUnit-cpu-arguments",
+    "chooseUnit-memory-arguments",
+    "consByteString-cpu-arguments-intercept",
+    "consByteString-cpu-arguments-slope",
+    "consByteString-memory-arguments-intercept",
+    "consByteString-memory-arguments-slope",
+    "constrData-cpu-arguments",
+    "constrData-memory-arguments",
+    "decodeUtf8-cpu-arguments-intercept",
+    "decodeUtf8-cpu-arguments-slope",
+    "decodeUtf8-memory-arguments-intercept",
+    "decodeUtf8-memory-arguments-slope",
+    "divideInteger-cpu-arguments-constant",
+    "divideInteger-cpu-arguments-model-arguments-intercept",
+    "divideInteger-cpu-arguments-model-arguments-slope",
+    "divideInteger-memory-arguments-intercept",
+    "divideInteger-memory-arguments-minimum",
+    "divideInteger-memory-arguments-slope",
+    "encodeUtf8-cpu-arguments-intercept",
+    "encodeUtf8-cpu-arguments-slope",
+    "encodeUtf8-memory-arguments-intercept",
+    "encodeUtf8-memory-arguments-slope",
+    "equalsByteString-cpu-arguments-constant",
+    "equalsByteString-cpu-arguments-intercept",
+    "equalsByteString-cpu-arguments-slope",
+    "equalsByteString-memory-arguments",
+    "equalsData-cpu-arguments-intercept",
+    "equalsData-cpu-arguments-slope",
+    "equalsData-memory-arguments",
+    "equalsInteger-cpu-arguments-intercept",
+    "equalsInteger-cpu-arguments-slope",
+    "equalsInteger-memory-arguments",
+    "equalsString-cpu-arguments-constant",
+    "equalsString-cpu-arguments-intercept",
+    "equalsString-cpu-arguments-slope",
+    "equalsString-memory-arguments",
+    "fstPair-cpu-arguments",
+    "fstPair-memory-arguments",
+    "headList-cpu-arguments",
+    "headList-memory-arguments",
+    "iData-cpu-arguments",
+    "iData-memory-arguments",
+    "ifThenElse-cpu-arguments",
+    "ifThenElse-memory-arguments",
+    "indexByteString-cpu-arguments",
+    "indexByteString-memory-arguments",
+    "lengthOfByteString-cpu-arguments",
+    "lengthOfByteString-memory-arguments",
+    "lessThanByteString-cpu-arguments-intercept",
+    "lessThanByteString-cpu-arguments-slope",
+    "lessThanByteString-memory-arguments",
+    "lessThanEqualsByteString-cpu-arguments-intercept",
+    "lessThanEqualsByteString-cpu-arguments-slope",
+    "lessThanEqualsByteString-memory-arguments",
+    "lessThanEqualsInteger-cpu-arguments-intercept",
+    "lessThanEqualsInteger-cpu-arguments-slope",
+    "lessThanEqualsInteger-memory-arguments",
+    "lessThanInteger-cpu-arguments-intercept",
+    "lessThanInteger-cpu-arguments-slope",
+    "lessThanInteger-memory-arguments",
+    "listData-cpu-arguments",
+    "listData-memory-arguments",
+    "mapData-cpu-arguments",
+    "mapData-memory-arguments",
+    "mkCons-cpu-arguments",
+    "mkCons-memory-arguments",
+    "mkNilData-cpu-arguments",
+    "mkNilData-memory-arguments",
+    "mkNilPairData-cpu-arguments",
+    "mkNilPairData-memory-arguments",
+    "mkPairData-cpu-arguments",
+    "mkPairData-memory-arguments",
+    "modInteger-cpu-arguments-constant",
+    "modInteger-cpu-arguments-model-arguments-intercept",
+    "modInteger-cpu-arguments-model-arguments-slope",
+    "modInteger-memory-arguments-intercept",
+    "modInteger-memory-arguments-minimum",
+    "modInteger-memory-arguments-slope",
+    "multiplyInteger-cpu-arguments-intercept",
+    "multiplyInteger-cpu-arguments-slope",
+    "multiplyInteger-memory-arguments-intercept",
+    "multiplyInteger-memory-arguments-slope",
+    "nullList-cpu-arguments",
+    "nullList-memory-arguments",
+    "quotientInteger-cpu-arguments-constant",
+    "quotientInteger-cpu-arguments-model-arguments-intercept",
+    "quotientInteger-cpu-arguments-model-arguments-slope",
+    "quotientInteger-memory-arguments-intercept",
+    "quotientInteger-memory-arguments-minimum",
+    "quotientInteger-memory-arguments-slope",
+    "remainderInteger-cpu-arguments-constant",
+    "remainderInteger-cpu-arguments-model-arguments-intercept",
+    "remainderInteger-cpu-arguments-model-arguments-slope",
+    "remainderInteger-memory-arguments-intercept",
+    "remainderInteger-memory-arguments-minimum",
+    "remainderInteger-memory-arguments-slope",
+    "sha2_256-cpu-arguments-intercept",
+    "sha2_256-cpu-arguments-slope",
+    "sha2_256-memory-arguments",
+    "sha3_256-cpu-arguments-intercept",
+    "sha3_256-cpu-arguments-slope",
+    "sha3_256-memory-arguments",
+    "sliceByteString-cpu-arguments-intercept",
+    "sliceByteString-cpu-arguments-slope",
+    "sliceByteString-memory-arguments-intercept",
+    "sliceByteString-memory-arguments-slope",
+    "sndPair-cpu-arguments",
+    "sndPair-memory-arguments",
+    "subtractInteger-cpu-arguments-intercept",
+    "subtractInteger-cpu-arguments-slope",
+    "subtractInteger-memory-arguments-intercept",
+    "subtractInteger-memory-arguments-slope",
+    "tailList-cpu-arguments",
+    "tailList-memory-arguments",
+    "trace-cpu-arguments",
+    "trace-memory-arguments",
+    "unBData-cpu-arguments",
+    "unBData-memory-arguments",
+    "unConstrData-cpu-arguments",
+    "unConstrData-memory-arguments",
+    "unIData-cpu-arguments",
+    "unIData-memory-arguments",
+    "unListData-cpu-arguments",
+    "unListData-memory-arguments",
+    "unMapData-cpu-arguments",
+    "unMapData-memory-arguments",
+    "verifyEd25519Signature-cpu-arguments-intercept",
+    "verifyEd25519Signature-cpu-arguments-slope",
+    "verifyEd25519Signature-memory-arguments",
+];
+
+/// Plutus V2 cost model parameters in the order of their ledger representation
+const PLUTUS_V2_COST_MODEL_PARAMETERS: [&str; 175] = [
+    "addInteger-cpu-arguments-intercept",
+    "addInteger-cpu-arguments-slope",
+    "addInteger-memory-arguments-intercept",
+    "addInteger-memory-arguments-slope",
+    "appendByteString-cpu-arguments-intercept",
+    "appendByteString-cpu-arguments-slope",
+    "appendByteString-memory-arguments-intercept",
+    "appendByteString-memory-arguments-slope",
+    "appendString-cpu-arguments-intercept",
+    "appendString-cpu-arguments-slope",
+    "appendString-memory-arguments-intercept",
+    "appendString-memory-arguments-slope",
+    "bData-cpu-arguments",
+    "bData-memory-arguments",
+    "blake2b_256-cpu-arguments-intercept",
+    "blake2b_256-cpu-arguments-slope",
+    "blake2b_256-memory-arguments",
+    "cekApplyCost-exBudgetCPU",
+    "cekApplyCost-exBudgetMemory",
+    "cekBuiltinCost-exBudgetCPU",
+    "cekBuiltinCost-exBudgetMemory",
+    "cekConstCost-exBudgetCPU",
+    "cekConstCost-exBudgetMemory",
+    "cekDelayCost-exBudgetCPU",
+    "cekDelayCost-exBudgetMemory",
+    "cekForceCost-exBudgetCPU",
+    "cekForceCost-exBudgetMemory",
+    "cekLamCost-exBudgetCPU",
+    "cekLamCost-exBudgetMemory",
+    "cekStartupCost-exBudgetCPU",
+    "cekStartupCost-exBudgetMemory",
+    "cekVarCost-exBudgetCPU",
+    "cekVarCost-exBudgetMemory",
+    "chooseData-cpu-arguments",
+    "chooseData-memory-arguments",
+    "chooseList-cpu-arguments",
+    "chooseList-memory-arguments",
+    "chooseUnit-cpu-arguments",
+    "chooseUnit-memory-arguments",
+    "consByteString-cpu-arguments-intercept",
+    "consByteString-cpu-arguments-slope",
+    "consByteString-memory-arguments-intercept",
+    "consByteString-memory-arguments-slope",
+    "constrData-cpu-arguments",
+    "constrData-memory-arguments",
+    "decodeUtf8-cpu-arguments-intercept",
+    "decodeUtf8-cpu-arguments-slope",
+    "decodeUtf8-memory-arguments-intercept",
+    "decodeUtf8-memory-arguments-slope",
+    "divideInteger-cpu-arguments-constant",
+    "divideInteger-cpu-arguments-model-arguments-intercept",
+    "divideInteger-cpu-arguments-model-arguments-slope",
+    "divideInteger-memory-arguments-intercept",
+    "divideInteger-memory-arguments-minimum",
+    "divideInteger-memory-arguments-slope",
+    "encodeUtf8-cpu-arguments-intercept",
+    "encodeUtf8-cpu-arguments-slope",
+    "encodeUtf8-memory-arguments-intercept",
+    "encodeUtf8-memory-arguments-slope",
+    "equalsByteString-cpu-arguments-constant",
+    "equalsByteString-cpu-arguments-intercept",
+    "equalsByteString-cpu-arguments-slope",
+    "equalsByteString-memory-arguments",
+    "equalsData-cpu-arguments-intercept",
+    "equalsData-cpu-arguments-slope",
+    "equalsData-memory-arguments",
+    "equalsInteger-cpu-arguments-intercept",
+    "equalsInteger-cpu-arguments-slope",
+    "equalsInteger-memory-arguments",
+    "equalsString-cpu-arguments-constant",
+    "equalsString-cpu-arguments-intercept",
+    "equalsString-cpu-arguments-slope",
+    "equalsString-memory-arguments",
+    "fstPair-cpu-arguments",
+    "fstPair-memory-arguments",
+    "headList-cpu-arguments",
+    "headList-memory-arguments",
+    "iData-cpu-arguments",
+    "iData-memory-arguments",
+    "ifThenElse-cpu-arguments",
+    "ifThenElse-memory-arguments",
+    "indexByteString-cpu-arguments",
+    "indexByteString-memory-arguments",
+    "lengthOfByteString-cpu-arguments",
+    "lengthOfByteString-memory-arguments",
+    "lessThanByteString-cpu-arguments-intercept",
+    "lessThanByteString-cpu-arguments-slope",
+    "lessThanByteString-memory-arguments",
+    "lessThanEqualsByteString-cpu-arguments-intercept",
+    "lessThanEqualsByteString-cpu-arguments-slope",
+    "lessThanEqualsByteString-memory-arguments",
+    "lessThanEqualsInteger-cpu-arguments-intercept",
+    "lessThanEqualsInteger-cpu-arguments-slope",
+    "lessThanEqualsInteger-memory-arguments",
+    "lessThanInteger-cpu-arguments-intercept",
+    "lessThanInteger-cpu-arguments-slope",
+    "lessThanInteger-memory-arguments",
+    "listData-cpu-arguments",
+    "listData-memory-arguments",
+    "mapData-cpu-arguments",
+    "mapData-memory-arguments",
+    "mkCons-cpu-arguments",
+    "mkCons-memory-arguments",
+    "mkNilData-cpu-arguments",
+    "mkNilData-memory-arguments",
+    "mkNilPairData-cpu-arguments",
+    "mkNilPairData-memory-arguments",
+    "mkPairData-cpu-arguments",
+    "mkPairData-memory-arguments",
+    "modInteger-cpu-arguments-constant",
+    "modInteger-cpu-arguments-model-arguments-intercept",
+    "modInteger-cpu-arguments-model-arguments-slope",
+    "modInteger-memory-arguments-intercept",
+    "modInteger-memory-arguments-minimum",
+    "modInteger-memory-arguments-slope",
+    "multiplyInteger-cpu-arguments-intercept",
+    "multiplyInteger-cpu-arguments-slope",
+    "multiplyInteger-memory-arguments-intercept",
+    "multiplyInteger-memory-arguments-slope",
+    "nullList-cpu-arguments",
+    "nullList-memory-arguments",
+    "quotientInteger-cpu-arguments-constant",
+    "quotientInteger-cpu-arguments-model-arguments-intercept",
+    "quotientInteger-cpu-arguments-model-arguments-slope",
+    "quotientInteger-memory-arguments-intercept",
+    "quotientInteger-memory-arguments-minimum",
+    "quotientInteger-memory-arguments-slope",
+    "remainderInteger-cpu-arguments-constant",
+    "remainderInteger-cpu-arguments-model-arguments-intercept",
+    "remainderInteger-cpu-arguments-model-arguments-slope",
+    "remainderInteger-memory-arguments-intercept",
+    "remainderInteger-memory-arguments-minimum",
+    "remainderInteger-memory-arguments-slope",
+    "serialiseData-cpu-arguments-intercept",
+    "serialiseData-cpu-arguments-slope",
+    "serialiseData-memory-arguments-intercept",
+    "serialiseData-memory-arguments-slope",
+    "sha2_256-cpu-arguments-intercept",
+    "sha2_256-cpu-arguments-slope",
+    "sha2_256-memory-arguments",
+    "sha3_256-cpu-arguments-intercept",
+    "sha3_256-cpu-arguments-slope",
+    "sha3_256-memory-arguments",
+    "sliceByteString-cpu-arguments-intercept",
+    "sliceByteString-cpu-arguments-slope",
+    "sliceByteString-memory-arguments-intercept",
+    "sliceByteString-memory-arguments-slope",
+    "sndPair-cpu-arguments",
+    "sndPair-memory-arguments",
+    "subtractInteger-cpu-arguments-intercept",
+    "subtractInteger-cpu-arguments-slope",
+    "subtractInteger-memory-arguments-intercept",
+    "subtractInteger-memory-arguments-slope",
+    "tailList-cpu-arguments",
+    "tailList-memory-arguments",
+    "trace-cpu-arguments",
+    "trace-memory-arguments",
+    "unBData-cpu-arguments",
+    "unBData-memory-arguments",
+    "unConstrData-cpu-arguments",
+    "unConstrData-memory-arguments",
+    "unIData-cpu-arguments",
+    "unIData-memory-arguments",
+    "unListData-cpu-arguments",
+    "unListData-memory-arguments",
+    "unMapData-cpu-arguments",
+    "unMapData-memory-arguments",
+    "verifyEcdsaSecp256k1Signature-cpu-arguments",
+    "verifyEcdsaSecp256k1Signature-memory-arguments",
+    "verifyEd25519Signature-cpu-arguments-intercept",
+    "verifyEd25519Signature-cpu-arguments-slope",
+    "verifyEd25519Signature-memory-arguments",
+    "verifySchnorrSecp256k1Signature-cpu-arguments-intercept",
+    "verifySchnorrSecp256k1Signature-cpu-arguments-slope",
+    "verifySchnorrSecp256k1Signature-memory-arguments",
+];
+
+/// Names some providers still use for parameters that were renamed, with their current name
+const LEGACY_COST_MODEL_PARAMETERS: [(&str, &str); 6] = [
+    (
+        "blake2b-cpu-arguments-intercept",
+        "blake2b_256-cpu-arguments-intercept",
+    ),
+    (
+        "blake2b-cpu-arguments-slope",
+        "blake2b_256-cpu-arguments-slope",
+    ),
+    ("blake2b-memory-arguments", "blake2b_256-memory-arguments"),
+    (
+        "verifySignature-cpu-arguments-intercept",
+        "verifyEd25519Signature-cpu-arguments-intercept",
+    ),
+    (
+        "verifySignature-cpu-arguments-slope",
+        "verifyEd25519Signature-cpu-arguments-slope",
+    ),
+    (
+        "verifySignature-memory-arguments",
+        "verifyEd25519Signature-memory-arguments",
+    ),
+];
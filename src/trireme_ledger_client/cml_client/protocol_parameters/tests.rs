@@ -0,0 +1,171 @@
+#![allow(non_snake_case)]
+
+use super::*;
+use std::path::PathBuf;
+
+// Parameters the tx builders were hard-coded with for the Vasil hard fork
+fn vasil_parameters_file() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("src/trireme_ledger_client/cml_client/protocol_parameters/vasil.json")
+}
+
+#[test]
+fn ratio__from_fraction_str() {
+    let actual = Ratio::from_fraction_str("577/10000").unwrap();
+    assert_eq!(actual, Ratio::new(577, 10000));
+}
+
+#[test]
+fn ratio__from_decimal_str() {
+    let actual = Ratio::from_decimal_str("0.0000721").unwrap();
+    assert_eq!(actual, Ratio::new(721, 10000000));
+}
+
+#[test]
+fn ratio__from_decimal_str_with_exponent() {
+    let actual = Ratio::from_decimal_str("7.21e-5").unwrap();
+    assert_eq!(actual, Ratio::new(721, 10000000));
+}
+
+#[test]
+fn ratio__rejects_garbage() {
+    assert!(Ratio::from_fraction_str("577").is_err());
+    assert!(Ratio::from_decimal_str("0.05x").is_err());
+}
+
+#[test]
+fn save_and_load__round_trips() {
+    // given
+    let params = ProtocolParameters::load(&vasil_parameters_file()).unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("cache").join("protocol_parameters.json");
+
+    // when
+    params.save(&path).unwrap();
+    let loaded = ProtocolParameters::load(&path).unwrap();
+
+    // then
+    assert_eq!(loaded, params);
+}
+
+#[test]
+fn tx_builder__builds_offline_from_file() {
+    let params = ProtocolParameters::load(&vasil_parameters_file()).unwrap();
+
+    assert!(params.tx_builder(&TransactionVersion::V1).is_ok());
+    assert!(params.tx_builder(&TransactionVersion::V2).is_ok());
+    assert_eq!(params.min_ada_calculator().coins_per_utxo_byte(), 4310);
+}
+
+#[test]
+fn tx_builder__errors_without_cost_model() {
+    // given
+    let mut params = ProtocolParameters::load(&vasil_parameters_file()).unwrap();
+    params.plutus_v2_cost_model.clear();
+
+    // when
+    let res = params.tx_builder(&TransactionVersion::V2);
+
+    // then
+    assert!(matches!(
+        res,
+        Err(ProtocolParametersError::MissingCostModel(
+            TransactionVersion::V2
+        ))
+    ));
+}
+
+fn named(names: &[&str], values: &[i64]) -> BTreeMap<String, i64> {
+    names
+        .iter()
+        .map(|name| name.to_string())
+        .zip(values.iter().copied())
+        .collect()
+}
+
+#[test]
+fn cost_model_from_named__orders_like_the_ledger() {
+    // given
+    let params = ProtocolParameters::load(&vasil_parameters_file()).unwrap();
+    let named = named(
+        &PLUTUS_V2_COST_MODEL_PARAMETERS,
+        &params.plutus_v2_cost_model,
+    );
+
+    // when
+    let actual =
+        ProtocolParameters::cost_model_from_named(&TransactionVersion::V2, Some(&named)).unwrap();
+
+    // then
+    assert_eq!(actual, params.plutus_v2_cost_model);
+}
+
+#[test]
+fn cost_model_from_named__accepts_legacy_names() {
+    // given
+    let params = ProtocolParameters::load(&vasil_parameters_file()).unwrap();
+    let mut named = named(
+        &PLUTUS_V1_COST_MODEL_PARAMETERS,
+        &params.plutus_v1_cost_model,
+    );
+    for (legacy, current) in LEGACY_COST_MODEL_PARAMETERS {
+        let value = named.remove(current).unwrap();
+        named.insert(legacy.to_string(), value);
+    }
+
+    // when
+    let actual =
+        ProtocolParameters::cost_model_from_named(&TransactionVersion::V1, Some(&named)).unwrap();
+
+    // then
+    assert_eq!(actual, params.plutus_v1_cost_model);
+}
+
+#[test]
+fn cost_model_from_named__errors_on_unknown_parameter() {
+    // given
+    let params = ProtocolParameters::load(&vasil_parameters_file()).unwrap();
+    let mut named = named(
+        &PLUTUS_V1_COST_MODEL_PARAMETERS,
+        &params.plutus_v1_cost_model,
+    );
+    named.insert("integerToByteString-cpu-arguments-c0".to_string(), 1);
+
+    // when
+    let res = ProtocolParameters::cost_model_from_named(&TransactionVersion::V1, Some(&named));
+
+    // then
+    assert!(matches!(
+        res,
+        Err(ProtocolParametersError::UnknownCostModelParameter(TransactionVersion::V1, name))
+            if name == "integerToByteString-cpu-arguments-c0"
+    ));
+}
+
+#[test]
+fn cost_model_from_named__errors_on_missing_parameter() {
+    // given
+    let params = ProtocolParameters::load(&vasil_parameters_file()).unwrap();
+    let mut named = named(
+        &PLUTUS_V2_COST_MODEL_PARAMETERS,
+        &params.plutus_v2_cost_model,
+    );
+    named.remove("serialiseData-cpu-arguments-slope");
+
+    // when
+    let res = ProtocolParameters::cost_model_from_named(&TransactionVersion::V2, Some(&named));
+
+    // then
+    assert!(matches!(
+        res,
+        Err(ProtocolParametersError::MissingCostModelParameter(TransactionVersion::V2, name))
+            if name == "serialiseData-cpu-arguments-slope"
+    ));
+}
+
+#[test]
+fn cost_model_from_named__empty_without_the_language() {
+    let actual = ProtocolParameters::cost_model_from_named(&TransactionVersion::V2, None).unwrap();
+
+    assert!(actual.is_empty());
+}
@@ -0,0 +1,496 @@
+use super::{error::*, ExecutionCost, Ledger, UTxO};
+use crate::output::DatumEncoding;
+use crate::trireme_ledger_client::cml_client::protocol_parameters::ProtocolParameters;
+use async_trait::async_trait;
+use cardano_multiplatform_lib::{
+    address::Address as CMLAddress,
+    crypto::TransactionHash,
+    ledger::common::value::{BigNum, Value as CMLValue},
+    plutus::PlutusData,
+    Transaction as CMLTransaction,
+};
+use pallas_addresses::Address;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+use thiserror::Error;
+
+#[cfg(test)]
+mod tests;
+
+#[derive(Debug, Error)]
+pub enum FixtureError {
+    #[error("No recorded response for {0}")]
+    NoRecordedResponse(String),
+    #[error("Recorded response doesn't match the request: {0}")]
+    UnexpectedResponse(String),
+    #[error("Recorded ledger error: {0}")]
+    Recorded(String),
+    #[error("Fixture file: {0:?}")]
+    File(#[from] std::io::Error),
+    #[error("Fixture serde: {0:?}")]
+    Serde(#[from] serde_json::Error),
+    #[error("Mutex error: {0:?}")]
+    Mutex(String),
+}
+
+/// A `Ledger` call. Txs aren't part of the request because their bytes change whenever the
+/// builder does, so `CalculateExUnits` and `SubmitTransaction` are replayed in order.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "call", rename_all = "snake_case")]
+pub enum LedgerRequest {
+    LastBlockTimeSecs,
+    GetUtxosForAddr {
+        address: String,
+        count: usize,
+    },
+    GetAllUtxosForAddr {
+        address: String,
+    },
+    CalculateExUnits,
+    TxConfirmations {
+        tx_id: String,
+    },
+    UtxoById {
+        tx_hash: String,
+        index: u64,
+    },
+    AddressesWithAsset {
+        policy_id: String,
+        asset_name: Option<String>,
+    },
+    ProtocolParameters,
+    SubmitTransaction,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LedgerResponse {
+    BlockTime(i64),
+    Utxos(Vec<RecordedUTxO>),
+    ExUnits(HashMap<u64, ExecutionCost>),
+    Confirmations(Option<u64>),
+    Utxo(Option<(String, RecordedUTxO)>),
+    Addresses(Vec<String>),
+    ProtocolParameters(ProtocolParameters),
+    TxId(String),
+}
+
+/// Errors are only kept as text, apart from the ones `CMLLedgerCLient` falls back on
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordedError {
+    TxLookupUnsupported,
+    AssetLookupUnsupported,
+    Other(String),
+}
+
+impl From<&CMLLCError> for RecordedError {
+    fn from(error: &CMLLCError) -> Self {
+        match error {
+            CMLLCError::TxLookupUnsupported => RecordedError::TxLookupUnsupported,
+            CMLLCError::AssetLookupUnsupported => RecordedError::AssetLookupUnsupported,
+            other => RecordedError::Other(other.to_string()),
+        }
+    }
+}
+
+impl From<RecordedError> for CMLLCError {
+    fn from(error: RecordedError) -> Self {
+        match error {
+            RecordedError::TxLookupUnsupported => CMLLCError::TxLookupUnsupported,
+            RecordedError::AssetLookupUnsupported => CMLLCError::AssetLookupUnsupported,
+            RecordedError::Other(msg) => FixtureError::Recorded(msg).into(),
+        }
+    }
+}
+
+/// `UTxO` with its value and datum as CBOR hex
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedUTxO {
+    pub tx_hash: String,
+    pub output_index: u64,
+    pub amount: String,
+    pub datum: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub datum_encoding: Option<DatumEncoding>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reference_script_hash: Option<String>,
+}
+
+impl From<&UTxO> for RecordedUTxO {
+    fn from(utxo: &UTxO) -> Self {
+        RecordedUTxO {
+            tx_hash: hex::encode(utxo.tx_hash().to_bytes()),
+            output_index: utxo.output_index().into(),
+            amount: hex::encode(utxo.amount().to_bytes()),
+            datum: utxo
+                .datum()
+                .as_ref()
+                .map(|datum| hex::encode(datum.to_bytes())),
+            datum_encoding: utxo.datum_encoding(),
+            reference_script_hash: utxo.reference_script_hash().map(hex::encode),
+        }
+    }
+}
+
+impl TryFrom<&RecordedUTxO> for UTxO {
+    type Error = CMLLCError;
+
+    fn try_from(recorded: &RecordedUTxO) -> Result<Self> {
+        let tx_hash = TransactionHash::from_hex(&recorded.tx_hash)
+            .map_err(|e| CMLLCError::JsError(e.to_string()))?;
+        let amount = CMLValue::from_bytes(hex::decode(&recorded.amount)?)
+            .map_err(|e| CMLLCError::Deserialize(e.to_string()))?;
+        let datum = recorded
+            .datum
+            .as_ref()
+            .map(|datum| {
+                PlutusData::from_bytes(hex::decode(datum)?)
+                    .map_err(|e| CMLLCError::Deserialize(e.to_string()))
+            })
+            .transpose()?;
+        let mut utxo = UTxO::new(tx_hash, BigNum::from(recorded.output_index), amount, datum);
+        if let Some(datum_encoding) = recorded.datum_encoding {
+            utxo = utxo.with_datum_encoding(datum_encoding);
+        }
+        if let Some(script_hash) = &recorded.reference_script_hash {
+            utxo = utxo.with_reference_script_hash(hex::decode(script_hash)?);
+        }
+        Ok(utxo)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedCall {
+    pub request: LedgerRequest,
+    pub response: std::result::Result<LedgerResponse, RecordedError>,
+}
+
+/// Every call made to a `Ledger`, in order
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LedgerFixture {
+    pub calls: Vec<RecordedCall>,
+}
+
+impl LedgerFixture {
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = std::fs::read_to_string(path).map_err(FixtureError::from)?;
+        let fixture = serde_json::from_str(&json).map_err(FixtureError::from)?;
+        Ok(fixture)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(FixtureError::from)?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(FixtureError::from)?;
+        std::fs::write(path, json).map_err(FixtureError::from)?;
+        Ok(())
+    }
+}
+
+fn cml_address_string(addr: &CMLAddress) -> Result<String> {
+    addr.to_bech32(None)
+        .map_err(|e| CMLLCError::JsError(e.to_string()))
+}
+
+fn recorded_utxos(utxos: &[UTxO]) -> LedgerResponse {
+    LedgerResponse::Utxos(utxos.iter().map(RecordedUTxO::from).collect())
+}
+
+/// Passes calls through to `inner` and writes each one, with its response, to the fixture at
+/// `path` for `ReplayLedger` to serve later
+pub struct RecordingLedger<L> {
+    inner: L,
+    path: PathBuf,
+    fixture: Mutex<LedgerFixture>,
+}
+
+impl<L: Ledger> RecordingLedger<L> {
+    pub fn new(inner: L, path: PathBuf) -> Self {
+        RecordingLedger {
+            inner,
+            path,
+            fixture: Mutex::new(LedgerFixture::default()),
+        }
+    }
+
+    fn record<T>(
+        &self,
+        request: LedgerRequest,
+        res: &Result<T>,
+        to_response: impl FnOnce(&T) -> Result<LedgerResponse>,
+    ) -> Result<()> {
+        let response = match res {
+            Ok(value) => Ok(to_response(value)?),
+            Err(e) => Err(RecordedError::from(e)),
+        };
+        let fixture = {
+            let mut fixture = self
+                .fixture
+                .lock()
+                .map_err(|e| FixtureError::Mutex(format!("{e:?}")))?;
+            fixture.calls.push(RecordedCall { request, response });
+            fixture.clone()
+        };
+        fixture.save(&self.path)
+    }
+}
+
+#[async_trait]
+impl<L: Ledger + Send + Sync> Ledger for RecordingLedger<L> {
+    async fn last_block_time_secs(&self) -> Result<i64> {
+        let res = self.inner.last_block_time_secs().await;
+        self.record(LedgerRequest::LastBlockTimeSecs, &res, |time| {
+            Ok(LedgerResponse::BlockTime(*time))
+        })?;
+        res
+    }
+
+    async fn get_utxos_for_addr(&self, addr: &CMLAddress, count: usize) -> Result<Vec<UTxO>> {
+        let request = LedgerRequest::GetUtxosForAddr {
+            address: cml_address_string(addr)?,
+            count,
+        };
+        let res = self.inner.get_utxos_for_addr(addr, count).await;
+        self.record(request, &res, |utxos| Ok(recorded_utxos(utxos)))?;
+        res
+    }
+
+    async fn get_all_utxos_for_addr(&self, addr: &CMLAddress) -> Result<Vec<UTxO>> {
+        let request = LedgerRequest::GetAllUtxosForAddr {
+            address: cml_address_string(addr)?,
+        };
+        let res = self.inner.get_all_utxos_for_addr(addr).await;
+        self.record(request, &res, |utxos| Ok(recorded_utxos(utxos)))?;
+        res
+    }
+
+    async fn calculate_ex_units(&self, tx: &CMLTransaction) -> Result<HashMap<u64, ExecutionCost>> {
+        let res = self.inner.calculate_ex_units(tx).await;
+        self.record(LedgerRequest::CalculateExUnits, &res, |costs| {
+            Ok(LedgerResponse::ExUnits(costs.clone()))
+        })?;
+        res
+    }
+
+    async fn tx_confirmations(&self, tx_id: &str) -> Result<Option<u64>> {
+        let request = LedgerRequest::TxConfirmations {
+            tx_id: tx_id.to_string(),
+        };
+        let res = self.inner.tx_confirmations(tx_id).await;
+        self.record(request, &res, |confirmations| {
+            Ok(LedgerResponse::Confirmations(*confirmations))
+        })?;
+        res
+    }
+
+    async fn utxo_by_id(&self, tx_hash: &str, index: u64) -> Result<Option<(Address, UTxO)>> {
+        let request = LedgerRequest::UtxoById {
+            tx_hash: tx_hash.to_string(),
+            index,
+        };
+        let res = self.inner.utxo_by_id(tx_hash, index).await;
+        self.record(request, &res, |utxo| {
+            let utxo = utxo
+                .as_ref()
+                .map(|(owner, utxo)| -> Result<_> {
+                    Ok((owner.to_bech32()?, RecordedUTxO::from(utxo)))
+                })
+                .transpose()?;
+            Ok(LedgerResponse::Utxo(utxo))
+        })?;
+        res
+    }
+
+    async fn addresses_with_asset(
+        &self,
+        policy_id: &str,
+        asset_name: Option<&str>,
+    ) -> Result<Vec<Address>> {
+        let request = LedgerRequest::AddressesWithAsset {
+            policy_id: policy_id.to_string(),
+            asset_name: asset_name.map(str::to_string),
+        };
+        let res = self.inner.addresses_with_asset(policy_id, asset_name).await;
+        self.record(request, &res, |addresses| {
+            let addresses = addresses
+                .iter()
+                .map(|address| address.to_bech32())
+                .collect::<std::result::Result<_, _>>()?;
+            Ok(LedgerResponse::Addresses(addresses))
+        })?;
+        res
+    }
+
+    async fn protocol_parameters(&self) -> Result<ProtocolParameters> {
+        let res = self.inner.protocol_parameters().await;
+        self.record(LedgerRequest::ProtocolParameters, &res, |params| {
+            Ok(LedgerResponse::ProtocolParameters(params.clone()))
+        })?;
+        res
+    }
+
+    async fn submit_transaction(&self, tx: &CMLTransaction) -> Result<String> {
+        let res = self.inner.submit_transaction(tx).await;
+        self.record(LedgerRequest::SubmitTransaction, &res, |tx_id| {
+            Ok(LedgerResponse::TxId(tx_id.clone()))
+        })?;
+        res
+    }
+}
+
+/// Serves a `LedgerFixture` offline. Each request gets the first unused response recorded for
+/// it, and keeps getting the last one once they're used up.
+pub struct ReplayLedger {
+    calls: Vec<RecordedCall>,
+    used: Mutex<Vec<bool>>,
+}
+
+impl ReplayLedger {
+    pub fn new(fixture: LedgerFixture) -> Self {
+        let used = Mutex::new(vec![false; fixture.calls.len()]);
+        ReplayLedger {
+            calls: fixture.calls,
+            used,
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let fixture = LedgerFixture::load(path)?;
+        Ok(Self::new(fixture))
+    }
+
+    fn replay(&self, request: LedgerRequest) -> Result<LedgerResponse> {
+        let mut used = self
+            .used
+            .lock()
+            .map_err(|e| FixtureError::Mutex(format!("{e:?}")))?;
+        let matching: Vec<usize> = self
+            .calls
+            .iter()
+            .enumerate()
+            .filter(|(_, call)| call.request == request)
+            .map(|(index, _)| index)
+            .collect();
+        let index = matching
+            .iter()
+            .find(|index| !used[**index])
+            .or_else(|| matching.last())
+            .copied()
+            .ok_or_else(|| FixtureError::NoRecordedResponse(format!("{request:?}")))?;
+        used[index] = true;
+        let response = self.calls[index].response.clone()?;
+        Ok(response)
+    }
+}
+
+fn unexpected(response: LedgerResponse) -> CMLLCError {
+    FixtureError::UnexpectedResponse(format!("{response:?}")).into()
+}
+
+fn replayed_utxos(utxos: &[RecordedUTxO]) -> Result<Vec<UTxO>> {
+    utxos.iter().map(UTxO::try_from).collect()
+}
+
+#[async_trait]
+impl Ledger for ReplayLedger {
+    async fn last_block_time_secs(&self) -> Result<i64> {
+        match self.replay(LedgerRequest::LastBlockTimeSecs)? {
+            LedgerResponse::BlockTime(time) => Ok(time),
+            other => Err(unexpected(other)),
+        }
+    }
+
+    async fn get_utxos_for_addr(&self, addr: &CMLAddress, count: usize) -> Result<Vec<UTxO>> {
+        let request = LedgerRequest::GetUtxosForAddr {
+            address: cml_address_string(addr)?,
+            count,
+        };
+        match self.replay(request)? {
+            LedgerResponse::Utxos(utxos) => replayed_utxos(&utxos),
+            other => Err(unexpected(other)),
+        }
+    }
+
+    async fn get_all_utxos_for_addr(&self, addr: &CMLAddress) -> Result<Vec<UTxO>> {
+        let request = LedgerRequest::GetAllUtxosForAddr {
+            address: cml_address_string(addr)?,
+        };
+        match self.replay(request)? {
+            LedgerResponse::Utxos(utxos) => replayed_utxos(&utxos),
+            other => Err(unexpected(other)),
+        }
+    }
+
+    async fn calculate_ex_units(
+        &self,
+        _tx: &CMLTransaction,
+    ) -> Result<HashMap<u64, ExecutionCost>> {
+        match self.replay(LedgerRequest::CalculateExUnits)? {
+            LedgerResponse::ExUnits(costs) => Ok(costs),
+            other => Err(unexpected(other)),
+        }
+    }
+
+    async fn tx_confirmations(&self, tx_id: &str) -> Result<Option<u64>> {
+        let request = LedgerRequest::TxConfirmations {
+            tx_id: tx_id.to_string(),
+        };
+        match self.replay(request)? {
+            LedgerResponse::Confirmations(confirmations) => Ok(confirmations),
+            other => Err(unexpected(other)),
+        }
+    }
+
+    async fn utxo_by_id(&self, tx_hash: &str, index: u64) -> Result<Option<(Address, UTxO)>> {
+        let request = LedgerRequest::UtxoById {
+            tx_hash: tx_hash.to_string(),
+            index,
+        };
+        match self.replay(request)? {
+            LedgerResponse::Utxo(utxo) => utxo
+                .map(|(owner, utxo)| -> Result<_> {
+                    Ok((Address::from_bech32(&owner)?, UTxO::try_from(&utxo)?))
+                })
+                .transpose(),
+            other => Err(unexpected(other)),
+        }
+    }
+
+    async fn addresses_with_asset(
+        &self,
+        policy_id: &str,
+        asset_name: Option<&str>,
+    ) -> Result<Vec<Address>> {
+        let request = LedgerRequest::AddressesWithAsset {
+            policy_id: policy_id.to_string(),
+            asset_name: asset_name.map(str::to_string),
+        };
+        match self.replay(request)? {
+            LedgerResponse::Addresses(addresses) => addresses
+                .iter()
+                .map(|address| -> Result<_> { Ok(Address::from_bech32(address)?) })
+                .collect(),
+            other => Err(unexpected(other)),
+        }
+    }
+
+    async fn protocol_parameters(&self) -> Result<ProtocolParameters> {
+        match self.replay(LedgerRequest::ProtocolParameters)? {
+            LedgerResponse::ProtocolParameters(params) => Ok(params),
+            other => Err(unexpected(other)),
+        }
+    }
+
+    async fn submit_transaction(&self, _tx: &CMLTransaction) -> Result<String> {
+        match self.replay(LedgerRequest::SubmitTransaction)? {
+            LedgerResponse::TxId(tx_id) => Ok(tx_id),
+            other => Err(unexpected(other)),
+        }
+    }
+}
@@ -0,0 +1,170 @@
+#![allow(non_snake_case)]
+
+use super::*;
+use crate::trireme_ledger_client::cml_client::test_utils::{alice, ALICE, TX_HASH};
+
+/// Answers every call the same way, so recordings can be checked against it
+struct FakeLedger;
+
+fn fake_utxo() -> UTxO {
+    UTxO::new(
+        TransactionHash::from_hex(TX_HASH).unwrap(),
+        BigNum::from(1),
+        CMLValue::new(&BigNum::from(10_000_000)),
+        Some(PlutusData::from_bytes(hex::decode("d87980").unwrap()).unwrap()),
+    )
+}
+
+#[async_trait]
+impl Ledger for FakeLedger {
+    async fn last_block_time_secs(&self) -> Result<i64> {
+        Ok(1_666_000_000)
+    }
+
+    async fn get_utxos_for_addr(&self, _addr: &CMLAddress, _count: usize) -> Result<Vec<UTxO>> {
+        Ok(vec![fake_utxo()])
+    }
+
+    async fn get_all_utxos_for_addr(&self, _addr: &CMLAddress) -> Result<Vec<UTxO>> {
+        Ok(vec![fake_utxo()])
+    }
+
+    async fn calculate_ex_units(
+        &self,
+        _tx: &CMLTransaction,
+    ) -> Result<HashMap<u64, ExecutionCost>> {
+        Ok(HashMap::from([(0, ExecutionCost::new_spend(1_000, 2_000))]))
+    }
+
+    async fn tx_confirmations(&self, _tx_id: &str) -> Result<Option<u64>> {
+        Err(CMLLCError::TxLookupUnsupported)
+    }
+
+    async fn utxo_by_id(&self, _tx_hash: &str, _index: u64) -> Result<Option<(Address, UTxO)>> {
+        Ok(Some((Address::from_bech32(ALICE).unwrap(), fake_utxo())))
+    }
+
+    async fn addresses_with_asset(
+        &self,
+        _policy_id: &str,
+        _asset_name: Option<&str>,
+    ) -> Result<Vec<Address>> {
+        Ok(vec![Address::from_bech32(ALICE).unwrap()])
+    }
+
+    async fn protocol_parameters(&self) -> Result<ProtocolParameters> {
+        Err(CMLLCError::OgmiosResponse("offline".to_string()))
+    }
+
+    async fn submit_transaction(&self, _tx: &CMLTransaction) -> Result<String> {
+        Ok(TX_HASH.to_string())
+    }
+}
+
+fn recorded_utxos_of(utxos: &[UTxO]) -> Vec<RecordedUTxO> {
+    utxos.iter().map(RecordedUTxO::from).collect()
+}
+
+#[tokio::test]
+async fn record_then_replay__serves_recorded_responses() {
+    // given
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("fixtures").join("ledger.json");
+    let recording = RecordingLedger::new(FakeLedger, path.clone());
+    let recorded_time = recording.last_block_time_secs().await.unwrap();
+    let recorded_utxos = recording.get_all_utxos_for_addr(&alice()).await.unwrap();
+    let recorded_owner = recording.utxo_by_id(TX_HASH, 1).await.unwrap().unwrap().0;
+
+    // when
+    let replay = ReplayLedger::load(&path).unwrap();
+    let time = replay.last_block_time_secs().await.unwrap();
+    let utxos = replay.get_all_utxos_for_addr(&alice()).await.unwrap();
+    let owner = replay.utxo_by_id(TX_HASH, 1).await.unwrap().unwrap().0;
+
+    // then
+    assert_eq!(time, recorded_time);
+    assert_eq!(
+        recorded_utxos_of(&utxos),
+        recorded_utxos_of(&recorded_utxos)
+    );
+    assert_eq!(owner, recorded_owner);
+}
+
+#[tokio::test]
+async fn record_then_replay__keeps_errors_the_client_falls_back_on() {
+    // given
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("ledger.json");
+    let recording = RecordingLedger::new(FakeLedger, path.clone());
+    let _ = recording.tx_confirmations(TX_HASH).await;
+    let _ = recording.protocol_parameters().await;
+
+    // when
+    let replay = ReplayLedger::load(&path).unwrap();
+    let confirmations = replay.tx_confirmations(TX_HASH).await;
+    let params = replay.protocol_parameters().await;
+
+    // then
+    assert!(matches!(
+        confirmations,
+        Err(CMLLCError::TxLookupUnsupported)
+    ));
+    assert!(matches!(
+        params,
+        Err(CMLLCError::Fixture(FixtureError::Recorded(_)))
+    ));
+}
+
+#[tokio::test]
+async fn replay__serves_responses_in_order_then_repeats_the_last() {
+    // given
+    let request = LedgerRequest::TxConfirmations {
+        tx_id: TX_HASH.to_string(),
+    };
+    let fixture = LedgerFixture {
+        calls: vec![
+            RecordedCall {
+                request: request.clone(),
+                response: Ok(LedgerResponse::Confirmations(None)),
+            },
+            RecordedCall {
+                request,
+                response: Ok(LedgerResponse::Confirmations(Some(1))),
+            },
+        ],
+    };
+    let replay = ReplayLedger::new(fixture);
+
+    // when
+    let first = replay.tx_confirmations(TX_HASH).await.unwrap();
+    let second = replay.tx_confirmations(TX_HASH).await.unwrap();
+    let third = replay.tx_confirmations(TX_HASH).await.unwrap();
+
+    // then
+    assert_eq!(first, None);
+    assert_eq!(second, Some(1));
+    assert_eq!(third, Some(1));
+}
+
+#[tokio::test]
+async fn replay__errors_for_unrecorded_request() {
+    // given
+    let fixture = LedgerFixture {
+        calls: vec![RecordedCall {
+            request: LedgerRequest::GetAllUtxosForAddr {
+                address: ALICE.to_string(),
+            },
+            response: Ok(LedgerResponse::Utxos(vec![])),
+        }],
+    };
+    let replay = ReplayLedger::new(fixture);
+
+    // when
+    let res = replay.get_utxos_for_addr(&alice(), 10).await;
+
+    // then
+    assert!(matches!(
+        res,
+        Err(CMLLCError::Fixture(FixtureError::NoRecordedResponse(_)))
+    ));
+}
@@ -1,53 +1,93 @@
 use super::*;
 use crate::trireme_ledger_client::cml_client::{
-    blockfrost_ledger::BlockFrostLedger, key_manager::KeyManager,
+    blockfrost_ledger::BlockFrostLedger,
+    key_manager::KeyManager,
+    recorded_ledger::{
+        LedgerFixture, LedgerRequest, LedgerResponse, RecordedCall, RecordedError, RecordedUTxO,
+        RecordingLedger, ReplayLedger,
+    },
+    test_utils::TX_HASH,
 };
 use crate::trireme_ledger_client::Network;
-use crate::PolicyId;
+use crate::{values::Values, PolicyId};
 use blockfrost_http_client::{load_key_from_file, PREPROD_NETWORK_URL};
-use cardano_multiplatform_lib::address::BaseAddress;
+use cardano_multiplatform_lib::{address::BaseAddress, AssetName};
 use std::time::Duration;
 use test_helpers::{
-    always_succeeds_script_address, claim_always_succeeds_datum_tx, lock_at_always_succeeds_tx,
-    output_from_tx, transfer_tx,
+    always_succeeds_script_address, claim_always_succeeds_datum_tx,
+    claim_always_succeeds_v2_datum_tx, lock_at_always_succeeds_tx, output_from_tx, transfer_tx,
 };
-use tokio::time::sleep;
 
 mod test_helpers;
 
-// Must include a TOML file at your project root with the field:
+// Tests replay the ledger calls in `tests/fixtures/<test name>.json`, signing with the
+// testnet-only wallet in `tests/fixtures/wallet.toml`. The committed fixtures are synthetic:
+// they follow the recorded format, but their UTxOs, hashes and tx ids are made up rather than
+// taken from preprod. To record real ones, fund that wallet, set `NAUMACHIA_RECORD_FIXTURES`
+// and include a TOML file at your project root with the field:
 //   project_id = <INSERT API KEY HERE>
 const CONFIG_PATH: &str = ".blockfrost.toml";
+const RECORD_FIXTURES_VAR: &str = "NAUMACHIA_RECORD_FIXTURES";
 
-async fn get_test_client<Datum: PlutusDataInterop, Redeemer: PlutusDataInterop>() -> (
-    CMLLedgerCLient<BlockFrostLedger, KeyManager, Datum, Redeemer>,
-    BaseAddress,
-) {
-    let api_key = load_key_from_file(CONFIG_PATH).unwrap();
-    let ledger = BlockFrostLedger::new(PREPROD_NETWORK_URL, &api_key);
-    let keys = KeyManager::new(CONFIG_PATH.to_string(), Network::Preprod.into());
+fn fixtures_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("src/trireme_ledger_client/cml_client/tests/fixtures")
+}
+
+async fn get_test_client<Datum, Redeemer>(
+    fixture: &str,
+) -> (Box<dyn LedgerClient<Datum, Redeemer>>, BaseAddress)
+where
+    Datum: PlutusDataInterop + Send + Sync + Debug + Clone + 'static,
+    Redeemer: PlutusDataInterop + Send + Sync + 'static,
+{
+    let fixture_path = fixtures_dir().join(format!("{fixture}.json"));
+    let keys = test_keys();
     let base_addr = keys.base_addr().await.unwrap();
-    (
-        CMLLedgerCLient::new(ledger, keys, Network::Preprod.into()),
-        base_addr,
+    let network_settings = NetworkSettings::try_from(Network::Preprod).unwrap();
+    let client: Box<dyn LedgerClient<Datum, Redeemer>> =
+        if std::env::var(RECORD_FIXTURES_VAR).is_ok() {
+            let api_key = load_key_from_file(CONFIG_PATH).unwrap();
+            let ledger = RecordingLedger::new(
+                BlockFrostLedger::new(PREPROD_NETWORK_URL, &api_key),
+                fixture_path,
+            );
+            Box::new(CMLLedgerCLient::new(ledger, keys, network_settings))
+        } else {
+            let ledger = ReplayLedger::load(&fixture_path).unwrap();
+            Box::new(CMLLedgerCLient::new(ledger, keys, network_settings))
+        };
+    (client, base_addr)
+}
+
+fn test_keys() -> KeyManager {
+    let wallet_path = fixtures_dir().join("wallet.toml");
+    KeyManager::new(
+        wallet_path.to_string_lossy().to_string(),
+        Network::Preprod.into(),
     )
 }
 
-#[ignore]
+/// Client over a ledger that only answers `calls`
+fn replay_client(calls: Vec<RecordedCall>) -> CMLLedgerCLient<ReplayLedger, KeyManager, (), ()> {
+    let ledger = ReplayLedger::new(LedgerFixture { calls });
+    let network_settings = NetworkSettings::try_from(Network::Preprod).unwrap();
+    CMLLedgerCLient::new(ledger, test_keys(), network_settings)
+}
+
 #[tokio::test]
 async fn get_all_my_utxos() {
-    let (client, base_addr) = get_test_client::<(), ()>().await;
+    let (client, base_addr) = get_test_client::<(), ()>("get_all_my_utxos").await;
     let addr_string = base_addr.to_address().to_bech32(None).unwrap();
     let my_addr = Address::from_bech32(&addr_string).unwrap();
     let my_utxos = client.all_outputs_at_address(&my_addr).await.unwrap();
 
-    dbg!(my_utxos);
+    assert_eq!(my_utxos.len(), 3);
 }
 
-#[ignore]
 #[tokio::test]
 async fn get_my_lovelace_balance() {
-    let (client, base_addr) = get_test_client::<(), ()>().await;
+    let (client, base_addr) = get_test_client::<(), ()>("get_my_lovelace_balance").await;
     let addr_string = base_addr.to_address().to_bech32(None).unwrap();
     let my_addr = Address::from_bech32(&addr_string).unwrap();
     let my_balance = client
@@ -55,66 +95,309 @@ async fn get_my_lovelace_balance() {
         .await
         .unwrap();
 
-    println!();
-    println!("ADA: {my_balance:?}");
+    assert!(my_balance > 0);
 }
 
-#[ignore]
 #[tokio::test]
 async fn get_my_native_token_balance() {
-    let (client, base_addr) = get_test_client::<(), ()>().await;
+    let (client, base_addr) = get_test_client::<(), ()>("get_my_native_token_balance").await;
     let addr_string = base_addr.to_address().to_bech32(None).unwrap();
     let my_addr = Address::from_bech32(&addr_string).unwrap();
     let policy = PolicyId::native_token(
         "57fca08abbaddee36da742a839f7d83a7e1d2419f1507fcbf3916522",
-        &None,
+        &Some("TEST".to_string()),
     );
     let my_balance = client.balance_at_address(&my_addr, &policy).await.unwrap();
 
-    println!();
-    println!("Native Token {policy:?}: {my_balance:?}");
+    assert_eq!(my_balance, 1);
 }
 
-#[ignore]
 #[tokio::test]
 async fn transfer_self_tx() {
-    let (client, base_addr) = get_test_client::<(), ()>().await;
+    let (client, base_addr) = get_test_client::<(), ()>("transfer_self_tx").await;
+    let addr_string = base_addr.to_address().to_bech32(None).unwrap();
+    let my_addr = Address::from_bech32(&addr_string).unwrap();
+    let transfer_amount = 6_000_000;
+    let unbuilt_tx = transfer_tx(my_addr, transfer_amount);
+    let tx_id = client.issue(unbuilt_tx).await.unwrap();
+
+    assert_eq!(
+        tx_id.as_str(),
+        "3e8b5d0a7c2f9e4b1d6a3c8f5e0b7d2a9c4f1e6b3d8a5c0f7e2b9d4a1c6f3e8b"
+    );
+}
+
+#[tokio::test]
+async fn build_unsigned_transfer_tx() {
+    let (client, base_addr) = get_test_client::<(), ()>("build_unsigned_transfer_tx").await;
     let addr_string = base_addr.to_address().to_bech32(None).unwrap();
     let my_addr = Address::from_bech32(&addr_string).unwrap();
     let transfer_amount = 6_000_000;
     let unbuilt_tx = transfer_tx(my_addr, transfer_amount);
-    let res = client.issue(unbuilt_tx).await.unwrap();
-    println!("{res:?}");
+    let unsigned = client.build_unsigned(unbuilt_tx).await.unwrap();
+    let bytes = hex::decode(unsigned.cbor_hex()).unwrap();
+    let tx = CMLTransaction::from_bytes(bytes).unwrap();
+    assert!(tx.witness_set().vkeys().is_none());
+    assert!(unsigned.summary().fee > 0);
 }
 
-#[ignore]
 #[tokio::test]
 async fn create_datum_wait_and_then_redeem_same_datum() {
     let lock_amount = 6_000_000;
     let unbuilt_tx = lock_at_always_succeeds_tx(lock_amount);
-    let (client, _) = get_test_client::<(), ()>().await;
+    let (client, _) =
+        get_test_client::<(), ()>("create_datum_wait_and_then_redeem_same_datum").await;
     let tx_id = client.issue(unbuilt_tx).await.unwrap();
-    println!("{:?}", &tx_id);
     let script_addr = always_succeeds_script_address(Network::Preprod.into());
 
-    let mut tries = 30;
-    println!("Attempting to find and spend datum from {:?}", &tx_id);
-    loop {
-        println!("...");
-        sleep(Duration::from_secs(5)).await;
-        let script_outputs = client.all_outputs_at_address(&script_addr).await.unwrap();
-        if let Some(my_output) = output_from_tx::<()>(&tx_id.as_str(), &script_outputs) {
-            println!("Found UTxO");
-            println!("Issuing redeeming tx");
-            let unbuilt_tx = claim_always_succeeds_datum_tx(my_output);
-            let res = client.issue(unbuilt_tx).await.unwrap();
-            println!("{res:?}");
-            return;
-        }
-        tries -= 1;
-        if tries < 0 {
-            println!("Failed to find UTxO for {tx_id:?}");
-            return;
-        }
+    client
+        .await_tx(&tx_id, 1, Duration::from_secs(150))
+        .await
+        .unwrap();
+    let script_outputs = client.all_outputs_at_address(&script_addr).await.unwrap();
+    let my_output = output_from_tx::<()>(&tx_id.as_str(), &script_outputs).unwrap();
+    let unbuilt_tx = claim_always_succeeds_datum_tx(my_output);
+    let redeem_tx_id = client.issue(unbuilt_tx).await.unwrap();
+    assert_ne!(redeem_tx_id, tx_id);
+}
+
+#[tokio::test]
+async fn build_v1_tx_with_reference_input_errors() {
+    let client = replay_client(vec![]);
+    let script_address = always_succeeds_script_address(Network::Preprod.into());
+    let reference_input = Output::new_wallet(vec![1; 32], 0, script_address, Values::default());
+    let mut unbuilt_tx = lock_at_always_succeeds_tx(6_000_000);
+    unbuilt_tx.reference_inputs.push(reference_input);
+
+    let error = client.build_unsigned(unbuilt_tx).await.unwrap_err();
+
+    match error {
+        LedgerClientError::FailedToIssueTx(inner) => assert!(matches!(
+            inner.downcast_ref::<CMLLCError>(),
+            Some(CMLLCError::ReferenceInputsInV1)
+        )),
+        other => panic!("expected FailedToIssueTx, got {other:?}"),
+    }
+}
+
+fn tx_lookup_unsupported() -> RecordedCall {
+    RecordedCall {
+        request: LedgerRequest::TxConfirmations {
+            tx_id: TX_HASH.to_string(),
+        },
+        response: Err(RecordedError::TxLookupUnsupported),
+    }
+}
+
+#[tokio::test]
+async fn tx_confirmations_errors_without_tx_lookup() {
+    let client = replay_client(vec![tx_lookup_unsupported()]);
+
+    let res = client.tx_confirmations(&TxId::new(TX_HASH)).await;
+
+    assert!(matches!(res, Err(LedgerClientError::Unsupported(_))));
+}
+
+#[tokio::test]
+async fn await_one_confirmation_without_tx_lookup_checks_wallet_utxos() {
+    let my_address = test_keys().base_addr().await.unwrap().to_address();
+    let wallet_utxos = RecordedCall {
+        request: LedgerRequest::GetAllUtxosForAddr {
+            address: my_address.to_bech32(None).unwrap(),
+        },
+        response: Ok(LedgerResponse::Utxos(vec![RecordedUTxO {
+            tx_hash: TX_HASH.to_string(),
+            output_index: 0,
+            amount: hex::encode(CMLValue::new(&BigNum::from(2_000_000)).to_bytes()),
+            datum: None,
+            datum_encoding: None,
+            reference_script_hash: None,
+        }])),
+    };
+    let client = replay_client(vec![tx_lookup_unsupported(), wallet_utxos]);
+
+    let res = client
+        .await_tx(&TxId::new(TX_HASH), 1, Duration::from_secs(1))
+        .await;
+
+    assert!(res.is_ok());
+}
+
+#[tokio::test]
+async fn await_more_confirmations_without_tx_lookup_errors() {
+    let client = replay_client(vec![tx_lookup_unsupported()]);
+
+    let res = client
+        .await_tx(&TxId::new(TX_HASH), 2, Duration::from_secs(1))
+        .await;
+
+    assert!(matches!(res, Err(LedgerClientError::Unsupported(_))));
+}
+
+#[test]
+fn mint_assets_sum_duplicate_asset_names() {
+    let assets = vec![
+        (Some("token".to_string()), 5),
+        (None, 1),
+        (Some("token".to_string()), -2),
+    ];
+
+    let mint_assets = cml_mint_assets(&assets).unwrap();
+
+    assert_eq!(mint_assets.len(), 2);
+    let token = AssetName::new(b"token".to_vec()).unwrap();
+    assert_eq!(mint_assets.get(&token).unwrap().to_str(), "3");
+}
+
+#[test]
+fn mint_assets_overflow_errors() {
+    let assets = vec![(None, i64::MAX), (None, 1)];
+
+    let res = cml_mint_assets(&assets);
+
+    assert!(matches!(res, Err(LedgerClientError::FailedToIssueTx(_))));
+}
+
+#[test]
+fn validity_slot_within_stable_horizon() {
+    let network_settings = NetworkSettings::try_from(Network::Preprod).unwrap();
+    let tip_posix = 1693686777;
+
+    let lower_bound = ValidityBound::PosixMillis((tip_posix - 60) * 1000);
+    let upper_bound = ValidityBound::Slot(38007177);
+
+    let lower = validity_slot(&network_settings, tip_posix, lower_bound, "lower").unwrap();
+    let upper = validity_slot(&network_settings, tip_posix, upper_bound, "upper").unwrap();
+
+    assert_eq!(lower, 38003517);
+    assert_eq!(upper, 38007177);
+}
+
+#[test]
+fn validity_slot_past_stable_horizon_errors() {
+    let network_settings = NetworkSettings::try_from(Network::Preprod).unwrap();
+    let tip_posix = 1693686777;
+    let past_horizon = 38003577 + network_settings.stability_window() + 1;
+
+    let res = validity_slot(
+        &network_settings,
+        tip_posix,
+        ValidityBound::Slot(past_horizon),
+        "upper",
+    );
+
+    assert!(matches!(res, Err(LedgerClientError::ValidityRange(_))));
+}
+
+#[test]
+fn validity_slot_before_network_start_errors() {
+    let network_settings = NetworkSettings::try_from(Network::Preview).unwrap();
+    let tip_posix = 1693686823;
+
+    let bound = ValidityBound::PosixMillis(1666655999999);
+
+    let res = validity_slot(&network_settings, tip_posix, bound, "lower");
+
+    assert!(matches!(res, Err(LedgerClientError::ValidityRange(_))));
+}
+
+#[tokio::test]
+async fn protocol_parameters_survive_unwritable_cache() {
+    let params = ProtocolParameters::load(
+        &PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("src/trireme_ledger_client/cml_client/protocol_parameters/vasil.json"),
+    )
+    .unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let not_a_dir = dir.path().join("not_a_dir");
+    std::fs::write(&not_a_dir, "").unwrap();
+    let client = replay_client(vec![RecordedCall {
+        request: LedgerRequest::ProtocolParameters,
+        response: Ok(LedgerResponse::ProtocolParameters(params.clone())),
+    }])
+    .with_protocol_parameters_cache(not_a_dir.join("protocol_parameters.json"));
+
+    let fetched = client.protocol_parameters().await.unwrap();
+
+    assert_eq!(fetched, params);
+}
+
+#[tokio::test]
+async fn spending_inline_datum_output_leaves_datum_out_of_witnesses() {
+    let my_address = test_keys().base_addr().await.unwrap().to_address();
+    let params = ProtocolParameters::load(
+        &PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("src/trireme_ledger_client/cml_client/protocol_parameters/vasil.json"),
+    )
+    .unwrap();
+    let wallet_utxos = RecordedCall {
+        request: LedgerRequest::GetAllUtxosForAddr {
+            address: my_address.to_bech32(None).unwrap(),
+        },
+        response: Ok(LedgerResponse::Utxos(vec![RecordedUTxO {
+            tx_hash: TX_HASH.to_string(),
+            output_index: 1,
+            amount: hex::encode(CMLValue::new(&BigNum::from(100_000_000)).to_bytes()),
+            datum: None,
+            datum_encoding: None,
+            reference_script_hash: None,
+        }])),
+    };
+    let ex_units = RecordedCall {
+        request: LedgerRequest::CalculateExUnits,
+        response: Ok(LedgerResponse::ExUnits(HashMap::from([(
+            0,
+            ExecutionCost::new_spend(1700, 476468),
+        )]))),
+    };
+    let client = replay_client(vec![
+        wallet_utxos,
+        RecordedCall {
+            request: LedgerRequest::ProtocolParameters,
+            response: Ok(LedgerResponse::ProtocolParameters(params)),
+        },
+        ex_units,
+    ]);
+    let mut values = Values::default();
+    values.add_one_value(&PolicyId::Lovelace, 6_000_000);
+    let script_address = always_succeeds_script_address(Network::Preprod.into());
+    let script_output =
+        Output::new_validator(hex::decode(TX_HASH).unwrap(), 0, script_address, values, ())
+            .with_datum_encoding(DatumEncoding::Inline);
+
+    let unsigned = client
+        .build_unsigned(claim_always_succeeds_v2_datum_tx(&script_output))
+        .await
+        .unwrap();
+
+    let tx = CMLTransaction::from_bytes(hex::decode(unsigned.cbor_hex()).unwrap()).unwrap();
+    assert!(tx.witness_set().plutus_data().is_none());
+    assert!(tx.witness_set().redeemers().is_some());
+}
+
+#[tokio::test]
+async fn spending_inline_datum_output_with_v1_script_errors() {
+    let client = replay_client(vec![]);
+    let script_address = always_succeeds_script_address(Network::Preprod.into());
+    let script_output = Output::new_validator(
+        hex::decode(TX_HASH).unwrap(),
+        0,
+        script_address,
+        Values::default(),
+        (),
+    )
+    .with_datum_encoding(DatumEncoding::Inline);
+
+    let error = client
+        .build_unsigned(claim_always_succeeds_datum_tx(&script_output))
+        .await
+        .unwrap_err();
+
+    match error {
+        LedgerClientError::FailedToIssueTx(inner) => assert!(matches!(
+            inner.downcast_ref::<CMLLCError>(),
+            Some(CMLLCError::InlineDatumInV1)
+        )),
+        _ => panic!("Expected FailedToIssueTx"),
     }
 }
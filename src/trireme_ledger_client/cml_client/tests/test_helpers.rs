@@ -1,6 +1,6 @@
 use crate::scripts::raw_script::PlutusScriptFile;
 use crate::scripts::raw_validator_script::RawPlutusValidator;
-use crate::transaction::TransactionVersion;
+use crate::transaction::{validity::ValidityInterval, TransactionVersion};
 use crate::trireme_ledger_client::Network;
 use crate::{
     output::{Output, UnbuiltOutput},
@@ -25,7 +25,13 @@ pub fn transfer_tx(recipient: Address, amount: u64) -> UnbuiltTransaction<(), ()
         unbuilt_outputs: vec![output],
         minting: Default::default(),
         specific_wallet_inputs: vec![],
-        valid_range: (None, None),
+        reference_inputs: vec![],
+        metadata: Default::default(),
+        certificates: vec![],
+        withdrawals: vec![],
+        required_signers: vec![],
+        min_ada_top_up: false,
+        valid_range: ValidityInterval::default(),
     }
 }
 
@@ -40,7 +46,13 @@ pub fn lock_at_always_succeeds_tx(amount: u64) -> UnbuiltTransaction<(), ()> {
         unbuilt_outputs: vec![output],
         minting: Default::default(),
         specific_wallet_inputs: vec![],
-        valid_range: (None, None),
+        reference_inputs: vec![],
+        metadata: Default::default(),
+        certificates: vec![],
+        withdrawals: vec![],
+        required_signers: vec![],
+        min_ada_top_up: false,
+        valid_range: ValidityInterval::default(),
     }
 }
 
@@ -86,7 +98,25 @@ pub fn claim_always_succeeds_datum_tx(script_input: &Output<()>) -> UnbuiltTrans
         unbuilt_outputs: vec![],
         minting: Default::default(),
         specific_wallet_inputs: vec![],
-        valid_range: (None, None),
+        reference_inputs: vec![],
+        metadata: Default::default(),
+        certificates: vec![],
+        withdrawals: vec![],
+        required_signers: vec![],
+        min_ada_top_up: false,
+        valid_range: ValidityInterval::default(),
+    }
+}
+
+/// Spend `script_input` with the always succeeds script run as PlutusV2, which can read inline
+/// datums
+pub fn claim_always_succeeds_v2_datum_tx(script_input: &Output<()>) -> UnbuiltTransaction<(), ()> {
+    let script = RawPlutusValidator::new_v2(always_succeeds_hex()).unwrap();
+    let script = Box::new(script) as Box<dyn ValidatorCode<(), ()>>;
+    UnbuiltTransaction {
+        script_version: TransactionVersion::V2,
+        script_inputs: vec![(script_input.clone(), (), script)],
+        ..claim_always_succeeds_datum_tx(script_input)
     }
 }
 
@@ -60,6 +60,12 @@ impl Keys for RawSecretPhraseKeys {
         let priv_key = account_key.derive(0).derive(0).to_raw_key();
         Ok(priv_key)
     }
+
+    async fn stake_private_key(&self) -> CMLLCResult<PrivateKey> {
+        let account_key = self.get_account_key().await?;
+        let stake_key = account_key.derive(2).derive(0).to_raw_key();
+        Ok(stake_key)
+    }
 }
 
 #[derive(Serialize, Deserialize)]
@@ -67,6 +67,13 @@ impl<P: Password + Send + Sync> Keys for PasswordProtectedPhraseKeys<P> {
         let priv_key = account_key.derive(0).derive(0).to_raw_key();
         Ok(priv_key)
     }
+
+    async fn stake_private_key(&self) -> CMLResult<PrivateKey> {
+        let phrase = self.read_phrase().await?;
+        let account_key = secret_phrase_to_account_key(&phrase)?;
+        let stake_key = account_key.derive(2).derive(0).to_raw_key();
+        Ok(stake_key)
+    }
 }
 
 pub struct TerminalPasswordUpfront {
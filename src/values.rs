@@ -7,6 +7,9 @@ use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
 use std::collections::HashMap;
 
+#[cfg(test)]
+mod tests;
+
 #[serde_with::serde_as]
 #[derive(Clone, PartialEq, Debug, Eq, Deserialize, Serialize, Default)]
 pub struct Values {
@@ -49,6 +52,9 @@ impl Values {
         }
 
         let other_remainders: Vec<_> = mine_cloned.into_iter().collect();
+        if !other_remainders.is_empty() {
+            there_is_a_difference = true;
+        }
         remainders.extend(other_remainders);
 
         let values = remainders.into_iter().collect();
@@ -82,6 +88,17 @@ impl Values {
         self.values.get(policy).copied()
     }
 
+    /// Whether any of `asset` is held. A policy without an asset name matches every asset under it.
+    pub fn contains_asset(&self, asset: &PolicyId) -> bool {
+        match asset {
+            PolicyId::NativeToken(id, None) => self.values.keys().any(|policy| match policy {
+                PolicyId::NativeToken(held_id, _) => held_id == id,
+                PolicyId::Lovelace => false,
+            }),
+            _ => self.values.contains_key(asset),
+        }
+    }
+
     pub fn take(&mut self, policy: &PolicyId) -> Option<u64> {
         self.values.remove(policy)
     }
@@ -0,0 +1,82 @@
+#![allow(non_snake_case)]
+
+use super::*;
+
+fn values_of(amounts: &[(PolicyId, u64)]) -> Values {
+    let mut values = Values::default();
+    for (policy, amount) in amounts {
+        values.add_one_value(policy, *amount);
+    }
+    values
+}
+
+fn nft() -> PolicyId {
+    PolicyId::native_token("abcdef", &Some("nft".to_string()))
+}
+
+#[test]
+fn try_subtract__returns_remainder() {
+    // given
+    let mine = values_of(&[(PolicyId::Lovelace, 10_000_000), (nft(), 1)]);
+    let other = values_of(&[(PolicyId::Lovelace, 4_000_000)]);
+
+    // when
+    let actual = mine.try_subtract(&other).unwrap();
+
+    // then
+    let expected = values_of(&[(PolicyId::Lovelace, 6_000_000), (nft(), 1)]);
+    assert_eq!(actual, Some(expected));
+}
+
+#[test]
+fn try_subtract__none_when_nothing_is_left() {
+    // given
+    let mine = values_of(&[(PolicyId::Lovelace, 10_000_000), (nft(), 1)]);
+
+    // when
+    let actual = mine.try_subtract(&mine.clone()).unwrap();
+
+    // then
+    assert_eq!(actual, None);
+}
+
+#[test]
+fn try_subtract__nothing_keeps_everything() {
+    // given
+    let mine = values_of(&[(PolicyId::Lovelace, 10_000_000)]);
+
+    // when
+    let actual = mine.try_subtract(&Values::default()).unwrap();
+
+    // then
+    assert_eq!(actual, Some(mine));
+}
+
+#[test]
+fn try_subtract__more_than_held_errors() {
+    // given
+    let mine = values_of(&[(PolicyId::Lovelace, 10_000_000)]);
+    let other = values_of(&[(PolicyId::Lovelace, 10_000_001)]);
+
+    // when
+    let actual = mine.try_subtract(&other);
+
+    // then
+    assert!(matches!(
+        actual,
+        Err(Error::InsufficientAmountOf(PolicyId::Lovelace))
+    ));
+}
+
+#[test]
+fn try_subtract__missing_asset_errors() {
+    // given
+    let mine = values_of(&[(PolicyId::Lovelace, 10_000_000)]);
+    let other = values_of(&[(nft(), 1)]);
+
+    // when
+    let actual = mine.try_subtract(&other);
+
+    // then
+    assert!(matches!(actual, Err(Error::InsufficientAmountOf(policy)) if policy == nft()));
+}
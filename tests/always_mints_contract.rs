@@ -83,7 +83,11 @@ fn mint(amount: u64, _recipient: Address) -> SCLogicResult<TxActions<(), ()>> {
 async fn can_mint_from_always_true_minting_policy() {
     let me = Address::from_bech32("addr_test1qpuy2q9xel76qxdw8r29skldzc876cdgg9cugfg7mwh0zvpg3292mxuf3kq7nysjumlxjrlsfn9tp85r0l54l29x3qcs7nvyfm").unwrap();
     let policy = PolicyId::native_token(&hex::encode(MINT_POLICY_ID), &None);
-    let backend = TestLedgerClientBuilder::new(&me).build_in_memory();
+    let backend = TestLedgerClientBuilder::new(&me)
+        .start_output(&me)
+        .with_value(PolicyId::Lovelace, 10_000_000)
+        .finish_output()
+        .build_in_memory();
     // Call mint endpoint
     let amount = 69;
     let call = Endpoint::Mint { amount };
@@ -50,11 +50,11 @@ async fn can_transfer_and_keep_remainder() {
     let me = Address::from_bech32("addr_test1qpuy2q9xel76qxdw8r29skldzc876cdgg9cugfg7mwh0zvpg3292mxuf3kq7nysjumlxjrlsfn9tp85r0l54l29x3qcs7nvyfm").unwrap();
     let alice = Address::from_bech32("addr_test1qzvrhz9v6lwcr26a52y8mmk2nzq37lky68359keq3dgth4lkzpnnjv8vf98m20lhqdzl60mcftq7r2lc4xtcsv0w6xjstag0ua").unwrap();
 
-    let input_amount = 666;
+    let input_amount = 10_000_000;
     let extra_policy = PolicyId::native_token("arcade token", &None);
     let extra_amount = 50;
 
-    let amount = 590;
+    let amount = 5_900_000;
 
     let ledger_client = TestLedgerClientBuilder::new(&me)
         .start_output(&me)
@@ -11,12 +11,16 @@ use naumachia::{
     ledger_client::{
         test_ledger_client::local_persisted_storage::LocalPersistedStorage, LedgerClient,
     },
+    scripts::raw_validator_script::plutus_data::PlutusData,
     trireme_ledger_client::{
-        cml_client::blockfrost_ledger::BlockfrostApiKey, get_trireme_config_from_file,
-        get_trireme_ledger_client_from_file, path_to_client_config_file,
-        path_to_trireme_config_dir, path_to_trireme_config_file, read_toml_struct_from_file,
-        write_toml_struct_to_file, ClientConfig, ClientVariant, KeySource, LedgerSource, Network,
-        TriremeConfig, TriremeLedgerClient,
+        cml_client::{
+            blockfrost_ledger::{blockfrost_url, BlockfrostApiKey},
+            network_settings::NetworkSettings,
+        },
+        get_trireme_config_from_file, get_trireme_ledger_client_from_file,
+        path_to_client_config_file, path_to_trireme_config_dir, path_to_trireme_config_file,
+        read_toml_struct_from_file, write_toml_struct_to_file, ClientConfig, ClientVariant,
+        KeySource, LedgerSource, Network, TriremeConfig, TriremeLedgerClient,
     },
     Address,
 };
@@ -84,7 +88,7 @@ fn get_env_type() -> Result<EnvironmentType> {
 }
 
 async fn setup_password_protected_blockfrost_env(name: &str) -> Result<()> {
-    let ledger_source = get_ledger_source(name).await?;
+    let (ledger_source, network) = get_ledger_source(name).await?;
 
     let secret_phrase: String = Input::new()
         .with_prompt("⚠️  Insert testnet secret phrase ⚠️  ")
@@ -112,9 +116,6 @@ async fn setup_password_protected_blockfrost_env(name: &str) -> Result<()> {
         &encryption_nonce,
     )
     .await?;
-    // TODO: Do a prompt or derive network from api key
-    let network = Network::Preprod;
-
     write_cml_client_config_with_password_protection(
         &name,
         &name,
@@ -132,6 +133,8 @@ async fn setup_password_protected_blockfrost_env(name: &str) -> Result<()> {
 enum LedgerTypes {
     BlockFrost,
     OgmiosAndScrolls,
+    KupoAndOgmios,
+    Ogmios,
 }
 
 impl ToString for LedgerTypes {
@@ -139,12 +142,20 @@ impl ToString for LedgerTypes {
         match self {
             LedgerTypes::BlockFrost => "Blockfrost API".to_string(),
             LedgerTypes::OgmiosAndScrolls => "Ogmios and Scrolls".to_string(),
+            LedgerTypes::KupoAndOgmios => "Kupo and Ogmios".to_string(),
+            LedgerTypes::Ogmios => "Ogmios v6".to_string(),
         }
     }
 }
 
-async fn get_ledger_source(env_name: &str) -> Result<LedgerSource> {
-    let items = vec![LedgerTypes::BlockFrost, LedgerTypes::OgmiosAndScrolls];
+/// Ledger source and the network it serves
+async fn get_ledger_source(env_name: &str) -> Result<(LedgerSource, Network)> {
+    let items = vec![
+        LedgerTypes::BlockFrost,
+        LedgerTypes::OgmiosAndScrolls,
+        LedgerTypes::KupoAndOgmios,
+        LedgerTypes::Ogmios,
+    ];
     let item_index = Select::new()
         .with_prompt("What is your ledger data provider?")
         .items(&items)
@@ -156,19 +167,85 @@ async fn get_ledger_source(env_name: &str) -> Result<LedgerSource> {
 
     match ledger_type {
         LedgerTypes::BlockFrost => setup_blockfrost_ledger(env_name).await,
-        LedgerTypes::OgmiosAndScrolls => setup_ogmios_and_scrolls_ledger(),
+        LedgerTypes::OgmiosAndScrolls => Ok((setup_ogmios_and_scrolls_ledger()?, get_network()?)),
+        LedgerTypes::KupoAndOgmios => Ok((setup_kupo_and_ogmios_ledger()?, get_network()?)),
+        LedgerTypes::Ogmios => Ok((setup_ogmios_ledger()?, get_network()?)),
     }
 }
 
-async fn setup_blockfrost_ledger(env_name: &str) -> Result<LedgerSource> {
+fn get_network() -> Result<Network> {
+    let names = vec![
+        "Preprod",
+        "Preview",
+        "Mainnet",
+        "Custom (from genesis files)",
+    ];
+    let item_index = Select::new()
+        .with_prompt("Which network?")
+        .items(&names)
+        .default(0)
+        .interact()?;
+    let network = match item_index {
+        0 => Network::Preprod,
+        1 => Network::Preview,
+        2 => Network::Mainnet,
+        _ => get_custom_network()?,
+    };
+    Ok(network)
+}
+
+fn get_custom_network() -> Result<Network> {
+    let byron_genesis_file: String = Input::new()
+        .with_prompt("Path to Byron genesis file")
+        .interact_text()?;
+    let shelley_genesis_file: String = Input::new()
+        .with_prompt("Path to Shelley genesis file")
+        .interact_text()?;
+    let shelley_start_epoch: u64 = Input::new()
+        .with_prompt("Epoch the network hard forked into Shelley at")
+        .default(0)
+        .interact_text()?;
+    // Envs are used from any directory, so keep absolute paths
+    let network = Network::Custom {
+        byron_genesis_file: std::fs::canonicalize(byron_genesis_file)?,
+        shelley_genesis_file: std::fs::canonicalize(shelley_genesis_file)?,
+        shelley_start_epoch,
+    };
+    NetworkSettings::try_from(network.clone())?;
+    Ok(network)
+}
+
+async fn setup_blockfrost_ledger(env_name: &str) -> Result<(LedgerSource, Network)> {
     let api_key: String = Input::new()
-        .with_prompt("Insert blockfrost testnet api key")
+        .with_prompt("Insert blockfrost api key")
         .interact_text()?;
+    let network = match BlockfrostApiKey::from_str(&api_key)?.network() {
+        Some(network) => {
+            println!("Using {network:?} network for this api key");
+            network
+        }
+        None => get_network()?,
+    };
+    let base_url = match blockfrost_url(&network) {
+        Some(default_url) => {
+            let url: String = Input::new()
+                .with_prompt("Blockfrost base URL")
+                .default(default_url.to_string())
+                .interact_text()?;
+            (url != default_url).then_some(url)
+        }
+        None => Some(
+            Input::new()
+                .with_prompt("Blockfrost-compatible base URL for this network")
+                .interact_text()?,
+        ),
+    };
     let blockfrost_api_key_path = write_blockfrost_api_key(&api_key, env_name).await?;
     let ledger_source = LedgerSource::BlockFrost {
         api_key_file: blockfrost_api_key_path,
+        base_url,
     };
-    Ok(ledger_source)
+    Ok((ledger_source, network))
 }
 
 fn setup_ogmios_and_scrolls_ledger() -> Result<LedgerSource> {
@@ -197,6 +274,43 @@ fn setup_ogmios_and_scrolls_ledger() -> Result<LedgerSource> {
     Ok(ledger_source)
 }
 
+fn setup_kupo_and_ogmios_ledger() -> Result<LedgerSource> {
+    let kupo_url: String = Input::new()
+        .with_prompt("URL of Kupo")
+        .default("http://127.0.0.1:1442".to_string())
+        .interact_text()?;
+    let ogmios_ip: String = Input::new()
+        .with_prompt("Ip address of Ogmios")
+        .default("127.0.0.1".to_string())
+        .interact_text()?;
+    let ogmios_port: String = Input::new()
+        .with_prompt("Port for Ogmios")
+        .default("1337".to_string())
+        .interact_text()?;
+    let ledger_source = LedgerSource::KupoOgmios {
+        kupo_url,
+        ogmios_ip,
+        ogmios_port,
+    };
+    Ok(ledger_source)
+}
+
+fn setup_ogmios_ledger() -> Result<LedgerSource> {
+    let ogmios_ip: String = Input::new()
+        .with_prompt("Ip address of Ogmios")
+        .default("127.0.0.1".to_string())
+        .interact_text()?;
+    let ogmios_port: String = Input::new()
+        .with_prompt("Port for Ogmios")
+        .default("1337".to_string())
+        .interact_text()?;
+    let ledger_source = LedgerSource::Ogmios {
+        ogmios_ip,
+        ogmios_port,
+    };
+    Ok(ledger_source)
+}
+
 fn get_password_with_prompt(prompt: &str) -> Result<String> {
     let password = InputPassword::new().with_prompt(prompt).interact()?;
     Ok(password)
@@ -332,7 +446,8 @@ pub async fn active_signer_impl() -> Result<()> {
 }
 
 pub async fn get_address_impl() -> Result<()> {
-    let ledger_client: TriremeLedgerClient<(), ()> = get_trireme_ledger_client_from_file().await?;
+    let ledger_client: TriremeLedgerClient<PlutusData, PlutusData> =
+        get_trireme_ledger_client_from_file().await?;
     let address = ledger_client.signer_base_address().await?;
     let address_string = address.to_bech32()?;
     println!("Address: {address_string}");
@@ -340,7 +455,8 @@ pub async fn get_address_impl() -> Result<()> {
 }
 
 pub async fn get_pubkey_hash_impl() -> Result<()> {
-    let ledger_client: TriremeLedgerClient<(), ()> = get_trireme_ledger_client_from_file().await?;
+    let ledger_client: TriremeLedgerClient<PlutusData, PlutusData> =
+        get_trireme_ledger_client_from_file().await?;
     let address = ledger_client.signer_base_address().await?;
     let pubkey_hash = pub_key_hash_from_address_if_available(&address).ok_or(Error::CLI(
         "Could not derive Pubkey Hash from Address".to_string(),
@@ -394,7 +510,7 @@ pub async fn current_time_impl() -> Result<()> {
                 );
             }
             ClientVariant::Test(_) => {
-                let ledger_client: TriremeLedgerClient<(), ()> =
+                let ledger_client: TriremeLedgerClient<PlutusData, PlutusData> =
                     get_trireme_ledger_client_from_file().await?;
                 let current_time = ledger_client.current_time().await?;
                 println!(
@@ -408,14 +524,16 @@ pub async fn current_time_impl() -> Result<()> {
 }
 
 pub async fn last_block_time_impl() -> Result<()> {
-    let ledger_client: TriremeLedgerClient<(), ()> = get_trireme_ledger_client_from_file().await?;
+    let ledger_client: TriremeLedgerClient<PlutusData, PlutusData> =
+        get_trireme_ledger_client_from_file().await?;
     let last_block_time = ledger_client.last_block_time_secs().await?;
     println!("Last block time: {}", last_block_time);
     Ok(())
 }
 
 pub async fn advance_blocks(count: i64) -> Result<()> {
-    let ledger_client: TriremeLedgerClient<(), ()> = get_trireme_ledger_client_from_file().await?;
+    let ledger_client: TriremeLedgerClient<PlutusData, PlutusData> =
+        get_trireme_ledger_client_from_file().await?;
     ledger_client.advance_blocks(count).await?;
     println!("Advancing blocks by: {}", count);
     let block_time = ledger_client.current_time().await?;
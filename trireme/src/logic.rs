@@ -3,6 +3,7 @@ use naumachia::{
     address::PolicyId,
     ledger_client::LedgerClient,
     logic::{as_lookup_err, SCLogic, SCLogicResult},
+    scripts::raw_validator_script::plutus_data::PlutusData,
     transaction::TxActions,
     values::Values,
 };
@@ -27,8 +28,8 @@ impl SCLogic for TriremeLogic {
     type Endpoints = ();
     type Lookups = TriremeLookups;
     type LookupResponses = TriremeResponses;
-    type Datums = ();
-    type Redeemers = ();
+    type Datums = PlutusData;
+    type Redeemers = PlutusData;
 
     async fn handle_endpoint<Record: LedgerClient<Self::Datums, Self::Redeemers>>(
         _endpoint: Self::Endpoints,
@@ -48,7 +49,7 @@ impl SCLogic for TriremeLogic {
     }
 }
 
-async fn impl_lovelace_balance<LC: LedgerClient<(), ()>>(
+async fn impl_lovelace_balance<LC: LedgerClient<PlutusData, PlutusData>>(
     ledger_client: &LC,
 ) -> SCLogicResult<TriremeResponses> {
     let address = ledger_client
@@ -63,7 +64,7 @@ async fn impl_lovelace_balance<LC: LedgerClient<(), ()>>(
     Ok(response)
 }
 
-async fn impl_total_balance<LC: LedgerClient<(), ()>>(
+async fn impl_total_balance<LC: LedgerClient<PlutusData, PlutusData>>(
     ledger_client: &LC,
 ) -> SCLogicResult<TriremeResponses> {
     let address = ledger_client